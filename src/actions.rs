@@ -1,26 +1,89 @@
 use crate::{pane::Pane, utils, window::Window};
 
+use crate::command::{unprocess_command, PaneCommand, DEFAULT_EXPECT_TIMEOUT};
 use crate::config::Config;
+use crate::defaults::Defaults;
+use crate::dependency::{Dependency, DEFAULT_READY_TIMEOUT};
+use crate::diagnostic::Diagnostic;
+use crate::pane_reference::PaneReference;
 use crate::pane_split::PaneSplit;
-use crate::project::Project;
+use crate::project::{FileFormat, Project, DEPRECATED_FIELDS, SCHEMA_VERSION};
+use crate::reference;
 use crate::startup_window::StartupWindow;
 use crate::utils::{tmux_join, tmux_quote};
 
+use console::{Key, Term};
 use mkdirp::mkdirp;
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use shell_words::quote;
 use shellexpand::env_with_context;
 use snafu::{ensure, Snafu};
 use tempfile::NamedTempFile;
 
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::env;
 use std::error;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::{self, prelude::*};
 use std::iter;
 use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus, Stdio};
-
-const FILE_EXTENSIONS: &[&str] = &["yml", "yaml", "json"];
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &str = "history";
+const REGISTRY_FILE: &str = "sessions";
+const LOCKS_DIR: &str = "locks";
+// How long to wait for another airmux process to finish starting the same session
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+// Locks older than this are assumed to be left over by an airmux process that didn't exit cleanly
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(300);
+const REMOTE_PROJECTS_DIR: &str = "remote";
+const HISTORY_SIZE: usize = 50;
+const SOURCE_HASH_VAR: &str = "__AIRMUX_SOURCE_HASH";
+
+// Session variable listing the env_capture variables for freeze to pick up
+const ENV_CAPTURE_VAR: &str = "__AIRMUX_ENV_CAPTURE";
+// Window options mapping the panes' project order to their tmux ids while their window is created
+const PANE_OPTION_PREFIX: &str = "@airmux_pane_";
+// Temporary name of the windows being created, so they can be targeted wherever they end up
+const WINDOW_NAME_PREFIX: &str = "__airmux_window_";
+// Session variable naming the project a session was adopted by
+const PROJECT_VAR: &str = "__AIRMUX_PROJECT";
+// Prefix of the tmux buffers left behind by hooks killed after hook_timeout
+const HOOK_TIMEOUT_BUFFER_PREFIX: &str = "__airmux_hook_timeout_";
+// Prefix of the tmux buffers left behind by expect steps that timed out
+const EXPECT_TIMEOUT_BUFFER_PREFIX: &str = "__airmux_expect_timeout_";
+// Set by hooks that fail when strict_hooks is set, so the rest of the source is skipped
+const HOOK_FAILED_VAR: &str = "__AIRMUX_HOOK_FAILED";
+// Tells airmux which hook failed once the source is done
+const HOOK_FAILED_BUFFER: &str = "__airmux_hook_failed";
+// Session options telling the rest of the source whether the session was just created, updated
+// by it, or already there, kept on the session so concurrent starts don't clear each other's
+const SESSION_CREATED_OPTION: &str = "@airmux_created";
+const SESSION_UPDATED_OPTION: &str = "@airmux_updated";
+const SESSION_ATTACHED_OPTION: &str = "@airmux_attached";
+// Created to make sure the tmux server runs while a session is started, killed right after
+const DUMMY_SESSION_NAME: &str = "__airmux_dummy_session_";
+// Prefix of the global variables that only live while a session is being started
+const GLOBAL_VAR_PREFIX: &str = "__AIRMUX_";
+// Hooks left commented out in frozen projects by `freeze --with-comments`
+const PROJECT_HOOKS: &[&str] = &[
+    "on_start",
+    "on_first_start",
+    "on_every_start",
+    "on_restart",
+    "on_exit",
+    "on_stop",
+    "post_create",
+    "on_window_create",
+    "post_window_create",
+    "on_pane_create",
+    "post_pane_create",
+];
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -46,6 +109,84 @@ pub enum Error {
     CannotExtractProjectName { project_file: PathBuf },
     #[snafu(display("cannot edit a piped project file"))]
     CannotEditStdinProject,
+    #[snafu(display("cannot watch a piped project file"))]
+    CannotWatchStdinProject,
+    #[snafu(display("no project was started recently"))]
+    NoRecentProject,
+    #[snafu(display("TOML project files are not supported, use YAML or JSON instead"))]
+    TomlNotSupported,
+    #[snafu(display(
+        "the generated project file does not parse back to the same project (fields: {})",
+        fields.join(", ")
+    ))]
+    RoundTripFailed { fields: Vec<String> },
+    #[snafu(display("comments can only be added to YAML project files"))]
+    CommentsRequireYaml,
+    #[snafu(display("{}, startup was aborted (strict_hooks is set)", message))]
+    HookFailed { message: String },
+    #[snafu(display("{}", message))]
+    ExpectTimedOut { message: String },
+    #[snafu(display(
+        "the timeout command was not found, hook_timeout and pane command timeouts need it (it comes with GNU coreutils)"
+    ))]
+    TimeoutCommandNotFound,
+    #[snafu(display(
+        "session {:?} closed while it was started, make sure the shells of its windows and panes keep running",
+        session_name
+    ))]
+    SessionClosedWhileStarting { session_name: String },
+    #[snafu(display("cannot freeze the session into {:?}: {}", project_file, reason))]
+    CannotFreezeInto {
+        project_file: PathBuf,
+        reason: String,
+    },
+    #[snafu(display("cannot extract a project name from url {:?}", url))]
+    CannotExtractProjectNameFromUrl { url: String },
+    #[snafu(display("failed to fetch project from {:?}", url))]
+    FetchFailed { url: String },
+    #[snafu(display("the ui can only run in a terminal"))]
+    UiNeedsTerminal,
+    #[snafu(display(
+        "project file version {} is not supported (latest: {})",
+        version,
+        SCHEMA_VERSION
+    ))]
+    UnsupportedProjectVersion { version: usize },
+    #[snafu(display("depends_on: dependency cycle between projects {}", cycle))]
+    DependencyCycle { cycle: String },
+    #[snafu(display(
+        "dependency {:?} was not ready after {} seconds",
+        project_name,
+        timeout
+    ))]
+    DependencyNotReady { project_name: String, timeout: u64 },
+    #[snafu(display("command failed: {}", command))]
+    ServiceCommandFailed { command: String },
+    #[snafu(display("project file {:?} still has errors", path))]
+    ProjectFileHasErrors { path: PathBuf },
+    #[snafu(display("no sessions to import from {:?}", path))]
+    NothingToImport { path: PathBuf },
+    #[snafu(display("session {:?} was not found in {:?}", session_name, path))]
+    ImportSessionNotFound { session_name: String, path: PathBuf },
+    #[snafu(display("session {:?} does not exist", session_name))]
+    SessionDoesNotExist { session_name: String },
+    #[snafu(display(
+        "session {:?} is being started by another airmux process (remove {:?} if it isn't)",
+        session_name,
+        path
+    ))]
+    SessionLocked { session_name: String, path: PathBuf },
+    #[snafu(display("cannot select {:?}, there is no such window or pane", target))]
+    AttachTargetNotFound { target: String },
+    #[snafu(display("backend {:?} is not supported (expected wezterm or kitty)", backend))]
+    UnsupportedBackend { backend: String },
+    #[snafu(display(
+        "the project file defines several sessions ({}), pick one with --session",
+        session_names.join(", ")
+    ))]
+    SeveralSessions { session_names: Vec<String> },
+    #[snafu(display("session {:?} is not defined in the project file", session_name))]
+    SessionNotDefined { session_name: String },
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -55,66 +196,360 @@ pub fn start_project(
     project_file: Option<&str>,
     force_attach: Option<bool>,
     show_source: bool,
+    copy_source: bool,
     verbose: bool,
     args: &[&str],
     switch: bool,
+    prune: bool,
+    refresh: bool,
+    no_input: bool,
+    implicit: bool,
+    print_attach_command: bool,
+    control_mode: bool,
+    check_splits: bool,
+    attach_window: Option<&str>,
+    attach_pane: Option<&str>,
+    session: Option<&str>,
 ) -> Result<(), Box<dyn error::Error>> {
-    let (project_name, project_file) = project::get_filename(config, project_name, project_file)?;
-    ensure!(project_file.is_file(), ProjectDoesNotExist { project_name });
+    let is_local = project_name.is_none() && project_file.is_none();
 
-    let project = project::load(config, &project_name, &project_file, force_attach, args)?;
-    project.check()?;
+    // Use `-` as a project name to start the last started project, like `cd -`
+    let (project_name, project_file) = match (project_name, project_file) {
+        (Some("-"), None) => history::get_last_project(config)?,
+        _ => project::get_filename(config, project_name, project_file)?,
+    };
+
+    // Without a local project file, let the user pick a project if a picker is set
+    if let (true, Some(picker)) = (is_local && !project_file.is_file(), &config.picker) {
+        return match list::pick_project(config, picker)? {
+            None => Ok(()),
+            Some(project_name) => start_project(
+                config,
+                Some(&project_name),
+                None,
+                force_attach,
+                show_source,
+                copy_source,
+                verbose,
+                args,
+                switch,
+                prune,
+                refresh,
+                no_input,
+                false,
+                print_attach_command,
+                control_mode,
+                check_splits,
+                attach_window,
+                attach_pane,
+                session,
+            ),
+        };
+    }
+
+    // Or fall back to a single window in the current directory
+    let implicit = implicit || env::var("AIRMUX_IMPLICIT_PROJECT").is_ok();
+    if is_local && implicit && !project_file.is_file() {
+        return up_session(
+            config,
+            Some(&project_name),
+            &[],
+            force_attach,
+            show_source,
+            switch,
+        );
+    }
+
+    // An empty path means the project is read from stdin
+    let from_stdin = project_file == PathBuf::new();
+    ensure!(
+        from_stdin || project_file.is_file(),
+        ProjectDoesNotExist { project_name }
+    );
+
+    // Piped projects can only be read once, their defaults are left out
+    let defaults = if from_stdin {
+        Defaults::default()
+    } else {
+        project::get_defaults(&project_file)
+    };
+    let force_attach = defaults.force_attach(force_attach);
+    let args = &defaults.args(args)[..];
+    let verbose = verbose || defaults.verbose;
+    let switch = switch || defaults.switch;
+    let prune = prune || defaults.prune;
+    let refresh = refresh || defaults.refresh;
+    let no_input = no_input || defaults.no_input;
+    let check_splits = check_splits || defaults.check_splits;
+
+    let mut profile = profile::Profile::new(config.profile);
+
+    // Files that define several sessions start all of them, the first one is attached last
+    let project_yaml = project::read(&project_file, args)?;
+    let mut projects = project::load_sessions(
+        config,
+        &project_name,
+        &project_file,
+        &project_yaml,
+        force_attach,
+        session,
+    )?;
+    profile.step("load");
+
+    let project = projects.remove(0);
+    for mut other_project in projects {
+        other_project.attach = false;
+        start_session(
+            config,
+            &project_name,
+            &project_file,
+            &other_project,
+            from_stdin,
+            show_source,
+            copy_source,
+            verbose,
+            prune,
+            refresh,
+            no_input,
+            check_splits,
+            &mut profile,
+        )?;
+    }
+    start_session(
+        config,
+        &project_name,
+        &project_file,
+        &project,
+        from_stdin,
+        show_source,
+        copy_source,
+        verbose,
+        prune,
+        refresh,
+        no_input,
+        check_splits,
+        &mut profile,
+    )?;
+
+    if !show_source && !copy_source {
+        if print_attach_command || project.attach {
+            source::select_attach_target(&project, attach_window, attach_pane)?;
+        }
+
+        if print_attach_command {
+            println!(
+                "{}",
+                source::attach_command(&project, switch, control_mode)?
+            );
+        } else if project.attach {
+            return source::attach(&project, switch, control_mode, &mut profile);
+        }
+    }
+
+    profile.print();
+    Ok(())
+}
+
+// Creates or updates the session of an already loaded project, without attaching it
+#[allow(clippy::too_many_arguments)]
+fn start_session(
+    config: &Config,
+    project_name: &str,
+    project_file: &Path,
+    project: &Project,
+    from_stdin: bool,
+    show_source: bool,
+    copy_source: bool,
+    verbose: bool,
+    prune: bool,
+    refresh: bool,
+    no_input: bool,
+    check_splits: bool,
+    profile: &mut profile::Profile,
+) -> Result<(), Box<dyn error::Error>> {
+    project
+        .check()
+        .map_err(|err| Diagnostic::new(project_file, err.to_string()))?;
+    profile.step("check");
+
+    // Resolve dependencies upfront, so cycles are reported even when only printing the source
+    let dependencies = dependencies::resolve(config, project_name, project)?;
+    profile.step("dependencies");
 
-    let source = source::generate(&project, verbose)?;
+    let source = source::generate(project, verbose)?;
+    profile.step("generate");
 
     // Run tmux
-    if show_source {
+    if copy_source {
+        utils::copy_to_clipboard(&source)?;
+        println!(
+            "Copied the tmux source of project {:?} to the clipboard.",
+            project_name
+        );
+        return Ok(());
+    } else if show_source {
         println!("{}", source);
+        return Ok(());
+    }
+
+    dependencies::start(config, &dependencies, verbose)?;
+    profile.step("start dependencies");
+
+    // Keep simultaneous starts of the same session from interleaving their commands,
+    // the lock is released once the session is ready, before it's attached
+    let _lock =
+        lock::SessionLock::acquire(config, project.session_name.as_ref().unwrap(), LOCK_TIMEOUT)?;
+    profile.step("lock");
+
+    // Re-entering a session that already matches the project only needs an attach
+    let source_hash = source::get_hash(&source);
+    let session_hash = source::get_session_hash(project);
+    let unchanged = !refresh
+        && !source::runs_on_every_start(project)
+        && session_hash.as_deref() == Some(&source_hash);
+    profile.step("hash check");
+
+    // Sourcing the project again only adds what's missing, windows and the panes they lack
+    if session_hash.is_some() && session_hash.as_deref() != Some(&source_hash) {
+        eprintln!(
+            "warning: session {:?} was started from a different version of project {:?}, \
+            its existing windows only get the panes they're missing (use --prune to remove the windows that \
+            aren't in the project anymore, or kill the session to start it over)",
+            project.session_name.as_ref().unwrap(),
+            project_name
+        );
+    }
+
+    if unchanged {
+        if verbose {
+            println!(
+                "no changes to existing session: {}",
+                project.session_name.as_ref().unwrap()
+            );
+        }
     } else {
-        // Some tmux versions close the tmux server if there are no running sessions
-        // This prevents us from running `tmux source`.
-        // So we create a dummy tmux session that we'll discard at the end
-        let dummy_session = source::TmuxDummySession::new(&project)?;
+        // Better to warn upfront than to let tmux fail halfway through the source
+        if check_splits {
+            source::warn_unfitting_splits(project);
+        }
 
-        // Get tmux version
-        let (tmux_command, tmux_args) = project.tmux_command(&["-V"])?;
-        let version_output = Command::new(tmux_command).args(tmux_args).output()?;
-        let version = String::from_utf8_lossy(&version_output.stdout);
+        let source = source::with_hash(project, &source, &source_hash);
+        source::exec_tmux(project, &source, profile)?;
+    }
 
-        let status = if version.starts_with("tmux 2.") {
-            source::exec_tmux_2(&project, &source)?
-        } else {
-            source::exec_tmux_3(&project, &source)?
-        };
+    // Kill windows that aren't declared in the project
+    if prune {
+        prune::prune_windows(project, no_input)?;
+        profile.step("prune");
+    }
 
-        // Make sure to remove the dummy session before attaching,
-        // Otherwise it'll pollute the session list the entire time we're attached
-        // Because airmux won't quit until `tmux attach-session` returns
-        drop(dummy_session);
+    // Piped projects cannot be started again from history
+    if !from_stdin {
+        history::add_entry(config, project_name, project_file)?;
+        registry::add_entry(
+            config,
+            project.session_name.as_ref().unwrap(),
+            project_name,
+            project_file,
+        )?;
+        profile.step("history");
+    }
 
-        // Check tmux exit code
-        ensure!(
-            status.success(),
-            TmuxFailed {
-                exit_code: status.code().unwrap_or(-1)
-            }
-        );
+    Ok(())
+}
+
+// Starts a transient session made of windows given as `[name:] command`
+pub fn up_session(
+    config: &Config,
+    session_name: Option<&str>,
+    windows: &[&str],
+    force_attach: Option<bool>,
+    show_source: bool,
+    switch: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    let session_name = match session_name {
+        Some(session_name) => session_name.to_string(),
+        None => project::get_filename(config, None, None)?.0,
+    };
+
+    let mut project = Project {
+        working_dir: Some(env::current_dir()?),
+        ..Project::default()
+    };
+    if !windows.is_empty() {
+        project.windows = windows.iter().map(|spec| up::parse_window(spec)).collect();
+    }
+
+    let mut profile = profile::Profile::new(config.profile);
+
+    let project = project.prepare(config, &session_name, force_attach);
+    project.check()?;
+    profile.step("check");
+
+    let source = source::generate(&project, false)?;
+    profile.step("generate");
+
+    if show_source {
+        println!("{}", source);
+    } else {
+        source::exec_tmux(&project, &source, &mut profile)?;
 
-        // Attach
         if project.attach {
-            let session_name = project.session_name.as_ref().unwrap();
-            let use_switch = switch
-                || matches!(env::var("TMUX"), Ok(_))
-                || matches!(env::var("AIRMUX_FORCE_SWITCH"), Ok(_));
-            let (tmux_command, tmux_args) = if use_switch {
-                project.tmux_command(&["switch-client", "-t", session_name])?
-            } else {
-                project.tmux_command(&["attach-session", "-t", session_name])?
-            };
-            Command::new(tmux_command).args(tmux_args).spawn()?.wait()?;
+            return source::attach(&project, switch, false, &mut profile);
         }
     }
 
+    profile.print();
+    Ok(())
+}
+
+// Starts the windows of several projects as a single session, named after the first project
+pub fn start_merged_projects(
+    config: &Config,
+    project_names: &[&str],
+    force_attach: Option<bool>,
+    show_source: bool,
+    switch: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut profile = profile::Profile::new(config.profile);
+
+    let mut projects = vec![];
+    let mut project_files = vec![];
+    for project_name in project_names {
+        let (project_name, project_file) = project::get_filename(config, Some(project_name), None)?;
+        ensure!(project_file.is_file(), ProjectDoesNotExist { project_name });
+
+        let project = project::load(config, &project_name, &project_file, force_attach, &[])?;
+        projects.push((project_name, project));
+        project_files.push(project_file);
+    }
+    profile.step("load");
+
+    // The merged session is started, locked and registered as the first project's
+    let project_name = projects[0].0.clone();
+    let project_file = project_files.remove(0);
+    let project = merge::merge_projects(projects).ok_or(Error::ProjectNameEmpty)?;
+    start_session(
+        config,
+        &project_name,
+        &project_file,
+        &project,
+        false,
+        show_source,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        &mut profile,
+    )?;
+
+    if !show_source && project.attach {
+        return source::attach(&project, switch, false, &mut profile);
+    }
+
+    profile.print();
     Ok(())
 }
 
@@ -122,40 +557,240 @@ pub fn kill_project(
     config: &Config,
     project_name: Option<&str>,
     project_file: Option<&str>,
+    freeze: bool,
     args: &[&str],
+    session: Option<&str>,
 ) -> Result<(), Box<dyn error::Error>> {
     let (project_name, project_file) = project::get_filename(config, project_name, project_file)?;
     ensure!(project_file.is_file(), ProjectDoesNotExist { project_name });
 
-    let project = project::load(
+    let project_yaml = project::read(&project_file, args)?;
+    let projects = project::load_sessions(
         config,
         &project_name,
         &project_file,
+        &project_yaml,
         None,
-        &args.iter().map(AsRef::as_ref).collect::<Vec<&str>>(),
+        session,
     )?;
-    project.check()?;
 
-    let session_name = project
-        .session_name
-        .to_owned()
-        .ok_or(/* should never happen */ Error::SessionNameNotSet {})?;
+    // Files that define several sessions kill all of them, the ones that aren't running are skipped
+    let kill_all = projects.len() > 1;
+    for project in projects {
+        project
+            .check()
+            .map_err(|err| Diagnostic::new(&project_file, err.to_string()))?;
 
-    // Run tmux
-    let (tmux_command, tmux_args) = project.tmux_command(&["kill-session", "-t", &session_name])?;
+        if kill_all && !source::session_exists(&project) {
+            continue;
+        }
 
-    let status = Command::new(tmux_command).args(tmux_args).spawn()?.wait()?;
+        let session_name = project
+            .session_name
+            .to_owned()
+            .ok_or(/* should never happen */ Error::SessionNameNotSet {})?;
+
+        // Save the layout of the session into the project file before it's gone
+        if freeze || project.freeze_on_stop {
+            if let Err(err) = autofreeze::freeze_into(config, &project_file, &session_name) {
+                eprintln!("warning: cannot freeze project {:?}: {}", project_name, err);
+            }
+        }
+
+        // Run tmux
+        let (tmux_command, tmux_args) =
+            project.tmux_command(&["kill-session", "-t", &session_name])?;
+
+        let status = Command::new(tmux_command).args(tmux_args).spawn()?.wait()?;
+
+        ensure!(
+            status.success(),
+            TmuxFailed {
+                exit_code: status.code().unwrap_or(-1)
+            }
+        );
+
+        registry::remove_entry(config, &session_name)?;
+    }
+
+    Ok(())
+}
+
+// Kills the running sessions of all known projects, their on_stop hooks run as the sessions close
+pub fn stop_all_projects(config: &Config, dry_run: bool) -> Result<(), Box<dyn error::Error>> {
+    // Sessions are killed by the name they run under, it may not be the one their project
+    // resolves to anymore (placeholders, files that define several sessions...)
+    let running_sessions = ui::get_running_sessions(config);
+    let mut entries = registry::get_entries(config)?;
+    for entry in ui::get_entries(config)? {
+        if entry.running
+            && entries
+                .iter()
+                .all(|registered| registered.session_name != entry.session_name)
+        {
+            let project_file = project::get_filename(config, Some(&entry.project_name), None)
+                .map(|(_, project_file)| project_file)
+                .unwrap_or_default();
+            entries.push(registry::Entry {
+                session_name: entry.session_name,
+                project_name: entry.project_name,
+                project_file,
+            });
+        }
+    }
+
+    for entry in entries {
+        // Projects may run on their own tmux server, sessions of the projects that don't load
+        // anymore are looked for on the default one
+        let project = load_registered_project(config, &entry);
+        let running = match &project {
+            Some(project) => {
+                ui::list_sessions(project.tmux_command(&["ls", "-F", "#{session_name}"]))
+                    .contains(&entry.session_name)
+            }
+            None => running_sessions.contains(&entry.session_name),
+        };
+        if !running {
+            continue;
+        }
+
+        if dry_run {
+            println!("{}\t{}", entry.session_name, entry.project_name);
+            continue;
+        }
+
+        if project
+            .as_ref()
+            .is_some_and(|project| project.freeze_on_stop)
+        {
+            if let Err(err) =
+                autofreeze::freeze_into(config, &entry.project_file, &entry.session_name)
+            {
+                eprintln!(
+                    "warning: cannot freeze project {:?}: {}",
+                    entry.project_name, err
+                );
+            }
+        }
+
+        // One session failing to close shouldn't keep the other sessions running
+        let kill_session = ["kill-session", "-t", &entry.session_name];
+        let (tmux_command, tmux_args) = match &project {
+            Some(project) => project.tmux_command(&kill_session)?,
+            None => config.get_tmux_command(&kill_session)?,
+        };
+        match Command::new(tmux_command).args(tmux_args).status() {
+            Ok(status) if status.success() => {
+                registry::remove_entry(config, &entry.session_name)?;
+                println!("Session {:?} stopped.", entry.session_name);
+            }
+            _ => eprintln!(
+                "warning: cannot stop session {:?} of project {:?}",
+                entry.session_name, entry.project_name
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+// Loads the project of a registered session, among the sessions its file defines
+fn load_registered_project(config: &Config, entry: &registry::Entry) -> Option<Project> {
+    let project_yaml = project::read(&entry.project_file, &[]).ok()?;
+    let mut projects = project::load_sessions(
+        config,
+        &entry.project_name,
+        &entry.project_file,
+        &project_yaml,
+        None,
+        None,
+    )
+    .ok()?;
+
+    let position = projects
+        .iter()
+        .position(|project| project.session_name.as_ref() == Some(&entry.session_name));
+    match (position, projects.len()) {
+        (Some(position), _) => Some(projects.swap_remove(position)),
+        (None, 1) => projects.pop(),
+        // Sessions that don't match any of the file's anymore still run on its tmux server
+        (None, _) => Some(Project {
+            freeze_on_stop: false,
+            ..projects.swap_remove(0)
+        }),
+    }
+}
+
+pub fn check_project(
+    config: &Config,
+    project_name: Option<&str>,
+    project_file: Option<&str>,
+    watch: bool,
+    args: &[&str],
+) -> Result<(), Box<dyn error::Error>> {
+    let (project_name, project_file) = project::get_filename(config, project_name, project_file)?;
 
+    // An empty path means the project is read from stdin
+    let from_stdin = project_file == PathBuf::new();
+    ensure!(!(from_stdin && watch), CannotWatchStdinProject);
     ensure!(
-        status.success(),
-        TmuxFailed {
-            exit_code: status.code().unwrap_or(-1)
+        from_stdin || project_file.is_file(),
+        ProjectDoesNotExist { project_name }
+    );
+
+    if !watch {
+        edit::check_project_file(config, &project_name, &project_file, args, None)?;
+        println!("{}: ok", project_file.to_string_lossy());
+        return Ok(());
+    }
+
+    // Poll the file's modification time, editors tend to replace files rather than write to them
+    let mut last_modified = None;
+    loop {
+        let modified = fs::metadata(&project_file)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+
+            match edit::check_project_file(config, &project_name, &project_file, args, None) {
+                Ok(()) => println!("{}: ok", project_file.to_string_lossy()),
+                Err(err) => println!("{}", err),
+            }
         }
+
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+// Prints the project as a wezterm script or a kitty session file, to lay it out without tmux
+pub fn export_project(
+    config: &Config,
+    project_name: Option<&str>,
+    project_file: Option<&str>,
+    backend: &str,
+    args: &[&str],
+) -> Result<(), Box<dyn error::Error>> {
+    let (project_name, project_file) = project::get_filename(config, project_name, project_file)?;
+
+    // An empty path means the project is read from stdin
+    let from_stdin = project_file == PathBuf::new();
+    ensure!(
+        from_stdin || project_file.is_file(),
+        ProjectDoesNotExist { project_name }
     );
 
+    let project = project::load(config, &project_name, &project_file, None, args)?;
+    project
+        .check()
+        .map_err(|err| Diagnostic::new(&project_file, err.to_string()))?;
+
+    print!("{}", backend::render(&project, backend)?);
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn edit_project(
     config: &Config,
     project_name: Option<&str>,
@@ -163,7 +798,9 @@ pub fn edit_project(
     extension: Option<&str>,
     editor: &str,
     no_check: bool,
+    no_open: bool,
     args: &[&str],
+    session: Option<&str>,
 ) -> Result<(), Box<dyn error::Error>> {
     let (project_name, project_file) = project::get_filename(config, project_name, project_file)?;
     let extension = match extension {
@@ -175,9 +812,27 @@ pub fn edit_project(
 
     ensure!(project_file != PathBuf::new(), CannotEditStdinProject);
 
-    edit::check_supported_extension(&extension)?;
+    edit::check_supported_extension(config, &extension)?;
     let project_file = project_file.with_extension(&extension);
 
+    // Only create the project file, for scripts that don't need an editor
+    if no_open {
+        let created = edit::prepare_project_file(&project_name, &project_file, &extension, None)?;
+        if !no_check {
+            edit::check_project_file(config, &project_name, &project_file, args, session)?;
+        }
+
+        if created {
+            println!("Project {:?} created at {:?}.", project_name, project_file);
+        } else {
+            println!(
+                "Project {:?} already exists at {:?}.",
+                project_name, project_file
+            );
+        }
+        return Ok(());
+    }
+
     edit::open_in_editor(
         config,
         &project_name,
@@ -187,6 +842,7 @@ pub fn edit_project(
         None,
         no_check,
         args,
+        session,
     )
 }
 
@@ -226,32 +882,114 @@ pub fn remove_project(
     Ok(())
 }
 
-pub fn list_projects(config: &Config) -> Result<(), Box<dyn error::Error>> {
+pub fn list_projects(
+    config: &Config,
+    sessions: bool,
+    dirty: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    // Running sessions, along with the project they were started from
+    if sessions {
+        let entries = registry::get_entries(config)?;
+        for session_name in ui::get_running_sessions(config) {
+            let project_name = entries
+                .iter()
+                .find(|entry| entry.session_name == session_name)
+                .map_or("-", |entry| entry.project_name.as_str());
+            println!("{}\t{}", session_name, project_name);
+        }
+
+        return Ok(());
+    }
+
+    // Projects that fail to load or to check, along with the first line of their error
+    if dirty {
+        for (project_name, err) in list::get_dirty_projects(config)? {
+            println!(
+                "{}\t{}",
+                project_name,
+                err.lines().next().unwrap_or_default()
+            );
+        }
+
+        return Ok(());
+    }
+
     let data_dir = config.get_projects_dir("")?;
 
-    let projects = list::get_projects(data_dir)?;
+    let projects = list::get_projects(config, data_dir)?;
     println!("{}", projects.join("\n"));
 
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn freeze_project(
-    config: &Config,
-    stdout: bool,
-    project_name: Option<&str>,
-    extension: Option<&str>,
-    editor: &str,
-    no_input: bool,
-    no_check: bool,
-    args: &[&str],
-) -> Result<(), Box<dyn error::Error>> {
-    let project = freeze::get_project(config)?;
-    let as_json = matches!(&extension, Some(ext) if ext.to_lowercase() == "json");
-    let content = project.serialize_compact(as_json)?;
+pub fn print_menu(config: &Config, title: &str, exec: bool) -> Result<(), Box<dyn error::Error>> {
+    let mut projects = list::get_projects(config, config.get_projects_dir("")?)?;
+    projects.sort();
 
-    if stdout {
-        println!("{}", content);
+    let airmux = env::current_exe()?;
+    let menu_args = menu::get_menu_args(config, &airmux.to_string_lossy(), title, &projects);
+    let menu_args: Vec<&str> = menu_args.iter().map(AsRef::as_ref).collect();
+
+    if !exec {
+        println!("{}", tmux_join(&menu_args));
+        return Ok(());
+    }
+
+    let (tmux_command, tmux_args) = config.get_tmux_command(&menu_args)?;
+    let status = Command::new(tmux_command).args(tmux_args).status()?;
+    ensure!(
+        status.success(),
+        TmuxFailed {
+            exit_code: status.code().unwrap_or(1)
+        }
+    );
+
+    Ok(())
+}
+
+pub fn list_recent_projects(config: &Config, count: usize) -> Result<(), Box<dyn error::Error>> {
+    let now = history::timestamp();
+
+    for entry in history::get_entries(config)?.into_iter().take(count) {
+        let elapsed = utils::format_elapsed(now.saturating_sub(entry.timestamp));
+        println!("{}\t{}", entry.project_name, elapsed);
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn freeze_project(
+    config: &Config,
+    stdout: bool,
+    project_name: Option<&str>,
+    extension: Option<&str>,
+    format: Option<&str>,
+    editor: &str,
+    no_input: bool,
+    no_check: bool,
+    verify: bool,
+    with_comments: bool,
+    args: &[&str],
+    session: Option<&str>,
+) -> Result<(), Box<dyn error::Error>> {
+    let session_target = session.map(|session| format!("={}:", config.get_session_name(session)));
+    let project = freeze::get_project(config, session_target.as_deref())?;
+    let format = match format {
+        Some(format) => FileFormat::from_name(format),
+        None => FileFormat::from_name(extension.unwrap_or_default()),
+    };
+    let mut content = project.serialize_compact(&format)?;
+    if verify {
+        verify_round_trip(&project, &content)?;
+    }
+    if with_comments {
+        ensure!(format == FileFormat::Yaml, CommentsRequireYaml);
+        content = freeze::add_comments(&content);
+    }
+
+    if stdout {
+        println!("{}", content);
         return Ok(());
     }
 
@@ -263,9 +1001,24 @@ pub fn freeze_project(
             .map_or_else(|| String::from("yml"), |e| e.to_string_lossy().to_string()),
     };
 
-    edit::check_supported_extension(&extension)?;
+    edit::check_supported_extension(config, &extension)?;
     let project_file = project_file.with_extension(&extension);
 
+    // Files that define several sessions only get the entry of the frozen session replaced
+    let current_content = fs::read_to_string(&project_file).unwrap_or_default();
+    if project::get_sessions(&current_content)?.is_some() {
+        ensure!(
+            !with_comments,
+            CannotFreezeInto {
+                project_file,
+                reason: "comments cannot be added to files that define several sessions"
+            }
+        );
+
+        let format = FileFormat::from_name(&extension);
+        content = autofreeze::replace_session(&project_file, &current_content, &project, &format)?;
+    }
+
     if project_file.exists()
         && !no_input
         && !utils::prompt_confirmation(
@@ -289,1133 +1042,6069 @@ pub fn freeze_project(
         Some(&content),
         no_check,
         args,
+        session,
     )
 }
 
-mod project {
-    use super::*;
-
-    pub fn get_filename(
-        config: &Config,
-        project_name: Option<&str>,
-        project_file: Option<&str>,
-    ) -> Result<(String, PathBuf), Box<dyn error::Error>> {
-        // If the project file is supplied, use it directly
-        if let Some(project_file) = project_file {
-            // Return an empty project file if reading from stdin
-            if project_file == "-" {
-                // If reading project from stdin, project_name must be explicitly set
-                ensure!(project_name.is_some(), ProjectNameEmpty);
+// Makes a session created by hand the session of a project, optionally saving its layout first
+pub fn adopt_session(
+    config: &Config,
+    session_name: &str,
+    project_name: &str,
+    freeze: bool,
+    extension: Option<&str>,
+    no_input: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    let session_id =
+        freeze::get_tmux_value(config, "session_id", Some(&format!("={}:", session_name)))?;
+    ensure!(!session_id.is_empty(), SessionDoesNotExist { session_name });
+
+    let (project_name, mut project_file) = project::get_filename(config, Some(project_name), None)?;
+
+    if freeze {
+        let extension = match extension {
+            Some(extension) => extension.to_lowercase(),
+            None => project_file
+                .extension()
+                .map_or_else(|| String::from("yml"), |e| e.to_string_lossy().to_string()),
+        };
+        edit::check_supported_extension(config, &extension)?;
+        project_file = project_file.with_extension(&extension);
+
+        if project_file.exists()
+            && !no_input
+            && !utils::prompt_confirmation(
+                &format!(
+                    "Project {:?} already exists, are you sure you want to override it?",
+                    project_name
+                ),
+                false,
+            )?
+        {
+            println!("Aborted.");
+            return Ok(());
+        }
 
-                let project_name = project_name.unwrap();
-                ensure!(!project_name.is_empty(), ProjectNameEmpty);
+        let project = freeze::get_project(config, Some(&session_id))?;
+        let content = project.serialize_compact(&FileFormat::from_name(&extension))?;
+        edit::create_project(&project_name, &project_file, &extension, Some(&content))?;
+    }
 
-                return Ok((project_name.to_string(), PathBuf::new()));
-            }
+    ensure!(project_file.is_file(), ProjectDoesNotExist { project_name });
 
-            // extract filename from project file path if it's not set
-            let project_file = PathBuf::from(project_file);
-            if project_name.is_none() {
-                let filename = project_file.file_name();
-                ensure!(
-                    filename.is_some(),
-                    CannotExtractProjectName { project_file },
-                );
+    let project = project::load(config, &project_name, &project_file, None, &[])?;
+    project
+        .check()
+        .map_err(|err| Diagnostic::new(&project_file, err.to_string()))?;
 
-                let project_name = filename.unwrap().to_string_lossy().to_string();
-                return Ok((project_name, project_file));
-            }
-        }
+    let project_session_name = project
+        .session_name
+        .to_owned()
+        .ok_or(/* should never happen */ Error::SessionNameNotSet {})?;
 
-        if let Some(project_name) = project_name {
-            ensure!(!project_name.is_empty(), ProjectNameEmpty);
+    // The session takes the project's session name so the other commands can find it
+    let mut tmux_args = vec!["setenv", "-t", &session_id, PROJECT_VAR, &project_name];
+    if project_session_name != session_name {
+        tmux_args.extend_from_slice(&[";", "rename-session", "-t", &session_id]);
+        tmux_args.push(&project_session_name);
+    }
 
-            let projects_dir = config.get_projects_dir("")?;
-            let project_file = projects_dir.join(project_name);
-            let project_file = test_for_file_extensions(project_file)?;
+    let (tmux_command, tmux_args) = project.tmux_command(&tmux_args)?;
+    let status = Command::new(tmux_command).args(tmux_args).spawn()?.wait()?;
 
-            return Ok((project_name.to_string(), project_file));
+    ensure!(
+        status.success(),
+        TmuxFailed {
+            exit_code: status.code().unwrap_or(-1)
         }
+    );
 
-        // Try to find a local project file in current directory and all ancestors
-        let mut project_dir = env::current_dir()?;
-        loop {
-            let project_file = project_dir.join(PathBuf::from(".airmux"));
-
-            // Try for each supported file extension
-            for ext in FILE_EXTENSIONS {
-                let project_file = project_file.with_extension(ext);
-                if project_file.exists() && !project_file.is_dir() {
-                    let project_name = project_dir
-                        .file_name()
-                        .map_or_else(String::new, |name| name.to_string_lossy().to_string());
-                    return Ok((project_name, project_file));
-                }
-            }
+    registry::add_entry(config, &project_session_name, &project_name, &project_file)?;
 
-            // Move on to parent if nothing is found
-            match project_dir.parent() {
-                None => break,
-                Some(parent_dir) => project_dir = parent_dir.to_path_buf(),
-            }
-        }
+    println!(
+        "Session {:?} adopted by project {:?}.",
+        session_name, project_name
+    );
+    Ok(())
+}
 
-        // Fall back to local project file
-        let project_dir = env::current_dir()?;
-        let project_file = project_dir.join(".airmux.yml");
-        let project_name = project_dir.file_name().map_or_else(String::new, |name| {
-            // Remove dots and colons
-            name.to_string_lossy().replace(&['.', ':'][..], "")
-        });
+pub fn init_project(
+    config: &Config,
+    extension: Option<&str>,
+    editor: &str,
+    no_input: bool,
+    no_check: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    let extension = extension.unwrap_or(config.get_file_extensions()[0]);
+    edit::check_supported_extension(config, extension)?;
 
-        Ok((project_name, project_file))
-    }
+    let project_dir = env::current_dir()?;
+    let project_file = project_dir.join(".airmux").with_extension(extension);
+    let project_name = project_dir.file_name().map_or_else(String::new, |name| {
+        // Remove dots and colons
+        name.to_string_lossy().replace(&['.', ':'][..], "")
+    });
 
-    pub fn load<P>(
-        config: &Config,
-        project_name: &str,
-        project_file: P,
-        force_attach: Option<bool>,
-        args: &[&str],
-    ) -> Result<Project, Box<dyn error::Error>>
-    where
-        P: AsRef<Path>,
+    if project_file.exists()
+        && !no_input
+        && !utils::prompt_confirmation(
+            &format!(
+                "{:?} already exists, are you sure you want to override it?",
+                project_file
+            ),
+            false,
+        )?
     {
-        let project_yaml = if project_file.as_ref() == PathBuf::new() {
-            let mut buffer = String::new();
-            io::stdin().read_to_string(&mut buffer)?;
-            buffer
-        } else {
-            fs::read_to_string(project_file)?
-        };
+        println!("Aborted.");
+        return Ok(());
+    }
 
-        let project_yaml = env_with_context(&project_yaml, |s| env_context(s, args))
-            .map_err(|x| x.to_string())?
-            .to_string();
+    let project = Project {
+        session_name: Some(project_name.to_owned()),
+        windows: init::detect_windows(&project_dir),
+        ..Project::default()
+    };
+    let content = project.serialize_compact(&FileFormat::from_name(extension))?;
 
-        Ok(serde_yaml::from_str::<Project>(&project_yaml)?.prepare(
-            &config,
-            project_name,
-            force_attach,
-        ))
-    }
+    edit::open_in_editor(
+        config,
+        &project_name,
+        project_file,
+        extension,
+        editor,
+        Some(&content),
+        no_check,
+        &[],
+        None,
+    )
+}
 
-    pub fn env_context(s: &str, args: &[&str]) -> Result<Option<String>, Box<dyn error::Error>> {
-        // Check if it's a number and that it's > 0 and <= args.len()
-        if let Ok(arg_index) = s.parse::<usize>() {
-            if arg_index > 0 && arg_index <= args.len() {
-                return Ok(Some(args[arg_index - 1].replace("\\", "\\\\")));
-            }
-        }
+pub fn run_ui(config: &Config, editor: &str) -> Result<(), Box<dyn error::Error>> {
+    let term = Term::stdout();
+    ensure!(term.features().is_attended(), UiNeedsTerminal);
 
-        // Fallback to env vars
-        Ok(env::var(s).ok().map(|s| s.replace("\\", "\\\\")))
-    }
+    let mut selected = 0;
+    let mut message = String::new();
+    term.hide_cursor()?;
 
-    pub fn test_for_file_extensions<P>(path: P) -> Result<PathBuf, Box<dyn error::Error>>
-    where
-        P: AsRef<Path>,
-    {
-        let path = path.as_ref();
+    loop {
+        let entries = ui::get_entries(config)?;
+        selected = selected.min(entries.len().saturating_sub(1));
 
-        // If the path already contains an extension, use it directly
-        if let Some(extension) = path.extension() {
-            let extension = extension.to_string_lossy();
+        term.clear_screen()?;
+        ui::draw(&term, &entries, selected, &message)?;
+        message.clear();
 
-            edit::check_supported_extension(&extension)?;
-            return Ok(path.to_path_buf());
-        }
+        let key = term.read_key()?;
+        let project_name = match entries.get(selected) {
+            Some(entry) => entry.project_name.as_str(),
+            None => "",
+        };
 
-        // Loop over extensions and try to file an existing file to reuse
-        for extension in FILE_EXTENSIONS {
-            let filename = path.with_extension(extension);
-            if filename.exists() && !filename.is_dir() {
-                return Ok(filename);
+        // Give the terminal back to tmux or the editor while the action runs
+        let result = match key {
+            Key::ArrowUp | Key::Char('k') => {
+                selected = selected.saturating_sub(1);
+                Ok(())
             }
-        }
+            Key::ArrowDown | Key::Char('j') => {
+                selected = (selected + 1).min(entries.len().saturating_sub(1));
+                Ok(())
+            }
+            Key::Char('q') | Key::Escape => break,
+            _ if project_name.is_empty() => Ok(()),
+            Key::Enter | Key::Char('s') | Key::Char('d') => {
+                term.clear_screen()?;
+                term.show_cursor()?;
+                let force_attach = if key == Key::Char('d') {
+                    Some(false)
+                } else {
+                    None
+                };
+                start_project(
+                    config,
+                    Some(project_name),
+                    None,
+                    force_attach,
+                    false,
+                    false,
+                    false,
+                    &[],
+                    false,
+                    false,
+                    false,
+                    false,
+                    false,
+                    false,
+                    false,
+                    false,
+                    None,
+                    None,
+                    None,
+                )
+            }
+            Key::Char('x') => kill_project(config, Some(project_name), None, false, &[], None),
+            Key::Char('e') => {
+                term.clear_screen()?;
+                term.show_cursor()?;
+                edit_project(
+                    config,
+                    Some(project_name),
+                    None,
+                    None,
+                    editor,
+                    false,
+                    false,
+                    &[],
+                    None,
+                )
+            }
+            Key::Char('f') => {
+                term.clear_screen()?;
+                term.show_cursor()?;
+                freeze_project(
+                    config,
+                    false,
+                    Some(project_name),
+                    None,
+                    None,
+                    editor,
+                    false,
+                    false,
+                    false,
+                    false,
+                    &[],
+                    None,
+                )
+            }
+            _ => Ok(()),
+        };
 
-        // If no file was found, fall back to the first extension in the list
-        Ok(path.with_extension(FILE_EXTENSIONS[0]))
+        term.hide_cursor()?;
+        if let Err(err) = result {
+            message = format!("error: {}", err);
+        }
     }
+
+    term.clear_screen()?;
+    term.show_cursor()?;
+    Ok(())
 }
 
-mod source {
-    use super::*;
+// Downloads a project file to the remote projects directory once the user approves its commands
+pub fn fetch_project(
+    config: &Config,
+    url: &str,
+    project_name: Option<&str>,
+    no_input: bool,
+    args: &[&str],
+) -> Result<Option<(String, PathBuf)>, Box<dyn error::Error>> {
+    let project_name = match project_name {
+        Some(project_name) => project_name.to_string(),
+        None => fetch::get_project_name(url).ok_or(Error::CannotExtractProjectNameFromUrl {
+            url: url.to_string(),
+        })?,
+    };
 
-    pub fn exec_tmux_2(
-        project: &Project,
-        source: &str,
-    ) -> Result<ExitStatus, Box<dyn error::Error>> {
-        // Save the source to a temporary file
-        let mut source_file = NamedTempFile::new()?;
-        source_file.write_all(source.as_bytes())?;
-        let source_file_path = source_file.into_temp_path();
+    let content = fetch::download(url)?;
+    let extension = match project::detect_format(&content) {
+        FileFormat::Json => "json",
+        _ => "yml",
+    };
 
-        // Source our tmux config file
-        let (tmux_command, tmux_args) =
-            project.tmux_command(&["source", &source_file_path.to_string_lossy()])?;
+    let remote_dir = config.get_config_dir(REMOTE_PROJECTS_DIR)?;
+    let project_file = remote_dir.join(&project_name).with_extension(extension);
+    if let Some(parent) = project_file.parent() {
+        mkdirp(parent)?;
+    }
+    fs::write(&project_file, content)?;
 
-        let mut command = Command::new(tmux_command);
-        command.args(tmux_args);
+    let approved =
+        project::load(config, &project_name, &project_file, None, args).and_then(|project| {
+            let commands = fetch::get_commands(&project);
+            if no_input || commands.is_empty() {
+                return Ok(true);
+            }
 
-        if let Some(path) = &project.working_dir {
-            if path.is_dir() {
-                command.current_dir(path);
+            println!(
+                "Project {:?} from {} runs the following commands:",
+                project_name, url
+            );
+            for command in commands {
+                println!("  {}", command);
             }
-        }
 
-        let mut child = command.spawn()?;
+            utils::prompt_confirmation("Start it?", false)
+        });
 
-        // Wait until tmux completely finished processing input
-        Ok(child.wait()?)
+    match approved {
+        Ok(true) => Ok(Some((project_name, project_file))),
+        Ok(false) => {
+            fs::remove_file(&project_file)?;
+            println!("Aborted.");
+            Ok(None)
+        }
+        Err(err) => {
+            fs::remove_file(&project_file)?;
+            Err(err)
+        }
     }
+}
 
-    pub fn exec_tmux_3(
-        project: &Project,
-        source: &str,
-    ) -> Result<ExitStatus, Box<dyn error::Error>> {
-        // Source our tmux config file
-        let (tmux_command, tmux_args) = project.tmux_command(&["source", "-"])?;
-
-        let mut command = Command::new(tmux_command);
-        command.args(tmux_args).stdin(Stdio::piped());
-
-        if let Some(path) = &project.working_dir {
-            if path.is_dir() {
-                command.current_dir(path);
-            }
-        }
+pub fn migrate_project(
+    config: &Config,
+    project_name: Option<&str>,
+    project_file: Option<&str>,
+    stdout: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    let (project_name, project_file) = project::get_filename(config, project_name, project_file)?;
+    ensure!(project_file.is_file(), ProjectDoesNotExist { project_name });
 
-        let mut child = command.spawn()?;
-        child
-            .stdin
-            .as_mut()
-            .ok_or(Error::CannotPipeToTmux)?
-            .write_all(source.as_bytes())?;
+    let content = fs::read_to_string(&project_file)?;
+    let value = serde_yaml::from_str::<Value>(&content)
+        .map_err(|err| Diagnostic::from_yaml_error(&project_file, &content, err))?;
 
-        // Wait until tmux completely finished processing input
-        Ok(child.wait()?)
-    }
+    let version = migrate::get_version(&value);
+    ensure!(
+        version <= SCHEMA_VERSION,
+        UnsupportedProjectVersion { version }
+    );
 
-    pub fn generate(project: &Project, verbose: bool) -> Result<String, Box<dyn error::Error>> {
-        let tmux_command = project.tmux(&[] as &[&str])?;
-        let tmux_command = &tmux_command;
+    let up_to_date = version == SCHEMA_VERSION && Project::deprecated_fields(&value).is_empty();
+    let content = if up_to_date {
+        content
+    } else if project::detect_format(&content) == FileFormat::Json {
+        migrate::migrate_json(&content)?
+    } else {
+        migrate::migrate_yaml(&content)
+    };
 
-        let session_name = project.session_name.to_owned().unwrap();
-        let session_name = &session_name;
-        let session_name_quoted = &tmux_quote(session_name);
+    if stdout {
+        print!("{}", content);
+    } else if up_to_date {
+        println!("Project {:?} is already up to date.", project_name);
+    } else {
+        fs::write(&project_file, content)?;
+        println!(
+            "Project {:?} migrated to version {}.",
+            project_name, SCHEMA_VERSION
+        );
+    }
 
-        let mut source_commands = Vec::new();
+    Ok(())
+}
 
-        // Clean up potentially lingering tmux env vars
-        source_commands.push(String::from("setenv -gu __AIRMUX_SESSION_CREATED"));
-        source_commands.push(String::from("setenv -gu __AIRMUX_SESSION_UPDATED"));
+pub fn import_projects(
+    config: &Config,
+    save_file: Option<&str>,
+    session_name: Option<&str>,
+    extension: Option<&str>,
+    stdout: bool,
+    no_input: bool,
+    verify: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    let save_file = match save_file {
+        Some(save_file) => PathBuf::from(save_file),
+        None => import::get_resurrect_file(),
+    };
 
-        // Assume that the tmux session will be freshly attached until proven otherwise
-        source_commands.push(String::from("setenv -g __AIRMUX_SESSION_ATTACHED 1"));
+    let content = fs::read_to_string(&save_file)?;
+    let mut projects = import::parse_resurrect(&content);
 
-        // on_start commands
-        if !project.on_start.is_empty() {
-            source_commands.push(tmux_join(&[
-                "run",
-                &project
-                    .on_start
-                    .join("; ")
-                    .replace("__TMUX__", tmux_command),
-            ]));
+    if let Some(session_name) = session_name {
+        projects.retain(|project| project.session_name.as_deref() == Some(session_name));
+        ensure!(
+            !projects.is_empty(),
+            ImportSessionNotFound {
+                session_name,
+                path: save_file
+            }
+        );
+    }
+    ensure!(!projects.is_empty(), NothingToImport { path: save_file });
+
+    let extension = extension
+        .unwrap_or(config.get_file_extensions()[0])
+        .to_lowercase();
+    edit::check_supported_extension(config, &extension)?;
+    let format = FileFormat::from_name(&extension);
+
+    for project in projects {
+        let content = project.serialize_compact(&format)?;
+        if verify {
+            verify_round_trip(&project, &content)?;
         }
 
-        // create session if it does not exist
-        {
-            let if_command = format!(
-                "! {} | {}",
-                project.tmux(&["ls", "-F", "##S"])?,
-                tmux_join(&["grep", "-Fx", session_name]),
-            );
+        if stdout {
+            println!("{}", content);
+            continue;
+        }
 
-            let mut commands = vec![];
+        let (project_name, project_file) =
+            project::get_filename(config, project.session_name.as_deref(), None)?;
+        let project_file = project_file.with_extension(&extension);
+
+        if project_file.exists()
+            && !no_input
+            && !utils::prompt_confirmation(
+                &format!(
+                    "Project {:?} already exists, are you sure you want to override it?",
+                    project_name
+                ),
+                false,
+            )?
+        {
+            println!("Skipped {:?}.", project_name);
+            continue;
+        }
 
-            // Create new session
-            commands.push(tmux_join(&["new", "-s", session_name, "-d"]));
+        edit::create_project(&project_name, &project_file, &extension, Some(&content))?;
+        println!("Project {:?} imported.", project_name);
+    }
 
-            // Move the first window away temporarily
-            commands.push(tmux_join(&[
-                "movew",
-                "-s",
-                &format!("{}:^", session_name),
-                "-t",
-                &format!("{}:999999", session_name),
-            ]));
+    Ok(())
+}
 
-            // on_first_start commands
-            if !project.on_first_start.is_empty() {
-                commands.push(tmux_join(&[
-                    "run",
-                    &project
-                        .on_first_start
-                        .join("; ")
-                        .replace("__TMUX__", tmux_command)
-                        .replace("__SESSION__", session_name_quoted),
-                ]))
-            }
+// Guards against the compact serializer dropping fields that the project sets
+pub fn verify_round_trip(project: &Project, content: &str) -> Result<(), Box<dyn error::Error>> {
+    let fields = project.compact_round_trip_diff(content)?;
+    ensure!(fields.is_empty(), RoundTripFailed { fields });
 
-            // on_exit commands
-            if !project.on_exit.is_empty() {
-                let run_shell_command = tmux_join(&[
-                    "run",
-                    &project.on_exit.join("; ").replace("__TMUX__", tmux_command),
-                ]);
+    Ok(())
+}
 
-                commands.push(tmux_join(&[
-                    "set-hook",
-                    "-t",
-                    session_name,
-                    "client-detached",
-                    &run_shell_command,
-                ]));
+// Freezes running sessions of known projects that set autofreeze back into their project files
+pub fn autofreeze_projects(
+    config: &Config,
+    interval: Duration,
+    once: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    loop {
+        for entry in ui::get_entries(config)? {
+            if !entry.running {
+                continue;
             }
 
-            // on_stop (+on_exit) commands
-            if !project.on_exit.is_empty() || !project.on_stop.is_empty() {
-                let command_list = project
-                    .on_exit
-                    .to_owned()
-                    .into_iter()
-                    .chain(project.on_stop.to_owned().into_iter())
-                    .chain(iter::once(project.tmux(&[
-                        "set-hook",
-                        "-gu",
-                        "session-closed[#{session_created}]",
-                    ])?))
-                    .collect::<Vec<String>>();
+            match autofreeze::freeze_session(config, &entry.project_name, &entry.session_name) {
+                Ok(true) => println!("Project {:?} updated.", entry.project_name),
+                Ok(false) => {}
+                Err(err) => eprintln!(
+                    "warning: cannot freeze project {:?}: {}",
+                    entry.project_name, err
+                ),
+            }
+        }
 
-                let if_command = format!(
-                    "! {} | {}",
-                    project.tmux(&["ls", "-F", "####S"])?,
-                    tmux_join(&["grep", "-Fx", session_name]),
-                );
+        if once {
+            return Ok(());
+        }
 
-                let run_shell_command = tmux_join(&[
-                    "run",
-                    &command_list.join("; ").replace("__TMUX__", tmux_command),
-                ]);
+        thread::sleep(interval);
+    }
+}
 
-                let hook_command = tmux_join(&["if", &if_command, &run_shell_command]);
+// Keeps the sessions of projects marked with keep_alive running, restarting them when they die
+pub fn run_daemon(config: &Config, interval: Duration) -> Result<(), Box<dyn error::Error>> {
+    loop {
+        for entry in ui::get_entries(config)? {
+            if !entry.running && project::has_flag(config, &entry.project_name, "keep_alive") {
+                project::start_detached(config, &entry);
+            }
+        }
 
-                let set_hook_command = project.tmux(&[
-                    "set-hook",
-                    "-g",
-                    "session-closed[#{session_created}]",
-                    &hook_command,
-                ])?;
+        thread::sleep(interval);
+    }
+}
 
-                let run_shell_set_hook_command =
-                    tmux_join(&["run", "-t", session_name, &set_hook_command]);
+// Speaks the language server protocol over stdin/stdout until the client asks it to exit
+pub fn run_lsp(config: &Config) -> Result<(), Box<dyn error::Error>> {
+    let stdin = io::stdin();
+    lsp::serve(config, &mut stdin.lock(), &mut io::stdout())
+}
 
-                // Failible on tmux version that don't support it
-                commands.push(tmux_join(&[
-                    "run",
-                    &format!(
-                        "{} || true",
-                        project.tmux(&["set", "-g", "exit-empty", "off"])?,
-                    ),
-                ]));
+// Starts all the projects marked with autostart, meant to be called once at login
+pub fn boot_projects(config: &Config) -> Result<(), Box<dyn error::Error>> {
+    for entry in ui::get_entries(config)? {
+        if !entry.running && project::has_flag(config, &entry.project_name, "autostart") {
+            project::start_detached(config, &entry);
+        }
+    }
 
-                commands.push(run_shell_set_hook_command);
-            }
+    Ok(())
+}
 
-            // Set whether the session was created or not
-            commands.push(String::from("setenv -g __AIRMUX_SESSION_CREATED 1"));
+// Removes what interrupted starts leave behind: stale locks, the dummy session,
+// global variables and registry entries of sessions or projects that are gone
+pub fn clean_state(config: &Config, dry_run: bool) -> Result<(), Box<dyn error::Error>> {
+    let action = if dry_run { "would remove" } else { "removed" };
 
-            // Unset the session attached variable
-            commands.push(String::from("setenv -gu __AIRMUX_SESSION_ATTACHED"));
+    let running_sessions = ui::get_running_sessions(config);
+    let (stale_locks, starting) = clean::get_locks(config)?;
+    for path in stale_locks {
+        if !dry_run {
+            fs::remove_file(&path)?;
+        }
+        println!("{} stale lock {}", action, path.to_string_lossy());
+    }
 
-            source_commands.push(tmux_join(&["if", &if_command, &commands.join("; ")]));
+    // The dummy session and global variables are still in use while a session is starting
+    if starting {
+        eprintln!("warning: a session is being started, leaving the tmux server alone");
+    } else {
+        if running_sessions
+            .iter()
+            .any(|name| name == DUMMY_SESSION_NAME)
+        {
+            if !dry_run {
+                clean::run_tmux(config, &["kill-session", "-t", DUMMY_SESSION_NAME])?;
+            }
+            println!("{} session {}", action, DUMMY_SESSION_NAME);
         }
 
-        // on_restart commands
-        if !project.on_restart.is_empty() {
-            source_commands.push(tmux_join(&[
-                "if",
-                "-F",
-                "#{__AIRMUX_SESSION_ATTACHED}",
-                &tmux_join(&[
-                    "run",
-                    &project
-                        .on_restart
-                        .join("; ")
-                        .replace("__TMUX__", tmux_command)
-                        .replace("__SESSION__", session_name_quoted),
-                ]),
-            ]));
+        let output = clean::tmux_output(config, &["showenv", "-g"]);
+        for variable in clean::get_global_variables(&output) {
+            if !dry_run {
+                clean::run_tmux(config, &["setenv", "-gu", &variable])?;
+            }
+            println!("{} global variable {}", action, variable);
         }
+    }
 
-        // window base index
-        source_commands.push(tmux_join(&[
-            "set",
-            "-s",
-            "-t",
-            session_name,
-            "base-index",
-            &project.window_base_index.to_string(),
-        ]));
+    let (entries, stale_entries) =
+        clean::split_stale_entries(registry::get_entries(config)?, &running_sessions);
+    if !stale_entries.is_empty() && !dry_run {
+        registry::write_entries(config, &entries)?;
+    }
+    for entry in stale_entries {
+        println!(
+            "{} registry entry {} ({})",
+            action, entry.session_name, entry.project_name
+        );
+    }
 
-        // Setup windows
-        for (window_index, window) in project.windows.iter().enumerate() {
-            let window_tmux_index = window_index + project.window_base_index;
-            let target_window = &format!("{}:{}", session_name, window_tmux_index);
+    Ok(())
+}
 
-            let target_window_quoted = &tmux_quote(target_window);
+// Prints, or installs, a user service that starts the project at login
+pub fn install_service(
+    config: &Config,
+    project_name: Option<&str>,
+    install: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    let (project_name, project_file) = project::get_filename(config, project_name, None)?;
+    ensure!(project_file.is_file(), ProjectDoesNotExist { project_name });
 
-            let if_command = format!(
-                "! {} | {}",
-                project.tmux(&["lsw", "-t", session_name, "-F", "##I",])?,
-                tmux_join(&["grep", "-Fx", &window_tmux_index.to_string()])
-            );
+    let airmux = env::current_exe()?;
+    let airmux = airmux.to_string_lossy();
+    let start_command = service::get_command(config, &airmux, "start", &project_name);
+    let kill_command = service::get_command(config, &airmux, "kill", &project_name);
+
+    let (content, service_file, enable_commands) = if cfg!(target_os = "macos") {
+        let service_file = PathBuf::from(shellexpand::tilde("~/Library/LaunchAgents").to_string())
+            .join(format!("{}.plist", service::get_label(&project_name)));
+        let content = service::get_launchd_plist(&project_name, &start_command);
+        let service_path = service_file.to_string_lossy().to_string();
+
+        let enable_commands = vec![vec![
+            String::from("launchctl"),
+            String::from("load"),
+            String::from("-w"),
+            service_path,
+        ]];
+
+        (content, service_file, enable_commands)
+    } else {
+        let service_file = PathBuf::from(shellexpand::tilde("~/.config/systemd/user").to_string())
+            .join(format!("{}.service", service::get_label(&project_name)));
+        let content = service::get_systemd_unit(&project_name, &start_command, &kill_command);
+        let service_name = service_file
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
 
-            let mut new_window_command = vec!["neww", "-d", "-t", target_window];
+        let enable_commands = vec![
+            vec![
+                String::from("systemctl"),
+                String::from("--user"),
+                String::from("daemon-reload"),
+            ],
+            vec![
+                String::from("systemctl"),
+                String::from("--user"),
+                String::from("enable"),
+                service_name,
+            ],
+        ];
+
+        (content, service_file, enable_commands)
+    };
 
-            let mut found_working_dir = false;
-            let mut working_dir = String::new();
+    if !install {
+        print!("{}", content);
+        return Ok(());
+    }
 
-            if !window.panes.is_empty() {
-                if let Some(wd) = &window.panes[0].working_dir {
-                    working_dir = wd.to_string_lossy().to_string();
-                    found_working_dir = true;
-                }
-            }
-            if !found_working_dir {
-                if let Some(wd) = &window.working_dir {
-                    working_dir = wd.to_string_lossy().to_string();
-                    found_working_dir = true;
-                }
-            }
-            if !found_working_dir {
-                if let Some(wd) = &project.working_dir {
-                    working_dir = wd.to_string_lossy().to_string();
-                    found_working_dir = true;
-                }
-            }
+    if let Some(parent) = service_file.parent() {
+        mkdirp(parent)?;
+    }
+    fs::write(&service_file, content)?;
 
-            if found_working_dir {
-                new_window_command.splice(2..2, vec!["-c", &working_dir].into_iter());
+    for command in enable_commands {
+        let status = Command::new(&command[0]).args(&command[1..]).status()?;
+        ensure!(
+            status.success(),
+            ServiceCommandFailed {
+                command: shell_words::join(command)
             }
+        );
+    }
 
-            let mut window_commands = Vec::new();
+    println!("Service installed at {:?}.", service_file);
+    Ok(())
+}
 
-            // Create the window
-            window_commands.push(tmux_join(&new_window_command));
+mod project {
+    use super::*;
 
-            // Pane base index for this window
-            window_commands.push(tmux_join(&[
-                "set",
-                "-s",
-                "-t",
-                target_window,
-                "pane-base-index",
-                &project.pane_base_index.to_string(),
-            ]));
+    // Reads the flag without loading the whole project, so a broken project file only fails when started
+    pub fn has_flag(config: &Config, project_name: &str, flag: &str) -> bool {
+        get_filename(config, Some(project_name), None)
+            .ok()
+            .and_then(|(_, project_file)| fs::read_to_string(project_file).ok())
+            .and_then(|content| serde_yaml::from_str::<Value>(&content).ok())
+            .is_some_and(|value| value.get(flag) == Some(&Value::Bool(true)))
+    }
 
-            // Rename the window (if a name is set)
-            // Renaming is a separate step so that we could update existing windows
-            if let Some(window_name) = &window.name {
-                window_commands.push(tmux_join(&["renamew", "-t", target_window, window_name]));
-            }
+    // Failures are reported without stopping, so one broken project doesn't hold the others back
+    pub fn start_detached(config: &Config, entry: &ui::Entry) {
+        let result = start_project(
+            config,
+            Some(&entry.project_name),
+            None,
+            Some(false),
+            false,
+            false,
+            false,
+            &[],
+            false,
+            false,
+            false,
+            true,
+            false,
+            false,
+            false,
+            false,
+            None,
+            None,
+            None,
+        );
 
-            // Window on_create commands
-            if !window.on_create.is_empty() {
-                window_commands.push(tmux_join(&[
+        match result {
+            Ok(_) => println!("Session {:?} started.", entry.session_name),
+            Err(err) => eprintln!(
+                "warning: cannot start project {:?}: {}",
+                entry.project_name, err
+            ),
+        }
+    }
+
+    pub fn get_filename(
+        config: &Config,
+        project_name: Option<&str>,
+        project_file: Option<&str>,
+    ) -> Result<(String, PathBuf), Box<dyn error::Error>> {
+        // If the project file is supplied, use it directly
+        if let Some(project_file) = project_file {
+            // Return an empty project file if reading from stdin
+            if project_file == "-" {
+                // If reading project from stdin, project_name must be explicitly set
+                ensure!(project_name.is_some(), ProjectNameEmpty);
+
+                let project_name = project_name.unwrap();
+                ensure!(!project_name.is_empty(), ProjectNameEmpty);
+
+                return Ok((project_name.to_string(), PathBuf::new()));
+            }
+
+            // extract filename from project file path if it's not set
+            let project_file = PathBuf::from(project_file);
+            match project_name {
+                None => {
+                    let filename = project_file.file_name();
+                    ensure!(
+                        filename.is_some(),
+                        CannotExtractProjectName { project_file },
+                    );
+
+                    let project_name = filename.unwrap().to_string_lossy().to_string();
+                    return Ok((project_name, project_file));
+                }
+                Some(project_name) => {
+                    ensure!(!project_name.is_empty(), ProjectNameEmpty);
+                    return Ok((project_name.to_string(), project_file));
+                }
+            }
+        }
+
+        if let Some(project_name) = project_name {
+            ensure!(!project_name.is_empty(), ProjectNameEmpty);
+
+            let projects_dir = config.get_projects_dir("")?;
+            let project_file = projects_dir.join(project_name);
+            let project_file = test_for_file_extensions(config, project_file)?;
+
+            return Ok((project_name.to_string(), project_file));
+        }
+
+        // Try to find a local project file in current directory and all ancestors
+        let mut project_dir = env::current_dir()?;
+        loop {
+            let project_file = project_dir.join(PathBuf::from(".airmux"));
+            if project_file.is_file() {
+                let project_name = project_dir
+                    .file_name()
+                    .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+                return Ok((project_name, project_file));
+            }
+
+            // Try for each supported file extension
+            for ext in config.get_file_extensions() {
+                let project_file = project_file.with_extension(ext);
+                if project_file.exists() && !project_file.is_dir() {
+                    let project_name = project_dir
+                        .file_name()
+                        .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+                    return Ok((project_name, project_file));
+                }
+            }
+
+            // Move on to parent if nothing is found
+            match project_dir.parent() {
+                None => break,
+                Some(parent_dir) => project_dir = parent_dir.to_path_buf(),
+            }
+        }
+
+        // Fall back to local project file
+        let project_dir = env::current_dir()?;
+        let project_file = project_dir.join(".airmux.yml");
+        let project_name = project_dir.file_name().map_or_else(String::new, |name| {
+            // Remove dots and colons
+            name.to_string_lossy().replace(&['.', ':'][..], "")
+        });
+
+        Ok((project_name, project_file))
+    }
+
+    pub fn load<P>(
+        config: &Config,
+        project_name: &str,
+        project_file: P,
+        force_attach: Option<bool>,
+        args: &[&str],
+    ) -> Result<Project, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        load_session(config, project_name, project_file, force_attach, args, None)
+    }
+
+    // Loads one of the sessions of project files that define several,
+    // files that define only one don't need it to be named
+    pub fn load_session<P>(
+        config: &Config,
+        project_name: &str,
+        project_file: P,
+        force_attach: Option<bool>,
+        args: &[&str],
+        session_name: Option<&str>,
+    ) -> Result<Project, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let project_yaml = read(&project_file, args)?;
+        if let (Some(sessions), None) = (get_sessions(&project_yaml)?, session_name) {
+            ensure!(
+                sessions.len() == 1,
+                SeveralSessions {
+                    session_names: sessions
+                        .into_iter()
+                        .map(|(name, _)| name)
+                        .collect::<Vec<String>>()
+                }
+            );
+        }
+
+        let mut projects = load_sessions(
+            config,
+            project_name,
+            project_file,
+            &project_yaml,
+            force_attach,
+            session_name,
+        )?;
+        Ok(projects.remove(0))
+    }
+
+    // Loads the named session of the (already read) project file, or all of its sessions
+    pub fn load_sessions<P>(
+        config: &Config,
+        project_name: &str,
+        project_file: P,
+        project_yaml: &str,
+        force_attach: Option<bool>,
+        session_name: Option<&str>,
+    ) -> Result<Vec<Project>, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let session_yamls = match (get_sessions(project_yaml)?, session_name) {
+            (Some(sessions), None) => sessions
+                .iter()
+                .map(|(_, session)| serde_yaml::to_string(session))
+                .collect::<Result<_, _>>()?,
+            (Some(sessions), session_name) => vec![select_session(sessions, session_name)?],
+            (None, Some(session_name)) => {
+                return Err(Error::SessionNotDefined {
+                    session_name: session_name.to_string(),
+                }
+                .into())
+            }
+            (None, None) => vec![project_yaml.to_string()],
+        };
+
+        session_yamls
+            .iter()
+            .map(|session_yaml| {
+                if let Ok(value) = serde_yaml::from_str::<Value>(session_yaml) {
+                    for (alias, field) in Project::deprecated_fields(&value) {
+                        eprintln!(
+                            "warning: field {:?} is deprecated, use {:?} instead (or run `airmux migrate`)",
+                            alias, field
+                        );
+                    }
+                }
+
+                parse(
+                    config,
+                    project_name,
+                    &project_file,
+                    session_yaml,
+                    force_attach,
+                )
+            })
+            .collect()
+    }
+
+    // Reads a project file, or stdin if its path is empty, and expands its variables
+    pub fn read<P>(project_file: P, args: &[&str]) -> Result<String, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let project_yaml = if project_file.as_ref() == PathBuf::new() {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            buffer
+        } else {
+            fs::read_to_string(&project_file)?
+        };
+
+        expand_env(project_yaml, args)
+    }
+
+    pub fn expand_env(
+        project_yaml: String,
+        args: &[&str],
+    ) -> Result<String, Box<dyn error::Error>> {
+        if !expands_env(&project_yaml) {
+            return Ok(project_yaml);
+        }
+
+        Ok(env_with_context(&project_yaml, |s| env_context(s, args))
+            .map_err(|x| x.to_string())?
+            .to_string())
+    }
+
+    // Named session entries of a project file
+    pub type Sessions = Vec<(String, Value)>;
+
+    // Session entries of (already env-expanded) project files that have a `sessions` list
+    pub fn get_sessions(project_yaml: &str) -> Result<Option<Sessions>, Box<dyn error::Error>> {
+        let mapping = match serde_yaml::from_str::<Value>(project_yaml) {
+            Ok(Value::Mapping(mapping)) => mapping,
+            _ => return Ok(None),
+        };
+        let sessions = match mapping.get(&Value::from("sessions")) {
+            Some(Value::Sequence(sessions)) => sessions,
+            Some(_) => return Err("sessions: expected a list of projects".into()),
+            None => return Ok(None),
+        };
+
+        // Defaults are read before the file is split into sessions
+        let other_fields: Vec<&str> = mapping
+            .iter()
+            .filter_map(|(field, _)| field.as_str())
+            .filter(|field| !["sessions", "defaults"].contains(field))
+            .collect();
+        if !other_fields.is_empty() {
+            return Err(format!(
+                "sessions: cannot be combined with other project fields ({})",
+                other_fields.join(", ")
+            )
+            .into());
+        }
+        if sessions.is_empty() {
+            return Err("sessions: expected at least one session".into());
+        }
+
+        sessions
+            .iter()
+            .enumerate()
+            .map(|(session_pos, session)| {
+                let session_name = session
+                    .get("session_name")
+                    .or_else(|| session.get("name"))
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("sessions: session #{} has no name", session_pos + 1))?;
+
+                Ok((session_name.to_string(), session.to_owned()))
+            })
+            .collect::<Result<_, Box<dyn error::Error>>>()
+            .map(Some)
+    }
+
+    pub fn select_session(
+        sessions: Sessions,
+        session_name: Option<&str>,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let session = match session_name {
+            Some(session_name) => sessions
+                .into_iter()
+                .find(|(name, _)| name == session_name)
+                .ok_or_else(|| Error::SessionNotDefined {
+                    session_name: session_name.to_string(),
+                })?,
+            None if sessions.len() == 1 => sessions.into_iter().next().unwrap(),
+            None => {
+                return Err(Error::SeveralSessions {
+                    session_names: sessions.into_iter().map(|(name, _)| name).collect(),
+                }
+                .into())
+            }
+        };
+
+        Ok(serde_yaml::to_string(&session.1)?)
+    }
+
+    // Parses and prepares the (already env-expanded) content of a project file
+    pub fn parse<P>(
+        config: &Config,
+        project_name: &str,
+        project_file: P,
+        project_yaml: &str,
+        force_attach: Option<bool>,
+    ) -> Result<Project, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let project_file = project_file.as_ref();
+        ensure!(!is_toml(project_yaml), TomlNotSupported);
+        let format = detect_format(project_yaml);
+
+        let project = match serde_yaml::from_str::<Project>(project_yaml) {
+            Ok(project) => project,
+            Err(_) if config.lenient => {
+                let (project, unknown_fields) = parse_lenient(project_yaml)
+                    .map_err(|err| Diagnostic::new(project_file, err.to_string()))?;
+
+                eprintln!(
+                    "warning: ignoring unknown fields: {}",
+                    unknown_fields.join(", ")
+                );
+
+                project
+            }
+            Err(err) => {
+                // JSON is valid YAML, but serde_json reports JSON syntax errors more accurately
+                let diagnostic = match serde_json::from_str::<Project>(project_yaml) {
+                    Err(json_err) if format == FileFormat::Json => {
+                        Diagnostic::from_json_error(project_file, project_yaml, json_err)
+                    }
+                    _ => Diagnostic::from_yaml_error(project_file, project_yaml, err),
+                };
+
+                return Err(diagnostic.into());
+            }
+        };
+
+        Ok(project.prepare(config, project_name, force_attach))
+    }
+
+    // Guesses the format of a project file from its content, regardless of its extension
+    pub fn detect_format(content: &str) -> FileFormat {
+        match first_line(content) {
+            Some(line) if line.starts_with('{') => FileFormat::Json,
+            _ => FileFormat::Yaml,
+        }
+    }
+
+    // TOML isn't supported, but its files get a clearer error than a YAML syntax error
+    pub fn is_toml(content: &str) -> bool {
+        match first_line(content) {
+            Some(line) => is_toml_table(line) || is_toml_key_value(line),
+            None => false,
+        }
+    }
+
+    fn first_line(content: &str) -> Option<&str> {
+        content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+    }
+
+    fn is_toml_table(line: &str) -> bool {
+        line.starts_with('[')
+            && line.ends_with(']')
+            && !line.contains(',')
+            && line
+                .trim_matches(|c| c == '[' || c == ']')
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_-.\" ".contains(c))
+    }
+
+    fn is_toml_key_value(line: &str) -> bool {
+        match line.split_once('=') {
+            Some((key, _)) => {
+                let key = key.trim();
+                !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || "_-.\"".contains(c))
+            }
+            None => false,
+        }
+    }
+
+    // Drops the unknown fields of the windows and their panes,
+    // then the unknown top-level fields one at a time until the project parses
+    pub fn parse_lenient(project_yaml: &str) -> Result<(Project, Vec<String>), serde_yaml::Error> {
+        let mut value = serde_yaml::from_str::<Value>(project_yaml)?;
+        let mut unknown_fields = vec![];
+
+        for key in &["windows", "window"] {
+            if let Some(windows) = value.get_mut(*key) {
+                strip_windows(windows, key, &mut unknown_fields);
+            }
+        }
+
+        loop {
+            let err = match serde_yaml::from_value::<Project>(value.clone()) {
+                Ok(project) => return Ok((project, unknown_fields)),
+                Err(err) => err,
+            };
+
+            let message = err.to_string();
+            let field = message
+                .strip_prefix("unknown field `")
+                .and_then(|rest| rest.split('`').next());
+
+            let removed = match (field, value.as_mapping_mut()) {
+                (Some(field), Some(mapping)) => mapping.remove(&Value::from(field)).is_some(),
+                _ => false,
+            };
+
+            match field {
+                Some(field) if removed => unknown_fields.push(String::from(field)),
+                _ => return Err(err),
+            }
+        }
+    }
+
+    fn strip_windows(windows: &mut Value, path: &str, unknown_fields: &mut Vec<String>) {
+        match windows {
+            Value::Sequence(windows) => {
+                for (index, window) in windows.iter_mut().enumerate() {
+                    strip_window(window, &format!("{}[{}]", path, index), unknown_fields);
+                }
+            }
+            window => strip_window(window, path, unknown_fields),
+        }
+    }
+
+    fn strip_window(window: &mut Value, path: &str, unknown_fields: &mut Vec<String>) {
+        let mapping = match window.as_mapping_mut() {
+            Some(mapping) => mapping,
+            None => return,
+        };
+
+        let name_key = get_name_key(mapping, &["name", "title"], Window::is_field);
+        strip_fields(
+            mapping,
+            name_key.as_ref(),
+            Window::is_field,
+            path,
+            unknown_fields,
+        );
+        strip_window_panes(mapping, path, unknown_fields);
+
+        // `- name: {definition}` and `- name: [panes]` hold the window under its name
+        if let Some(name_key) = &name_key {
+            let path = format!("{}.{}", path, name_key.as_str().unwrap_or("~"));
+            match mapping.get_mut(name_key) {
+                Some(Value::Mapping(definition)) => {
+                    strip_fields(definition, None, Window::is_field, &path, unknown_fields);
+                    strip_window_panes(definition, &path, unknown_fields);
+                }
+                Some(panes @ Value::Sequence(_)) => strip_panes(panes, &path, unknown_fields),
+                _ => {}
+            }
+        }
+    }
+
+    fn strip_window_panes(window: &mut Mapping, path: &str, unknown_fields: &mut Vec<String>) {
+        for key in &["panes", "pane"] {
+            if let Some(panes) = window.get_mut(&Value::from(*key)) {
+                strip_panes(panes, &format!("{}.{}", path, key), unknown_fields);
+            }
+        }
+    }
+
+    fn strip_panes(panes: &mut Value, path: &str, unknown_fields: &mut Vec<String>) {
+        match panes {
+            Value::Sequence(panes) => {
+                for (index, pane) in panes.iter_mut().enumerate() {
+                    strip_pane(pane, &format!("{}[{}]", path, index), unknown_fields);
+                }
+            }
+            pane => strip_pane(pane, path, unknown_fields),
+        }
+    }
+
+    fn strip_pane(pane: &mut Value, path: &str, unknown_fields: &mut Vec<String>) {
+        let mapping = match pane.as_mapping_mut() {
+            Some(mapping) => mapping,
+            None => return,
+        };
+
+        let name_key = get_name_key(
+            mapping,
+            &["name", "title", "commands", "command"],
+            Pane::is_field,
+        );
+        strip_fields(
+            mapping,
+            name_key.as_ref(),
+            Pane::is_field,
+            path,
+            unknown_fields,
+        );
+
+        // `- name: {definition}` holds the pane under its name
+        if let Some(name_key) = &name_key {
+            let path = format!("{}.{}", path, name_key.as_str().unwrap_or("~"));
+            if let Some(Value::Mapping(definition)) = mapping.get_mut(name_key) {
+                strip_fields(definition, None, Pane::is_field, &path, unknown_fields);
+            }
+        }
+    }
+
+    // The first key of a map names the window or pane, unless it's a field or the map is a definition
+    fn get_name_key(
+        mapping: &Mapping,
+        definition_keys: &[&str],
+        is_field: fn(&str) -> bool,
+    ) -> Option<Value> {
+        let is_definition = definition_keys
+            .iter()
+            .any(|key| mapping.contains_key(&Value::from(*key)));
+
+        mapping
+            .iter()
+            .next()
+            .map(|(key, _)| key.clone())
+            .filter(|key| !is_definition && !key.as_str().is_some_and(is_field))
+    }
+
+    // Removes the keys that are neither fields nor the name of the window or pane
+    fn strip_fields(
+        mapping: &mut Mapping,
+        name_key: Option<&Value>,
+        is_field: fn(&str) -> bool,
+        path: &str,
+        unknown_fields: &mut Vec<String>,
+    ) {
+        let unknown_keys: Vec<String> = mapping
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| Some(*key) != name_key)
+            .filter_map(Value::as_str)
+            .filter(|key| !is_field(key))
+            .map(String::from)
+            .collect();
+
+        for key in unknown_keys {
+            mapping.remove(&Value::from(key.as_str()));
+            unknown_fields.push(format!("{}.{}", path, key));
+        }
+    }
+
+    // Peeks at the defaults field before the file is expanded, since it can hold the arguments
+    // Broken defaults are reported once the whole file is parsed
+    pub fn get_defaults<P>(project_file: P) -> Defaults
+    where
+        P: AsRef<Path>,
+    {
+        #[derive(Deserialize)]
+        struct DefaultsSettings {
+            #[serde(default)]
+            defaults: Defaults,
+        }
+
+        fs::read_to_string(project_file)
+            .ok()
+            .and_then(|content| serde_yaml::from_str::<DefaultsSettings>(&content).ok())
+            .map(|settings| settings.defaults)
+            .unwrap_or_default()
+    }
+
+    // Peeks at the autofreeze field, projects that don't opt in are never written to
+    pub fn autofreezes(project_yaml: &str) -> bool {
+        #[derive(Deserialize)]
+        struct AutofreezeSettings {
+            #[serde(default)]
+            autofreeze: bool,
+        }
+
+        serde_yaml::from_str::<AutofreezeSettings>(project_yaml)
+            .map(|settings| settings.autofreeze)
+            .unwrap_or(false)
+    }
+
+    // Peeks at the expand_env field before the file is expanded
+    // Falls back to expanding if the file cannot be parsed as is
+    pub fn expands_env(project_yaml: &str) -> bool {
+        #[derive(Deserialize)]
+        struct EnvSettings {
+            expand_env: Option<bool>,
+        }
+
+        serde_yaml::from_str::<EnvSettings>(project_yaml)
+            .ok()
+            .and_then(|settings| settings.expand_env)
+            .unwrap_or(true)
+    }
+
+    pub fn env_context(s: &str, args: &[&str]) -> Result<Option<String>, Box<dyn error::Error>> {
+        // Check if it's a number and that it's > 0 and <= args.len()
+        if let Ok(arg_index) = s.parse::<usize>() {
+            if arg_index > 0 && arg_index <= args.len() {
+                return Ok(Some(args[arg_index - 1].replace("\\", "\\\\")));
+            }
+        }
+
+        // Fallback to env vars
+        Ok(env::var(s).ok().map(|s| s.replace("\\", "\\\\")))
+    }
+
+    pub fn test_for_file_extensions<P>(
+        config: &Config,
+        path: P,
+    ) -> Result<PathBuf, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        // If the path already contains an extension, use it directly
+        if let Some(extension) = path.extension() {
+            let extension = extension.to_string_lossy();
+
+            // The format of existing files is detected from their content
+            if path.is_file() {
+                return Ok(path.to_path_buf());
+            }
+
+            edit::check_supported_extension(config, &extension)?;
+            return Ok(path.to_path_buf());
+        }
+
+        // Loop over extensions and try to file an existing file to reuse
+        let extensions = config.get_file_extensions();
+        for extension in &extensions {
+            let filename = path.with_extension(extension);
+            if filename.exists() && !filename.is_dir() {
+                return Ok(filename);
+            }
+        }
+
+        // If no file was found, fall back to the first extension in the list
+        Ok(path.with_extension(extensions[0]))
+    }
+}
+
+mod source {
+    use super::*;
+
+    pub fn exec_tmux(
+        project: &Project,
+        source: &str,
+        profile: &mut profile::Profile,
+    ) -> Result<(), Box<dyn error::Error>> {
+        // Pane command timeouts and hook_timeout are run by the panes' shells using `timeout`
+        ensure!(
+            !uses_timeout_command(project) || utils::is_in_path("timeout"),
+            TimeoutCommandNotFound
+        );
+
+        // Some tmux versions close the tmux server if there are no running sessions
+        // This prevents us from running `tmux source`.
+        // So we create a dummy tmux session that we'll discard at the end
+        let dummy_session = TmuxDummySession::new(project)?;
+        profile.step("dummy session");
+
+        // Get tmux version
+        let (tmux_command, tmux_args) = project.tmux_command(&["-V"])?;
+        let version_output = Command::new(tmux_command).args(tmux_args).output()?;
+        let version = String::from_utf8_lossy(&version_output.stdout);
+        profile.step("tmux version");
+
+        let status = if version.starts_with("tmux 2.") {
+            exec_tmux_2(project, source)?
+        } else {
+            exec_tmux_3(project, source)?
+        };
+        profile.step("source");
+
+        if project.hook_timeout.is_some() {
+            report_hook_timeouts(project)?;
+        }
+        if project.strict_hooks {
+            report_failed_hook(project)?;
+        }
+        if has_expect_steps(project) {
+            report_expect_timeouts(project)?;
+        }
+
+        // Make sure to remove the dummy session before attaching,
+        // Otherwise it'll pollute the session list the entire time we're attached
+        // Because airmux won't quit until `tmux attach-session` returns
+        drop(dummy_session);
+        profile.step("dummy cleanup");
+
+        // Check tmux exit code
+        ensure!(
+            status.success(),
+            TmuxFailed {
+                exit_code: status.code().unwrap_or(-1)
+            }
+        );
+
+        // Some tmux versions don't report the commands that fail inside `if` branches,
+        // like when the shell of the session's only window exits right away
+        ensure!(
+            session_exists(project),
+            SessionClosedWhileStarting {
+                session_name: project.session_name.to_owned().unwrap_or_default()
+            }
+        );
+
+        Ok(())
+    }
+
+    pub fn session_exists(project: &Project) -> bool {
+        let session_target = format!("={}", project.session_name.as_deref().unwrap_or_default());
+
+        project
+            .tmux_command(&["has-session", "-t", &session_target])
+            .ok()
+            .and_then(|(tmux, arguments)| {
+                Command::new(tmux)
+                    .args(arguments)
+                    .stderr(Stdio::null())
+                    .status()
+                    .ok()
+            })
+            .is_some_and(|status| status.success())
+    }
+
+    // The hash only covers the source, not the state of the session, so hooks that run each
+    // time the project is started and the reordering of moved windows can't be skipped
+    pub fn runs_on_every_start(project: &Project) -> bool {
+        !project.on_start.is_empty()
+            || !project.on_every_start.is_empty()
+            || !project.on_restart.is_empty()
+            || project.reselect_startup
+            || project.reorder_windows
+    }
+
+    pub fn get_hash(source: &str) -> String {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    // Reads the hash of the source the session was last created or updated with
+    pub fn get_session_hash(project: &Project) -> Option<String> {
+        let session_target = format!("={}", project.session_name.as_ref()?);
+        let output = project
+            .tmux_command(&["show-environment", "-t", &session_target, SOURCE_HASH_VAR])
+            .ok()
+            .and_then(|(tmux, arguments)| Command::new(tmux).args(arguments).output().ok())?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .strip_prefix(&format!("{}=", SOURCE_HASH_VAR))
+            .map(String::from)
+    }
+
+    pub fn with_hash(project: &Project, source: &str, source_hash: &str) -> String {
+        let session_name = project.session_name.as_ref().unwrap();
+        let set_hash = tmux_join(&["setenv", "-t", session_name, SOURCE_HASH_VAR, source_hash]);
+
+        // The session may be gone if a strict hook failed
+        if project.strict_hooks {
+            let condition = format!("#{{?{},0,1}}", HOOK_FAILED_VAR);
+            return format!(
+                "{}; {}",
+                source,
+                tmux_join(&["if", "-F", &condition, &set_hash])
+            );
+        }
+
+        format!("{}; {}", source, set_hash)
+    }
+
+    pub fn attach(
+        project: &Project,
+        switch: bool,
+        control_mode: bool,
+        profile: &mut profile::Profile,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let session_name = project.session_name.as_ref().unwrap();
+
+        // Switching to the session the client is already in would be a no-op
+        if current_session(project).as_ref() == Some(session_name) {
+            profile.print();
+            return Ok(());
+        }
+
+        let use_switch = use_switch(switch);
+        if control_mode && use_switch {
+            eprintln!("warning: control mode is only supported when attaching from outside of tmux, switching clients instead");
+        }
+
+        // The project's own attach command may not return until the client detaches either
+        if project.attach_command.is_some() && !control_mode {
+            profile.print();
+            let command = attach_command(project, switch, control_mode)?;
+            Command::new("sh").args(["-c", &command]).spawn()?.wait()?;
+            return Ok(());
+        }
+
+        if !use_switch {
+            // attach-session only returns once the client detaches, so it can't be timed
+            profile.print();
+        }
+        let (tmux_command, tmux_args) =
+            project.tmux_command(&attach_args(session_name, use_switch, control_mode))?;
+        Command::new(tmux_command).args(tmux_args).spawn()?.wait()?;
+
+        if use_switch {
+            profile.step("attach");
+            profile.print();
+        }
+
+        Ok(())
+    }
+
+    pub fn attach_command(
+        project: &Project,
+        switch: bool,
+        control_mode: bool,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let session_name = project.session_name.as_ref().unwrap();
+        match &project.attach_command {
+            Some(command) if !control_mode => Ok(command
+                .replace("__TMUX__", &project.tmux(&[] as &[&str])?)
+                .replace("__SESSION__", &quote(session_name))),
+            _ => project.tmux(&attach_args(session_name, use_switch(switch), control_mode)),
+        }
+    }
+
+    // Control mode (-CC) only applies to new clients, so it's dropped when switching
+    pub fn attach_args(session_name: &str, use_switch: bool, control_mode: bool) -> Vec<&str> {
+        if use_switch {
+            vec!["switch-client", "-t", session_name]
+        } else if control_mode {
+            vec!["-CC", "attach-session", "-t", session_name]
+        } else {
+            vec!["attach-session", "-t", session_name]
+        }
+    }
+
+    fn use_switch(switch: bool) -> bool {
+        switch || env::var("TMUX").is_ok() || env::var("AIRMUX_FORCE_SWITCH").is_ok()
+    }
+
+    // Selects the window and pane to land on once attached
+    pub fn select_attach_target(
+        project: &Project,
+        window: Option<&str>,
+        pane: Option<&str>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let session_name = project.session_name.as_ref().unwrap();
+        let target_window = format!("{}:{}", session_name, window.unwrap_or_default());
+
+        let mut commands = vec![];
+        if window.is_some() {
+            commands.push(("selectw", target_window.to_owned()));
+        }
+        if let Some(pane) = pane {
+            commands.push(("selectp", format!("{}.{}", target_window, pane)));
+        }
+
+        for (command, target) in commands {
+            let (tmux_command, tmux_args) = project.tmux_command(&[command, "-t", &target])?;
+            let output = Command::new(tmux_command).args(tmux_args).output()?;
+            ensure!(output.status.success(), AttachTargetNotFound { target });
+        }
+
+        Ok(())
+    }
+
+    // Size of the client the session is going to be displayed in, minus the status line
+    pub fn client_size(project: &Project) -> Option<(usize, usize)> {
+        if env::var("TMUX").is_err() {
+            let (rows, columns) = Term::stdout().size_checked()?;
+            return Some((columns as usize, (rows as usize).saturating_sub(1)));
+        }
+
+        let output = project
+            .tmux_command(&["display", "-p", "#{client_width} #{client_height}"])
+            .ok()
+            .and_then(|(tmux, arguments)| Command::new(tmux).args(arguments).output().ok())?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let output = String::from_utf8_lossy(&output.stdout);
+        let (width, height) = output.trim().split_once(' ')?;
+        Some((
+            width.parse().ok()?,
+            height.parse::<usize>().ok()?.saturating_sub(1),
+        ))
+    }
+
+    pub fn warn_unfitting_splits(project: &Project) {
+        let (width, height) = match client_size(project) {
+            Some(size) => size,
+            None => return,
+        };
+
+        for (window_index, window) in project.windows.iter().enumerate() {
+            for pane_index in window.unfitting_panes(project.pane_base_index, width, height) {
+                eprintln!(
+                    "warning: pane {}.{} does not fit in a {}x{} client, tmux will fail to split it",
+                    window_index + project.window_base_index,
+                    pane_index,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    // Name of the session that the pane airmux runs in belongs to, if it's on the project's server
+    pub fn current_session(project: &Project) -> Option<String> {
+        env::var("TMUX").ok()?;
+        let pane = env::var("TMUX_PANE").ok()?;
+        let output = project
+            .tmux_command(&["display", "-p", "-t", &pane, "#{session_name}"])
+            .ok()
+            .and_then(|(tmux, arguments)| Command::new(tmux).args(arguments).output().ok())?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub fn exec_tmux_2(
+        project: &Project,
+        source: &str,
+    ) -> Result<ExitStatus, Box<dyn error::Error>> {
+        // Save the source to a temporary file
+        let mut source_file = NamedTempFile::new()?;
+        source_file.write_all(source.as_bytes())?;
+        let source_file_path = source_file.into_temp_path();
+
+        // Source our tmux config file
+        let (tmux_command, tmux_args) =
+            project.tmux_command(&["source", &source_file_path.to_string_lossy()])?;
+
+        let mut command = Command::new(tmux_command);
+        command.args(tmux_args);
+
+        if let Some(path) = &project.working_dir {
+            if path.is_dir() {
+                command.current_dir(path);
+            }
+        }
+
+        let mut child = command.spawn()?;
+
+        // Wait until tmux completely finished processing input
+        Ok(child.wait()?)
+    }
+
+    pub fn exec_tmux_3(
+        project: &Project,
+        source: &str,
+    ) -> Result<ExitStatus, Box<dyn error::Error>> {
+        // Source our tmux config file
+        let (tmux_command, tmux_args) = project.tmux_command(&["source", "-"])?;
+
+        let mut command = Command::new(tmux_command);
+        command.args(tmux_args).stdin(Stdio::piped());
+
+        if let Some(path) = &project.working_dir {
+            if path.is_dir() {
+                command.current_dir(path);
+            }
+        }
+
+        let mut child = command.spawn()?;
+        child
+            .stdin
+            .as_mut()
+            .ok_or(Error::CannotPipeToTmux)?
+            .write_all(source.as_bytes())?;
+
+        // Wait until tmux completely finished processing input
+        Ok(child.wait()?)
+    }
+
+    pub fn generate(project: &Project, verbose: bool) -> Result<String, Box<dyn error::Error>> {
+        let tmux_command = project.tmux(&[] as &[&str])?;
+        let tmux_command = &tmux_command;
+
+        let session_name = project.session_name.to_owned().unwrap();
+        let session_name = &session_name;
+        let session_name_quoted = &tmux_quote(session_name);
+
+        let mut source_commands = Vec::new();
+
+        // Without any window to reuse it for, the session's window is moved away as usual
+        let preserve_first_window = project.preserve_first_window && !project.windows.is_empty();
+
+        let session_created = &format!("#{{{}}}", SESSION_CREATED_OPTION);
+        let unset_session_flags = [
+            SESSION_CREATED_OPTION,
+            SESSION_UPDATED_OPTION,
+            SESSION_ATTACHED_OPTION,
+        ]
+        .iter()
+        .map(|option| tmux_join(&["set", "-u", "-t", session_name, option]))
+        .collect::<Vec<String>>();
+
+        if project.strict_hooks {
+            source_commands.push(tmux_join(&["setenv", "-gu", HOOK_FAILED_VAR]));
+        }
+
+        // on_start commands
+        if !project.on_start.is_empty() {
+            source_commands.push(tmux_join(&[
+                "run",
+                &strict_hook_command(
+                    project,
+                    "on_start hook",
+                    project
+                        .on_start
+                        .join("; ")
+                        .replace("__TMUX__", tmux_command),
+                )?,
+            ]));
+        }
+        let after_on_start = source_commands.len();
+
+        // create session if it does not exist
+        {
+            let if_command = format!(
+                "! {} | {}",
+                project.tmux(&["ls", "-F", "##S"])?,
+                tmux_join(&["grep", "-Fx", session_name]),
+            );
+
+            let mut commands = vec![];
+
+            if preserve_first_window {
+                // Create new session, its window is picked up as the project's first window
+                let first_window_name =
+                    format!("{}{}", WINDOW_NAME_PREFIX, project.window_base_index);
+                commands.push(tmux_join(&[
+                    "new",
+                    "-s",
+                    session_name,
+                    "-d",
+                    "-n",
+                    &first_window_name,
+                ]));
+
+                // The window isn't one from an older version, it must not be tagged as such
+                commands.push(tmux_join(&[
+                    "set",
+                    "-t",
+                    session_name,
+                    "@airmux_windows",
+                    "1",
+                ]));
+            } else {
+                // Create new session
+                commands.push(tmux_join(&["new", "-s", session_name, "-d"]));
+
+                // Move the first window away temporarily
+                commands.push(tmux_join(&[
+                    "movew",
+                    "-s",
+                    &format!("{}:^", session_name),
+                    "-t",
+                    &format!("{}:999999", session_name),
+                ]));
+            }
+
+            // Load plugins, running their init scripts the same way tpm does
+            if !project.plugins.is_empty() {
+                let plugins_dir = match &project.plugins_dir {
+                    Some(path) => quote(&path.to_string_lossy()).into_owned(),
+                    None => String::from(r#""${TMUX_PLUGIN_MANAGER_PATH:-$HOME/.tmux/plugins}""#),
+                };
+
+                let scripts = project
+                    .plugins
+                    .iter()
+                    .map(|plugin| {
+                        format!(
+                            "{}/{}/*.tmux",
+                            plugins_dir,
+                            quote(Project::plugin_name(plugin))
+                        )
+                    })
+                    .collect::<Vec<String>>();
+
+                commands.push(tmux_join(&[
+                    "run",
+                    &format!(
+                        r#"for script in {}; do [ -x "$script" ] && "$script"; done; true"#,
+                        scripts.join(" ")
+                    ),
+                ]));
+            }
+
+            // on_first_start commands
+            if !project.on_first_start.is_empty() {
+                commands.push(tmux_join(&[
+                    "run",
+                    &strict_hook_command(
+                        project,
+                        "on_first_start hook",
+                        project
+                            .on_first_start
+                            .join("; ")
+                            .replace("__TMUX__", tmux_command)
+                            .replace("__SESSION__", session_name_quoted),
+                    )?,
+                ]))
+            }
+
+            // on_exit commands
+            if !project.on_exit.is_empty() {
+                let mut on_exit = project.on_exit.to_owned();
+                if project.notify {
+                    on_exit.push(notify_command(&format!(
+                        "on_exit finished for session {}",
+                        session_name
+                    )));
+                }
+
+                let run_shell_command = tmux_join(&[
+                    "run",
+                    &logged_hook_command(
+                        project,
+                        "on_exit hook",
+                        on_exit.join("; ").replace("__TMUX__", tmux_command),
+                    ),
+                ]);
+
+                commands.push(tmux_join(&[
+                    "set-hook",
+                    "-t",
+                    session_name,
+                    "client-detached",
+                    &run_shell_command,
+                ]));
+            }
+
+            // on_stop (+on_exit) commands
+            if !project.on_exit.is_empty() || !project.on_stop.is_empty() {
+                let command_list = project
+                    .on_exit
+                    .iter()
+                    .cloned()
+                    .chain(project.on_stop.iter().cloned())
+                    .chain(iter::once(project.tmux(&[
+                        "set-hook",
+                        "-gu",
+                        "session-closed[#{session_created}]",
+                    ])?))
+                    .collect::<Vec<String>>();
+
+                let if_command = format!(
+                    "! {} | {}",
+                    project.tmux(&["ls", "-F", "####S"])?,
+                    tmux_join(&["grep", "-Fx", session_name]),
+                );
+
+                let run_shell_command = tmux_join(&[
+                    "run",
+                    &logged_hook_command(
+                        project,
+                        "on_stop hook",
+                        command_list.join("; ").replace("__TMUX__", tmux_command),
+                    ),
+                ]);
+
+                let hook_command = tmux_join(&["if", &if_command, &run_shell_command]);
+
+                let set_hook_command = project.tmux(&[
+                    "set-hook",
+                    "-g",
+                    "session-closed[#{session_created}]",
+                    &hook_command,
+                ])?;
+
+                let run_shell_set_hook_command =
+                    tmux_join(&["run", "-t", session_name, &set_hook_command]);
+
+                // Failible on tmux version that don't support it
+                commands.push(tmux_join(&[
+                    "run",
+                    &format!(
+                        "{} || true",
+                        project.tmux(&["set", "-g", "exit-empty", "off"])?,
+                    ),
+                ]));
+
+                commands.push(run_shell_set_hook_command);
+            }
+
+            // Set whether the session was created or not
+            commands.push(tmux_join(&[
+                "set",
+                "-t",
+                session_name,
+                SESSION_CREATED_OPTION,
+                "1",
+            ]));
+
+            // A session that was already there is only attached, after clearing the flags
+            // that may linger from an interrupted start
+            let mut existing_commands = unset_session_flags.clone();
+            existing_commands.push(tmux_join(&[
+                "set",
+                "-t",
+                session_name,
+                SESSION_ATTACHED_OPTION,
+                "1",
+            ]));
+
+            source_commands.push(tmux_join(&[
+                "if",
+                &if_command,
+                &commands.join("; "),
+                &existing_commands.join("; "),
+            ]));
+        }
+        let after_first_start = source_commands.len();
+
+        // on_every_start commands, run on every start once the session exists
+        if !project.on_every_start.is_empty() {
+            source_commands.push(tmux_join(&[
+                "run",
+                &hook_command(
+                    project,
+                    "on_every_start hook",
+                    project
+                        .on_every_start
+                        .join("; ")
+                        .replace("__TMUX__", tmux_command)
+                        .replace("__SESSION__", session_name_quoted),
+                )?,
+            ]));
+        }
+
+        // on_restart commands
+        if !project.on_restart.is_empty() {
+            source_commands.push(tmux_join(&[
+                "if",
+                "-F",
+                "-t",
+                session_name,
+                &format!("#{{{}}}", SESSION_ATTACHED_OPTION),
+                &tmux_join(&[
+                    "run",
+                    &hook_command(
+                        project,
+                        "on_restart hook",
+                        project
+                            .on_restart
+                            .join("; ")
+                            .replace("__TMUX__", tmux_command)
+                            .replace("__SESSION__", session_name_quoted),
+                    )?,
+                ]),
+            ]));
+        }
+
+        // window base index
+        source_commands.push(tmux_join(&[
+            "set",
+            "-s",
+            "-t",
+            session_name,
+            "base-index",
+            &project.window_base_index.to_string(),
+        ]));
+
+        // Session options, set before the windows so their panes pick up the history limit
+        if let Some(mouse) = project.mouse {
+            source_commands.push(tmux_join(&[
+                "set",
+                "-t",
+                session_name,
+                "mouse",
+                if mouse { "on" } else { "off" },
+            ]));
+        }
+        if let Some(history_limit) = project.history_limit {
+            source_commands.push(tmux_join(&[
+                "set",
+                "-t",
+                session_name,
+                "history-limit",
+                &history_limit.to_string(),
+            ]));
+        }
+        if let Some(titles_string) = &project.set_titles {
+            source_commands.push(tmux_join(&["set", "-t", session_name, "set-titles", "on"]));
+            source_commands.push(tmux_join(&[
+                "set",
+                "-t",
+                session_name,
+                "set-titles-string",
+                titles_string,
+            ]));
+        }
+
+        // Session environment, set before the windows so their panes inherit it
+        for (name, value) in &project.environment {
+            source_commands.push(tmux_join(&["setenv", "-t", session_name, name, value]));
+        }
+        if !project.env_capture.is_empty() {
+            source_commands.push(tmux_join(&[
+                "setenv",
+                "-t",
+                session_name,
+                ENV_CAPTURE_VAR,
+                &project.env_capture.join(" "),
+            ]));
+        }
+
+        // Sessions started by older versions have no window markers, tag the windows at their indexes
+        source_commands.push(tmux_join(&[
+            "run",
+            &legacy_window_markers_command(project, session_name)?,
+        ]));
+
+        // Setup windows
+        for (window_index, window) in project.windows.iter().enumerate() {
+            let window_tmux_index = window_index + project.window_base_index;
+
+            // Windows are created under a temporary name and targeted by it, rather than by their
+            // index, which may already be taken by a window the user moved or created
+            let display_window = &format!("{}:{}", session_name, window_tmux_index);
+            let temporary_name = format!("{}{}", WINDOW_NAME_PREFIX, window_tmux_index);
+            let target_window = &format!("{}:={}", session_name, temporary_name);
+
+            // Hooks run targeting the window and get its id, which outlives the temporary name
+            let window_id = "#{window_id}";
+            let window_index_string = &window_tmux_index.to_string();
+            let window_name_quoted = &tmux_quote(window.name.as_deref().unwrap_or_default());
+
+            // Move the window back where it belongs if it drifted to another index
+            if let (true, Some(window_name)) = (project.reorder_windows, &window.name) {
+                source_commands.push(tmux_join(&[
+                    "run",
+                    &reorder_window_command(project, session_name, window_name, window_tmux_index)?,
+                ]));
+            }
+
+            // Windows created by airmux are recognized by their marker wherever they were moved
+            let if_command = format!(
+                "! {} | {}",
+                project.tmux(&["lsw", "-t", session_name, "-F", "##{@airmux_window}"])?,
+                tmux_join(&["grep", "-Fx", &window_tmux_index.to_string()])
+            );
+
+            let mut new_window_command = vec!["neww", "-d", "-n", &temporary_name];
+
+            let mut found_working_dir = false;
+            let mut working_dir = String::new();
+
+            if !window.panes.is_empty() {
+                if let Some(wd) = &window.panes[0].working_dir {
+                    working_dir = wd.to_string_lossy().to_string();
+                    found_working_dir = true;
+                }
+            }
+            if !found_working_dir {
+                if let Some(wd) = &window.working_dir {
+                    working_dir = wd.to_string_lossy().to_string();
+                    found_working_dir = true;
+                }
+            }
+            if !found_working_dir {
+                if let Some(wd) = &project.working_dir {
+                    working_dir = wd.to_string_lossy().to_string();
+                    found_working_dir = true;
+                }
+            }
+
+            if found_working_dir {
+                new_window_command.splice(2..2, vec!["-c", &working_dir]);
+            }
+
+            // Command or shell of the first pane, which goes last as neww's shell command
+            let first_pane = window.panes.first();
+            let first_pane_exec = first_pane.and_then(Pane::exec_command);
+            let first_pane_shell = first_pane_exec
+                .as_deref()
+                .or_else(|| first_pane.and_then(|pane| pane.shell.as_deref()))
+                .or(window.shell.as_deref());
+            new_window_command.extend(first_pane_shell);
+
+            let mut window_commands = Vec::new();
+
+            // Create the window at its index, or at the next free one when it's taken
+            let index_taken = format!(
+                "{} | {}",
+                project.tmux(&["lsw", "-t", session_name, "-F", "##I"])?,
+                tmux_join(&["grep", "-Fx", &window_tmux_index.to_string()])
+            );
+            let next_free_index = format!("{}:", session_name);
+            let window_index_target = display_window;
+            let create_window = tmux_join(&[
+                "if",
+                &index_taken,
+                &tmux_join(&[&new_window_command[..], &["-t", &next_free_index]].concat()),
+                &tmux_join(&[&new_window_command[..], &["-t", window_index_target]].concat()),
+            ]);
+
+            if preserve_first_window && window_index == 0 {
+                // Reuse the session's own window when the session was just created,
+                // moving it to its index (which fails if it's already there) and restarting
+                // it in the window's working directory and shell
+                let mut reuse_window = vec![tmux_join(&[
+                    "run",
+                    &format!(
+                        "{} 2>/dev/null || true",
+                        project.tmux(&["movew", "-s", target_window, "-t", display_window])?
+                    ),
+                ])];
+                if found_working_dir || first_pane_shell.is_some() {
+                    let mut respawn_command = vec!["respawnw", "-k", "-t", target_window];
+                    if found_working_dir {
+                        respawn_command.extend(["-c", &working_dir]);
+                    }
+                    respawn_command.extend(first_pane_shell);
+                    reuse_window.push(tmux_join(&respawn_command));
+                }
+
+                window_commands.push(tmux_join(&[
+                    "if",
+                    "-F",
+                    "-t",
+                    session_name,
+                    session_created,
+                    &reuse_window.join("; "),
+                    &create_window,
+                ]));
+            } else {
+                window_commands.push(create_window);
+            }
+            window_commands.push(tmux_join(&[
+                "set",
+                "-w",
+                "-t",
+                target_window,
+                "@airmux_window",
+                &window_tmux_index.to_string(),
+            ]));
+
+            // Pane base index for this window
+            window_commands.push(tmux_join(&[
+                "set",
+                "-s",
+                "-t",
+                target_window,
+                "pane-base-index",
+                &project.pane_base_index.to_string(),
+            ]));
+
+            // project's on_window_create
+            // plus window's on_create commands
+            let on_create_commands: Vec<String> = project
+                .on_window_create
+                .iter()
+                .cloned()
+                .chain(window.on_create.iter().cloned())
+                .collect();
+            if !on_create_commands.is_empty() {
+                window_commands.push(tmux_join(&[
+                    "run",
+                    "-t",
+                    target_window,
+                    &hook_command(
+                        project,
+                        &format!("on_create hook of window {}", display_window),
+                        on_create_commands
+                            .join("; ")
+                            .replace("__TMUX__", tmux_command)
+                            .replace("__SESSION__", session_name_quoted)
+                            .replace("__WINDOW__", window_id)
+                            .replace("__WINDOW_INDEX__", window_index_string)
+                            .replace("__WINDOW_NAME__", window_name_quoted),
+                    )?,
+                ]));
+            };
+
+            // Commands that create each pane after the first, to add missing panes to an existing window
+            let mut missing_panes = vec![];
+
+            // Panes
+            for (pane_index, pane) in window.panes.iter().enumerate() {
+                let target_pane_index = pane_index + project.pane_base_index;
+                let pane_start = window_commands.len();
+
+                // Create pane (first one is automatically created)
+                if pane_index > 0 {
+                    // Split direction (defaults to horizontal)
+                    let mut split_command = vec![
+                        "splitw",
+                        match &pane.split {
+                            Some(split) if *split == PaneSplit::Vertical => "-v",
+                            _ => "-h",
+                        },
+                    ];
+
+                    // Working directory
+                    let mut found_working_dir = true;
+                    let mut working_dir = String::new();
+
+                    if let Some(wd) = &pane.working_dir {
+                        working_dir = wd.to_string_lossy().to_string();
+                    } else if let Some(wd) = &window.working_dir {
+                        working_dir = wd.to_string_lossy().to_string();
+                    } else if let Some(wd) = &project.working_dir {
+                        working_dir = wd.to_string_lossy().to_string();
+                    } else {
+                        found_working_dir = false;
+                    }
+
+                    if found_working_dir {
+                        split_command.append(&mut vec!["-c", &working_dir]);
+                    }
+
+                    // Split size
+                    if let Some(split_size) = &pane.split_size {
+                        split_command.append(&mut vec!["-l", split_size]);
+                    }
+
+                    // Command or shell the pane starts with, which goes last as splitw's shell command
+                    let exec_command = pane.exec_command();
+                    let shell = exec_command
+                        .as_deref()
+                        .or(pane.shell.as_deref())
+                        .or(window.shell.as_deref());
+
+                    // Target pane, tmux doesn't expand formats in targets so pane
+                    // references have to go through the shell
+                    match window.split_from_index(pane_index, project.pane_base_index) {
+                        None => {
+                            split_command.append(&mut vec!["-t", target_window]);
+                            split_command.extend(shell);
+                            window_commands.push(tmux_join(&split_command));
+                        }
+                        Some(split_from) => {
+                            let split_from_target = pane_reference(split_from);
+                            split_command.append(&mut vec!["-t", &split_from_target]);
+                            split_command.extend(shell);
+                            window_commands.push(tmux_join(&[
+                                "run",
+                                "-t",
+                                target_window,
+                                &project.tmux(&split_command)?,
+                            ]));
+                        }
+                    }
+                }
+
+                // Everything that's done to the pane is batched into a single run-shell
+                // targeting the window, whose active pane (#D) is the pane that was just created
+                let target_pane = "#D";
+                let target_pane_quoted = &tmux_quote(target_pane);
+                let mut pane_steps: Vec<String> = vec![];
+
+                // Keep the pane's id in a window option, to reference it by its project order
+                // Window options don't leak into other sessions being created at the same time
+                pane_steps.push(project.tmux(&[
+                    "set",
+                    "-w",
+                    "-t",
+                    target_pane,
+                    &format!("{}{}", PANE_OPTION_PREFIX, target_pane_index),
+                    target_pane,
+                ])?);
+
+                // Mark the pane, so it can be targeted with `{marked}`
+                if pane.mark {
+                    pane_steps.push(project.tmux(&["selectp", "-m", "-t", target_pane])?);
+                }
+
+                if let Some(style) = &pane.style {
+                    pane_steps.push(project.tmux(&["selectp", "-t", target_pane, "-P", style])?);
+                }
+
+                // project and window's on_pane_create
+                // plus pane's on_create commands
+                let on_create_commands: Vec<String> = project
+                    .on_pane_create
+                    .iter()
+                    .cloned()
+                    .chain(window.on_pane_create.iter().cloned())
+                    .chain(pane.on_create.iter().cloned())
+                    .collect();
+                if !on_create_commands.is_empty() {
+                    pane_steps.push(hook_command(
+                        project,
+                        &format!(
+                            "on_create hook of pane {}.{}",
+                            display_window, target_pane_index
+                        ),
+                        on_create_commands
+                            .join("; ")
+                            .replace("__TMUX__", tmux_command)
+                            .replace("__SESSION__", session_name_quoted)
+                            .replace("__WINDOW__", window_id)
+                            .replace("__WINDOW_INDEX__", window_index_string)
+                            .replace("__WINDOW_NAME__", window_name_quoted)
+                            .replace("__PANE__", target_pane_quoted)
+                            .replace("__PANE_INDEX__", &target_pane_index.to_string()),
+                    )?);
+                }
+
+                // project and window's pane_commands
+                // plus pane commands
+                let join_commands = pane
+                    .join_commands
+                    .as_deref()
+                    .or(project.join_commands.as_deref());
+                let mut run_commands: Vec<String> = vec![];
+                for command in project
+                    .pane_commands
+                    .iter()
+                    .chain(window.pane_commands.iter())
+                    .chain(pane.commands.iter())
+                    .filter(|command| !pane.exec && !command.is_empty())
+                {
+                    let step = match command {
+                        PaneCommand::Run { .. } => {
+                            run_commands.push(command.shell_command().unwrap_or_default());
+                            continue;
+                        }
+                        PaneCommand::Sleep(seconds) => format!("sleep {}", seconds),
+                        PaneCommand::Expect {
+                            expect,
+                            send,
+                            timeout,
+                        } => expect_command(project, target_pane, expect, send, *timeout)?,
+                    };
+
+                    // Sleep and expect steps break command chains
+                    pane_steps.extend(send_commands(
+                        project,
+                        target_pane,
+                        &run_commands,
+                        join_commands,
+                    )?);
+                    run_commands.clear();
+                    pane_steps.push(step);
+                }
+                pane_steps.extend(send_commands(
+                    project,
+                    target_pane,
+                    &run_commands,
+                    join_commands,
+                )?);
+
+                // project and window's post_pane_create
+                // plus pane's post_create commands
+                let post_pane_commands: Vec<String> = project
+                    .post_pane_create
+                    .iter()
+                    .cloned()
+                    .chain(window.post_pane_create.iter().cloned())
+                    .chain(pane.post_create.iter().cloned())
+                    .collect();
+                if !post_pane_commands.is_empty() {
+                    pane_steps.push(hook_command(
+                        project,
+                        &format!(
+                            "post_create hook of pane {}.{}",
+                            display_window, target_pane_index
+                        ),
+                        post_pane_commands
+                            .join("; ")
+                            .replace("__TMUX__", tmux_command)
+                            .replace("__SESSION__", session_name_quoted)
+                            .replace("__WINDOW__", window_id)
+                            .replace("__WINDOW_INDEX__", window_index_string)
+                            .replace("__WINDOW_NAME__", window_name_quoted)
+                            .replace("__PANE__", target_pane_quoted)
+                            .replace("__PANE_INDEX__", &target_pane_index.to_string()),
+                    )?);
+                }
+
+                // send_keys for the pane
+                let mut send_keys: Vec<&str> = pane
+                    .send_keys
+                    .iter()
+                    .filter_map(|command| {
+                        if pane.exec || command.is_empty() {
+                            None
+                        } else {
+                            Some(command.as_str())
+                        }
+                    })
+                    .collect();
+
+                // pane's clear
+                if !pane.exec && (pane.clear || window.clear_panes || project.clear_panes) {
+                    send_keys.push("C-l");
+                }
+
+                // Send keys
+                if !send_keys.is_empty() {
+                    let send_keys: Vec<&str> = vec!["send", "-t", target_pane]
+                        .into_iter()
+                        .chain(send_keys)
+                        .collect();
+                    pane_steps.push(project.tmux(&send_keys)?);
+                }
+
+                window_commands.push(tmux_join(&[
+                    "run",
+                    "-t",
+                    target_window,
+                    &pane_steps.join("; "),
+                ]));
+
+                if pane_index > 0 {
+                    let mut pane_commands = vec![];
+
+                    // Panes are split from the previous one, like when the window is created
+                    if pane.split_from.is_none() {
+                        pane_commands.push(tmux_join(&[
+                            "run",
+                            "-t",
+                            target_window,
+                            &project.tmux(&[
+                                "selectp",
+                                "-t",
+                                &pane_reference(target_pane_index - 1),
+                            ])?,
+                        ]));
+                    }
+                    pane_commands.extend_from_slice(&window_commands[pane_start..]);
+
+                    let pane_missing = format!(
+                        "[ \"$({})\" -le {} ]",
+                        project.tmux(&[
+                            "display",
+                            "-p",
+                            "-t",
+                            target_window,
+                            "##{window_panes}"
+                        ])?,
+                        pane_index
+                    );
+                    missing_panes.push(tmux_join(&[
+                        "if",
+                        &pane_missing,
+                        &pane_commands.join("; "),
+                    ]));
+                }
+            }
+
+            // Main pane size, used by the main-vertical and main-horizontal layouts
+            if let Some(width) = &window.main_pane_width {
+                window_commands.push(tmux_join(&[
+                    "setw",
+                    "-t",
+                    target_window,
+                    "main-pane-width",
+                    width,
+                ]));
+            }
+            if let Some(height) = &window.main_pane_height {
+                window_commands.push(tmux_join(&[
+                    "setw",
+                    "-t",
+                    target_window,
+                    "main-pane-height",
+                    height,
+                ]));
+            }
+
+            // Project-wide window status styles
+            if let Some(style) = &project.window_status_style {
+                window_commands.push(tmux_join(&[
+                    "setw",
+                    "-t",
+                    target_window,
+                    "window-status-style",
+                    style,
+                ]));
+            }
+            if let Some(style) = &project.window_status_current_style {
+                window_commands.push(tmux_join(&[
+                    "setw",
+                    "-t",
+                    target_window,
+                    "window-status-current-style",
+                    style,
+                ]));
+            }
+
+            // Pane border labels
+            if let Some(status) = &window.pane_border_status {
+                window_commands.push(tmux_join(&[
+                    "setw",
+                    "-t",
+                    target_window,
+                    "pane-border-status",
+                    status,
+                ]));
+            }
+            if let Some(format) = &window.pane_border_format {
+                window_commands.push(tmux_join(&[
+                    "setw",
+                    "-t",
+                    target_window,
+                    "pane-border-format",
+                    format,
+                ]));
+            }
+
+            // Window layout
+            let layout_command = if let Some(layout) = &window.layout {
+                Some(tmux_join(&["select-layout", "-t", target_window, layout]))
+            } else if window.even_out {
+                Some(tmux_join(&[
+                    "select-layout",
+                    "-t",
+                    target_window,
+                    window.even_layout(),
+                ]))
+            } else {
+                None
+            };
+            window_commands.extend(layout_command.to_owned());
+
+            // Select the window's startup pane, or its first pane
+            let startup_pane = window.startup_pane.unwrap_or(project.pane_base_index);
+            let target_pane = format!("{}.{}", target_window, startup_pane);
+            window_commands.push(tmux_join(&["selectp", "-t", &target_pane]));
+
+            // Zoom the pane, which also makes it the window's active pane
+            if let Some(pane_index) = window.panes.iter().position(|pane| pane.zoom) {
+                let target_pane_index = pane_index + project.pane_base_index;
+                let zoomed_pane = pane_reference(target_pane_index);
+                window_commands.push(tmux_join(&[
+                    "run",
+                    "-t",
+                    target_window,
+                    &project.tmux(&["resizep", "-Z", "-t", &zoomed_pane])?,
+                ]));
+            }
+
+            // The pane ids aren't needed once the window is set up
+            let unset_pane_options = (0..window.panes.len())
+                .map(|pane_index| {
+                    let target_pane_index = pane_index + project.pane_base_index;
+                    let pane_option = format!("{}{}", PANE_OPTION_PREFIX, target_pane_index);
+                    tmux_join(&["set", "-wu", "-t", target_window, &pane_option])
+                })
+                .collect::<Vec<String>>();
+            window_commands.extend_from_slice(&unset_pane_options);
+
+            // project's post_window_create
+            // plus window's post_create commands
+            let post_create_commands: Vec<String> = project
+                .post_window_create
+                .iter()
+                .cloned()
+                .chain(window.post_create.iter().cloned())
+                .collect();
+            if !post_create_commands.is_empty() {
+                window_commands.push(tmux_join(&[
+                    "run",
+                    "-t",
+                    target_window,
+                    &hook_command(
+                        project,
+                        &format!("post_create hook of window {}", display_window),
+                        post_create_commands
+                            .join("; ")
+                            .replace("__TMUX__", tmux_command)
+                            .replace("__SESSION__", session_name_quoted)
+                            .replace("__WINDOW__", window_id)
+                            .replace("__WINDOW_INDEX__", window_index_string)
+                            .replace("__WINDOW_NAME__", window_name_quoted),
+                    )?,
+                ]));
+            }
+
+            // Give the window its name once it doesn't need to be targeted anymore
+            let rename_command = match &window.name {
+                Some(window_name) => tmux_join(&["renamew", "-t", target_window, window_name]),
+                None => tmux_join(&["setw", "-t", target_window, "automatic-rename", "on"]),
+            };
+            window_commands.push(rename_command.to_owned());
+
+            // Flag session as updated
+            let set_session_updated =
+                tmux_join(&["set", "-t", session_name, SESSION_UPDATED_OPTION, "1"]);
+            window_commands.push(set_session_updated.to_owned());
+
+            if missing_panes.is_empty() {
+                source_commands.push(tmux_join(&["if", &if_command, &window_commands.join("; ")]));
+                continue;
+            }
+
+            // When the window already exists, only the panes it's missing are created, under the
+            // window's temporary name, and with the existing panes' ids kept like for a new window
+            let existing_window = format!(
+                "\"$({} | awk '$1 == {} {{ print $2; exit }}')\"",
+                project.tmux(&[
+                    "lsw",
+                    "-t",
+                    session_name,
+                    "-F",
+                    "##{@airmux_window} ##{window_id}"
+                ])?,
+                window_tmux_index
+            );
+            let has_missing_panes = format!(
+                "[ \"$({} -p -t {} '##{{window_panes}}')\" -lt {} ]",
+                project.tmux(&["display"])?,
+                existing_window,
+                window.panes.len()
+            );
+
+            let mut update_commands = vec![
+                tmux_join(&[
+                    "run",
+                    &format!(
+                        "{} -t {} {}",
+                        project.tmux(&["renamew"])?,
+                        existing_window,
+                        quote(&temporary_name)
+                    ),
+                ]),
+                tmux_join(&[
                     "run",
-                    &window
-                        .on_create
+                    &format!(
+                        "{} | while read index id; do {} \"{}$index\" \"$id\"; done",
+                        project.tmux(&[
+                            "lsp",
+                            "-t",
+                            target_window,
+                            "-F",
+                            "##{pane_index} ##{pane_id}"
+                        ])?,
+                        project.tmux(&["set", "-w", "-t", target_window])?,
+                        PANE_OPTION_PREFIX
+                    ),
+                ]),
+            ];
+            update_commands.extend(missing_panes);
+            update_commands.extend(layout_command);
+            update_commands.extend(unset_pane_options);
+            update_commands.push(rename_command);
+            update_commands.push(set_session_updated);
+
+            source_commands.push(tmux_join(&[
+                "if",
+                &if_command,
+                &window_commands.join("; "),
+                &tmux_join(&["if", &has_missing_panes, &update_commands.join("; ")]),
+            ]));
+        }
+
+        let startup_window = project
+            .startup_window_position()
+            .ok()
+            .and_then(|window_pos| project.windows.get(window_pos));
+
+        // Windows that zoom a pane or have a startup_pane already selected their startup pane
+        let startup_pane_selected = startup_window.is_some_and(|window| {
+            window.startup_pane.is_some() || window.panes.iter().any(|pane| pane.zoom)
+        });
+
+        let startup_window_target = match &project.startup_window {
+            StartupWindow::Index(startup_window) => format!("{}:{}", session_name, startup_window),
+            // Windows that share their name are told apart by their index
+            StartupWindow::Name(startup_window) => {
+                match StartupWindow::split_name(startup_window) {
+                    (_, Some(index)) => format!("{}:{}", session_name, index),
+                    (name, None) => format!("{}:{}", session_name, name),
+                }
+            }
+            StartupWindow::Default => format!("{}:^", session_name),
+        };
+        let zoom_startup_pane = if project.startup_zoom {
+            tmux_join(&["if", "-F", "#{?window_zoomed_flag,0,1}", "resizep -Z"])
+        } else {
+            String::new()
+        };
+
+        // Post-window creation routing for when the session is freshly created
+        let created_commands = [
+            // Remove the original window, unless it was reused
+            if preserve_first_window {
+                String::new()
+            } else {
+                tmux_join(&["killw", "-t", &format!("{}:999999", session_name)])
+            },
+            // Set startup window
+            tmux_join(&["selectw", "-t", &startup_window_target]),
+            // Set startup pane, unless the startup window already selected one
+            match &project.startup_pane {
+                None if startup_pane_selected => String::new(),
+                None => tmux_join(&["selectp", "-t", &project.pane_base_index.to_string()]),
+                Some(pane) => tmux_join(&["selectp", "-t", &pane.to_string()]),
+            },
+            // Zoom the startup pane
+            zoom_startup_pane.to_owned(),
+        ]
+        .iter()
+        .filter(|command| !command.is_empty())
+        .cloned()
+        .collect::<Vec<String>>()
+        .join("; ");
+
+        if project.reselect_startup {
+            // Land on the startup window and pane of an existing session too, the pane is only
+            // selected when one is configured since the window keeps track of its active pane
+            let startup_pane = project
+                .startup_pane
+                .or_else(|| startup_window.and_then(|window| window.startup_pane));
+            let reselect_commands = [
+                tmux_join(&["selectw", "-t", &startup_window_target]),
+                match startup_pane {
+                    Some(pane) => tmux_join(&[
+                        "selectp",
+                        "-t",
+                        &format!("{}.{}", startup_window_target, pane),
+                    ]),
+                    None => String::new(),
+                },
+                zoom_startup_pane,
+            ]
+            .iter()
+            .filter(|command| !command.is_empty())
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("; ");
+
+            source_commands.push(tmux_join(&[
+                "if",
+                "-F",
+                "-t",
+                session_name,
+                session_created,
+                &created_commands,
+                &reselect_commands,
+            ]));
+        } else {
+            source_commands.push(tmux_join(&[
+                "if",
+                "-F",
+                "-t",
+                session_name,
+                session_created,
+                &created_commands,
+            ]));
+        }
+
+        // post_create commands
+        if !project.post_create.is_empty() {
+            source_commands.push(tmux_join(&[
+                "run",
+                &hook_command(
+                    project,
+                    "post_create hook",
+                    project
+                        .post_create
                         .join("; ")
                         .replace("__TMUX__", tmux_command)
-                        .replace("__SESSION__", session_name_quoted)
-                        .replace("__WINDOW__", target_window_quoted),
-                ]));
+                        .replace("__SESSION__", session_name_quoted),
+                )?,
+            ]));
+        }
+
+        // Let the user know the session is ready, without holding up the attach
+        if project.notify {
+            source_commands.push(tmux_join(&[
+                "run",
+                "-b",
+                &notify_command(&format!("session {} is ready", session_name)),
+            ]));
+        }
+
+        // Show indicator message
+        if verbose {
+            source_commands.push(format!(
+                "display -p -t {} '#{{?{},created new session:, \
+                    #{{?{},updated session:, \
+                    no changes to existing session:}}}} {} '",
+                session_name_quoted, SESSION_CREATED_OPTION, SESSION_UPDATED_OPTION, session_name,
+            ));
+        }
+
+        // Clear the session's flags
+        source_commands.extend(unset_session_flags);
+
+        // Skip everything that comes after a failed strict hook
+        if project.strict_hooks {
+            if !project.on_first_start.is_empty() {
+                let rest = source_commands.split_off(after_first_start);
+                source_commands.push(strict_hooks_guard(Some(session_name), &rest));
+            }
+            // The session doesn't exist yet when on_start fails, unless it was already there
+            if !project.on_start.is_empty() {
+                let rest = source_commands.split_off(after_on_start);
+                source_commands.push(strict_hooks_guard(None, &rest));
+            }
+        }
+
+        Ok(source_commands.join("; "))
+    }
+
+    // Runs the commands unless a strict hook failed, in which case the session is killed
+    // if it was created by this source
+    pub fn strict_hooks_guard(session_name: Option<&str>, commands: &[String]) -> String {
+        let session_name = match session_name {
+            Some(session_name) => session_name,
+            None => {
+                let condition = format!("#{{?{},0,1}}", HOOK_FAILED_VAR);
+                return tmux_join(&["if", "-F", &condition, &commands.join("; ")]);
+            }
+        };
+
+        let teardown = tmux_join(&[
+            "if",
+            "-F",
+            "-t",
+            session_name,
+            &format!("#{{{}}}", SESSION_CREATED_OPTION),
+            &tmux_join(&["kill-session", "-t", session_name]),
+        ]);
+
+        tmux_join(&[
+            "if",
+            "-F",
+            &format!("#{{{}}}", HOOK_FAILED_VAR),
+            &teardown,
+            &commands.join("; "),
+        ])
+    }
+
+    // Format expanding to the id of a pane of the window being set up, from its project order
+    fn pane_reference(pane_index: usize) -> String {
+        format!("#{{{}{}}}", PANE_OPTION_PREFIX, pane_index)
+    }
+
+    fn legacy_window_markers_command(
+        project: &Project,
+        session_name: &str,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let mark_windows: Vec<String> = (0..project.windows.len())
+            .map(|window_index| {
+                let window_index = (window_index + project.window_base_index).to_string();
+                let target = format!("{}:{}", session_name, window_index);
+                project
+                    .tmux(&["set", "-w", "-t", &target, "@airmux_window", &window_index])
+                    .map(|command| format!("{} 2>/dev/null", command))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(format!(
+            "{} >/dev/null 2>&1 || {{ {}; }}; {}",
+            project.tmux(&["show", "-t", session_name, "-v", "@airmux_windows"])?,
+            iter::once(String::from("true"))
+                .chain(mark_windows)
+                .collect::<Vec<String>>()
+                .join("; "),
+            project.tmux(&["set", "-t", session_name, "@airmux_windows", "1"])?
+        ))
+    }
+
+    fn reorder_window_command(
+        project: &Project,
+        session_name: &str,
+        window_name: &str,
+        window_index: usize,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let source = format!("{}:={}", session_name, window_name);
+        let target = format!("{}:{}", session_name, window_index);
+
+        // Nothing to do if the window is already where it belongs
+        let current_name = format!(
+            "[ \"$({} 2>/dev/null)\" = {} ]",
+            project.tmux(&["display", "-p", "-t", &target, "##W"])?,
+            quote(window_name)
+        );
+
+        // Swapping fails when there is no window at the target index, move it instead
+        let swap_window = project.tmux(&["swapw", "-d", "-s", &source, "-t", &target])?;
+        let move_window = project.tmux(&["movew", "-s", &source, "-t", &target])?;
+
+        Ok(format!(
+            "{} || {} 2>/dev/null || {} 2>/dev/null || true",
+            current_name, swap_window, move_window
+        ))
+    }
+
+    fn send_commands(
+        project: &Project,
+        target_pane: &str,
+        commands: &[String],
+        join_commands: Option<&str>,
+    ) -> Result<Vec<String>, Box<dyn error::Error>> {
+        if commands.is_empty() {
+            return Ok(vec![]);
+        }
+
+        match join_commands {
+            Some(separator) => {
+                let command = commands.join(&format!(" {} ", separator.trim()));
+                Ok(vec![project.tmux(&[
+                    "send",
+                    "-t",
+                    target_pane,
+                    &command,
+                    "C-m",
+                ])?])
+            }
+            None => commands
+                .iter()
+                .map(|command| project.tmux(&["send", "-t", target_pane, command, "C-m"]))
+                .collect(),
+        }
+    }
+
+    fn expect_command(
+        project: &Project,
+        target_pane: &str,
+        expect: &str,
+        send: &str,
+        timeout: Option<u64>,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let timeout = timeout.unwrap_or(DEFAULT_EXPECT_TIMEOUT);
+
+        // Poll the pane's content 5 times a second until the expected text shows up
+        // Polls are counted rather than relying on `timeout`, which isn't available everywhere
+        let capture = project.tmux(&["capture-pane", "-p", "-t", target_pane])?;
+        let wait = format!(
+            "polls=0; until {} | grep -qF -- {}; do [ $polls -lt {} ] || exit 1; polls=$((polls + 1)); sleep 0.2; done",
+            capture,
+            quote(expect),
+            timeout * 5
+        );
+        let send = project.tmux(&["send", "-t", target_pane, send, "C-m"])?;
+
+        // Timed out steps leave a tmux buffer behind that fails the start once the source is done
+        let message = format!(
+            "{:?} did not show up in pane {} after {} seconds",
+            expect, target_pane, timeout
+        );
+        let buffer_name = format!("{}{}", EXPECT_TIMEOUT_BUFFER_PREFIX, get_hash(&message));
+        let mut on_timeout = vec![project.tmux(&["setb", "-b", &buffer_name, &message])?];
+        if project.notify {
+            on_timeout.push(notify_command(&format!(
+                "{:?} did not show up in pane {}",
+                expect, target_pane
+            )));
+        }
+
+        Ok(format!(
+            "if sh -c {}; then {}; else {}; fi",
+            quote(&wait),
+            send,
+            on_timeout.join("; ")
+        ))
+    }
+
+    // Kills the hook once it runs past the project's hook_timeout
+    // Timed out hooks leave a tmux buffer behind that's reported once the source is done
+    // The resulting command expects a POSIX-compatible shell
+    pub fn hook_command(
+        project: &Project,
+        hook: &str,
+        command: String,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let timeout = match project.hook_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(logged_hook_command(project, hook, command)),
+        };
+
+        let run_command = format!(
+            "timeout {} sh -c {}",
+            timeout,
+            quote(&shell_hook_command(project, command.to_owned()))
+        );
+        let message = format!("{} was killed after {} seconds", hook, timeout);
+        let buffer_name = format!("{}{}", HOOK_TIMEOUT_BUFFER_PREFIX, get_hash(&message));
+        Ok(format!(
+            "{}; [ $? -ne 124 ] || {}",
+            log_hook_status(project, hook, &command, run_command),
+            project.tmux(&["setb", "-b", &buffer_name, &message])?,
+        ))
+    }
+
+    // Same as hook_command, but flags the hook as failed when it exits with a non-zero status
+    // (or times out) and strict_hooks is set
+    pub fn strict_hook_command(
+        project: &Project,
+        hook: &str,
+        command: String,
+    ) -> Result<String, Box<dyn error::Error>> {
+        if !project.strict_hooks {
+            return hook_command(project, hook, command);
+        }
+
+        let mut run_command = format!(
+            "sh -c {}",
+            quote(&shell_hook_command(project, command.to_owned()))
+        );
+        if let Some(timeout) = project.hook_timeout {
+            run_command = format!("timeout {} {}", timeout, run_command);
+        }
+
+        let mut steps = vec![
+            log_hook_status(project, hook, &command, run_command),
+            String::from("status=$?"),
+        ];
+        if let Some(timeout) = project.hook_timeout {
+            let message = format!("{} was killed after {} seconds", hook, timeout);
+            let buffer_name = format!("{}{}", HOOK_TIMEOUT_BUFFER_PREFIX, get_hash(&message));
+            steps.push(format!(
+                "[ $status -ne 124 ] || {}",
+                project.tmux(&["setb", "-b", &buffer_name, &message])?
+            ));
+        }
+        steps.push(format!(
+            "[ $status -eq 0 ] || {{ {} {}\"$status\"; {}; }}",
+            project.tmux(&["setb", "-b", HOOK_FAILED_BUFFER])?,
+            quote(&format!("{} failed with exit status ", hook)),
+            project.tmux(&["setenv", "-g", HOOK_FAILED_VAR, "1"])?,
+        ));
+
+        Ok(steps.join("; "))
+    }
+
+    // Same as shell_hook_command, but also records the hook in the project's hook_log
+    pub fn logged_hook_command(project: &Project, hook: &str, command: String) -> String {
+        if project.hook_log.is_none() {
+            return shell_hook_command(project, command);
+        }
+
+        let run_command = format!(
+            "sh -c {}",
+            quote(&shell_hook_command(project, command.to_owned()))
+        );
+        log_hook_status(project, hook, &command, run_command)
+    }
+
+    // Appends a timestamped line with the hook's exit status and expanded command to hook_log
+    // The hook's exit status is kept so it can still be checked afterwards
+    fn log_hook_status(
+        project: &Project,
+        hook: &str,
+        command: &str,
+        run_command: String,
+    ) -> String {
+        let log_file = match &project.hook_log {
+            Some(log_file) => log_file,
+            None => return run_command,
+        };
+
+        format!(
+            "{}; status=$?; printf '%s %s exited with %s: %s\\n' \"$(date '+%Y-%m-%d %H:%M:%S')\" {} \"$status\" {} >> {}; (exit $status)",
+            run_command,
+            quote(hook),
+            quote(command),
+            quote(&log_file.to_string_lossy()),
+        )
+    }
+
+    // Runs the hook through the user's login shell when hook_login_shell is set
+    // so it picks up the same environment as interactive shells
+    pub fn shell_hook_command(project: &Project, command: String) -> String {
+        if project.hook_login_shell {
+            format!(r#""${{SHELL:-/bin/sh}}" -lc {}"#, quote(&command))
+        } else {
+            command
+        }
+    }
+
+    pub fn report_failed_hook(project: &Project) -> Result<(), Box<dyn error::Error>> {
+        let (tmux_command, tmux_args) =
+            project.tmux_command(&["showb", "-b", HOOK_FAILED_BUFFER])?;
+        let output = Command::new(tmux_command).args(tmux_args).output()?;
+        if !output.status.success() {
+            return Ok(());
+        }
+
+        for args in &[
+            ["deleteb", "-b", HOOK_FAILED_BUFFER],
+            ["setenv", "-gu", HOOK_FAILED_VAR],
+        ] {
+            let (tmux_command, tmux_args) = project.tmux_command(args)?;
+            Command::new(tmux_command).args(tmux_args).output()?;
+        }
+
+        Err(Error::HookFailed {
+            message: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        }
+        .into())
+    }
+
+    fn all_pane_commands(project: &Project) -> impl Iterator<Item = &PaneCommand> {
+        project
+            .pane_commands
+            .iter()
+            .chain(project.windows.iter().flat_map(|window| {
+                window
+                    .pane_commands
+                    .iter()
+                    .chain(window.panes.iter().flat_map(|pane| pane.commands.iter()))
+            }))
+    }
+
+    fn has_expect_steps(project: &Project) -> bool {
+        all_pane_commands(project).any(|command| matches!(command, PaneCommand::Expect { .. }))
+    }
+
+    pub fn uses_timeout_command(project: &Project) -> bool {
+        project.hook_timeout.is_some()
+            || all_pane_commands(project).any(|command| {
+                matches!(
+                    command,
+                    PaneCommand::Run {
+                        timeout: Some(_),
+                        ..
+                    }
+                )
+            })
+    }
+
+    // Reads and deletes the buffers starting with prefix, oldest first
+    fn take_buffers(project: &Project, prefix: &str) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let (tmux_command, tmux_args) = project.tmux_command(&["lsb", "-F", "#{buffer_name}"])?;
+        let output = Command::new(tmux_command).args(tmux_args).output()?;
+
+        // Buffers are listed most recent first
+        let buffer_names = String::from_utf8_lossy(&output.stdout);
+        let mut contents = vec![];
+        for buffer_name in buffer_names
+            .lines()
+            .rev()
+            .filter(|name| name.starts_with(prefix))
+        {
+            let (tmux_command, tmux_args) = project.tmux_command(&["showb", "-b", buffer_name])?;
+            let output = Command::new(tmux_command).args(tmux_args).output()?;
+            contents.push(String::from_utf8_lossy(&output.stdout).trim().to_string());
+
+            let (tmux_command, tmux_args) =
+                project.tmux_command(&["deleteb", "-b", buffer_name])?;
+            Command::new(tmux_command).args(tmux_args).output()?;
+        }
+
+        Ok(contents)
+    }
+
+    pub fn report_expect_timeouts(project: &Project) -> Result<(), Box<dyn error::Error>> {
+        let messages = take_buffers(project, EXPECT_TIMEOUT_BUFFER_PREFIX)?;
+        ensure!(
+            messages.is_empty(),
+            ExpectTimedOut {
+                message: messages.join(", ")
+            }
+        );
+
+        Ok(())
+    }
+
+    pub fn report_hook_timeouts(project: &Project) -> Result<(), Box<dyn error::Error>> {
+        for message in take_buffers(project, HOOK_TIMEOUT_BUFFER_PREFIX)? {
+            eprintln!("warning: {}", message);
+        }
+
+        Ok(())
+    }
+
+    // Desktop notifications are best effort, a missing notifier shouldn't break the session
+    pub fn notify_command(message: &str) -> String {
+        if cfg!(target_os = "macos") {
+            let script = format!("display notification {:?} with title \"airmux\"", message);
+            format!("osascript -e {} >/dev/null 2>&1 || true", quote(&script))
+        } else {
+            format!(
+                "notify-send -a airmux airmux {} >/dev/null 2>&1 || true",
+                quote(message)
+            )
+        }
+    }
+
+    pub struct TmuxDummySession<'a> {
+        project: &'a Project,
+    }
+
+    impl<'a> TmuxDummySession<'a> {
+        pub fn new(project: &'a Project) -> Result<TmuxDummySession<'a>, Box<dyn error::Error>> {
+            // Create dummy tmux session to make sure the tmux server is up and running
+            let (tmux_command, tmux_args) =
+                project.tmux_command(&["new", "-s", DUMMY_SESSION_NAME, "-d"])?;
+
+            let _ = Command::new(tmux_command)
+                .args(tmux_args)
+                .env_remove("TMUX")
+                .spawn()?
+                .wait();
+
+            Ok(TmuxDummySession { project })
+        }
+    }
+
+    impl<'a> Drop for TmuxDummySession<'a> {
+        fn drop(&mut self) {
+            // Remove dummy session
+            if let Ok((tmux_command, tmux_args)) =
+                self.project
+                    .tmux_command(&["kill-session", "-t", DUMMY_SESSION_NAME])
+            {
+                if let Ok(mut child) = Command::new(tmux_command).args(tmux_args).spawn() {
+                    let _ = child.wait();
+                }
+            }
+        }
+    }
+}
+
+mod edit {
+    use super::*;
+
+    pub fn create_project<P>(
+        project_name: &str,
+        project_path: P,
+        extension: &str,
+        content: Option<&str>,
+    ) -> Result<(), Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let project_name = strip_extension_from_project_name(project_name);
+
+        let project_path = project_path.as_ref();
+        let mut file = fs::File::create(project_path)?;
+
+        let content = match content {
+            Some(content) => content.to_string(),
+            None => {
+                let as_json = extension == "json";
+
+                let content = if as_json {
+                    include_str!("assets/default_project.json")
+                } else {
+                    include_str!("assets/default_project.yml")
+                };
+
+                let project_name = if as_json {
+                    serde_json::to_string(&project_name)?
+                } else {
+                    // serde_yaml adds '---\n' at the beginning that we need to get rid of before using the name
+                    let serialized = serde_yaml::to_string(&project_name)?;
+                    serialized[4..].to_string()
+                };
+
+                content.replace("__PROJECT_NAME__", &project_name)
+            }
+        };
+
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+
+        Ok(())
+    }
+
+    pub fn check_supported_extension(
+        config: &Config,
+        extension: &str,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let extension = extension.to_lowercase();
+
+        ensure!(
+            config.is_supported_extension(&extension),
+            UnsupportedFileExtension { extension }
+        );
+
+        Ok(())
+    }
+
+    // Disguise the project name as a Path for easy access to .with_extension()
+    pub fn strip_extension_from_project_name<P>(project_name: P) -> String
+    where
+        P: AsRef<Path>,
+    {
+        project_name
+            .as_ref()
+            .with_extension("")
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn open_in_editor(
+        config: &Config,
+        project_name: &str,
+        project_file: PathBuf,
+        extension: &str,
+        editor: &str,
+        content: Option<&str>,
+        no_check: bool,
+        args: &[&str],
+        session: Option<&str>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        ensure!(!editor.is_empty(), EditorEmpty);
+
+        prepare_project_file(project_name, &project_file, extension, content)?;
+
+        let mut line = None;
+        loop {
+            // Open it with editor
+            let (command, command_args) = utils::editor_command(
+                editor,
+                &config.editor_args,
+                &project_file.to_string_lossy(),
+                line,
+            )?;
+            let mut child = Command::new(command).args(command_args).spawn()?;
+
+            // Wait for editor to close if  we want to check the project file's new content
+            if no_check {
+                return Ok(());
+            }
+            child.wait()?;
+
+            // Perform a check on the project
+            let err = match check_project_file(config, project_name, &project_file, args, session) {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+
+            // Offer to jump right back to the line the error is at
+            line = match err.downcast_ref::<Diagnostic>().and_then(|diag| diag.line) {
+                Some(line) if console::user_attended() => Some(line),
+                _ => return Err(err),
+            };
+
+            eprintln!("{}", err);
+            let message = format!("Re-open the editor at line {}?", line.unwrap());
+            if !utils::prompt_confirmation(&message, true)? {
+                return Err(Error::ProjectFileHasErrors { path: project_file }.into());
+            }
+        }
+    }
+
+    // Creates the project file unless it already exists, returns whether it was written
+    pub fn prepare_project_file(
+        project_name: &str,
+        project_file: &Path,
+        extension: &str,
+        content: Option<&str>,
+    ) -> Result<bool, Box<dyn error::Error>> {
+        // Make sure the project's parent directory exists
+        if let Some(parent) = project_file.parent() {
+            mkdirp(parent)?;
+        }
+
+        // Make sure the project file exists
+        ensure!(
+            !project_file.is_dir(),
+            ProjectFileIsADirectory { path: project_file }
+        );
+
+        // If file does not exist or we have updated content
+        if !project_file.exists() || content.is_some() {
+            edit::create_project(project_name, project_file, extension, content)?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    pub fn check_project_file(
+        config: &Config,
+        project_name: &str,
+        project_file: &Path,
+        args: &[&str],
+        session: Option<&str>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let project_yaml = project::read(project_file, args)?;
+        let projects = project::load_sessions(
+            config,
+            project_name,
+            project_file,
+            &project_yaml,
+            None,
+            session,
+        )?;
+        for project in projects {
+            project
+                .check()
+                .map_err(|err| Diagnostic::new(project_file, err.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
+
+mod list {
+    use super::*;
+
+    // Pipes the project list through the picker, an empty selection means it was cancelled
+    pub fn pick_project(
+        config: &Config,
+        picker: &str,
+    ) -> Result<Option<String>, Box<dyn error::Error>> {
+        // Running projects are marked after a tab, which is dropped from the selection
+        let projects: Vec<String> = ui::get_entries(config)?
+            .into_iter()
+            .map(|entry| {
+                if entry.running {
+                    format!("{}\trunning", entry.project_name)
+                } else {
+                    entry.project_name
+                }
+            })
+            .collect();
+
+        let (command, command_args) = utils::parse_command(picker, &[])?;
+        let mut child = Command::new(command)
+            .args(command_args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        // The picker may exit without reading the whole list
+        if let Some(stdin) = child.stdin.as_mut() {
+            match stdin.write_all(projects.join("\n").as_bytes()) {
+                Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err.into()),
+                _ => {}
+            }
+        }
+
+        let output = child.wait_with_output()?;
+        let selection = String::from_utf8_lossy(&output.stdout);
+        let selection = selection.split('\t').next().unwrap_or_default().trim();
+
+        if selection.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(selection.to_string()))
+        }
+    }
+
+    pub fn get_dirty_projects(
+        config: &Config,
+    ) -> Result<Vec<(String, String)>, Box<dyn error::Error>> {
+        let mut dirty_projects = vec![];
+
+        for project_name in get_projects(config, config.get_projects_dir("")?)? {
+            let (_, project_file) = project::get_filename(config, Some(&project_name), None)?;
+            if let Err(err) =
+                edit::check_project_file(config, &project_name, &project_file, &[], None)
+            {
+                dirty_projects.push((project_name, err.to_string()));
+            }
+        }
+
+        Ok(dirty_projects)
+    }
+
+    pub fn get_projects<P>(config: &Config, path: P) -> Result<Vec<String>, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut projects = vec![];
+
+        for entry in path.read_dir()? {
+            let entry = entry?;
+            let entry_path = entry.path();
+
+            if entry_path.is_file() {
+                // Ignore file if it doesn't have a supported file extension
+                if let Some(extension) = entry_path.extension() {
+                    let extension = extension.to_string_lossy();
+
+                    if config.is_supported_extension(&extension) {
+                        let file_path = entry_path.strip_prefix(path)?;
+                        let file_path_str =
+                            file_path.with_extension("").to_string_lossy().to_string();
+                        projects.push(file_path_str);
+                    }
+                }
+            } else if entry_path.is_dir() {
+                // Check for symlink loops
+                let subdir = if entry.file_type()?.is_symlink() {
+                    let subdir = entry_path.read_link()?;
+
+                    if entry_path.starts_with(&subdir) {
+                        continue;
+                    }
+
+                    subdir
+                } else {
+                    entry_path.clone()
+                };
+
+                let file_path = entry_path.strip_prefix(path)?;
+                let mut subdir_projects = list::get_projects(config, &subdir)?
+                    .into_iter()
+                    .map(|entry| file_path.join(entry).to_string_lossy().to_string())
+                    .collect();
+                projects.append(&mut subdir_projects);
+            }
+        }
+
+        Ok(projects)
+    }
+}
+
+mod menu {
+    use super::*;
+
+    const MENU_KEYS: &str = "1234567890abcdefghijklmnoprstuvwxyz";
+
+    // Items past the available keys are still listed, they just can't be picked by key
+    pub fn get_key(index: usize) -> String {
+        MENU_KEYS
+            .chars()
+            .nth(index)
+            .map_or_else(String::new, String::from)
+    }
+
+    pub fn get_menu_args(
+        config: &Config,
+        airmux: &str,
+        title: &str,
+        projects: &[String],
+    ) -> Vec<String> {
+        let mut start_command = vec![airmux.to_string()];
+        if let Some(config_dir) = &config.config_dir {
+            start_command.push(String::from("--config-dir"));
+            start_command.push(config_dir.to_string_lossy().to_string());
+        }
+        start_command.push(String::from("start"));
+        start_command.push(String::from("--switch"));
+        start_command.extend(config.get_tmux_args());
+
+        let mut menu_args = vec![
+            String::from("display-menu"),
+            String::from("-T"),
+            title.to_string(),
+        ];
+        for (index, project_name) in projects.iter().enumerate() {
+            let mut command = start_command.clone();
+            command.push(project_name.to_owned());
+
+            menu_args.push(project_name.to_owned());
+            menu_args.push(get_key(index));
+            menu_args.push(tmux_join(&["run-shell", &shell_words::join(command)]));
+        }
+
+        menu_args
+    }
+}
+
+mod profile {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    pub struct Profile {
+        enabled: bool,
+        start: Instant,
+        last: Instant,
+        steps: Vec<(&'static str, Duration)>,
+    }
+
+    impl Profile {
+        pub fn new(enabled: bool) -> Self {
+            let now = Instant::now();
+
+            Self {
+                enabled,
+                start: now,
+                last: now,
+                steps: vec![],
+            }
+        }
+
+        // Records the time spent since the previous step
+        pub fn step(&mut self, name: &'static str) {
+            if self.enabled {
+                let now = Instant::now();
+                self.steps.push((name, now - self.last));
+                self.last = now;
+            }
+        }
+
+        pub fn report(&self) -> String {
+            let total = self.last - self.start;
+
+            self.steps
+                .iter()
+                .chain(iter::once(&("total", total)))
+                .map(|(name, duration)| {
+                    format!("{:<16}{:>10.1}ms", name, duration.as_secs_f64() * 1000.0)
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+
+        pub fn print(&self) {
+            if self.enabled {
+                eprintln!("{}", self.report());
+            }
+        }
+    }
+}
+
+mod history {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Entry {
+        pub timestamp: u64,
+        pub project_name: String,
+        pub project_file: PathBuf,
+    }
+
+    pub fn timestamp() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs())
+    }
+
+    pub fn get_entries(config: &Config) -> Result<Vec<Entry>, Box<dyn error::Error>> {
+        let history_file = config.get_data_dir("")?.join(HISTORY_FILE);
+        if !history_file.is_file() {
+            return Ok(vec![]);
+        }
+
+        // Each line is formatted as: <timestamp>\t<project_name>\t<project_file>
+        let entries = fs::read_to_string(history_file)?
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, '\t');
+                let timestamp = parts.next()?.parse::<u64>().ok()?;
+                let project_name = parts.next()?.to_string();
+                let project_file = PathBuf::from(parts.next()?);
+
+                Some(Entry {
+                    timestamp,
+                    project_name,
+                    project_file,
+                })
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    pub fn add_entry<P>(
+        config: &Config,
+        project_name: &str,
+        project_file: P,
+    ) -> Result<(), Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let project_file = project_file.as_ref();
+
+        // Projects piped through stdin cannot be started again
+        if project_file == PathBuf::new() {
+            return Ok(());
+        }
+
+        let project_file = fs::canonicalize(project_file).unwrap_or_else(|_| project_file.into());
+
+        // Move the project to the top of the list
+        let entries: Vec<Entry> = iter::once(Entry {
+            timestamp: timestamp(),
+            project_name: project_name.to_string(),
+            project_file: project_file.to_owned(),
+        })
+        .chain(
+            history::get_entries(config)?
+                .into_iter()
+                .filter(|entry| entry.project_file != project_file),
+        )
+        .take(HISTORY_SIZE)
+        .collect();
+
+        let content: String = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{}\t{}\t{}\n",
+                    entry.timestamp,
+                    entry.project_name,
+                    entry.project_file.to_string_lossy()
+                )
+            })
+            .collect();
+
+        let history_file = config.get_data_dir("")?.join(HISTORY_FILE);
+        fs::write(history_file, content)?;
+
+        Ok(())
+    }
+
+    pub fn get_last_project(config: &Config) -> Result<(String, PathBuf), Box<dyn error::Error>> {
+        let entry = history::get_entries(config)?
+            .into_iter()
+            .next()
+            .ok_or(Error::NoRecentProject)?;
+
+        Ok((entry.project_name, entry.project_file))
+    }
+}
+
+mod registry {
+    use super::*;
+
+    // Sessions started or adopted by airmux, and the project files they belong to
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Entry {
+        pub session_name: String,
+        pub project_name: String,
+        pub project_file: PathBuf,
+    }
+
+    pub fn get_entries(config: &Config) -> Result<Vec<Entry>, Box<dyn error::Error>> {
+        let registry_file = config.get_data_dir("")?.join(REGISTRY_FILE);
+        if !registry_file.is_file() {
+            return Ok(vec![]);
+        }
+
+        // Each line is formatted as: <session_name>\t<project_name>\t<project_file>
+        let entries = fs::read_to_string(registry_file)?
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, '\t');
+                let session_name = parts.next()?.to_string();
+                let project_name = parts.next()?.to_string();
+                let project_file = PathBuf::from(parts.next()?);
+
+                Some(Entry {
+                    session_name,
+                    project_name,
+                    project_file,
+                })
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    pub fn add_entry<P>(
+        config: &Config,
+        session_name: &str,
+        project_name: &str,
+        project_file: P,
+    ) -> Result<(), Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let project_file = project_file.as_ref();
+        let project_file = fs::canonicalize(project_file).unwrap_or_else(|_| project_file.into());
+
+        let entries: Vec<Entry> = registry::get_entries(config)?
+            .into_iter()
+            .filter(|entry| entry.session_name != session_name)
+            .chain(iter::once(Entry {
+                session_name: session_name.to_string(),
+                project_name: project_name.to_string(),
+                project_file,
+            }))
+            .collect();
+
+        write_entries(config, &entries)
+    }
+
+    pub fn remove_entry(config: &Config, session_name: &str) -> Result<(), Box<dyn error::Error>> {
+        let entries = registry::get_entries(config)?;
+        if entries
+            .iter()
+            .all(|entry| entry.session_name != session_name)
+        {
+            return Ok(());
+        }
+
+        let entries: Vec<Entry> = entries
+            .into_iter()
+            .filter(|entry| entry.session_name != session_name)
+            .collect();
+
+        write_entries(config, &entries)
+    }
+
+    pub fn write_entries(config: &Config, entries: &[Entry]) -> Result<(), Box<dyn error::Error>> {
+        let content: String = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{}\t{}\t{}\n",
+                    entry.session_name,
+                    entry.project_name,
+                    entry.project_file.to_string_lossy()
+                )
+            })
+            .collect();
+
+        let registry_file = config.get_data_dir("")?.join(REGISTRY_FILE);
+        fs::write(registry_file, content)?;
+
+        Ok(())
+    }
+}
+
+mod lock {
+    use super::*;
+
+    // A lock file per session name, removed once the session is done being started
+    pub struct SessionLock {
+        path: PathBuf,
+    }
+
+    impl SessionLock {
+        pub fn acquire(
+            config: &Config,
+            session_name: &str,
+            timeout: Duration,
+        ) -> Result<SessionLock, Box<dyn error::Error>> {
+            let path = config
+                .get_data_dir(LOCKS_DIR)?
+                .join(format!("{}.lock", session_name.replace('/', "_")));
+
+            let start = Instant::now();
+            loop {
+                match fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                {
+                    Ok(mut file) => {
+                        let _ = writeln!(file, "{}", std::process::id());
+                        return Ok(SessionLock { path });
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                    Err(err) => return Err(err.into()),
+                }
+
+                if is_stale(&path) {
+                    let _ = fs::remove_file(&path);
+                    continue;
+                }
+
+                ensure!(
+                    start.elapsed() < timeout,
+                    SessionLocked { session_name, path }
+                );
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+
+    impl Drop for SessionLock {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    // A lock is stale once its owner is gone, or when it's too old to tell who owns it
+    pub fn is_stale(path: &Path) -> bool {
+        let owner = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| content.trim().parse::<u32>().ok());
+        if let Some(pid) = owner {
+            return !is_running(pid);
+        }
+
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > LOCK_STALE_AFTER)
+    }
+
+    pub fn is_running(pid: u32) -> bool {
+        Command::new("kill")
+            .arg("-0")
+            .arg(pid.to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+}
+
+mod clean {
+    use super::*;
+
+    // Returns the stale lock files, and whether any lock is still held by a running start
+    pub fn get_locks(config: &Config) -> Result<(Vec<PathBuf>, bool), Box<dyn error::Error>> {
+        let locks_dir = config.get_data_dir(LOCKS_DIR)?;
+        if !locks_dir.is_dir() {
+            return Ok((vec![], false));
+        }
+
+        let mut stale_locks = vec![];
+        let mut starting = false;
+        for dir_entry in fs::read_dir(locks_dir)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("lock") {
+                continue;
+            }
+
+            if lock::is_stale(&path) {
+                stale_locks.push(path);
+            } else {
+                starting = true;
+            }
+        }
+        stale_locks.sort();
+
+        Ok((stale_locks, starting))
+    }
+
+    // Parses the output of `showenv -g`, where removed variables are prefixed with a dash
+    pub fn get_global_variables(output: &str) -> Vec<String> {
+        output
+            .lines()
+            .map(|line| line.trim_start_matches('-'))
+            .map(|line| line.split('=').next().unwrap_or_default())
+            .filter(|name| name.starts_with(GLOBAL_VAR_PREFIX))
+            .map(String::from)
+            .collect()
+    }
+
+    // Registry entries are kept as long as both their session and their project file exist
+    pub fn split_stale_entries(
+        entries: Vec<registry::Entry>,
+        running_sessions: &[String],
+    ) -> (Vec<registry::Entry>, Vec<registry::Entry>) {
+        entries.into_iter().partition(|entry| {
+            running_sessions.contains(&entry.session_name) && entry.project_file.is_file()
+        })
+    }
+
+    pub fn tmux_output(config: &Config, args: &[&str]) -> String {
+        config
+            .get_tmux_command(args)
+            .ok()
+            .and_then(|(tmux, arguments)| Command::new(tmux).args(arguments).output().ok())
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn run_tmux(config: &Config, args: &[&str]) -> Result<(), Box<dyn error::Error>> {
+        let (tmux_command, tmux_args) = config.get_tmux_command(args)?;
+        Command::new(tmux_command).args(tmux_args).spawn()?.wait()?;
+
+        Ok(())
+    }
+}
+
+mod ui {
+    use super::*;
+
+    const HELP: &str =
+        "enter: start  d: start detached  x: kill  e: edit  f: freeze into  j/k: move  q: quit";
+
+    #[derive(Debug, PartialEq)]
+    pub struct Entry {
+        pub project_name: String,
+        pub session_name: String,
+        pub running: bool,
+    }
+
+    pub fn get_entries(config: &Config) -> Result<Vec<Entry>, Box<dyn error::Error>> {
+        let projects_dir = config.get_projects_dir("")?;
+        let mut project_names = list::get_projects(config, &projects_dir)?;
+        project_names.sort();
+
+        let running_sessions = get_running_sessions(config);
+        let registry_entries = registry::get_entries(config)?;
+
+        Ok(project_names
+            .into_iter()
+            .map(|project_name| {
+                // Sessions the project started or adopted may not be named after the project file
+                let registered_session = registry_entries.iter().find(|entry| {
+                    entry.project_name == project_name
+                        && running_sessions.contains(&entry.session_name)
+                });
+                let session_name = match registered_session {
+                    Some(entry) => entry.session_name.to_owned(),
+                    None => project::get_filename(config, Some(&project_name), None)
+                        .ok()
+                        .and_then(|(_, project_file)| fs::read_to_string(project_file).ok())
+                        .and_then(|content| get_session_name(config, &project_name, &content))
+                        .unwrap_or_else(|| config.get_session_name(&project_name)),
+                };
+                let running = running_sessions.contains(&session_name);
+
+                Entry {
+                    project_name,
+                    session_name,
+                    running,
+                }
+            })
+            .collect())
+    }
+
+    // Reads the session name without loading the whole project, to keep warnings off the screen
+    pub fn get_session_name(config: &Config, project_name: &str, content: &str) -> Option<String> {
+        #[derive(Deserialize)]
+        struct SessionName {
+            #[serde(alias = "name")]
+            session_name: Option<String>,
+            #[serde(default)]
+            sanitize_names: bool,
+            #[serde(default)]
+            per_worktree: bool,
+            #[serde(default, alias = "root")]
+            working_dir: Option<String>,
+        }
+
+        serde_yaml::from_str::<SessionName>(content)
+            .ok()
+            .and_then(|project| {
+                let working_dir = project
+                    .working_dir
+                    .map(|working_dir| PathBuf::from(shellexpand::tilde(&working_dir).to_string()));
+
+                Project {
+                    session_name: project.session_name,
+                    sanitize_names: project.sanitize_names,
+                    per_worktree: project.per_worktree,
+                    working_dir,
+                    ..Project::default()
+                }
+                .prepare(config, project_name, None)
+                .session_name
+            })
+    }
+
+    pub fn get_running_sessions(config: &Config) -> Vec<String> {
+        list_sessions(config.get_tmux_command(&["ls", "-F", "#{session_name}"]))
+    }
+
+    // Runs the `ls` command of a tmux server, which may not be the default one
+    pub fn list_sessions(
+        command: Result<(String, Vec<String>), Box<dyn error::Error>>,
+    ) -> Vec<String> {
+        let output = command
+            .ok()
+            .and_then(|(tmux, arguments)| Command::new(tmux).args(arguments).output().ok());
+
+        match output {
+            Some(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(String::from)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn draw(
+        term: &Term,
+        entries: &[Entry],
+        selected: usize,
+        message: &str,
+    ) -> Result<(), Box<dyn error::Error>> {
+        term.write_line("airmux projects")?;
+        term.write_line("")?;
+
+        if entries.is_empty() {
+            term.write_line("  no projects yet, create one with `airmux edit <project>`")?;
+        }
+
+        for (index, entry) in entries.iter().enumerate() {
+            let cursor = if index == selected { ">" } else { " " };
+            let status = if entry.running { "running" } else { "" };
+            term.write_line(&format!("{} {:<40} {}", cursor, entry.project_name, status))?;
+        }
+
+        term.write_line("")?;
+        term.write_line(HELP)?;
+        if !message.is_empty() {
+            term.write_line(message)?;
+        }
+
+        Ok(())
+    }
+}
+
+mod init {
+    use super::*;
+
+    // Picks up the usual entry points of the tools used in the directory
+    pub fn detect_windows(project_dir: &Path) -> Vec<Window> {
+        let mut windows = vec![window("editor", "${EDITOR:-vi} .")];
+
+        if project_dir.join("Cargo.toml").is_file() {
+            windows.push(window("cargo", "cargo build"));
+        }
+
+        if let Ok(package) = fs::read_to_string(project_dir.join("package.json")) {
+            let runner = if project_dir.join("yarn.lock").is_file() {
+                "yarn"
+            } else {
+                "npm"
+            };
+
+            let command = match get_package_script(&package) {
+                Some(script) if runner == "yarn" => format!("yarn {}", script),
+                Some(script) => format!("npm run {}", script),
+                None => format!("{} install", runner),
+            };
+            windows.push(window(runner, &command));
+        }
+
+        let makefile = ["GNUmakefile", "makefile", "Makefile"]
+            .iter()
+            .find_map(|name| fs::read_to_string(project_dir.join(name)).ok());
+        if let Some(makefile) = makefile {
+            let command = match get_make_target(&makefile) {
+                Some(target) => format!("make {}", target),
+                None => String::from("make"),
+            };
+            windows.push(window("make", &command));
+        }
+
+        windows
+    }
+
+    fn window(name: &str, command: &str) -> Window {
+        Window {
+            name: Some(name.to_string()),
+            panes: vec![Pane::from(command)],
+            ..Window::default()
+        }
+    }
+
+    pub fn get_package_script(package: &str) -> Option<String> {
+        let package = serde_json::from_str::<serde_json::Value>(package).ok()?;
+        let scripts = package.get("scripts")?.as_object()?;
+
+        ["dev", "start", "serve", "watch"]
+            .iter()
+            .find(|script| scripts.contains_key(**script))
+            .map(|script| script.to_string())
+    }
+
+    // The first target is the one make runs by default
+    pub fn get_make_target(makefile: &str) -> Option<String> {
+        makefile.lines().find_map(|line| {
+            let (target, rest) = line.split_once(':')?;
+            let is_target = !target.is_empty()
+                && !target.starts_with('.')
+                && !rest.starts_with('=')
+                && target
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "_-./".contains(c));
+
+            if is_target {
+                Some(target.to_string())
+            } else {
+                None
+            }
+        })
+    }
+}
+
+mod up {
+    use super::*;
+
+    // A leading word followed by a colon names the window, the rest is its command
+    pub fn parse_window(spec: &str) -> Window {
+        let (name, command) = match spec.split_once(':') {
+            Some((name, command)) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                (Some(name.to_string()), command.trim())
+            }
+            _ => (None, spec.trim()),
+        };
+
+        let pane = if command.is_empty() {
+            Pane::default()
+        } else {
+            Pane::from(command)
+        };
+
+        Window {
+            name,
+            panes: vec![pane],
+            ..Window::default()
+        }
+    }
+}
+
+mod fetch {
+    use super::*;
+
+    // Uses the last segment of the url's path, without its extension
+    pub fn get_project_name(url: &str) -> Option<String> {
+        let path = url.split(['?', '#']).next()?;
+        let (_, path) = path.split_once("://").unwrap_or(("", path));
+        let (_, file_name) = path.trim_end_matches('/').rsplit_once('/')?;
+
+        let project_name = edit::strip_extension_from_project_name(file_name);
+        if project_name.is_empty() {
+            None
+        } else {
+            Some(project_name)
+        }
+    }
+
+    pub fn download(url: &str) -> Result<String, Box<dyn error::Error>> {
+        let output = Command::new("curl").args(["-fsSL", "--", url]).output()?;
+        ensure!(
+            output.status.success(),
+            FetchFailed {
+                url: url.to_string()
+            }
+        );
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    // Lists the commands the project runs, either as hooks or typed in its panes
+    pub fn get_commands(project: &Project) -> Vec<String> {
+        fn pane_commands(commands: &[PaneCommand]) -> Vec<String> {
+            commands
+                .iter()
+                .filter_map(|command| match command {
+                    PaneCommand::Expect { send, .. } => Some(send.to_owned()),
+                    _ => command.shell_command(),
+                })
+                .collect()
+        }
+
+        let mut commands = vec![];
+        commands.extend(project.on_start.iter().cloned());
+        commands.extend(project.on_first_start.iter().cloned());
+        commands.extend(project.on_every_start.iter().cloned());
+        commands.extend(project.on_restart.iter().cloned());
+        commands.extend(project.on_exit.iter().cloned());
+        commands.extend(project.on_stop.iter().cloned());
+        commands.extend(project.post_create.iter().cloned());
+        commands.extend(project.on_window_create.iter().cloned());
+        commands.extend(project.post_window_create.iter().cloned());
+        commands.extend(project.on_pane_create.iter().cloned());
+        commands.extend(project.post_pane_create.iter().cloned());
+        commands.extend(pane_commands(&project.pane_commands));
+
+        for window in &project.windows {
+            commands.extend(window.on_create.iter().cloned());
+            commands.extend(window.post_create.iter().cloned());
+            commands.extend(window.on_pane_create.iter().cloned());
+            commands.extend(window.post_pane_create.iter().cloned());
+            commands.extend(pane_commands(&window.pane_commands));
+
+            for pane in &window.panes {
+                commands.extend(pane.on_create.iter().cloned());
+                commands.extend(pane.post_create.iter().cloned());
+                commands.extend(pane_commands(&pane.commands));
+            }
+        }
+
+        commands.into_iter().map(unprocess_command).collect()
+    }
+}
+
+mod migrate {
+    use super::*;
+
+    // Files without a version field predate versioning
+    pub fn get_version(project_yaml: &Value) -> usize {
+        project_yaml
+            .get("version")
+            .and_then(Value::as_u64)
+            .map_or(0, |version| version as usize)
+    }
+
+    // Works on lines rather than parsed yaml to preserve comments and formatting
+    pub fn migrate_yaml(content: &str) -> String {
+        let version_line = format!("version: {}", SCHEMA_VERSION);
+        let mut has_version = false;
+
+        let mut lines: Vec<String> = content
+            .lines()
+            .map(|line| {
+                if line.starts_with("version:") {
+                    has_version = true;
+                    return version_line.to_owned();
+                }
+
+                for (alias, field) in DEPRECATED_FIELDS {
+                    if let Some(rest) = line.strip_prefix(alias) {
+                        if rest.trim_start().starts_with(':') {
+                            return format!("{}{}", field, rest);
+                        }
+                    }
+                }
+
+                line.to_string()
+            })
+            .collect();
+
+        if !has_version {
+            let index = if matches!(lines.first(), Some(line) if line == "---") {
+                1
+            } else {
+                0
+            };
+            lines.insert(index, version_line);
+        }
+
+        let mut content = lines.join("\n");
+        content.push('\n');
+        content
+    }
+
+    pub fn migrate_json(content: &str) -> Result<String, Box<dyn error::Error>> {
+        let mut value = serde_json::from_str::<serde_json::Value>(content)?;
+
+        if let Some(object) = value.as_object_mut() {
+            for (alias, field) in DEPRECATED_FIELDS {
+                if let Some(field_value) = object.remove(*alias) {
+                    object.insert(field.to_string(), field_value);
+                }
+            }
+
+            object.insert(String::from("version"), SCHEMA_VERSION.into());
+        }
+
+        let mut content = serde_json::to_string_pretty(&value)?;
+        content.push('\n');
+        Ok(content)
+    }
+}
+
+mod prune {
+    use super::*;
+
+    pub struct TmuxWindow {
+        pub id: String,
+        pub index: usize,
+        pub name: String,
+        pub marker: Option<usize>,
+    }
+
+    pub fn prune_windows(project: &Project, no_input: bool) -> Result<(), Box<dyn error::Error>> {
+        let session_name = project
+            .session_name
+            .as_ref()
+            .ok_or(/* should never happen */ Error::SessionNameNotSet {})?;
+
+        let windows = get_windows(project, session_name)?;
+        let windows = get_undeclared_windows(project, windows);
+        if windows.is_empty() {
+            return Ok(());
+        }
+
+        let window_list = windows
+            .iter()
+            .map(|window| format!("{}:{}", window.index, window.name))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        if !no_input
+            && !utils::prompt_confirmation(
+                &format!(
+                    "Kill {} window(s) not declared in {:?} ({})?",
+                    windows.len(),
+                    session_name,
+                    window_list
+                ),
+                false,
+            )?
+        {
+            return Ok(());
+        }
+
+        // Target windows by id, indexes could change if renumber-windows is set
+        for window in windows {
+            let (tmux_command, tmux_args) = project.tmux_command(&["killw", "-t", &window.id])?;
+            Command::new(tmux_command).args(tmux_args).spawn()?.wait()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_windows(
+        project: &Project,
+        session_name: &str,
+    ) -> Result<Vec<TmuxWindow>, Box<dyn error::Error>> {
+        let (tmux_command, tmux_args) = project.tmux_command(&[
+            "lsw",
+            "-t",
+            session_name,
+            "-F",
+            "#{window_id} #{window_index} #{@airmux_window} #{window_name}",
+        ])?;
+        let output = Command::new(tmux_command).args(tmux_args).output()?;
+
+        // Tmux replaces tabs in formats, the name goes last since it may contain spaces
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(4, ' ');
+                let id = parts.next()?.to_string();
+                let index = parts.next()?.parse().ok()?;
+                let marker = parts.next()?.parse().ok();
+                let name = parts.next().unwrap_or_default().to_string();
+
+                Some(TmuxWindow {
+                    id,
+                    index,
+                    name,
+                    marker,
+                })
+            })
+            .collect())
+    }
+
+    // Windows are declared if they carry the marker of one of the project's windows,
+    // wherever they were moved and whatever they were renamed to
+    pub fn get_undeclared_windows(project: &Project, windows: Vec<TmuxWindow>) -> Vec<TmuxWindow> {
+        let declared_markers =
+            project.window_base_index..project.window_base_index + project.windows.len();
+
+        windows
+            .into_iter()
+            .filter(|window| {
+                !window
+                    .marker
+                    .is_some_and(|marker| declared_markers.contains(&marker))
+            })
+            .collect()
+    }
+}
+
+mod freeze {
+    use super::*;
+
+    // Freezes the given session, or the current one
+    pub fn get_project(
+        config: &Config,
+        session: Option<&str>,
+    ) -> Result<Project, Box<dyn error::Error>> {
+        let mut project = Project {
+            windows: vec![],
+            ..Project::default()
+        };
+
+        let session_id = freeze::get_tmux_value(config, "session_id", session)?;
+
+        // The prefix is added back when the project is started
+        let session_name = freeze::get_tmux_value(config, "session_name", Some(&session_id))?;
+        project.session_name = Some(match &config.session_prefix {
+            Some(prefix) => session_name
+                .strip_prefix(prefix.as_str())
+                .unwrap_or(&session_name)
+                .to_string(),
+            None => session_name,
+        });
+
+        // Values are read from the current shell when freezing the current session
+        if let Some(env_capture) = get_session_env(config, &session_id, ENV_CAPTURE_VAR) {
+            project.env_capture = env_capture.split_whitespace().map(String::from).collect();
+        }
+        for name in &project.env_capture {
+            let shell_value = match session {
+                None => env::var(name).ok(),
+                Some(_) => None,
+            };
+            let value = shell_value.or_else(|| get_session_env(config, &session_id, name));
+            if let Some(value) = value {
+                project.environment.insert(name.to_owned(), value);
+            }
+        }
+
+        let mut window_working_dir_map: HashMap<PathBuf, usize> = HashMap::new();
+        let mut window_most_used_working_dir = PathBuf::new();
+        let mut window_most_used_working_dir_count = 0;
+
+        let window_ids = freeze::get_tmux_list_values(config, "lsw", "window_id", &session_id)?;
+        for window_id in &window_ids {
+            let mut window = Window {
+                panes: vec![],
+                ..Window::default()
+            };
+
+            let window_name = freeze::get_tmux_value(config, "window_name", Some(window_id))?;
+            let mut window_name = if window_name.is_empty() {
+                None
+            } else {
+                Some(window_name)
+            };
+
+            let mut pane_working_dir_map: HashMap<PathBuf, usize> = HashMap::new();
+            let mut pane_most_used_working_dir = PathBuf::new();
+            let mut pane_most_used_working_dir_count = 0;
+
+            let pane_ids =
+                freeze::get_tmux_list_values(config, "list-panes", "pane_id", window_id)?;
+            for pane_id in &pane_ids {
+                let mut pane = Pane { ..Pane::default() };
+
+                let pane_current_path = PathBuf::from(freeze::get_tmux_value(
+                    config,
+                    "pane_current_path",
+                    Some(pane_id),
+                )?);
+                pane.working_dir = Some(pane_current_path.to_owned());
+
+                let pane_shell_path = freeze::get_tmux_value(config, "SHELL", Some(pane_id))?;
+
+                let pane_shell = PathBuf::from(&pane_shell_path)
+                    .file_name()
+                    .map_or_else(String::new, |s| s.to_string_lossy().to_string());
+
+                let pane_command_path =
+                    freeze::get_tmux_value(config, "pane_current_command", Some(pane_id))?;
+
+                let pane_command = PathBuf::from(&pane_command_path)
+                    .file_name()
+                    .map_or_else(String::new, |s| s.to_string_lossy().to_string());
+
+                let process_name = env::current_exe()?.file_name().map_or_else(
+                    || String::from("airmux"),
+                    |n| n.to_string_lossy().to_string(),
+                );
+
+                if let Some(name) = &window_name {
+                    if name == &pane_command || name == &pane_shell || name == &process_name {
+                        window_name = None
+                    }
+                }
+
+                match pane_working_dir_map.get(&pane_current_path) {
+                    Some(count_value) => {
+                        let count_value = count_value + 1;
+                        pane_working_dir_map.insert(pane_current_path.to_owned(), count_value);
+
+                        if count_value > pane_most_used_working_dir_count {
+                            pane_most_used_working_dir = pane_current_path;
+                            pane_most_used_working_dir_count = count_value;
+                        }
+                    }
+                    None => {
+                        let count_value = 1;
+                        pane_working_dir_map.insert(pane_current_path.to_owned(), count_value);
+
+                        if count_value >= pane_most_used_working_dir_count {
+                            pane_most_used_working_dir = pane_current_path;
+                            pane_most_used_working_dir_count = count_value;
+                        }
+                    }
+                }
+
+                window.panes.push(pane);
+            }
+
+            // Set window name
+            window.name = window_name;
+
+            // Set working directory if any
+            if pane_most_used_working_dir_count > 0 {
+                window.working_dir = Some(pane_most_used_working_dir.to_owned());
+
+                for pane in &mut window.panes {
+                    if let Some(working_dir) = &pane.working_dir {
+                        if working_dir == &pane_most_used_working_dir {
+                            pane.working_dir = None;
+                        }
+                    }
+                }
+
+                match window_working_dir_map.get(&pane_most_used_working_dir) {
+                    Some(count_value) => {
+                        let count_value = count_value + 1;
+                        window_working_dir_map
+                            .insert(pane_most_used_working_dir.to_owned(), count_value);
+
+                        if count_value > window_most_used_working_dir_count {
+                            window_most_used_working_dir = pane_most_used_working_dir;
+                            window_most_used_working_dir_count = count_value;
+                        }
+                    }
+                    None => {
+                        let count_value = 1;
+                        window_working_dir_map
+                            .insert(pane_most_used_working_dir.to_owned(), count_value);
+
+                        if count_value >= window_most_used_working_dir_count {
+                            window_most_used_working_dir = pane_most_used_working_dir;
+                            window_most_used_working_dir_count = count_value;
+                        }
+                    }
+                }
+            }
+
+            // Set layout
+            let layout = freeze::get_tmux_value(config, "window_layout", Some(window_id))?;
+            window.layout = Some(layout);
+
+            // Add window to project's window list
+            project.windows.push(window)
+        }
+
+        if window_most_used_working_dir_count > 0 {
+            project.working_dir = Some(window_most_used_working_dir.to_owned());
+
+            for window in &mut project.windows {
+                if let Some(working_dir) = &window.working_dir {
+                    if working_dir == &window_most_used_working_dir {
+                        window.working_dir = None;
+                    }
+                }
+            }
+        }
+
+        Ok(project)
+    }
+
+    // Documents the fields of a frozen project, and lists the hooks it doesn't set as comments
+    // The hooks go right before the windows, which usually make up the rest of the file
+    pub fn add_comments(content: &str) -> String {
+        let docs = lsp::reference(lsp::Context::Project);
+        let doc_lines = |field: &str| -> Vec<String> {
+            docs.iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, doc)| doc.lines().map(|line| format!("# {}", line)).collect())
+                .unwrap_or_default()
+        };
+
+        let is_field = |line: &str, field: &str| matches!(line.split_once(':'), Some((key, _)) if key == field);
+        let hooks: Vec<String> = PROJECT_HOOKS
+            .iter()
+            .filter(|hook| !content.lines().any(|line| is_field(line, hook)))
+            .flat_map(|hook| {
+                let mut lines = doc_lines(hook);
+                lines.push(format!("# {}:", hook));
+                lines.push(String::new());
+                lines
+            })
+            .collect();
+        let hooks: Vec<String> = if hooks.is_empty() {
+            hooks
+        } else {
+            iter::once(String::new()).chain(hooks).collect()
+        };
+
+        let mut output = vec![];
+        let mut hooks = Some(hooks);
+        for line in content.lines() {
+            let field = match line.split_once(':') {
+                Some((field, _)) if !line.starts_with([' ', '-', '#']) => field,
+                _ => {
+                    output.push(String::from(line));
+                    continue;
+                }
             };
 
-            // Panes
-            for (pane_index, pane) in window.panes.iter().enumerate() {
-                let target_pane_index = pane_index + project.pane_base_index;
-                let target_pane = &format!("#{{__AIRMUX_PANE_{}}}", target_pane_index);
-                let target_pane_quoted = &tmux_quote(target_pane);
+            if field == "windows" {
+                output.extend(hooks.take().unwrap_or_default());
+            }
+            output.extend(doc_lines(field));
+            output.push(String::from(line));
+        }
+        output.extend(hooks.take().unwrap_or_default());
 
-                // Create pane (first one is automatically created)
-                if pane_index > 0 {
-                    // Split direction (defaults to horizontal)
-                    let mut split_command = vec![
-                        "splitw",
-                        match &pane.split {
-                            Some(split) if *split == PaneSplit::Vertical => "-v",
-                            _ => "-h",
-                        },
-                    ];
+        output.join("\n").trim_end().to_string()
+    }
 
-                    // Working directory
-                    let mut found_working_dir = true;
-                    let mut working_dir = String::new();
+    pub fn get_tmux_value(
+        config: &Config,
+        value: &str,
+        target: Option<&str>,
+    ) -> Result<String, Box<dyn error::Error>> {
+        ensure!(
+            target.is_some() || env::var("TMUX").is_ok(),
+            NoActiveTmuxSession
+        );
 
-                    if let Some(wd) = &pane.working_dir {
-                        working_dir = wd.to_string_lossy().to_string();
-                    } else if let Some(wd) = &window.working_dir {
-                        working_dir = wd.to_string_lossy().to_string();
-                    } else if let Some(wd) = &project.working_dir {
-                        working_dir = wd.to_string_lossy().to_string();
-                    } else {
-                        found_working_dir = false;
-                    }
+        let mut tmux_args = vec!["display"];
 
-                    if found_working_dir {
-                        split_command.append(&mut vec!["-c", &working_dir]);
-                    }
+        if let Some(target) = target {
+            tmux_args.extend_from_slice(&["-t", target]);
+        }
 
-                    // Split size
-                    if let Some(split_size) = &pane.split_size {
-                        split_command.append(&mut vec!["-l", split_size]);
-                    }
+        let format_str = format!("#{{{}}}", value);
+        tmux_args.extend_from_slice(&["-p", &format_str]);
 
-                    // Target pane
-                    let split_from_target;
-                    split_command.append(&mut vec![
-                        "-t",
-                        match &pane.split_from {
-                            None => target_window,
-                            Some(split_from) => {
-                                split_from_target = format!("#{{__AIRMUX_PANE_{}}}", split_from);
-                                &split_from_target
-                            }
-                        },
-                    ]);
+        let (tmux, arguments) = config.get_tmux_command(&tmux_args)?;
+
+        let value = String::from_utf8(Command::new(tmux).args(arguments).output()?.stdout)?
+            .trim()
+            .to_string();
+        Ok(value)
+    }
+
+    // Reads a variable from the session's environment (tmux lists removed ones as -NAME)
+    pub fn get_session_env(config: &Config, target: &str, name: &str) -> Option<String> {
+        let output = config
+            .get_tmux_command(&["show-environment", "-t", target, name])
+            .ok()
+            .and_then(|(tmux, arguments)| Command::new(tmux).args(arguments).output().ok())?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end_matches('\n')
+            .strip_prefix(&format!("{}=", name))
+            .map(String::from)
+    }
+
+    pub fn get_tmux_list_values(
+        config: &Config,
+        list_command: &str,
+        value: &str,
+        target: &str,
+    ) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let tmux_args = &[list_command, "-t", target, "-F", &format!("#{{{}}}", value)];
+        let (tmux, arguments) = config.get_tmux_command(tmux_args)?;
 
-                    // Create pane
-                    window_commands.push(tmux_join(&["run", &project.tmux(&split_command)?]));
+        let values = String::from_utf8(Command::new(tmux).args(arguments).output()?.stdout)?;
+        let values = values
+            .split('\n')
+            .filter_map(|window_id| {
+                let window_id = window_id.trim();
+                if window_id.is_empty() {
+                    None
+                } else {
+                    Some(window_id.to_string())
                 }
+            })
+            .collect();
 
-                // Set real tmux pane index as a __AIRMUX_PANE_idx environment
-                // Allows us to reference tmux panes with their project order
-                window_commands.push(tmux_join(&[
-                    "run",
-                    "-t",
-                    target_window,
-                    &project.tmux(&[
-                        "setenv",
-                        "-t",
-                        session_name,
-                        "-g",
-                        &format!("__AIRMUX_PANE_{}", target_pane_index),
-                        "#D",
-                    ])?,
-                ]));
+        Ok(values)
+    }
+}
 
-                // project and window's on_pane_create
-                // plus pane's on_create commands
-                let on_create_commands: Vec<String> = project
-                    .on_pane_create
-                    .iter()
-                    .cloned()
-                    .chain(window.on_pane_create.iter().cloned())
-                    .chain(pane.on_create.iter().cloned())
+mod merge {
+    use super::*;
+
+    // Project-wide pane settings are moved down to each window, so they keep applying to
+    // their own windows only once the projects share a session
+    pub fn merge_projects(projects: Vec<(String, Project)>) -> Option<Project> {
+        let mut projects = projects.into_iter();
+        let (first_name, mut merged) = projects.next()?;
+
+        let first_windows = std::mem::take(&mut merged.windows);
+        merged.windows = get_windows(&first_name, &merged, first_windows, merged.pane_base_index);
+
+        merged.startup_window = merged
+            .startup_window
+            .map_name(|name| format!("{}/{}", first_name, name));
+
+        for (project_name, mut project) in projects {
+            let windows = std::mem::take(&mut project.windows);
+            merged.windows.extend(get_windows(
+                &project_name,
+                &project,
+                windows,
+                merged.pane_base_index,
+            ));
+
+            merged.on_start.extend(project.on_start);
+            merged.on_first_start.extend(project.on_first_start);
+            merged.on_every_start.extend(project.on_every_start);
+            merged.on_restart.extend(project.on_restart);
+            merged.on_exit.extend(project.on_exit);
+            merged.on_stop.extend(project.on_stop);
+            merged.post_create.extend(project.post_create);
+            merged.plugins.extend(project.plugins);
+            merged.depends_on.extend(project.depends_on);
+        }
+
+        merged.on_window_create = vec![];
+        merged.post_window_create = vec![];
+        merged.on_pane_create = vec![];
+        merged.post_pane_create = vec![];
+        merged.pane_commands = vec![];
+        merged.join_commands = None;
+        merged.clear_panes = false;
+
+        Some(merged)
+    }
+
+    fn get_windows(
+        project_name: &str,
+        project: &Project,
+        windows: Vec<Window>,
+        pane_base_index: usize,
+    ) -> Vec<Window> {
+        windows
+            .into_iter()
+            .map(|window| {
+                let panes = window
+                    .panes
+                    .into_iter()
+                    .map(|pane| Pane {
+                        split_from: pane.split_from.map(|split_from| match split_from {
+                            PaneReference::Index(index) => PaneReference::Index(
+                                index + pane_base_index - project.pane_base_index,
+                            ),
+                            name => name,
+                        }),
+                        join_commands: pane.join_commands.or_else(|| project.join_commands.clone()),
+                        ..pane
+                    })
                     .collect();
-                if !on_create_commands.is_empty() {
-                    window_commands.push(tmux_join(&[
-                        "run",
-                        &on_create_commands
-                            .join("; ")
-                            .replace("__TMUX__", tmux_command)
-                            .replace("__SESSION__", session_name_quoted)
-                            .replace("__WINDOW__", target_window_quoted)
-                            .replace("__PANE__", target_pane_quoted),
-                    ]));
+
+                Window {
+                    name: Some(match window.name {
+                        Some(name) => format!("{}/{}", project_name, name),
+                        None => project_name.to_string(),
+                    }),
+                    working_dir: window.working_dir.or_else(|| project.working_dir.clone()),
+                    on_create: [&project.on_window_create[..], &window.on_create[..]].concat(),
+                    post_create: [&project.post_window_create[..], &window.post_create[..]]
+                        .concat(),
+                    on_pane_create: [&project.on_pane_create[..], &window.on_pane_create[..]]
+                        .concat(),
+                    post_pane_create: [&project.post_pane_create[..], &window.post_pane_create[..]]
+                        .concat(),
+                    pane_commands: [&project.pane_commands[..], &window.pane_commands[..]].concat(),
+                    clear_panes: window.clear_panes || project.clear_panes,
+                    panes,
+                    ..window
                 }
+            })
+            .collect()
+    }
+}
 
-                // project and window's pane_commands
-                // plus pane commands
-                let pane_commands: Vec<String> = project
-                    .pane_commands
+mod dependencies {
+    use super::*;
+
+    // Lists the dependencies of a project, recursively, in the order they should be started
+    pub fn resolve(
+        config: &Config,
+        project_name: &str,
+        project: &Project,
+    ) -> Result<Vec<(Dependency, Project)>, Box<dyn error::Error>> {
+        let mut stack = vec![project_name.to_string()];
+        let mut resolved = vec![];
+
+        visit(config, &project.depends_on, &mut stack, &mut resolved)?;
+        Ok(resolved)
+    }
+
+    fn visit(
+        config: &Config,
+        depends_on: &[Dependency],
+        stack: &mut Vec<String>,
+        resolved: &mut Vec<(Dependency, Project)>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        for dependency in depends_on {
+            let name = &dependency.project;
+            if let Some(pos) = stack.iter().position(|stacked| stacked == name) {
+                let cycle = stack[pos..]
                     .iter()
-                    .chain(window.pane_commands.iter())
-                    .chain(pane.commands.iter())
-                    .filter(|command| !command.is_empty())
-                    .map(|command| project.tmux(&["send", "-t", target_pane, command, "C-m"]))
-                    .collect::<Result<_, _>>()?;
-                if !pane_commands.is_empty() {
-                    window_commands.push(tmux_join(&["run", &pane_commands.join("; ")]));
+                    .chain(iter::once(name))
+                    .map(|name| format!("{:?}", name))
+                    .collect::<Vec<String>>()
+                    .join(" -> ");
+                return Err(Error::DependencyCycle { cycle }.into());
+            }
+
+            if resolved
+                .iter()
+                .any(|(resolved, _)| &resolved.project == name)
+            {
+                continue;
+            }
+
+            let (project_name, project_file) = project::get_filename(config, Some(name), None)?;
+            ensure!(project_file.is_file(), ProjectDoesNotExist { project_name });
+
+            let project = project::load(config, &project_name, &project_file, Some(false), &[])?;
+            project
+                .check()
+                .map_err(|err| Diagnostic::new(&project_file, err.to_string()))?;
+
+            stack.push(name.to_owned());
+            visit(config, &project.depends_on, stack, resolved)?;
+            stack.pop();
+
+            resolved.push((dependency.to_owned(), project));
+        }
+
+        Ok(())
+    }
+
+    // Dependencies are started detached, and left alone if their session already exists
+    pub fn start(
+        config: &Config,
+        dependencies: &[(Dependency, Project)],
+        verbose: bool,
+    ) -> Result<(), Box<dyn error::Error>> {
+        for (dependency, project) in dependencies {
+            if !source::session_exists(project) {
+                if verbose {
+                    println!("starting dependency: {}", dependency.project);
                 }
 
-                // project and window's post_pane_create
-                // plus pane's post_create commands
-                let post_pane_commands: Vec<String> = project
-                    .post_pane_create
-                    .iter()
-                    .cloned()
-                    .chain(window.post_pane_create.iter().cloned())
-                    .chain(pane.post_create.iter().cloned())
-                    .collect();
-                if !post_pane_commands.is_empty() {
-                    window_commands.push(tmux_join(&[
-                        "run",
-                        &post_pane_commands
-                            .join("; ")
-                            .replace("__TMUX__", tmux_command)
-                            .replace("__SESSION__", session_name_quoted)
-                            .replace("__WINDOW__", target_window_quoted)
-                            .replace("__PANE__", target_pane_quoted),
-                    ]));
+                start_project(
+                    config,
+                    Some(&dependency.project),
+                    None,
+                    Some(false),
+                    false,
+                    false,
+                    verbose,
+                    &[],
+                    false,
+                    false,
+                    false,
+                    true,
+                    false,
+                    false,
+                    false,
+                    false,
+                    None,
+                    None,
+                    None,
+                )?;
+            }
+
+            if dependency.has_readiness_gate() {
+                if verbose {
+                    println!("waiting for dependency: {}", dependency.project);
+                }
+
+                wait_until_ready(dependency, project)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn wait_until_ready(
+        dependency: &Dependency,
+        project: &Project,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let timeout = dependency.timeout.unwrap_or(DEFAULT_READY_TIMEOUT);
+        let deadline = Instant::now() + Duration::from_secs(timeout);
+
+        loop {
+            if is_ready(dependency, project) {
+                return Ok(());
+            }
+
+            ensure!(
+                Instant::now() < deadline,
+                DependencyNotReady {
+                    project_name: &dependency.project,
+                    timeout
                 }
+            );
+
+            thread::sleep(Duration::from_millis(500));
+        }
+    }
+
+    fn is_ready(dependency: &Dependency, project: &Project) -> bool {
+        if let Some(window) = &dependency.window {
+            let session_target =
+                format!("={}", project.session_name.as_deref().unwrap_or_default());
+            let output = project
+                .tmux_command(&["lsw", "-t", &session_target, "-F", "#{window_name}"])
+                .ok()
+                .and_then(|(tmux, arguments)| Command::new(tmux).args(arguments).output().ok());
+
+            let has_window = output.is_some_and(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .any(|name| name == window)
+            });
+            if !has_window {
+                return false;
+            }
+        }
+
+        // Health commands run from the dependency's working directory
+        if let Some(ready) = &dependency.ready {
+            let mut command = Command::new("sh");
+            command
+                .args(["-c", ready])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            if let Some(working_dir) = &project.working_dir {
+                command.current_dir(working_dir);
+            }
+
+            return command.status().is_ok_and(|status| status.success());
+        }
+
+        true
+    }
+}
+
+mod service {
+    use super::*;
+
+    // Namespaced projects (ie: work/api) can't have slashes in their service name
+    pub fn get_label(project_name: &str) -> String {
+        format!("airmux-{}", project_name.replace('/', "-"))
+    }
+
+    pub fn get_command(
+        config: &Config,
+        airmux: &str,
+        action: &str,
+        project_name: &str,
+    ) -> Vec<String> {
+        let mut command = vec![airmux.to_string()];
+        if let Some(config_dir) = &config.config_dir {
+            command.push(String::from("--config-dir"));
+            command.push(config_dir.to_string_lossy().to_string());
+        }
+        command.push(action.to_string());
+        command.extend(config.get_tmux_args());
+        if action == "start" {
+            command.push(String::from("--no-attach"));
+            command.push(String::from("--no-input"));
+        }
+        command.push(project_name.to_string());
+
+        command
+    }
+
+    // The tmux server outlives the start command, so the unit stays active once it exits
+    pub fn get_systemd_unit(
+        project_name: &str,
+        start_command: &[String],
+        kill_command: &[String],
+    ) -> String {
+        format!(
+            "[Unit]\n\
+             Description=airmux project {}\n\
+             \n\
+             [Service]\n\
+             Type=oneshot\n\
+             RemainAfterExit=yes\n\
+             ExecStart={}\n\
+             ExecStop=-{}\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n",
+            project_name,
+            shell_words::join(start_command),
+            shell_words::join(kill_command),
+        )
+    }
+
+    pub fn get_launchd_plist(project_name: &str, start_command: &[String]) -> String {
+        fn escape(value: &str) -> String {
+            value
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        }
+
+        let arguments = start_command
+            .iter()
+            .map(|arg| format!("        <string>{}</string>\n", escape(arg)))
+            .collect::<String>();
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <plist version=\"1.0\">\n\
+             <dict>\n    \
+                 <key>Label</key>\n    \
+                 <string>{}</string>\n    \
+                 <key>ProgramArguments</key>\n    \
+                 <array>\n\
+                 {}    \
+                 </array>\n    \
+                 <key>RunAtLoad</key>\n    \
+                 <true/>\n\
+             </dict>\n\
+             </plist>\n",
+            escape(&get_label(project_name)),
+            arguments,
+        )
+    }
+}
+
+mod autofreeze {
+    use super::*;
+
+    // Returns whether the project file changed
+    pub fn freeze_session(
+        config: &Config,
+        project_name: &str,
+        session_name: &str,
+    ) -> Result<bool, Box<dyn error::Error>> {
+        let (_, project_file) = project::get_filename(config, Some(project_name), None)?;
+        if !project::autofreezes(&fs::read_to_string(&project_file)?) {
+            return Ok(false);
+        }
+
+        freeze_into(config, &project_file, session_name)
+    }
+
+    // Returns whether the project file changed
+    pub fn freeze_into(
+        config: &Config,
+        project_file: &Path,
+        session_name: &str,
+    ) -> Result<bool, Box<dyn error::Error>> {
+        let extension = project_file.extension().unwrap_or_default();
+        let format = FileFormat::from_name(&extension.to_string_lossy());
+
+        let session_target = format!("={}:", session_name);
+        let session_id = freeze::get_tmux_value(config, "session_id", Some(&session_target))?;
+        ensure!(!session_id.is_empty(), SessionDoesNotExist { session_name });
+
+        let frozen = freeze::get_project(config, Some(&session_id))?;
+
+        let current_content = fs::read_to_string(project_file).unwrap_or_default();
+        let content = if current_content.trim().is_empty() {
+            frozen.serialize_compact(&format)?
+        } else if let Some(sessions) = project::get_sessions(&current_content)? {
+            let session_name = frozen.session_name.to_owned().unwrap_or_default();
+            let (_, session) = sessions
+                .into_iter()
+                .find(|(name, _)| *name == session_name)
+                .ok_or_else(|| Error::CannotFreezeInto {
+                    project_file: project_file.to_owned(),
+                    reason: format!("it doesn't define session {:?}", session_name),
+                })?;
+
+            let current = parse_unexpanded(project_file, &serde_yaml::to_string(&session)?)?;
+            let project = merge_frozen(current.to_owned(), frozen);
+            if project == current {
+                return Ok(false);
+            }
 
-                // send_keys for the pane
-                let mut send_keys: Vec<&str> = pane
-                    .send_keys
-                    .iter()
-                    .filter_map(|command| {
-                        if command.is_empty() {
-                            None
-                        } else {
-                            Some(command.as_str())
-                        }
-                    })
-                    .collect();
+            replace_session(project_file, &current_content, &project, &format)?
+        } else {
+            let current = parse_unexpanded(project_file, &current_content)?;
+            let project = merge_frozen(current.to_owned(), frozen);
+            if project == current {
+                return Ok(false);
+            }
 
-                // pane's clear
-                if pane.clear || window.clear_panes || project.clear_panes {
-                    send_keys.push("C-l");
-                }
+            project.serialize_compact(&format)?
+        };
 
-                // Send keys
-                if !send_keys.is_empty() {
-                    let send_keys: Vec<&str> = vec!["send", "-t", target_pane]
-                        .into_iter()
-                        .chain(send_keys)
-                        .collect();
-                    window_commands.push(tmux_join(&["run", &project.tmux(&send_keys)?]));
-                }
+        fs::write(project_file, format!("{}\n", content))?;
+        Ok(true)
+    }
+
+    // Replaces the entry of the project's session in a file that defines several sessions
+    pub fn replace_session(
+        project_file: &Path,
+        content: &str,
+        project: &Project,
+        format: &FileFormat,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let session_name = project.session_name.as_deref().unwrap_or_default();
+        let session_pos = project::get_sessions(content)?
+            .unwrap_or_default()
+            .iter()
+            .position(|(name, _)| name == session_name)
+            .ok_or_else(|| Error::CannotFreezeInto {
+                project_file: project_file.to_owned(),
+                reason: format!("it doesn't define session {:?}", session_name),
+            })?;
+
+        let mut value = serde_yaml::from_str::<Value>(content)?;
+        value["sessions"][session_pos] =
+            serde_yaml::from_str(&project.serialize_compact(&FileFormat::Yaml)?)?;
+
+        Ok(match format {
+            FileFormat::Json => serde_json::to_string_pretty(&value)?,
+            _ => serde_yaml::to_string(&value)?,
+        })
+    }
+
+    // The file is parsed as written so its variables are written back as they are
+    // Files that don't parse without being expanded are left alone
+    pub fn parse_unexpanded(
+        project_file: &Path,
+        content: &str,
+    ) -> Result<Project, Box<dyn error::Error>> {
+        ensure!(!project::is_toml(content), TomlNotSupported);
+        ensure!(
+            project::get_sessions(content)?.is_none(),
+            CannotFreezeInto {
+                project_file,
+                reason: "it defines several sessions"
             }
+        );
 
-            // Window layout
-            if let Some(layout) = &window.layout {
-                window_commands.push(tmux_join(&["select-layout", "-t", target_window, layout]));
+        serde_yaml::from_str::<Project>(content).map_err(|err| {
+            Error::CannotFreezeInto {
+                project_file: project_file.to_owned(),
+                reason: err.to_string(),
             }
+            .into()
+        })
+    }
 
-            // Clean up panes index env vars
-            window_commands.push(tmux_join(&[
-                "run",
-                &window
-                    .panes
+    // Captured windows are matched with the project's by name, then by position, and their
+    // panes by position. The project keeps its commands, hooks and options, and takes the
+    // captured layouts, windows and panes, and the working directories it doesn't set
+    pub fn merge_frozen(project: Project, frozen: Project) -> Project {
+        let mut windows: Vec<Option<Window>> = project.windows.into_iter().map(Some).collect();
+
+        let merged_windows = frozen
+            .windows
+            .into_iter()
+            .enumerate()
+            .map(|(index, frozen_window)| {
+                let position = windows
                     .iter()
-                    .enumerate()
-                    .map(|(pane_index, _)| {
-                        let target_pane_index = pane_index + project.pane_base_index;
-                        let airmux_pane = format!("__AIRMUX_PANE_{}", target_pane_index);
-                        project.tmux(&["setenv", "-gu", &airmux_pane])
+                    .position(|window| match (window, &frozen_window.name) {
+                        (Some(window), Some(name)) => window.name.as_ref() == Some(name),
+                        _ => false,
                     })
-                    .collect::<Result<Vec<String>, Box<dyn error::Error>>>()?
-                    .join("; "),
-            ]));
-
-            // Select first pane
-            let target_pane = format!("{}.{}", target_window, project.pane_base_index);
-            window_commands.push(tmux_join(&["selectp", "-t", &target_pane]));
+                    .unwrap_or(index);
 
-            // window post_create commands
-            if !window.post_create.is_empty() {
-                window_commands.push(tmux_join(&[
-                    "run",
-                    &window
-                        .post_create
-                        .join("; ")
-                        .replace("__TMUX__", tmux_command)
-                        .replace("__SESSION__", session_name_quoted)
-                        .replace("__WINDOW__", target_window_quoted),
-                ]));
-            }
+                match windows.get_mut(position).and_then(Option::take) {
+                    Some(window) => merge_window(window, frozen_window),
+                    None => frozen_window,
+                }
+            })
+            .collect();
 
-            // Flag session as updated
-            window_commands.push(String::from("setenv -g __AIRMUX_SESSION_UPDATED 1"));
+        let mut environment = project.environment;
+        environment.extend(frozen.environment);
 
-            source_commands.push(tmux_join(&["if", &if_command, &window_commands.join("; ")]));
+        Project {
+            working_dir: project.working_dir.or(frozen.working_dir),
+            environment,
+            windows: merged_windows,
+            ..project
         }
+    }
 
-        // Post-window creation routing for when the session is freshly created
-        source_commands.push(tmux_join(&[
-            "if",
-            "-F",
-            "#{__AIRMUX_SESSION_CREATED}",
-            &vec![
-                // Remove the original window
-                tmux_join(&["killw", "-t", &format!("{}:999999", session_name)]),
-                // Set startup window
-                tmux_join(&[
-                    "selectw",
-                    "-t",
-                    &match &project.startup_window {
-                        StartupWindow::Index(startup_window) => {
-                            format!("{}:{}", session_name, startup_window)
-                        }
-                        StartupWindow::Name(startup_window) => {
-                            format!("{}:{}", session_name, startup_window)
-                        }
-                        StartupWindow::Default => format!("{}:^", session_name),
-                    },
-                ]),
-                // Set startup pane
-                tmux_join(&[
-                    "selectp",
-                    "-t",
-                    &match &project.startup_pane {
-                        None => project.pane_base_index,
-                        Some(pane) => *pane,
-                    }
-                    .to_string(),
-                ]),
-            ]
-            .join("; "),
-        ]));
+    fn merge_window(window: Window, frozen: Window) -> Window {
+        let mut panes = window.panes.into_iter();
+        let merged_panes = frozen
+            .panes
+            .into_iter()
+            .map(|frozen_pane| match panes.next() {
+                Some(pane) => Pane {
+                    working_dir: pane.working_dir.or(frozen_pane.working_dir),
+                    ..pane
+                },
+                None => frozen_pane,
+            })
+            .collect();
 
-        // post_create commands
-        if !project.post_create.is_empty() {
-            source_commands.push(tmux_join(&[
-                "run",
-                &project
-                    .post_create
-                    .join("; ")
-                    .replace("__TMUX__", tmux_command)
-                    .replace("__SESSION__", session_name_quoted),
-            ]));
+        Window {
+            name: window.name.or(frozen.name),
+            working_dir: window.working_dir.or(frozen.working_dir),
+            layout: frozen.layout,
+            panes: merged_panes,
+            ..window
         }
+    }
+}
 
-        // Show indicator message
-        if verbose {
-            source_commands.push(format!(
-                "display -p '#{{?__AIRMUX_SESSION_CREATED,created new session:, \
-                    #{{?__AIRMUX_SESSION_UPDATED,updated session:, \
-                    no changes to existing session:}}}} {} '",
-                session_name,
-            ));
+mod import {
+    use super::*;
+
+    // Where tmux-resurrect keeps a link to its latest save
+    pub fn get_resurrect_file() -> PathBuf {
+        let legacy_file = PathBuf::from(shellexpand::tilde("~/.tmux/resurrect/last").to_string());
+        if legacy_file.exists() {
+            return legacy_file;
         }
 
-        // Clear variables
-        source_commands.push(String::from("setenv -gu __AIRMUX_SESSION_CREATED"));
-        source_commands.push(String::from("setenv -gu __AIRMUX_SESSION_UPDATED"));
+        let data_dir = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(shellexpand::tilde("~/.local/share").to_string()));
 
-        Ok(source_commands.join("; "))
+        data_dir.join("tmux").join("resurrect").join("last")
     }
 
-    pub struct TmuxDummySession<'a> {
-        project: &'a Project,
-    }
+    // Save files are made of tab-separated lines, starting with the kind of entry they describe:
+    //   pane, session, window index, [:window name,] window active, :window flags, pane index,
+    //     [pane title,] :working dir, pane active, pane command, :full command
+    //   window, session, window index, :window name, window active, :window flags, layout, ...
+    // Older versions of tmux-resurrect store the window name in pane entries, newer ones the pane title
+    pub fn parse_resurrect(content: &str) -> Vec<Project> {
+        struct SavedPane {
+            index: usize,
+            working_dir: PathBuf,
+            active: bool,
+            command: String,
+        }
 
-    impl<'a> TmuxDummySession<'a> {
-        pub fn new(project: &'a Project) -> Result<TmuxDummySession, Box<dyn error::Error>> {
-            // Create dummy tmux session to make sure the tmux server is up and running
-            let (tmux_command, tmux_args) =
-                project.tmux_command(&["new", "-s", "__airmux_dummy_session_", "-d"])?;
+        #[derive(Default)]
+        struct SavedWindow {
+            name: Option<String>,
+            active: bool,
+            layout: Option<String>,
+            panes: Vec<SavedPane>,
+        }
 
-            let _ = Command::new(tmux_command)
-                .args(tmux_args)
-                .env_remove("TMUX")
-                .spawn()?
-                .wait();
+        type SavedSessions = Vec<(String, Vec<(usize, SavedWindow)>)>;
+
+        fn get_window<'a>(
+            sessions: &'a mut SavedSessions,
+            session_name: &str,
+            window_index: usize,
+        ) -> &'a mut SavedWindow {
+            let session_pos = match sessions.iter().position(|(name, _)| name == session_name) {
+                Some(pos) => pos,
+                None => {
+                    sessions.push((session_name.to_string(), vec![]));
+                    sessions.len() - 1
+                }
+            };
 
-            Ok(TmuxDummySession { project })
+            let windows = &mut sessions[session_pos].1;
+            let window_pos = match windows.iter().position(|(index, _)| *index == window_index) {
+                Some(pos) => pos,
+                None => {
+                    windows.push((window_index, SavedWindow::default()));
+                    windows.len() - 1
+                }
+            };
+
+            &mut windows[window_pos].1
         }
-    }
 
-    impl<'a> Drop for TmuxDummySession<'a> {
-        fn drop(&mut self) {
-            // Remove dummy session
-            if let Ok((tmux_command, tmux_args)) =
-                self.project
-                    .tmux_command(&["kill-session", "-t", "__airmux_dummy_session_"])
-            {
-                if let Ok(mut child) = Command::new(tmux_command).args(tmux_args).spawn() {
-                    let _ = child.wait();
+        let mut sessions: SavedSessions = vec![];
+
+        let strip = |field: &str| field.strip_prefix(':').unwrap_or(field).to_string();
+
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["pane", session_name, window_index, first, _, third, fourth, working_dir, active, _, command] =>
+                {
+                    let pane_index = if first.starts_with(':') {
+                        fourth
+                    } else {
+                        third
+                    };
+
+                    let window_index = match window_index.parse() {
+                        Ok(index) => index,
+                        Err(_) => continue,
+                    };
+
+                    let window = get_window(&mut sessions, session_name, window_index);
+                    window.panes.push(SavedPane {
+                        index: pane_index.parse().unwrap_or_default(),
+                        working_dir: PathBuf::from(strip(working_dir)),
+                        active: *active == "1",
+                        command: strip(command),
+                    });
+                }
+                ["window", session_name, window_index, window_name, window_active, _, layout, ..] =>
+                {
+                    let window_index = match window_index.parse() {
+                        Ok(index) => index,
+                        Err(_) => continue,
+                    };
+
+                    let window = get_window(&mut sessions, session_name, window_index);
+                    let window_name = strip(window_name);
+                    window.name = Some(window_name).filter(|name| !name.is_empty());
+                    window.active = *window_active == "1";
+                    window.layout = Some(layout.to_string()).filter(|layout| !layout.is_empty());
                 }
+                _ => {}
             }
         }
-    }
-}
 
-mod edit {
-    use super::*;
+        sessions
+            .into_iter()
+            .map(|(session_name, mut saved_windows)| {
+                saved_windows.sort_by_key(|(index, _)| *index);
 
-    pub fn create_project<P>(
-        project_name: &str,
-        project_path: P,
-        extension: &str,
-        content: Option<&str>,
-    ) -> Result<(), Box<dyn error::Error>>
-    where
-        P: AsRef<Path>,
-    {
-        let project_name = strip_extension_from_project_name(project_name);
+                let mut project = Project {
+                    session_name: Some(session_name),
+                    windows: vec![],
+                    ..Project::default()
+                };
 
-        let project_path = project_path.as_ref();
-        let mut file = fs::File::create(&project_path)?;
+                if let Some((index, _)) = saved_windows.first() {
+                    project.window_base_index = *index;
+                }
+                if let Some(pane) = saved_windows
+                    .iter()
+                    .flat_map(|(_, window)| window.panes.iter())
+                    .min_by_key(|pane| pane.index)
+                {
+                    project.pane_base_index = pane.index;
+                }
 
-        let content = match content {
-            Some(content) => content.to_string(),
-            None => {
-                let as_json = extension == "json";
+                for (window_pos, (_, mut saved_window)) in saved_windows.into_iter().enumerate() {
+                    saved_window.panes.sort_by_key(|pane| pane.index);
 
-                let content = if as_json {
-                    include_str!("assets/default_project.json")
-                } else {
-                    include_str!("assets/default_project.yml")
-                };
+                    // Only keep the selection when it's not the default one
+                    if saved_window.active && window_pos > 0 {
+                        project.startup_window =
+                            StartupWindow::Index(project.window_base_index + window_pos);
+                    }
+                    if saved_window.active {
+                        project.startup_pane = saved_window
+                            .panes
+                            .iter()
+                            .position(|pane| pane.active)
+                            .filter(|pane_pos| *pane_pos > 0)
+                            .map(|pane_pos| project.pane_base_index + pane_pos);
+                    }
 
-                let project_name = if as_json {
-                    serde_json::to_string(&project_name)?
-                } else {
-                    // serde_yaml adds '---\n' at the beginning that we need to get rid of before using the name
-                    let serialized = serde_yaml::to_string(&project_name)?;
-                    serialized[4..].to_string()
-                };
+                    let panes = saved_window
+                        .panes
+                        .into_iter()
+                        .map(|saved_pane| Pane {
+                            working_dir: Some(saved_pane.working_dir),
+                            commands: Some(saved_pane.command)
+                                .filter(|command| !command.is_empty())
+                                .map(PaneCommand::from)
+                                .into_iter()
+                                .collect(),
+                            ..Pane::default()
+                        })
+                        .collect();
 
-                content.replace("__PROJECT_NAME__", &project_name)
+                    project.windows.push(Window {
+                        name: saved_window.name,
+                        layout: saved_window.layout,
+                        panes,
+                        ..Window::default()
+                    });
+                }
+
+                hoist_working_dirs(&mut project);
+                project
+            })
+            .collect()
+    }
+
+    // Move the most used working dir of each level up to its parent
+    fn hoist_working_dirs(project: &mut Project) {
+        fn most_used<'a, I>(paths: I) -> Option<PathBuf>
+        where
+            I: Iterator<Item = &'a PathBuf>,
+        {
+            let mut counts: Vec<(&PathBuf, usize)> = vec![];
+            for path in paths {
+                match counts.iter_mut().find(|(counted, _)| *counted == path) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((path, 1)),
+                }
             }
-        };
 
-        file.write_all(content.as_bytes())?;
-        file.sync_all()?;
+            // Keep the first of the most used paths on ties
+            counts
+                .into_iter()
+                .rev()
+                .max_by_key(|(_, count)| *count)
+                .map(|(path, _)| path.to_owned())
+        }
 
-        Ok(())
-    }
+        for window in &mut project.windows {
+            window.working_dir = most_used(
+                window
+                    .panes
+                    .iter()
+                    .filter_map(|pane| pane.working_dir.as_ref()),
+            );
 
-    pub fn check_supported_extension(extension: &str) -> Result<(), Box<dyn error::Error>> {
-        let extension = extension.to_lowercase();
+            for pane in &mut window.panes {
+                if pane.working_dir == window.working_dir {
+                    pane.working_dir = None;
+                }
+            }
+        }
 
-        ensure!(
-            FILE_EXTENSIONS.contains(&extension.as_str()),
-            UnsupportedFileExtension { extension }
+        project.working_dir = most_used(
+            project
+                .windows
+                .iter()
+                .filter_map(|window| window.working_dir.as_ref()),
         );
 
-        Ok(())
+        for window in &mut project.windows {
+            if window.working_dir == project.working_dir {
+                window.working_dir = None;
+            }
+        }
     }
+}
 
-    // Disguise the project name as a Path for easy access to .with_extension()
-    pub fn strip_extension_from_project_name<P>(project_name: P) -> String
-    where
-        P: AsRef<Path>,
-    {
-        project_name
-            .as_ref()
-            .with_extension("")
-            .to_string_lossy()
-            .to_string()
+mod backend {
+    use super::*;
+
+    pub fn render(project: &Project, backend: &str) -> Result<String, Box<dyn error::Error>> {
+        match backend {
+            "wezterm" => Ok(wezterm_script(project)),
+            "kitty" => Ok(kitty_session(project)),
+            _ => Err(Error::UnsupportedBackend {
+                backend: backend.to_string(),
+            }
+            .into()),
+        }
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub fn open_in_editor(
-        config: &Config,
-        project_name: &str,
-        project_file: PathBuf,
-        extension: &str,
-        editor: &str,
-        content: Option<&str>,
-        no_check: bool,
-        args: &[&str],
-    ) -> Result<(), Box<dyn error::Error>> {
-        ensure!(!editor.is_empty(), EditorEmpty);
+    // Shell script that builds the session's windows as tabs of a new wezterm window
+    pub fn wezterm_script(project: &Project) -> String {
+        let session_name = project.session_name.as_deref().unwrap_or_default();
+        let mut lines = vec![
+            String::from("#!/bin/sh"),
+            format!(
+                "# wezterm layout of session {}, generated by airmux (experimental)",
+                session_name
+            ),
+            String::from("# Hooks, layouts and tmux options are left out"),
+            String::from("set -e"),
+            String::from(
+                "send() { printf '%s\\r' \"$2\" | wezterm cli send-text --pane-id \"$1\" --no-paste; }",
+            ),
+        ];
 
-        // Make sure the project's parent directory exists
-        if let Some(parent) = project_file.parent() {
-            mkdirp(parent)?;
-        }
+        let mut first_pane = None;
+        for (window_index, window) in project.windows.iter().enumerate() {
+            let window_index = window_index + project.window_base_index;
+            let pane_variable = |pane_index: usize| format!("pane_{}_{}", window_index, pane_index);
 
-        // Make sure the project file exists
-        ensure!(
-            !project_file.is_dir(),
-            ProjectFileIsADirectory { path: project_file }
-        );
+            for (pane_index, pane) in window.panes.iter().enumerate() {
+                let target_pane_index = pane_index + project.pane_base_index;
+                let variable = pane_variable(target_pane_index);
+
+                let mut command = vec![String::from("wezterm cli")];
+                if pane_index == 0 {
+                    command.push(String::from("spawn"));
+                    match &first_pane {
+                        None => command.push(String::from("--new-window")),
+                        Some(first_pane) => command.push(format!("--pane-id \"${}\"", first_pane)),
+                    }
+                } else {
+                    let split_from = window
+                        .split_from_index(pane_index, project.pane_base_index)
+                        .unwrap_or(target_pane_index - 1);
+                    command.push(String::from("split-pane"));
+                    command.push(format!("--pane-id \"${}\"", pane_variable(split_from)));
+                    command.push(String::from(match pane.split {
+                        Some(PaneSplit::Vertical) => "--bottom",
+                        _ => "--right",
+                    }));
 
-        // If file does not exist or we have updated content
-        if !project_file.exists() || content.is_some() {
-            edit::create_project(&project_name, &project_file, extension, content)?;
-        }
+                    if let Some(split_size) = &pane.split_size {
+                        command.push(match split_size.strip_suffix('%') {
+                            Some(percent) => format!("--percent {}", quote(percent)),
+                            None => format!("--cells {}", quote(split_size)),
+                        });
+                    }
+                }
 
-        // Open it with editor
-        let (command, command_args) =
-            utils::parse_command(editor, &[&project_file.to_string_lossy()])?;
-        let mut child = Command::new(command).args(command_args).spawn()?;
+                if let Some(working_dir) = working_dir(project, window, pane) {
+                    command.push(format!("--cwd {}", quote(&working_dir)));
+                }
+                if let Some(program) = program(window, pane) {
+                    command.push(format!("-- sh -c {}", quote(&program)));
+                }
 
-        // Wait for editor to close if  we want to check the project file's new content
-        if !no_check {
-            child.wait()?;
+                lines.push(format!("{}=$({})", variable, command.join(" ")));
+                if pane_index == 0 {
+                    if let Some(name) = &window.name {
+                        lines.push(format!(
+                            "wezterm cli set-tab-title --pane-id \"${}\" {}",
+                            variable,
+                            quote(name)
+                        ));
+                    }
+                    first_pane.get_or_insert(variable.to_owned());
+                }
 
-            // Perform a check on the project
-            let project = project::load(config, project_name, &project_file, None, args)?;
-            project.check()?;
+                for command in typed_commands(project, window, pane) {
+                    lines.push(format!("send \"${}\" {}", variable, quote(&command)));
+                }
+            }
         }
 
-        Ok(())
+        lines.join("\n") + "\n"
     }
-}
-
-mod list {
-    use super::*;
-
-    pub fn get_projects<P>(path: P) -> Result<Vec<String>, Box<dyn error::Error>>
-    where
-        P: AsRef<Path>,
-    {
-        let path = path.as_ref();
-        let mut projects = vec![];
 
-        for entry in path.read_dir()? {
-            let entry = entry?;
-            let entry_path = entry.path();
+    // Session file that opens the session's windows as kitty tabs, using the splits layout
+    pub fn kitty_session(project: &Project) -> String {
+        let session_name = project.session_name.as_deref().unwrap_or_default();
+        let mut lines = vec![
+            format!(
+                "# kitty session of session {}, generated by airmux (experimental)",
+                session_name
+            ),
+            String::from(
+                "# Hooks and tmux options are left out, and panes split the last created pane",
+            ),
+        ];
 
-            if entry_path.is_file() {
-                // Ignore file if it doesn't have a supported file extension
-                if let Some(extension) = entry_path.extension() {
-                    let extension = extension.to_string_lossy();
+        for window in &project.windows {
+            lines.push(String::new());
+            lines.push(match &window.name {
+                Some(name) => format!("new_tab {}", name),
+                None => String::from("new_tab"),
+            });
+            lines.push(String::from("layout splits"));
 
-                    if edit::check_supported_extension(&extension).is_ok() {
-                        let file_path = entry_path.strip_prefix(path)?;
-                        let file_path_str =
-                            file_path.with_extension("").to_string_lossy().to_string();
-                        projects.push(file_path_str);
+            for (pane_index, pane) in window.panes.iter().enumerate() {
+                let mut command = vec![String::from("launch")];
+                if pane_index > 0 {
+                    command.push(String::from(match pane.split {
+                        Some(PaneSplit::Vertical) => "--location hsplit",
+                        _ => "--location vsplit",
+                    }));
+
+                    if let Some(percent) = pane
+                        .split_size
+                        .as_deref()
+                        .and_then(|size| size.strip_suffix('%'))
+                    {
+                        command.push(format!("--bias {}", quote(percent)));
                     }
                 }
-            } else if entry_path.is_dir() {
-                // Check for symlink loops
-                let subdir = if entry.file_type()?.is_symlink() {
-                    let subdir = entry_path.read_link()?;
 
-                    if entry_path.starts_with(&subdir) {
-                        continue;
-                    }
+                if let Some(working_dir) = working_dir(project, window, pane) {
+                    command.push(format!("--cwd {}", quote(&working_dir)));
+                }
 
-                    subdir
-                } else {
-                    entry_path.clone()
-                };
+                // Typed commands run before handing the pane over to the user's shell
+                let typed_commands = typed_commands(project, window, pane);
+                if let Some(program) = program(window, pane) {
+                    command.push(format!("sh -c {}", quote(&program)));
+                } else if !typed_commands.is_empty() {
+                    let program = format!(
+                        "{}; exec \"${{SHELL:-/bin/sh}}\"",
+                        typed_commands.join("; ")
+                    );
+                    command.push(format!("sh -c {}", quote(&program)));
+                }
 
-                let file_path = entry_path.strip_prefix(path)?;
-                let mut subdir_projects = list::get_projects(&subdir)?
-                    .into_iter()
-                    .map(|entry| file_path.join(entry).to_string_lossy().to_string())
-                    .collect();
-                projects.append(&mut subdir_projects);
+                lines.push(command.join(" "));
             }
         }
 
-        Ok(projects)
+        lines.join("\n") + "\n"
     }
-}
 
-mod freeze {
-    use super::*;
+    // Same resolution order as the tmux source: pane, then window, then project
+    fn working_dir(project: &Project, window: &Window, pane: &Pane) -> Option<String> {
+        pane.working_dir
+            .as_ref()
+            .or(window.working_dir.as_ref())
+            .or(project.working_dir.as_ref())
+            .map(|working_dir| working_dir.to_string_lossy().to_string())
+    }
 
-    pub fn get_project(config: &Config) -> Result<Project, Box<dyn error::Error>> {
-        let mut project = Project {
-            windows: vec![],
-            ..Project::default()
-        };
+    // Program the pane runs in place of the user's shell
+    fn program(window: &Window, pane: &Pane) -> Option<String> {
+        pane.exec_command()
+            .or_else(|| pane.shell.to_owned())
+            .or_else(|| window.shell.to_owned())
+    }
 
-        let session_id = freeze::get_tmux_value(config, "session_id", None)?;
+    // Commands typed in the pane, expect commands are left out since they need tmux
+    fn typed_commands(project: &Project, window: &Window, pane: &Pane) -> Vec<String> {
+        if pane.exec {
+            return vec![];
+        }
 
-        project.session_name = Some(freeze::get_tmux_value(
-            config,
-            "session_name",
-            Some(&session_id),
-        )?);
+        project
+            .pane_commands
+            .iter()
+            .chain(window.pane_commands.iter())
+            .chain(pane.commands.iter())
+            .filter_map(|command| match command {
+                PaneCommand::Sleep(duration) => Some(format!("sleep {}", duration)),
+                _ => command.to_owned().into_raw().shell_command(),
+            })
+            .filter(|command| !command.is_empty())
+            .collect()
+    }
+}
 
-        let mut window_working_dir_map: HashMap<PathBuf, usize> = HashMap::new();
-        let mut window_most_used_working_dir = PathBuf::new();
-        let mut window_most_used_working_dir_count = 0;
+mod lsp {
+    use super::*;
+    use serde_json::{json, Value as JsonValue};
+
+    const COMPLETION_ITEM_KIND_PROPERTY: u8 = 10;
+    const DIAGNOSTIC_SEVERITY_ERROR: u8 = 1;
+    const DIAGNOSTIC_SEVERITY_WARNING: u8 = 2;
+    const PARSE_ERROR: i32 = -32700;
+    const METHOD_NOT_FOUND: i32 = -32601;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum Context {
+        Project,
+        Window,
+        Pane,
+    }
 
-        let window_ids = freeze::get_tmux_list_values(config, "lsw", "window_id", &session_id)?;
-        for window_id in &window_ids {
-            let mut window = Window {
-                panes: vec![],
-                ..Window::default()
-            };
+    pub struct Server<'a> {
+        config: &'a Config,
+        documents: HashMap<String, String>,
+        pub exit: bool,
+    }
 
-            let window_name = freeze::get_tmux_value(config, "window_name", Some(window_id))?;
-            let mut window_name = if window_name.is_empty() {
-                None
-            } else {
-                Some(window_name)
+    pub fn serve<R, W>(
+        config: &Config,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> Result<(), Box<dyn error::Error>>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        let mut server = Server::new(config);
+
+        while let Some(content) = read_content(reader)? {
+            // A malformed message is answered with an error, the next one may be fine
+            let replies = match serde_json::from_slice(&content) {
+                Ok(message) => server.handle(&message),
+                Err(error) => vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": JsonValue::Null,
+                    "error": {
+                        "code": PARSE_ERROR,
+                        "message": format!("parse error: {}", error),
+                    },
+                })],
             };
 
-            let mut pane_working_dir_map: HashMap<PathBuf, usize> = HashMap::new();
-            let mut pane_most_used_working_dir = PathBuf::new();
-            let mut pane_most_used_working_dir_count = 0;
-
-            let pane_ids =
-                freeze::get_tmux_list_values(config, "list-panes", "pane_id", window_id)?;
-            for pane_id in &pane_ids {
-                let mut pane = Pane { ..Pane::default() };
-
-                let pane_current_path = PathBuf::from(freeze::get_tmux_value(
-                    config,
-                    "pane_current_path",
-                    Some(pane_id),
-                )?);
-                pane.working_dir = Some(pane_current_path.to_owned());
+            for reply in replies {
+                write_message(writer, &reply)?;
+            }
 
-                let pane_shell_path = freeze::get_tmux_value(config, "SHELL", Some(pane_id))?;
+            if server.exit {
+                break;
+            }
+        }
 
-                let pane_shell = PathBuf::from(&pane_shell_path)
-                    .file_name()
-                    .map_or_else(String::new, |s| s.to_string_lossy().to_string());
+        Ok(())
+    }
 
-                let pane_command_path =
-                    freeze::get_tmux_value(config, "pane_current_command", Some(pane_id))?;
+    pub fn read_content<R>(reader: &mut R) -> Result<Option<Vec<u8>>, Box<dyn error::Error>>
+    where
+        R: BufRead,
+    {
+        let mut content_length = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 {
+                return Ok(None);
+            }
 
-                let pane_command = PathBuf::from(&pane_command_path)
-                    .file_name()
-                    .map_or_else(String::new, |s| s.to_string_lossy().to_string());
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
 
-                let process_name = env::current_exe()?.file_name().map_or_else(
-                    || String::from("airmux"),
-                    |n| n.to_string_lossy().to_string(),
-                );
+            if let Some(length) = header.strip_prefix("Content-Length:") {
+                content_length = Some(length.trim().parse::<usize>()?);
+            }
+        }
 
-                if let Some(name) = &window_name {
-                    if name == &pane_command || name == &pane_shell || name == &process_name {
-                        window_name = None
-                    }
-                }
+        let mut content = vec![0; content_length.unwrap_or(0)];
+        reader.read_exact(&mut content)?;
 
-                match pane_working_dir_map.get(&pane_current_path) {
-                    Some(count_value) => {
-                        let count_value = count_value + 1;
-                        pane_working_dir_map.insert(pane_current_path.to_owned(), count_value);
+        Ok(Some(content))
+    }
 
-                        if count_value > pane_most_used_working_dir_count {
-                            pane_most_used_working_dir = pane_current_path;
-                            pane_most_used_working_dir_count = count_value;
-                        }
-                    }
-                    None => {
-                        let count_value = 1;
-                        pane_working_dir_map.insert(pane_current_path.to_owned(), count_value);
+    pub fn write_message<W>(
+        writer: &mut W,
+        message: &JsonValue,
+    ) -> Result<(), Box<dyn error::Error>>
+    where
+        W: Write,
+    {
+        let content = message.to_string();
+        write!(
+            writer,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        writer.flush()?;
 
-                        if count_value >= pane_most_used_working_dir_count {
-                            pane_most_used_working_dir = pane_current_path;
-                            pane_most_used_working_dir_count = count_value;
-                        }
-                    }
-                }
+        Ok(())
+    }
 
-                window.panes.push(pane);
+    impl<'a> Server<'a> {
+        pub fn new(config: &'a Config) -> Self {
+            Self {
+                config,
+                documents: HashMap::new(),
+                exit: false,
             }
+        }
 
-            // Set window name
-            window.name = window_name;
-
-            // Set working directory if any
-            if pane_most_used_working_dir_count > 0 {
-                window.working_dir = Some(pane_most_used_working_dir.to_owned());
-
-                for pane in &mut window.panes {
-                    if let Some(working_dir) = &pane.working_dir {
-                        if working_dir == &pane_most_used_working_dir {
-                            pane.working_dir = None;
-                        }
+        // Returns the responses and notifications to send back to the client
+        pub fn handle(&mut self, message: &JsonValue) -> Vec<JsonValue> {
+            let method = message["method"].as_str().unwrap_or_default();
+            let params = &message["params"];
+            let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+            let result = match method {
+                "initialize" => json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "completionProvider": {},
+                        "hoverProvider": true,
+                    },
+                    "serverInfo": { "name": "airmux" },
+                }),
+                "shutdown" => JsonValue::Null,
+                "exit" => {
+                    self.exit = true;
+                    return vec![];
+                }
+                "textDocument/didOpen" => {
+                    let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                    self.documents.insert(String::from(uri), String::from(text));
+                    return vec![self.publish_diagnostics(uri)];
+                }
+                "textDocument/didChange" => {
+                    // Only full document syncs are advertised, so the last change has it all
+                    if let Some(text) = params["contentChanges"]
+                        .as_array()
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change["text"].as_str())
+                    {
+                        self.documents.insert(String::from(uri), String::from(text));
                     }
+                    return vec![self.publish_diagnostics(uri)];
+                }
+                "textDocument/didSave" => return vec![self.publish_diagnostics(uri)],
+                "textDocument/didClose" => {
+                    self.documents.remove(uri);
+                    return vec![notification(
+                        "textDocument/publishDiagnostics",
+                        json!({ "uri": uri, "diagnostics": [] }),
+                    )];
+                }
+                "textDocument/completion" => {
+                    let (line, _) = position(params);
+                    let text = self.documents.get(uri).map_or("", String::as_str);
+                    completion_items(text, line)
+                }
+                "textDocument/hover" => {
+                    let (line, character) = position(params);
+                    let text = self.documents.get(uri).map_or("", String::as_str);
+                    hover(text, line, character)
+                }
+                // Notifications don't get a reply, unknown requests get an error
+                _ if message.get("id").is_none() => return vec![],
+                _ => {
+                    return vec![json!({
+                        "jsonrpc": "2.0",
+                        "id": message["id"],
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("method not found: {}", method),
+                        },
+                    })]
                 }
+            };
 
-                match window_working_dir_map.get(&pane_most_used_working_dir) {
-                    Some(count_value) => {
-                        let count_value = count_value + 1;
-                        window_working_dir_map
-                            .insert(pane_most_used_working_dir.to_owned(), count_value);
+            vec![json!({ "jsonrpc": "2.0", "id": message["id"], "result": result })]
+        }
 
-                        if count_value > window_most_used_working_dir_count {
-                            window_most_used_working_dir = pane_most_used_working_dir;
-                            window_most_used_working_dir_count = count_value;
-                        }
-                    }
-                    None => {
-                        let count_value = 1;
-                        window_working_dir_map
-                            .insert(pane_most_used_working_dir.to_owned(), count_value);
+        fn publish_diagnostics(&self, uri: &str) -> JsonValue {
+            let text = self.documents.get(uri).map_or("", String::as_str);
+            notification(
+                "textDocument/publishDiagnostics",
+                json!({ "uri": uri, "diagnostics": diagnostics(self.config, uri, text) }),
+            )
+        }
+    }
 
-                        if count_value >= window_most_used_working_dir_count {
-                            window_most_used_working_dir = pane_most_used_working_dir;
-                            window_most_used_working_dir_count = count_value;
-                        }
-                    }
-                }
-            }
+    fn notification(method: &str, params: JsonValue) -> JsonValue {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
 
-            // Set layout
-            let layout = freeze::get_tmux_value(config, "window_layout", Some(window_id))?;
-            window.layout = Some(layout);
+    fn position(params: &JsonValue) -> (usize, usize) {
+        let line = params["position"]["line"].as_u64().unwrap_or(0);
+        let character = params["position"]["character"].as_u64().unwrap_or(0);
+        (line as usize, character as usize)
+    }
 
-            // Add window to project's window list
-            project.windows.push(window)
+    pub fn uri_to_path(uri: &str) -> PathBuf {
+        let path = uri.strip_prefix("file://").unwrap_or(uri);
+
+        // Decode percent-encoded bytes (ie: %20 for spaces)
+        let mut bytes = vec![];
+        let mut chars = path.bytes();
+        while let Some(byte) = chars.next() {
+            let decoded = match byte {
+                b'%' => {
+                    let hex: Vec<u8> = chars.by_ref().take(2).collect();
+                    std::str::from_utf8(&hex)
+                        .ok()
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                }
+                _ => None,
+            };
+            bytes.push(decoded.unwrap_or(byte));
         }
 
-        if window_most_used_working_dir_count > 0 {
-            project.working_dir = Some(window_most_used_working_dir.to_owned());
+        PathBuf::from(String::from_utf8_lossy(&bytes).to_string())
+    }
 
-            for window in &mut project.windows {
-                if let Some(working_dir) = &window.working_dir {
-                    if working_dir == &window_most_used_working_dir {
-                        window.working_dir = None;
-                    }
+    // Runs the same parsing and checks as `airmux start`
+    pub fn diagnostics(config: &Config, uri: &str, text: &str) -> Vec<JsonValue> {
+        let project_file = uri_to_path(uri);
+        let project_name = match project_file.file_name() {
+            // Local project files are named after their directory
+            Some(name) if name.to_string_lossy().starts_with('.') => project_file
+                .parent()
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().to_string()),
+            _ => project_file
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_string()),
+        }
+        .unwrap_or_default();
+
+        let mut diagnostics = vec![];
+
+        let result = project::expand_env(String::from(text), &[]).and_then(|text| {
+            if let Ok(value) = serde_yaml::from_str::<Value>(&text) {
+                for (alias, field) in Project::deprecated_fields(&value) {
+                    let message =
+                        format!("field {:?} is deprecated, use {:?} instead", alias, field);
+                    let line = text
+                        .lines()
+                        .position(|line| line.trim_start().starts_with(&format!("{}:", alias)))
+                        .unwrap_or(0);
+                    diagnostics.push(diagnostic(
+                        text.as_str(),
+                        line,
+                        0,
+                        &message,
+                        DIAGNOSTIC_SEVERITY_WARNING,
+                    ));
                 }
             }
+
+            project::parse(config, &project_name, &project_file, &text, None)?
+                .check()
+                .map_err(|err| Diagnostic::new(&project_file, err.to_string()).into())
+        });
+
+        if let Err(err) = result {
+            let (line, column, message) = match err.downcast_ref::<Diagnostic>() {
+                Some(diag) => (
+                    diag.line.unwrap_or(1).saturating_sub(1),
+                    diag.column.unwrap_or(1).saturating_sub(1),
+                    diag.message.to_owned(),
+                ),
+                None => (0, 0, err.to_string()),
+            };
+            diagnostics.push(diagnostic(
+                text,
+                line,
+                column,
+                &message,
+                DIAGNOSTIC_SEVERITY_ERROR,
+            ));
         }
 
-        Ok(project)
+        diagnostics
     }
 
-    pub fn get_tmux_value(
-        config: &Config,
-        value: &str,
-        target: Option<&str>,
-    ) -> Result<String, Box<dyn error::Error>> {
-        ensure!(env::var("TMUX").is_ok(), NoActiveTmuxSession);
+    // Ranges go from the reported column to the end of the line
+    fn diagnostic(
+        text: &str,
+        line: usize,
+        column: usize,
+        message: &str,
+        severity: u8,
+    ) -> JsonValue {
+        let end = text
+            .lines()
+            .nth(line)
+            .map_or(column, |line| line.chars().count().max(column));
+        json!({
+            "range": {
+                "start": { "line": line, "character": column },
+                "end": { "line": line, "character": end },
+            },
+            "severity": severity,
+            "source": "airmux",
+            "message": message,
+        })
+    }
 
-        let mut tmux_args = vec!["display"];
+    // Finds whether a line is part of the project, a window or a pane definition
+    // by walking up its parent keys
+    pub fn context(text: &str, line: usize) -> Context {
+        let lines: Vec<&str> = text.lines().collect();
+        let indent_of = |line: &str| line.len() - line.trim_start_matches([' ', '-']).len();
+
+        let mut indent = lines
+            .get(line)
+            .filter(|line| !line.trim().is_empty())
+            .map_or(usize::MAX, |line| indent_of(line));
+        let mut context = Context::Project;
+        let mut is_window = false;
+
+        for parent in lines.iter().take(line).rev() {
+            let trimmed = parent.trim_start_matches([' ', '-']);
+            if trimmed.is_empty() || trimmed.starts_with('#') || indent_of(parent) >= indent {
+                continue;
+            }
+            indent = indent_of(parent);
 
-        if let Some(target) = target {
-            tmux_args.extend_from_slice(&["-t", target]);
+            match trimmed.split(':').next().unwrap_or_default().trim() {
+                "panes" | "pane" if context == Context::Project => context = Context::Pane,
+                "windows" | "window" => is_window = true,
+                _ => {}
+            }
         }
 
-        let format_str = format!("#{{{}}}", value);
-        tmux_args.extend_from_slice(&["-p", &format_str]);
+        match context {
+            Context::Project if is_window => Context::Window,
+            context => context,
+        }
+    }
 
-        let (tmux, arguments) = config.get_tmux_command(&tmux_args)?;
+    // Documented fields of a section of the project file, along with their docs
+    pub fn reference(context: Context) -> Vec<(String, String)> {
+        let fields = match context {
+            Context::Project => reference::PROJECT_FIELDS,
+            Context::Window => reference::WINDOW_FIELDS,
+            Context::Pane => reference::PANE_FIELDS,
+        };
 
-        let value = String::from_utf8(Command::new(tmux).args(arguments).output()?.stdout)?
-            .trim()
-            .to_string();
-        Ok(value)
+        fields
+            .iter()
+            .map(|(field, doc)| (String::from(*field), String::from(*doc)))
+            .collect()
     }
 
-    pub fn get_tmux_list_values(
-        config: &Config,
-        list_command: &str,
-        value: &str,
-        target: &str,
-    ) -> Result<Vec<String>, Box<dyn error::Error>> {
-        let tmux_args = &[list_command, "-t", target, "-F", &format!("#{{{}}}", value)];
-        let (tmux, arguments) = config.get_tmux_command(tmux_args)?;
-
-        let values = String::from_utf8(Command::new(tmux).args(arguments).output()?.stdout)?;
-        let values = values
-            .split('\n')
-            .filter_map(|window_id| {
-                let window_id = window_id.trim();
-                if window_id.is_empty() {
-                    None
-                } else {
-                    Some(window_id.to_string())
-                }
+    fn completion_items(text: &str, line: usize) -> JsonValue {
+        let items: Vec<JsonValue> = reference(context(text, line))
+            .into_iter()
+            .map(|(key, doc)| {
+                json!({
+                    "label": key,
+                    "kind": COMPLETION_ITEM_KIND_PROPERTY,
+                    "insertText": format!("{}: ", key),
+                    "documentation": doc,
+                })
             })
             .collect();
 
-        Ok(values)
+        JsonValue::from(items)
+    }
+
+    pub fn hover(text: &str, line: usize, character: usize) -> JsonValue {
+        let current_line = text.lines().nth(line).unwrap_or_default();
+
+        // Only keys get docs, so the cursor has to be before the colon
+        let key_start = current_line.len() - current_line.trim_start_matches([' ', '-', '"']).len();
+        let key = current_line[key_start..]
+            .split([':', '"'])
+            .next()
+            .unwrap_or_default();
+        if key.is_empty() || character < key_start || character > key_start + key.len() {
+            return JsonValue::Null;
+        }
+
+        match reference(context(text, line))
+            .into_iter()
+            .find(|(field, doc)| field == key && !doc.is_empty())
+        {
+            Some((field, doc)) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("**{}**\n\n{}", field, doc),
+                },
+            }),
+            None => JsonValue::Null,
+        }
     }
 }
 
 #[cfg(test)]
 #[path = "test/actions.rs"]
+#[allow(
+    clippy::needless_borrow,
+    clippy::needless_borrows_for_generic_args,
+    clippy::unneeded_struct_pattern
+)]
 mod tests;
@@ -33,15 +33,98 @@ fn main() -> Result<(), MainError> {
                 .value_name("DIR")
                 .env("AIRMUX_CONFIG"),
         )
+        .arg(
+            Arg::with_name("tmux_socket")
+                .global(true)
+                .help("name of the tmux server socket to use, overrides tmux_socket in project files")
+                .long("socket")
+                .value_name("NAME")
+                .env("AIRMUX_SOCKET"),
+        )
+        .arg(
+            Arg::with_name("tmux_options")
+                .global(true)
+                .help("options passed to tmux, overrides tmux_options in project files")
+                .long("tmux-options")
+                .value_name("OPTIONS")
+                .allow_hyphen_values(true)
+                .env("AIRMUX_TMUX_OPTIONS"),
+        )
+        .arg(
+            Arg::with_name("picker")
+                .global(true)
+                .help("command used to pick a project when none is given (e.g. fzf)")
+                .long("picker")
+                .value_name("COMMAND")
+                .env("AIRMUX_PICKER"),
+        )
+        .arg(
+            Arg::with_name("session_prefix")
+                .global(true)
+                .help("prefix added to the name of the sessions started by airmux (e.g. am/)")
+                .long("session-prefix")
+                .value_name("PREFIX")
+                .env("AIRMUX_SESSION_PREFIX"),
+        )
+        .arg(
+            Arg::with_name("editor_args")
+                .global(true)
+                .help("argument passed verbatim to the editor, {file} is replaced with the project file")
+                .long("editor-arg")
+                .value_name("ARG")
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::with_name("file_extensions")
+                .global(true)
+                .help("extensions of the project files, the first one is used for new projects [default: yml,yaml,json]")
+                .long("file-ext")
+                .value_name("EXT")
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .env("AIRMUX_FILE_EXTENSIONS"),
+        )
+        .arg(
+            Arg::with_name("lenient")
+                .global(true)
+                .help("ignore unknown fields in project files instead of failing")
+                .long("lenient"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .global(true)
+                .help("print how long each startup step took")
+                .long("profile"),
+        )
         .subcommands(vec![
             SubCommand::with_name("list")
                 .about("List all configured projects")
-                .alias("ls"),
+                .alias("ls")
+                .args(&[
+                    Arg::with_name("sessions")
+                        .help("list the running sessions along with their projects instead")
+                        .short("s")
+                        .long("sessions"),
+                    Arg::with_name("dirty")
+                        .help("list the projects that fail to load or to check, with their error")
+                        .short("d")
+                        .long("dirty")
+                        .conflicts_with("sessions"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
             SubCommand::with_name("start")
                 .about("Start a project as a tmux session")
                 .args(&[
                     Arg::with_name("project_name")
-                        .help("name of the project")
+                        .help("name of the project (use - for the last started project)")
                         .value_name("PROJECT_NAME")
                         .index(1),
                     Arg::with_name("project_file")
@@ -70,6 +153,166 @@ fn main() -> Result<(), MainError> {
                         .help("use switch-client instead of attach-session even if TMUX is not set")
                         .short("s")
                         .long("switch"),
+                    Arg::with_name("prune")
+                        .help("kill windows of an existing session that are not in the project")
+                        .long("prune"),
+                    Arg::with_name("print_attach_command")
+                        .help("print the command that attaches the session instead of attaching it")
+                        .long("print-attach-command")
+                        .conflicts_with_all(&["attach", "merge"]),
+                    Arg::with_name("control_mode")
+                        .help("attach the session in control mode (tmux -CC), for iTerm2's tmux integration")
+                        .long("cc")
+                        .conflicts_with_all(&["no_attach", "switch", "merge"]),
+                    Arg::with_name("attach_window")
+                        .help("window to select before attaching the session (index or name)")
+                        .long("attach-window")
+                        .value_name("WINDOW")
+                        .conflicts_with_all(&["no_attach", "merge"]),
+                    Arg::with_name("attach_pane")
+                        .help("pane to select before attaching the session (index)")
+                        .long("attach-pane")
+                        .value_name("PANE")
+                        .conflicts_with_all(&["no_attach", "merge"]),
+                    Arg::with_name("check_splits")
+                        .help("warn about panes that won't fit in the current terminal before creating them")
+                        .long("check-splits"),
+                    Arg::with_name("session")
+                        .help("only start this session when the project file defines several")
+                        .long("session")
+                        .value_name("SESSION")
+                        .conflicts_with("merge"),
+                    Arg::with_name("refresh")
+                        .help("update the session even if it already matches the project")
+                        .short("r")
+                        .long("refresh"),
+                    Arg::with_name("implicit")
+                        .help("start a single window in the current directory if there's no local project file")
+                        .short("i")
+                        .long("implicit"),
+                    Arg::with_name("stdin")
+                        .help("read the project file from stdin (same as `-f -`)")
+                        .long("stdin")
+                        .conflicts_with("project_file"),
+                    Arg::with_name("from_url")
+                        .help("fetch the project file from a url (requires curl)")
+                        .long("from-url")
+                        .value_name("URL")
+                        .conflicts_with_all(&["project_name", "project_file", "stdin"]),
+                    Arg::with_name("merge")
+                        .help("start the given projects as a single session (project names are passed as arguments)")
+                        .short("m")
+                        .long("merge")
+                        .requires("project_name")
+                        .conflicts_with_all(&["project_file", "stdin", "from_url", "prune", "implicit"]),
+                    Arg::with_name("name")
+                        .help("name of the project when reading it from stdin or a url")
+                        .short("n")
+                        .long("name")
+                        .value_name("NAME"),
+                    Arg::with_name("no_input")
+                        .help("do not prompt for confirmation")
+                        .short("y")
+                        .long("no-input"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("ui")
+                .about("Manage projects from an interactive list")
+                .args(&[
+                    Arg::with_name("editor")
+                        .help("the editor to use")
+                        .short("E")
+                        .long("editor")
+                        .required(true)
+                        .value_name("EDITOR")
+                        .env("EDITOR"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("menu")
+                .about("Print a tmux display-menu command listing all projects")
+                .args(&[
+                    Arg::with_name("title")
+                        .help("title of the menu")
+                        .short("T")
+                        .long("title")
+                        .value_name("TITLE")
+                        .default_value("airmux"),
+                    Arg::with_name("exec")
+                        .help("display the menu in the current tmux client instead of printing it")
+                        .short("x")
+                        .long("exec"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("init")
+                .about("Create a local project file for the current directory")
+                .args(&[
+                    Arg::with_name("extension")
+                        .help("the extension to use for the project file (see --file-ext)")
+                        .short("e")
+                        .long("ext")
+                        .value_name("FILE_EXT"),
+                    Arg::with_name("no_input")
+                        .help("do not prompt for confirmation")
+                        .short("y")
+                        .long("no-input"),
+                    Arg::with_name("editor")
+                        .help("the editor to use")
+                        .short("E")
+                        .long("editor")
+                        .required(true)
+                        .value_name("EDITOR")
+                        .env("EDITOR"),
+                    Arg::with_name("no_check")
+                        .help("do not check the project file")
+                        .short("C")
+                        .long("no-check"),
+                ]),
+            SubCommand::with_name("up")
+                .about("Start a transient session from the command line, without a project file")
+                .args(&[
+                    Arg::with_name("window")
+                        .help("window to create, as `[name:] command` (can be repeated)")
+                        .short("w")
+                        .long("window")
+                        .value_name("WINDOW")
+                        .multiple(true)
+                        .number_of_values(1),
+                    Arg::with_name("name")
+                        .help("name of the session (defaults to the current directory's name)")
+                        .short("n")
+                        .long("name")
+                        .value_name("NAME"),
+                    Arg::with_name("attach")
+                        .help("force attach the session")
+                        .short("a")
+                        .long("attach")
+                        .conflicts_with("no_attach"),
+                    Arg::with_name("no_attach")
+                        .help("don't automatically attach the session")
+                        .short("d")
+                        .long("no-attach"),
+                    Arg::with_name("switch")
+                        .help("use switch-client instead of attach-session even if TMUX is not set")
+                        .short("s")
+                        .long("switch"),
+                    Arg::with_name("debug")
+                        .help("print tmux source without actually running tmux")
+                        .long("debug"),
                     Arg::with_name("tmux_command")
                         .help("tmux command to use")
                         .short("t")
@@ -77,6 +320,22 @@ fn main() -> Result<(), MainError> {
                         .value_name("COMMAND")
                         .env("AIRMUX_COMMAND"),
                 ]),
+            SubCommand::with_name("recent")
+                .about("List recently started projects")
+                .arg(
+                    Arg::with_name("count")
+                        .help("number of projects to list")
+                        .short("n")
+                        .long("count")
+                        .value_name("COUNT")
+                        .default_value("10")
+                        .validator(|value| {
+                            value
+                                .parse::<usize>()
+                                .map(|_| ())
+                                .map_err(|_| String::from("count should be a positive integer"))
+                        }),
+                ),
             SubCommand::with_name("debug")
                 .about("Print tmux source without actually running tmux")
                 .args(&[
@@ -102,6 +361,13 @@ fn main() -> Result<(), MainError> {
                         .help("print a message if the session was created or updated")
                         .short("V")
                         .long("verbose"),
+                    Arg::with_name("copy")
+                        .help("copy the tmux source to the clipboard instead of printing it")
+                        .long("copy"),
+                    Arg::with_name("session")
+                        .help("session to use when the project file defines several")
+                        .long("session")
+                        .value_name("SESSION"),
                     Arg::with_name("args")
                         .help("arguments to be passed as variables to the yaml file")
                         .value_name("ARGUMENT")
@@ -113,6 +379,71 @@ fn main() -> Result<(), MainError> {
                         .value_name("COMMAND")
                         .env("AIRMUX_COMMAND"),
                 ]),
+            SubCommand::with_name("check")
+                .about("Check a project file for errors")
+                .args(&[
+                    Arg::with_name("project_name")
+                        .help("name of the project")
+                        .value_name("PROJECT_NAME")
+                        .index(1),
+                    Arg::with_name("project_file")
+                        .help("explicitly specify a project file to use")
+                        .short("f")
+                        .long("file")
+                        .value_name("PROJECT_FILE"),
+                    Arg::with_name("watch")
+                        .help("keep checking the project file each time it's saved")
+                        .short("w")
+                        .long("watch"),
+                    Arg::with_name("args")
+                        .help("arguments to be passed as variables to the yaml file")
+                        .value_name("ARGUMENT")
+                        .multiple(true),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("export")
+                .about("Print a project as a wezterm script or a kitty session file (experimental)")
+                .args(&[
+                    Arg::with_name("project_name")
+                        .help("name of the project")
+                        .value_name("PROJECT_NAME")
+                        .index(1),
+                    Arg::with_name("project_file")
+                        .help("explicitly specify a project file to use (use - for stdin)")
+                        .short("f")
+                        .long("file")
+                        .value_name("PROJECT_FILE"),
+                    Arg::with_name("backend")
+                        .help("terminal to lay the project out in")
+                        .short("b")
+                        .long("backend")
+                        .value_name("BACKEND")
+                        .possible_values(&["wezterm", "kitty"])
+                        .required(true),
+                    Arg::with_name("args")
+                        .help("arguments to be passed as variables to the yaml file")
+                        .value_name("ARGUMENT")
+                        .multiple(true),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("lsp")
+                .about("Run a language server for project files over stdin/stdout")
+                .args(&[Arg::with_name("tmux_command")
+                    .help("tmux command to use")
+                    .short("t")
+                    .long("command")
+                    .value_name("COMMAND")
+                    .env("AIRMUX_COMMAND")]),
             SubCommand::with_name("kill")
                 .about("Kill tmux session that matches the project")
                 .args(&[
@@ -125,6 +456,14 @@ fn main() -> Result<(), MainError> {
                         .short("f")
                         .long("file")
                         .value_name("PROJECT_FILE"),
+                    Arg::with_name("freeze")
+                        .help("save the layout of the session into the project file before killing it")
+                        .short("F")
+                        .long("freeze"),
+                    Arg::with_name("session")
+                        .help("only kill this session when the project file defines several")
+                        .long("session")
+                        .value_name("SESSION"),
                     Arg::with_name("args")
                         .help("arguments to be passed as variables to the yaml file")
                         .value_name("ARGUMENT")
@@ -136,6 +475,20 @@ fn main() -> Result<(), MainError> {
                         .value_name("COMMAND")
                         .env("AIRMUX_COMMAND"),
                 ]),
+            SubCommand::with_name("stop-all")
+                .about("Kill the tmux sessions of all the projects that are running")
+                .args(&[
+                    Arg::with_name("dry_run")
+                        .help("only list the sessions that would be killed")
+                        .short("n")
+                        .long("dry-run"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
             SubCommand::with_name("edit")
                 .about("Create or edit a project")
                 .alias("new")
@@ -150,23 +503,29 @@ fn main() -> Result<(), MainError> {
                         .long("file")
                         .value_name("PROJECT_FILE"),
                     Arg::with_name("extension")
-                        .help("the extension to use for the project file (yml|yaml|json)")
+                        .help("the extension to use for the project file (see --file-ext)")
                         .short("e")
                         .long("ext")
-                        .value_name("FILE_EXT")
-                        .possible_values(&["yml", "yaml", "json"])
-                        .case_insensitive(true),
+                        .value_name("FILE_EXT"),
                     Arg::with_name("editor")
                         .help("the editor to use")
                         .short("E")
                         .long("editor")
-                        .required(true)
+                        .required_unless("no_open")
                         .value_name("EDITOR")
                         .env("EDITOR"),
                     Arg::with_name("no_check")
                         .help("do not check the project file")
                         .short("C")
                         .long("no-check"),
+                    Arg::with_name("no_open")
+                        .help("only create the project file, without opening it in an editor")
+                        .short("N")
+                        .long("no-open"),
+                    Arg::with_name("session")
+                        .help("only check this session when the project file defines several")
+                        .long("session")
+                        .value_name("SESSION"),
                     Arg::with_name("args")
                         .help("arguments to be passed as variables to the yaml file when checking")
                         .value_name("ARGUMENT")
@@ -191,6 +550,23 @@ fn main() -> Result<(), MainError> {
                         .short("y")
                         .long("no-input"),
                 ]),
+            SubCommand::with_name("migrate")
+                .about("Upgrade a project file to the latest format")
+                .args(&[
+                    Arg::with_name("project_name")
+                        .help("name of the project")
+                        .value_name("PROJECT_NAME")
+                        .index(1),
+                    Arg::with_name("project_file")
+                        .help("explicitly specify a project file to use")
+                        .short("f")
+                        .long("file")
+                        .value_name("PROJECT_FILE"),
+                    Arg::with_name("stdout")
+                        .help("print the migrated project file to stdout instead")
+                        .short("s")
+                        .long("stdout"),
+                ]),
             SubCommand::with_name("freeze")
                 .about("Save current tmux session as a project file (commands not included)")
                 .args(&[
@@ -210,12 +586,16 @@ fn main() -> Result<(), MainError> {
                         .value_name("PROJECT_NAME")
                         .index(1),
                     Arg::with_name("extension")
-                        .help("the extension to use for the project file (yml|yaml|json)")
+                        .help("the extension to use for the project file (see --file-ext)")
                         .short("e")
                         .long("ext")
-                        .value_name("FILE_EXT")
-                        .possible_values(&["yml", "yaml", "json"])
-                        .case_insensitive(true),
+                        .value_name("FILE_EXT"),
+                    Arg::with_name("format")
+                        .help("the format of the project printed to stdout")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["yaml", "json"])
+                        .requires("stdout"),
                     Arg::with_name("no_input")
                         .help("do not prompt for confirmation")
                         .short("y")
@@ -231,6 +611,16 @@ fn main() -> Result<(), MainError> {
                         .help("do not check the project file")
                         .short("C")
                         .long("no-check"),
+                    Arg::with_name("verify")
+                        .help("make sure the project file parses back to the frozen session")
+                        .long("verify"),
+                    Arg::with_name("with_comments")
+                        .help("document the fields and add the missing hooks as comments")
+                        .long("with-comments"),
+                    Arg::with_name("session")
+                        .help("session to freeze instead of the current one")
+                        .long("session")
+                        .value_name("SESSION"),
                     Arg::with_name("args")
                         .help("arguments to be passed as variables to the yaml file when checking")
                         .value_name("ARGUMENT")
@@ -242,17 +632,181 @@ fn main() -> Result<(), MainError> {
                         .value_name("COMMAND")
                         .env("AIRMUX_COMMAND"),
                 ]),
+            SubCommand::with_name("adopt")
+                .about("Make a session created by hand the session of a project")
+                .args(&[
+                    Arg::with_name("session_name")
+                        .help("name of the session to adopt")
+                        .value_name("SESSION")
+                        .required(true)
+                        .index(1),
+                    Arg::with_name("project_name")
+                        .help("name of the project")
+                        .value_name("PROJECT_NAME")
+                        .required(true)
+                        .index(2),
+                    Arg::with_name("freeze")
+                        .help("save the layout of the session into the project file")
+                        .short("F")
+                        .long("freeze"),
+                    Arg::with_name("extension")
+                        .help("the extension to use for the frozen project file (see --file-ext)")
+                        .short("e")
+                        .long("ext")
+                        .value_name("FILE_EXT")
+                        .requires("freeze"),
+                    Arg::with_name("no_input")
+                        .help("do not prompt for confirmation")
+                        .short("y")
+                        .long("no-input"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("autofreeze")
+                .about("Periodically save the sessions of projects marked with autofreeze into their files")
+                .args(&[
+                    Arg::with_name("interval")
+                        .help("time to wait between each save (ie: 30s, 15m, 1h)")
+                        .short("i")
+                        .long("interval")
+                        .value_name("DURATION")
+                        .default_value("15m"),
+                    Arg::with_name("once")
+                        .help("save the sessions once and exit, for use with cron or a timer")
+                        .long("once"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("daemon")
+                .about("Keep the sessions of projects marked with keep_alive running")
+                .args(&[
+                    Arg::with_name("interval")
+                        .help("time to wait between each check (ie: 5s, 1m)")
+                        .short("i")
+                        .long("interval")
+                        .value_name("DURATION")
+                        .default_value("5s"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("boot")
+                .about("Start all the projects marked with autostart, detached")
+                .arg(
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ),
+            SubCommand::with_name("clean")
+                .about("Remove the locks, sessions and variables left behind by interrupted starts")
+                .args(&[
+                    Arg::with_name("dry_run")
+                        .help("only print what would be removed")
+                        .short("n")
+                        .long("dry-run"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("service")
+                .about("Print or install a user service starting the project at login")
+                .args(&[
+                    Arg::with_name("project_name")
+                        .help("name of the project")
+                        .value_name("PROJECT_NAME")
+                        .index(1),
+                    Arg::with_name("install")
+                        .help("install and enable the service (systemd, or launchd on macOS)")
+                        .short("i")
+                        .long("install"),
+                    Arg::with_name("tmux_command")
+                        .help("tmux command to use")
+                        .short("t")
+                        .long("command")
+                        .value_name("COMMAND")
+                        .env("AIRMUX_COMMAND"),
+                ]),
+            SubCommand::with_name("import")
+                .about("Convert sessions saved by another tool into project files")
+                .args(&[
+                    Arg::with_name("format")
+                        .help("the tool the sessions were saved with")
+                        .value_name("FORMAT")
+                        .required(true)
+                        .possible_values(&["resurrect"])
+                        .index(1),
+                    Arg::with_name("save_file")
+                        .help("the save file to import (defaults to tmux-resurrect's last save)")
+                        .value_name("FILE")
+                        .index(2),
+                    Arg::with_name("session")
+                        .help("only import the given session")
+                        .short("S")
+                        .long("session")
+                        .value_name("SESSION"),
+                    Arg::with_name("extension")
+                        .help("the extension to use for the project files (see --file-ext)")
+                        .short("e")
+                        .long("ext")
+                        .value_name("FILE_EXT"),
+                    Arg::with_name("stdout")
+                        .help("print the project files to stdout instead")
+                        .short("s")
+                        .long("stdout")
+                        .conflicts_with("no_input"),
+                    Arg::with_name("no_input")
+                        .help("do not prompt for confirmation")
+                        .short("y")
+                        .long("no-input"),
+                    Arg::with_name("verify")
+                        .help("make sure the project files parse back to the imported sessions")
+                        .long("verify"),
+                ]),
         ]);
 
     let matches = app.get_matches();
     match matches.subcommand() {
         ("start", Some(sub_matches)) => command_start(sub_matches),
         ("debug", Some(sub_matches)) => command_debug(sub_matches),
+        ("up", Some(sub_matches)) => command_up(sub_matches),
+        ("init", Some(sub_matches)) => command_init(sub_matches),
+        ("ui", Some(sub_matches)) => command_ui(sub_matches),
+        ("menu", Some(sub_matches)) => command_menu(sub_matches),
+        ("check", Some(sub_matches)) => command_check(sub_matches),
+        ("export", Some(sub_matches)) => command_export(sub_matches),
+        ("lsp", Some(sub_matches)) => command_lsp(sub_matches),
         ("kill", Some(sub_matches)) => command_kill(sub_matches),
+        ("stop-all", Some(sub_matches)) => command_stop_all(sub_matches),
         ("edit", Some(sub_matches)) => command_edit(sub_matches),
         ("remove", Some(sub_matches)) => command_remove(sub_matches),
         ("list", Some(sub_matches)) => command_list(sub_matches),
+        ("recent", Some(sub_matches)) => command_recent(sub_matches),
         ("freeze", Some(sub_matches)) => command_freeze(sub_matches),
+        ("adopt", Some(sub_matches)) => command_adopt(sub_matches),
+        ("migrate", Some(sub_matches)) => command_migrate(sub_matches),
+        ("import", Some(sub_matches)) => command_import(sub_matches),
+        ("autofreeze", Some(sub_matches)) => command_autofreeze(sub_matches),
+        ("daemon", Some(sub_matches)) => command_daemon(sub_matches),
+        ("service", Some(sub_matches)) => command_service(sub_matches),
+        ("boot", Some(sub_matches)) => command_boot(sub_matches),
+        ("clean", Some(sub_matches)) => command_clean(sub_matches),
         _ => panic!(),
     }
     .map_err(|x| x.into())
@@ -261,14 +815,34 @@ fn main() -> Result<(), MainError> {
 fn command_start(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
     let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
 
-    let project_name = matches.value_of_lossy("project_name");
-    let project_file = matches.value_of_lossy("project_file");
+    let mut project_name = matches.value_of_lossy("project_name");
+    let mut project_file = matches.value_of_lossy("project_file");
+    let name = matches.value_of_lossy("name");
+
+    // Projects read from stdin are named with --name
+    if matches.is_present("stdin") {
+        project_file = Some("-".into());
+    }
+    if project_file.as_deref() == Some("-") && name.is_some() {
+        project_name = name.clone();
+    }
+
     let attach = matches.is_present("attach");
     let no_attach = matches.is_present("no_attach");
     let verbose = matches.is_present("verbose");
     let args = matches.values_of_lossy("args").unwrap_or_default();
     let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
     let switch = matches.is_present("switch");
+    let prune = matches.is_present("prune");
+    let refresh = matches.is_present("refresh");
+    let no_input = matches.is_present("no_input");
+    let implicit = matches.is_present("implicit");
+    let print_attach_command = matches.is_present("print_attach_command");
+    let control_mode = matches.is_present("control_mode");
+    let check_splits = matches.is_present("check_splits");
+    let attach_window = matches.value_of_lossy("attach_window");
+    let attach_pane = matches.value_of_lossy("attach_pane");
+    let session = matches.value_of_lossy("session");
 
     let force_attach = if attach {
         Some(true)
@@ -278,15 +852,52 @@ fn command_start(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
         None
     };
 
+    if matches.is_present("merge") {
+        let project_names: Vec<&str> = project_name
+            .as_deref()
+            .into_iter()
+            .chain(args.iter().copied())
+            .collect();
+
+        return actions::start_merged_projects(
+            &config,
+            &project_names,
+            force_attach,
+            false,
+            switch,
+        );
+    }
+
+    if let Some(url) = matches.value_of_lossy("from_url") {
+        match actions::fetch_project(&config, &url, name.as_deref(), no_input, &args)? {
+            None => return Ok(()),
+            Some((fetched_name, fetched_file)) => {
+                project_name = Some(fetched_name.into());
+                project_file = Some(fetched_file.to_string_lossy().to_string().into());
+            }
+        }
+    }
+
     actions::start_project(
         &config,
         project_name.as_deref(),
         project_file.as_deref(),
         force_attach,
         false,
+        false,
         verbose,
         &args,
         switch,
+        prune,
+        refresh,
+        no_input,
+        implicit,
+        print_attach_command,
+        control_mode,
+        check_splits,
+        attach_window.as_deref(),
+        attach_pane.as_deref(),
+        session.as_deref(),
     )
 }
 
@@ -298,6 +909,8 @@ fn command_debug(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
     let attach = matches.is_present("attach");
     let no_attach = matches.is_present("no_attach");
     let verbose = matches.is_present("verbose");
+    let copy = matches.is_present("copy");
+    let session = matches.value_of_lossy("session");
     let args = matches.values_of_lossy("args").unwrap_or_default();
     let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
 
@@ -315,17 +928,131 @@ fn command_debug(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
         project_file.as_deref(),
         force_attach,
         true,
+        copy,
         verbose,
         &args,
         false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        None,
+        None,
+        session.as_deref(),
+    )
+}
+
+fn command_ui(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let editor = matches.value_of_lossy("editor").unwrap();
+
+    actions::run_ui(&config, &editor)
+}
+
+fn command_menu(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let title = matches.value_of_lossy("title").unwrap();
+    let exec = matches.is_present("exec");
+
+    actions::print_menu(&config, &title, exec)
+}
+
+fn command_init(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let extension = matches
+        .value_of_lossy("extension")
+        .map(|extension| extension.to_lowercase());
+    let editor = matches.value_of_lossy("editor").unwrap();
+    let no_input = matches.is_present("no_input");
+    let no_check = matches.is_present("no_check");
+
+    actions::init_project(&config, extension.as_deref(), &editor, no_input, no_check)
+}
+
+fn command_up(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let name = matches.value_of_lossy("name");
+    let windows = matches.values_of_lossy("window").unwrap_or_default();
+    let windows: Vec<&str> = windows.iter().map(AsRef::as_ref).collect();
+    let attach = matches.is_present("attach");
+    let no_attach = matches.is_present("no_attach");
+    let switch = matches.is_present("switch");
+    let debug = matches.is_present("debug");
+
+    let force_attach = if attach {
+        Some(true)
+    } else if no_attach {
+        Some(false)
+    } else {
+        None
+    };
+
+    actions::up_session(
+        &config,
+        name.as_deref(),
+        &windows,
+        force_attach,
+        debug,
+        switch,
+    )
+}
+
+fn command_check(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let project_name = matches.value_of_lossy("project_name");
+    let project_file = matches.value_of_lossy("project_file");
+    let watch = matches.is_present("watch");
+    let args = matches.values_of_lossy("args").unwrap_or_default();
+    let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+
+    actions::check_project(
+        &config,
+        project_name.as_deref(),
+        project_file.as_deref(),
+        watch,
+        &args,
+    )
+}
+
+fn command_export(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let project_name = matches.value_of_lossy("project_name");
+    let project_file = matches.value_of_lossy("project_file");
+    let backend = matches.value_of_lossy("backend").unwrap_or_default();
+    let args = matches.values_of_lossy("args").unwrap_or_default();
+    let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+
+    actions::export_project(
+        &config,
+        project_name.as_deref(),
+        project_file.as_deref(),
+        &backend,
+        &args,
     )
 }
 
+fn command_lsp(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    actions::run_lsp(&config)
+}
+
 fn command_kill(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
     let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
 
     let project_name = matches.value_of_lossy("project_name");
     let project_file = matches.value_of_lossy("project_file");
+    let freeze = matches.is_present("freeze");
+    let session = matches.value_of_lossy("session");
     let args = matches.values_of_lossy("args").unwrap_or_default();
     let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
 
@@ -333,18 +1060,30 @@ fn command_kill(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
         &config,
         project_name.as_deref(),
         project_file.as_deref(),
+        freeze,
         &args,
+        session.as_deref(),
     )
 }
 
+fn command_stop_all(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let dry_run = matches.is_present("dry_run");
+
+    actions::stop_all_projects(&config, dry_run)
+}
+
 fn command_edit(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
     let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
 
     let project_name = matches.value_of_lossy("project_name");
     let project_file = matches.value_of_lossy("project_file");
     let extension = matches.value_of_lossy("extension");
-    let editor = matches.value_of_lossy("editor").unwrap();
+    let editor = matches.value_of_lossy("editor").unwrap_or_default();
     let no_check = matches.is_present("no_check");
+    let no_open = matches.is_present("no_open");
+    let session = matches.value_of_lossy("session");
     let args = matches.values_of_lossy("args").unwrap_or_default();
     let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
 
@@ -355,7 +1094,9 @@ fn command_edit(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
         extension.as_deref(),
         &editor,
         no_check,
+        no_open,
         &args,
+        session.as_deref(),
     )
 }
 
@@ -371,7 +1112,33 @@ fn command_remove(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
 fn command_list(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
     let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
 
-    actions::list_projects(&config)
+    let sessions = matches.is_present("sessions");
+    let dirty = matches.is_present("dirty");
+
+    actions::list_projects(&config, sessions, dirty)
+}
+
+fn command_recent(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let count = matches.value_of("count").unwrap().parse::<usize>()?;
+
+    actions::list_recent_projects(&config, count)
+}
+
+fn command_migrate(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let project_name = matches.value_of_lossy("project_name");
+    let project_file = matches.value_of_lossy("project_file");
+    let stdout = matches.is_present("stdout");
+
+    actions::migrate_project(
+        &config,
+        project_name.as_deref(),
+        project_file.as_deref(),
+        stdout,
+    )
 }
 
 fn command_freeze(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
@@ -380,9 +1147,13 @@ fn command_freeze(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
     let stdout = matches.is_present("stdout");
     let project_name = matches.value_of_lossy("project_name");
     let extension = matches.value_of_lossy("extension");
+    let format = matches.value_of_lossy("format");
     let no_input = matches.is_present("no_input");
     let editor = matches.value_of_lossy("editor").unwrap();
     let no_check = matches.is_present("no_check");
+    let verify = matches.is_present("verify");
+    let with_comments = matches.is_present("with_comments");
+    let session = matches.value_of_lossy("session");
     let args = matches.values_of_lossy("args").unwrap_or_default();
     let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
 
@@ -391,9 +1162,93 @@ fn command_freeze(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
         stdout,
         project_name.as_deref(),
         extension.as_deref(),
+        format.as_deref(),
         &editor,
         no_input,
         no_check,
+        verify,
+        with_comments,
         &args,
+        session.as_deref(),
+    )
+}
+
+fn command_adopt(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let session_name = matches.value_of_lossy("session_name").unwrap();
+    let project_name = matches.value_of_lossy("project_name").unwrap();
+    let freeze = matches.is_present("freeze");
+    let extension = matches.value_of_lossy("extension");
+    let no_input = matches.is_present("no_input");
+
+    actions::adopt_session(
+        &config,
+        &session_name,
+        &project_name,
+        freeze,
+        extension.as_deref(),
+        no_input,
+    )
+}
+
+fn command_autofreeze(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let interval = utils::parse_duration(&matches.value_of_lossy("interval").unwrap())?;
+    let once = matches.is_present("once");
+
+    actions::autofreeze_projects(&config, interval, once)
+}
+
+fn command_daemon(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let interval = utils::parse_duration(&matches.value_of_lossy("interval").unwrap())?;
+
+    actions::run_daemon(&config, interval)
+}
+
+fn command_boot(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    actions::boot_projects(&config)
+}
+
+fn command_clean(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let dry_run = matches.is_present("dry_run");
+
+    actions::clean_state(&config, dry_run)
+}
+
+fn command_service(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let project_name = matches.value_of_lossy("project_name");
+    let install = matches.is_present("install");
+
+    actions::install_service(&config, project_name.as_deref(), install)
+}
+
+fn command_import(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args(APP_NAME, APP_AUTHOR, matches).check()?;
+
+    let save_file = matches.value_of_lossy("save_file");
+    let session_name = matches.value_of_lossy("session");
+    let extension = matches.value_of_lossy("extension");
+    let stdout = matches.is_present("stdout");
+    let no_input = matches.is_present("no_input");
+    let verify = matches.is_present("verify");
+
+    actions::import_projects(
+        &config,
+        save_file.as_deref(),
+        session_name.as_deref(),
+        extension.as_deref(),
+        stdout,
+        no_input,
+        verify,
     )
 }
@@ -1,36 +1,332 @@
-use serde::{de, Deserialize};
+use serde::ser::{self, SerializeMap};
+use serde::{de, Deserialize, Serialize};
+use serde_yaml::Value;
+use shell_words::quote;
+
+use crate::utils::{describe_value, has_key, replace_placeholders};
+
+// How long an expect step waits for its text when no timeout is given, in seconds
+pub const DEFAULT_EXPECT_TIMEOUT: u64 = 60;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PaneCommand {
+    Run {
+        command: String,
+        retries: usize,
+        timeout: Option<u64>,
+        raw: bool,
+    },
+    Sleep(f64),
+    Expect {
+        expect: String,
+        send: String,
+        timeout: Option<u64>,
+    },
+}
+
+impl PaneCommand {
+    pub fn replace_placeholders(&self, placeholders: &[(&str, String)]) -> Self {
+        match self {
+            PaneCommand::Run {
+                command,
+                retries,
+                timeout,
+                raw,
+            } => PaneCommand::Run {
+                command: replace_placeholders(command, placeholders),
+                retries: *retries,
+                timeout: *timeout,
+                raw: *raw,
+            },
+            PaneCommand::Sleep(duration) => PaneCommand::Sleep(*duration),
+            PaneCommand::Expect {
+                expect,
+                send,
+                timeout,
+            } => PaneCommand::Expect {
+                expect: replace_placeholders(expect, placeholders),
+                send: replace_placeholders(send, placeholders),
+                timeout: *timeout,
+            },
+        }
+    }
+
+    pub fn is_simple(&self) -> bool {
+        matches!(
+            self,
+            PaneCommand::Run {
+                retries: 0,
+                timeout: None,
+                raw: false,
+                ..
+            }
+        )
+    }
+
+    // Reverts the escaping done by process_command, unless the command is already raw
+    pub fn into_raw(self) -> Self {
+        match self {
+            PaneCommand::Run {
+                command,
+                retries,
+                timeout,
+                raw: false,
+            } => PaneCommand::Run {
+                command: unprocess_command(command),
+                retries,
+                timeout,
+                raw: true,
+            },
+            command => command,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self, PaneCommand::Run { command, .. } if command.is_empty())
+    }
+
+    // Wraps the command in a timeout and/or a retry loop when needed
+    // The resulting command expects a POSIX-compatible shell
+    pub fn shell_command(&self) -> Option<String> {
+        let (command, retries, timeout) = match self {
+            PaneCommand::Run {
+                command,
+                retries,
+                timeout,
+                ..
+            } => (command, *retries, *timeout),
+            _ => return None,
+        };
+
+        let mut command = command.to_owned();
+
+        if let Some(timeout) = timeout {
+            command = format!("timeout {} sh -c {}", timeout, quote(&command));
+        }
+
+        if retries > 0 {
+            let attempts: Vec<String> = (1..=retries + 1).map(|n| n.to_string()).collect();
+            command = format!(
+                "for __airmux_attempt in {}; do {} && break; sleep 1; done",
+                attempts.join(" "),
+                command
+            );
+        }
+
+        Some(command)
+    }
+}
+
+impl From<&str> for PaneCommand {
+    fn from(command: &str) -> Self {
+        Self::from(command.to_string())
+    }
+}
+
+impl From<String> for PaneCommand {
+    fn from(command: String) -> Self {
+        PaneCommand::Run {
+            command,
+            retries: 0,
+            timeout: None,
+            raw: false,
+        }
+    }
+}
+
+impl Serialize for PaneCommand {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let map = match self {
+            PaneCommand::Run {
+                command,
+                retries,
+                timeout,
+                raw,
+            } => {
+                if self.is_simple() {
+                    return serializer.serialize_str(command);
+                }
+
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("command", command)?;
+                if *retries > 0 {
+                    map.serialize_entry("retries", retries)?;
+                }
+                if let Some(timeout) = timeout {
+                    map.serialize_entry("timeout", timeout)?;
+                }
+                if *raw {
+                    map.serialize_entry("raw", raw)?;
+                }
+                map
+            }
+            PaneCommand::Sleep(seconds) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("sleep", seconds)?;
+                map
+            }
+            PaneCommand::Expect {
+                expect,
+                send,
+                timeout,
+            } => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("expect", expect)?;
+                map.serialize_entry("send", send)?;
+                if let Some(timeout) = timeout {
+                    map.serialize_entry("timeout", timeout)?;
+                }
+                map
+            }
+        };
+
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PaneCommand {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        #[derive(Deserialize, Debug)]
+        #[serde(deny_unknown_fields)]
+        struct PaneCommandDef {
+            command: String,
+            #[serde(default)]
+            retries: usize,
+            #[serde(default)]
+            timeout: Option<u64>,
+            #[serde(default)]
+            raw: bool,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[serde(deny_unknown_fields)]
+        struct PaneSleepDef {
+            sleep: f64,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[serde(deny_unknown_fields)]
+        struct PaneExpectDef {
+            expect: String,
+            send: String,
+            #[serde(default)]
+            timeout: Option<u64>,
+        }
+
+        // Buffer the value so that we can pick the definition to use from its keys
+        // That way, the error message is about the definition the user meant to write
+        let value = Value::deserialize(deserializer)?;
+        let command =
+            match &value {
+                Value::String(_) => PaneCommand::from(process_command(
+                    String::deserialize(value).map_err(de::Error::custom)?,
+                )),
+                Value::Mapping(_) if has_key(&value, "command") => {
+                    let def = PaneCommandDef::deserialize(value).map_err(de::Error::custom)?;
+                    PaneCommand::Run {
+                        command: if def.raw {
+                            process_raw_command(def.command)
+                        } else {
+                            process_command(def.command)
+                        },
+                        retries: def.retries,
+                        timeout: def.timeout,
+                        raw: def.raw,
+                    }
+                }
+                Value::Mapping(_) if has_key(&value, "sleep") => {
+                    let def = PaneSleepDef::deserialize(value).map_err(de::Error::custom)?;
+                    if def.sleep < 0.0 {
+                        return Err(de::Error::custom("sleep duration cannot be negative"));
+                    }
+
+                    PaneCommand::Sleep(def.sleep)
+                }
+                Value::Mapping(_) if has_key(&value, "expect") => {
+                    let def = PaneExpectDef::deserialize(value).map_err(de::Error::custom)?;
+                    PaneCommand::Expect {
+                        expect: process_command(def.expect),
+                        send: process_command(def.send),
+                        timeout: def.timeout,
+                    }
+                }
+                Value::Mapping(_) => return Err(de::Error::custom(
+                    "invalid command definition: expected a `command`, `sleep` or `expect` field",
+                )),
+                _ => {
+                    return Err(de::Error::custom(format!(
+                        "invalid command: expected a string or a command definition, got {}",
+                        describe_value(&value)
+                    )))
+                }
+            };
+
+        Ok(command)
+    }
+}
 
 pub fn de_command_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
     D: de::Deserializer<'de>,
 {
-    #[derive(Deserialize, Debug)]
-    #[serde(untagged)]
-    enum CommandList {
-        List(Vec<String>),
-        Single(String),
-        Empty,
-    }
-
-    let command_list: CommandList = de::Deserialize::deserialize(deserializer)?;
-    Ok(match command_list {
-        CommandList::List(commands) => process_command_list(commands),
-        CommandList::Single(command) => vec![process_command(command)],
-        CommandList::Empty => vec![],
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::Null => vec![],
+        Value::String(command) => vec![process_command(command)],
+        Value::Sequence(_) => {
+            process_command_list(Vec::<String>::deserialize(value).map_err(de::Error::custom)?)
+        }
+        _ => {
+            return Err(de::Error::custom(format!(
+                "expected a command or a list of commands, got {}",
+                describe_value(&value)
+            )))
+        }
+    })
+}
+
+pub fn de_pane_command_list<'de, D>(deserializer: D) -> Result<Vec<PaneCommand>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::Null => vec![],
+        Value::Sequence(_) => Vec::<PaneCommand>::deserialize(value).map_err(de::Error::custom)?,
+        _ => vec![PaneCommand::deserialize(value).map_err(de::Error::custom)?],
     })
 }
 
 pub fn process_command(command: String) -> String {
-    command
-        .replace("#", "##")
-        .replace("\n", " ")
-        .replace("\r", "")
+    process_raw_command(command.replace("#", "##"))
+}
+
+// Same as process_command, but leaves pound signs as they are
+pub fn process_raw_command(command: String) -> String {
+    command.replace("\n", " ").replace("\r", "")
+}
+
+pub fn unprocess_command(command: String) -> String {
+    command.replace("##", "#")
 }
 
 pub fn process_command_list(commands: Vec<String>) -> Vec<String> {
     commands.into_iter().map(process_command).collect()
 }
 
+pub fn process_pane_command_list(commands: Vec<String>) -> Vec<PaneCommand> {
+    commands
+        .into_iter()
+        .map(|command| PaneCommand::from(process_command(command)))
+        .collect()
+}
+
 #[cfg(test)]
 #[path = "test/command.rs"]
 mod tests;
@@ -3,11 +3,15 @@ use crate::utils;
 use app_dirs::{get_app_root, AppDataType, AppInfo};
 use clap::ArgMatches;
 use mkdirp::mkdirp;
+use shell_words::split;
 use snafu::{ensure, Snafu};
 
 use std::error;
 use std::path::{Path, PathBuf};
 
+// Supported by default, the first one is used for new project files
+pub const FILE_EXTENSIONS: &[&str] = &["yml", "yaml", "json"];
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("app_name cannot be empty"))]
@@ -24,7 +28,15 @@ pub struct Config {
     pub app_name: &'static str,
     pub app_author: &'static str,
     pub tmux_command: Option<String>,
+    pub tmux_socket: Option<String>,
+    pub tmux_options: Option<String>,
     pub config_dir: Option<PathBuf>,
+    pub lenient: bool,
+    pub picker: Option<String>,
+    pub profile: bool,
+    pub session_prefix: Option<String>,
+    pub editor_args: Vec<String>,
+    pub file_extensions: Vec<String>,
 }
 
 impl Config {
@@ -34,13 +46,35 @@ impl Config {
         matches: &ArgMatches,
     ) -> Config {
         let tmux_command = matches.value_of_lossy("tmux_command").map(String::from);
+        let tmux_socket = matches.value_of_lossy("tmux_socket").map(String::from);
+        let tmux_options = matches.value_of_lossy("tmux_options").map(String::from);
         let config_dir = matches.value_of_os("config_dir").map(PathBuf::from);
+        let lenient = matches.is_present("lenient");
+        let picker = matches.value_of_lossy("picker").map(String::from);
+        let profile = matches.is_present("profile");
+        let session_prefix = matches.value_of_lossy("session_prefix").map(String::from);
+        let editor_args = matches.values_of_lossy("editor_args").unwrap_or_default();
+        let file_extensions = matches
+            .values_of_lossy("file_extensions")
+            .unwrap_or_default()
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .filter(|extension| !extension.is_empty())
+            .collect();
 
         Config {
             app_name,
             app_author,
             tmux_command,
+            tmux_socket,
+            tmux_options,
             config_dir,
+            lenient,
+            picker,
+            profile,
+            session_prefix,
+            editor_args,
+            file_extensions,
         }
     }
 
@@ -52,7 +86,7 @@ impl Config {
             let path = PathBuf::from(config_dir);
             ensure!(!path.is_file(), ConfigDirIsNotADirectory { path });
 
-            mkdirp(&config_dir)?;
+            mkdirp(config_dir)?;
         };
 
         Ok(self)
@@ -69,8 +103,8 @@ impl Config {
             path = get_app_root(
                 AppDataType::UserConfig,
                 &AppInfo {
-                    name: &self.app_name,
-                    author: &self.app_author,
+                    name: self.app_name,
+                    author: self.app_author,
                 },
             )?;
         };
@@ -88,6 +122,53 @@ impl Config {
         self.get_config_dir(sub_path)
     }
 
+    // State files (history, etc...) live next to the projects when config_dir is overridden
+    pub fn get_data_dir<P>(&self, sub_path: P) -> Result<PathBuf, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let path;
+        if let Some(dir) = &self.config_dir {
+            path = PathBuf::from(dir);
+        } else {
+            path = get_app_root(
+                AppDataType::UserData,
+                &AppInfo {
+                    name: self.app_name,
+                    author: self.app_author,
+                },
+            )?;
+        };
+
+        let path = path.join(&sub_path);
+        mkdirp(&path)?;
+
+        Ok(path)
+    }
+
+    // Extensions of the files listed and created as project files, in order of preference
+    pub fn get_file_extensions(&self) -> Vec<&str> {
+        if self.file_extensions.is_empty() {
+            return FILE_EXTENSIONS.to_vec();
+        }
+
+        self.file_extensions.iter().map(AsRef::as_ref).collect()
+    }
+
+    pub fn is_supported_extension(&self, extension: &str) -> bool {
+        let extension = extension.to_lowercase();
+        self.get_file_extensions().contains(&extension.as_str())
+    }
+
+    // Applied to all the sessions started by airmux
+    pub fn get_session_name(&self, session_name: &str) -> String {
+        match &self.session_prefix {
+            Some(prefix) => format!("{}{}", prefix, session_name),
+            None => session_name.to_string(),
+        }
+    }
+
+    // Also appends the --socket and --tmux-options arguments, like Project::tmux_command does
     pub fn get_tmux_command(
         &self,
         args: &[&str],
@@ -97,7 +178,38 @@ impl Config {
             .to_owned()
             .unwrap_or_else(|| String::from("tmux"));
 
-        utils::parse_command(&command, args)
+        let mut full_args = vec![];
+        if let Some(tmux_socket) = &self.tmux_socket {
+            full_args.extend_from_slice(&["-L", tmux_socket]);
+        }
+
+        let tmux_options_split;
+        if let Some(tmux_options) = &self.tmux_options {
+            tmux_options_split = split(tmux_options)?;
+            full_args.extend(tmux_options_split.iter().map(|x| x.as_str()));
+        }
+
+        full_args.extend_from_slice(args);
+        utils::parse_command(&command, &full_args)
+    }
+
+    // Arguments to pass the tmux settings on to other airmux invocations
+    pub fn get_tmux_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(tmux_command) = &self.tmux_command {
+            args.push(String::from("--command"));
+            args.push(tmux_command.to_owned());
+        }
+        if let Some(tmux_socket) = &self.tmux_socket {
+            args.push(String::from("--socket"));
+            args.push(tmux_socket.to_owned());
+        }
+        if let Some(tmux_options) = &self.tmux_options {
+            args.push(String::from("--tmux-options"));
+            args.push(tmux_options.to_owned());
+        }
+
+        args
     }
 }
 
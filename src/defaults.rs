@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::is_default;
+
+// Flags of `airmux start` that a project turns on for itself, on top of the ones passed to it
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    #[serde(skip_serializing_if = "is_default")]
+    pub no_attach: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    pub verbose: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    pub switch: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    pub prune: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    pub refresh: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    pub no_input: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    pub check_splits: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    pub args: Vec<String>,
+}
+
+impl Defaults {
+    // --attach and --no-attach win over the project's no_attach
+    pub fn force_attach(&self, force_attach: Option<bool>) -> Option<bool> {
+        if self.no_attach {
+            force_attach.or(Some(false))
+        } else {
+            force_attach
+        }
+    }
+
+    // Arguments passed on the command line replace the project's, they're positional
+    pub fn args<'a>(&'a self, args: &[&'a str]) -> Vec<&'a str> {
+        if args.is_empty() {
+            self.args.iter().map(AsRef::as_ref).collect()
+        } else {
+            args.to_vec()
+        }
+    }
+}
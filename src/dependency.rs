@@ -0,0 +1,136 @@
+use serde::ser::{self, SerializeMap};
+use serde::{de, Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::utils::{describe_value, has_key};
+
+// How long to wait for a dependency to be ready when no timeout is given, in seconds
+pub const DEFAULT_READY_TIMEOUT: u64 = 30;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Dependency {
+    pub project: String,
+    pub window: Option<String>,
+    pub ready: Option<String>,
+    pub timeout: Option<u64>,
+}
+
+impl Dependency {
+    pub fn is_simple(&self) -> bool {
+        self.window.is_none() && self.ready.is_none() && self.timeout.is_none()
+    }
+
+    // Whether there's anything to wait for once the dependency is started
+    pub fn has_readiness_gate(&self) -> bool {
+        self.window.is_some() || self.ready.is_some()
+    }
+}
+
+impl From<&str> for Dependency {
+    fn from(project: &str) -> Self {
+        Self::from(project.to_string())
+    }
+}
+
+impl From<String> for Dependency {
+    fn from(project: String) -> Self {
+        Dependency {
+            project,
+            window: None,
+            ready: None,
+            timeout: None,
+        }
+    }
+}
+
+impl Serialize for Dependency {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        if self.is_simple() {
+            return serializer.serialize_str(&self.project);
+        }
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("project", &self.project)?;
+        if let Some(window) = &self.window {
+            map.serialize_entry("window", window)?;
+        }
+        if let Some(ready) = &self.ready {
+            map.serialize_entry("ready", ready)?;
+        }
+        if let Some(timeout) = &self.timeout {
+            map.serialize_entry("timeout", timeout)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Dependency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        #[derive(Deserialize, Debug)]
+        #[serde(deny_unknown_fields)]
+        struct DependencyDef {
+            project: String,
+            #[serde(default)]
+            window: Option<String>,
+            #[serde(default)]
+            ready: Option<String>,
+            #[serde(default)]
+            timeout: Option<u64>,
+        }
+
+        let value = Value::deserialize(deserializer)?;
+        let dependency = match &value {
+            Value::String(_) => {
+                Dependency::from(String::deserialize(value).map_err(de::Error::custom)?)
+            }
+            Value::Mapping(_) if has_key(&value, "project") => {
+                let def = DependencyDef::deserialize(value).map_err(de::Error::custom)?;
+                Dependency {
+                    project: def.project,
+                    window: def.window,
+                    ready: def.ready,
+                    timeout: def.timeout,
+                }
+            }
+            Value::Mapping(_) => {
+                return Err(de::Error::custom(
+                    "invalid dependency definition: expected a `project` field",
+                ))
+            }
+            _ => {
+                return Err(de::Error::custom(format!(
+                "invalid dependency: expected a project name or a dependency definition, got {}",
+                describe_value(&value)
+            )))
+            }
+        };
+
+        if dependency.project.is_empty() {
+            return Err(de::Error::custom("dependency project name cannot be empty"));
+        }
+
+        Ok(dependency)
+    }
+}
+
+pub fn de_dependency_list<'de, D>(deserializer: D) -> Result<Vec<Dependency>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::Null => vec![],
+        Value::Sequence(_) => Vec::<Dependency>::deserialize(value).map_err(de::Error::custom)?,
+        _ => vec![Dependency::deserialize(value).map_err(de::Error::custom)?],
+    })
+}
+
+#[cfg(test)]
+#[path = "test/dependency.rs"]
+mod tests;
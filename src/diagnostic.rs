@@ -0,0 +1,145 @@
+use std::error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const HINTS: &[(&str, &str)] = &[
+    (
+        "did not match any variant of untagged enum",
+        "a field has a value of an unexpected type, refer to the project file reference in the README",
+    ),
+    ("unknown field", "check the field name for typos"),
+    ("unknown variant", "check the value for typos"),
+    ("invalid type", "check the type of the value"),
+    (", got ", "check the type of the value"),
+    (
+        "did not find expected",
+        "check the indentation and make sure quotes and brackets are closed",
+    ),
+    ("expected `", "make sure quotes, brackets and commas are balanced"),
+];
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+    pub snippet: Option<String>,
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new<P>(file: P, message: String) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let hint = HINTS
+            .iter()
+            .find(|(pattern, _)| message.contains(pattern))
+            .map(|(_, hint)| String::from(*hint));
+
+        Self {
+            file: file.as_ref().to_path_buf(),
+            line: None,
+            column: None,
+            message,
+            snippet: None,
+            hint,
+        }
+    }
+
+    // Line and column are both 1-based
+    pub fn with_location(self, source: &str, line: usize, column: usize) -> Self {
+        let snippet = source.lines().nth(line.saturating_sub(1)).map(String::from);
+
+        Self {
+            line: Some(line),
+            column: Some(column),
+            snippet,
+            ..self
+        }
+    }
+
+    pub fn from_yaml_error<P>(file: P, source: &str, error: serde_yaml::Error) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let message = error.to_string();
+
+        match error.location() {
+            None => Self::new(file, message),
+            Some(location) => {
+                // The location is already part of the message, no need to print it twice
+                let suffix = format!(" at line {} column {}", location.line(), location.column());
+                let message = message
+                    .strip_suffix(&suffix)
+                    .map_or_else(|| message.to_owned(), String::from);
+
+                Self::new(file, message).with_location(source, location.line(), location.column())
+            }
+        }
+    }
+
+    pub fn from_json_error<P>(file: P, source: &str, error: serde_json::Error) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let message = error.to_string();
+
+        // Same as yaml, the location is already part of the message
+        let suffix = format!(" at line {} column {}", error.line(), error.column());
+        let message = message
+            .strip_suffix(&suffix)
+            .map_or_else(|| message.to_owned(), String::from);
+
+        Self::new(file, message).with_location(source, error.line(), error.column())
+    }
+
+    fn file_name(&self) -> String {
+        if self.file == PathBuf::new() {
+            String::from("<stdin>")
+        } else {
+            self.file.to_string_lossy().to_string()
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file_name(),
+                line,
+                column,
+                self.message
+            )?,
+            _ => write!(f, "{}: {}", self.file_name(), self.message)?,
+        }
+
+        if let (Some(line), Some(column), Some(snippet)) = (self.line, self.column, &self.snippet) {
+            let gutter = " ".repeat(line.to_string().len());
+            write!(f, "\n{} |", gutter)?;
+            write!(f, "\n{} | {}", line, snippet)?;
+            write!(
+                f,
+                "\n{} | {}^",
+                gutter,
+                " ".repeat(column.saturating_sub(1))
+            )?;
+        }
+
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  = hint: {}", hint)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for Diagnostic {}
+
+#[cfg(test)]
+#[path = "test/diagnostic.rs"]
+mod tests;
@@ -1,9 +1,14 @@
 pub mod actions;
 pub mod command;
 pub mod config;
+pub mod defaults;
+pub mod dependency;
+pub mod diagnostic;
 pub mod pane;
+pub mod pane_reference;
 pub mod pane_split;
 pub mod project;
+pub mod reference;
 pub mod startup_window;
 pub mod utils;
 pub mod window;
@@ -1,9 +1,16 @@
-use crate::command::{de_command_list, process_command, process_command_list};
+use crate::command::{
+    de_command_list, de_pane_command_list, process_command, process_command_list,
+    process_pane_command_list, unprocess_command, PaneCommand,
+};
+use crate::pane_reference::PaneReference;
 use crate::pane_split::PaneSplit;
 use crate::working_dir::{de_working_dir, home_working_dir, process_working_dir};
 
+use crate::utils::{describe_value, has_key, replace_placeholders};
+
 use de::Visitor;
 use serde::{de, Deserialize, Serialize};
+use serde_yaml::Value;
 
 use std::error::Error;
 use std::fmt;
@@ -14,13 +21,20 @@ pub struct Pane {
     pub name: Option<String>,
     pub working_dir: Option<PathBuf>,
     pub split: Option<PaneSplit>,
-    pub split_from: Option<usize>,
+    pub split_from: Option<PaneReference>,
     pub split_size: Option<String>,
     pub clear: bool,
+    pub zoom: bool,
+    pub mark: bool,
+    pub style: Option<String>,
+    pub shell: Option<String>,
     pub on_create: Vec<String>,
     pub post_create: Vec<String>,
-    pub commands: Vec<String>,
+    pub commands: Vec<PaneCommand>,
+    pub join_commands: Option<String>,
     pub send_keys: Vec<String>,
+    pub raw: bool,
+    pub exec: bool,
 }
 
 impl Pane {
@@ -36,26 +50,101 @@ impl Pane {
             }
         }
 
+        // The command becomes the pane process, so there's nothing to type the others into
+        if self.exec && self.commands.len() != 1 {
+            return Err("pane exec requires a single command".into());
+        }
+
         Ok(())
     }
 
+    // Command the pane runs in place of its shell when exec is set, unescaped since it's not typed
+    pub fn exec_command(&self) -> Option<String> {
+        if !self.exec {
+            return None;
+        }
+
+        self.commands
+            .first()
+            .and_then(|command| command.to_owned().into_raw().shell_command())
+    }
+
+    pub fn replace_placeholders(&self, placeholders: &[(&str, String)]) -> Self {
+        let replace_list = |commands: &[String]| -> Vec<String> {
+            commands
+                .iter()
+                .map(|command| replace_placeholders(command, placeholders))
+                .collect()
+        };
+
+        Self {
+            name: self
+                .name
+                .as_deref()
+                .map(|name| replace_placeholders(name, placeholders)),
+            on_create: replace_list(&self.on_create),
+            post_create: replace_list(&self.post_create),
+            commands: self
+                .commands
+                .iter()
+                .map(|command| command.replace_placeholders(placeholders))
+                .collect(),
+            send_keys: replace_list(&self.send_keys),
+            ..self.to_owned()
+        }
+    }
+
+    // Whether the key is a field of a pane definition rather than the name of the pane
+    pub fn is_field(key: &str) -> bool {
+        Self::expected_value(Some(key)) != Self::expected_value(None)
+    }
+
+    // Describes the values a pane field accepts, for error messages
+    fn expected_value(key: Option<&str>) -> &'static str {
+        match key {
+            Some("name") | Some("title") => "a string or a number",
+            Some("working_dir") | Some("root") => "a path",
+            Some("split") => "one of v, h, vertical or horizontal",
+            Some("split_from") => "a pane index or name",
+            Some("split_size") => "a number of cells or a percentage",
+            Some("clear")
+            | Some("zoom")
+            | Some("mark")
+            | Some("raw")
+            | Some("exec")
+            | Some("inherit_working_dir") => "a boolean",
+            Some("on_create") | Some("post_create") | Some("send_keys") => {
+                "a command or a list of commands"
+            }
+            Some("commands") | Some("command") => {
+                "a command, a command definition or a list of those"
+            }
+            Some("join_commands") | Some("shell") => "a string",
+            Some("style") => "a tmux style (ie: bg=colour236)",
+            _ => "a command, a list of commands or a pane definition",
+        }
+    }
+
+    fn default_inherit_working_dir() -> bool {
+        true
+    }
+
     fn de_split_size<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
     where
         D: de::Deserializer<'de>,
     {
-        #[derive(Deserialize, Debug)]
-        #[serde(untagged)]
-        enum SplitSize {
-            Cells(usize),
-            Percent(String),
-            None,
-        }
-
-        let size: SplitSize = de::Deserialize::deserialize(deserializer)?;
-        Ok(match size {
-            SplitSize::Cells(size) => Some(size.to_string()),
-            SplitSize::Percent(percent) => Some(percent),
-            SplitSize::None => None,
+        let value = Value::deserialize(deserializer)?;
+        Ok(match value {
+            Value::Null => None,
+            Value::String(percent) => Some(percent),
+            Value::Number(cells) if cells.is_u64() => Some(cells.to_string()),
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "split_size: expected {}, got {}",
+                    Self::expected_value(Some("split_size")),
+                    describe_value(&value)
+                )))
+            }
         })
     }
 }
@@ -69,7 +158,7 @@ impl From<&str> for Pane {
 impl From<String> for Pane {
     fn from(command: String) -> Self {
         Self {
-            commands: vec![process_command(command)],
+            commands: vec![PaneCommand::from(process_command(command))],
             ..Self::default()
         }
     }
@@ -78,7 +167,16 @@ impl From<String> for Pane {
 impl From<Vec<String>> for Pane {
     fn from(commands: Vec<String>) -> Self {
         Self {
-            commands: commands.into_iter().map(process_command).collect(),
+            commands: process_pane_command_list(commands),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<PaneCommand> for Pane {
+    fn from(command: PaneCommand) -> Self {
+        Self {
+            commands: vec![command],
             ..Self::default()
         }
     }
@@ -89,7 +187,7 @@ impl<'de> Visitor<'de> for PaneVisitor {
     type Value = Pane;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a pane definition")
+        formatter.write_str(Pane::expected_value(None))
     }
 
     fn visit_none<E>(self) -> Result<Self::Value, E>
@@ -137,22 +235,38 @@ impl<'de> Visitor<'de> for PaneVisitor {
         struct PaneDef {
             #[serde(default, alias = "root", deserialize_with = "de_working_dir")]
             working_dir: Option<PathBuf>,
+            #[serde(default = "Pane::default_inherit_working_dir")]
+            inherit_working_dir: bool,
             #[serde(default)]
             split: Option<PaneSplit>,
             #[serde(default)]
-            split_from: Option<usize>,
+            split_from: Option<PaneReference>,
             #[serde(default, deserialize_with = "Pane::de_split_size")]
             split_size: Option<String>,
             #[serde(default)]
             clear: bool,
+            #[serde(default)]
+            zoom: bool,
+            #[serde(default)]
+            mark: bool,
+            #[serde(default)]
+            style: Option<String>,
+            #[serde(default)]
+            shell: Option<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             on_create: Vec<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             post_create: Vec<String>,
-            #[serde(default, alias = "command", deserialize_with = "de_command_list")]
-            commands: Vec<String>,
+            #[serde(default, alias = "command", deserialize_with = "de_pane_command_list")]
+            commands: Vec<PaneCommand>,
+            #[serde(default)]
+            join_commands: Option<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             send_keys: Vec<String>,
+            #[serde(default)]
+            raw: bool,
+            #[serde(default)]
+            exec: bool,
         }
 
         #[derive(Deserialize, Debug)]
@@ -162,22 +276,38 @@ impl<'de> Visitor<'de> for PaneVisitor {
             name: Option<String>,
             #[serde(default, alias = "root", deserialize_with = "de_working_dir")]
             working_dir: Option<PathBuf>,
+            #[serde(default = "Pane::default_inherit_working_dir")]
+            inherit_working_dir: bool,
             #[serde(default)]
             split: Option<PaneSplit>,
             #[serde(default)]
-            split_from: Option<usize>,
+            split_from: Option<PaneReference>,
             #[serde(default, deserialize_with = "Pane::de_split_size")]
             split_size: Option<String>,
             #[serde(default)]
             clear: bool,
+            #[serde(default)]
+            zoom: bool,
+            #[serde(default)]
+            mark: bool,
+            #[serde(default)]
+            style: Option<String>,
+            #[serde(default)]
+            shell: Option<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             on_create: Vec<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             post_create: Vec<String>,
-            #[serde(default, alias = "command", deserialize_with = "de_command_list")]
-            commands: Vec<String>,
+            #[serde(default, alias = "command", deserialize_with = "de_pane_command_list")]
+            commands: Vec<PaneCommand>,
+            #[serde(default)]
+            join_commands: Option<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             send_keys: Vec<String>,
+            #[serde(default)]
+            raw: bool,
+            #[serde(default)]
+            exec: bool,
         }
 
         #[derive(Deserialize, Debug)]
@@ -188,13 +318,75 @@ impl<'de> Visitor<'de> for PaneVisitor {
             Number(usize),
             String(String),
             CommandList(Vec<String>),
+            PaneCommandList(Vec<PaneCommand>),
             Definition(PaneDef),
             DefinitionWithName(PaneDefWithName),
+            PaneCommand(PaneCommand),
+        }
+
+        // Tries the shapes the field accepts again to surface the most relevant error
+        fn invalid_value<E>(key: Option<&str>, value: Value, first_entry: bool) -> E
+        where
+            E: de::Error,
+        {
+            let field_error = match key {
+                Some("commands") | Some("command") => de_pane_command_list(value.clone()).err(),
+                Some(field) if Pane::expected_value(Some(field)) != Pane::expected_value(None) => {
+                    None
+                }
+                _ if !first_entry => {
+                    return E::custom(format!("unknown pane field {:?}", key.unwrap_or("~")))
+                }
+                _ if has_key(&value, "name") || has_key(&value, "title") => {
+                    PaneDefWithName::deserialize(value.clone()).err()
+                }
+                _ => match &value {
+                    Value::Mapping(_) => PaneDef::deserialize(value.clone()).err(),
+                    Value::Sequence(_) => de_pane_command_list(value.clone()).err(),
+                    _ => None,
+                },
+            };
+
+            let target = match key {
+                Some(key) if Pane::expected_value(Some(key)) != Pane::expected_value(None) => {
+                    format!("pane field {:?}", key)
+                }
+                Some(name) => format!("pane {:?}", name),
+                None => String::from("pane"),
+            };
+
+            match field_error {
+                Some(err) => E::custom(format!("{}: {}", target, err)),
+                None => E::custom(format!(
+                    "{}: expected {}, got {}",
+                    target,
+                    Pane::expected_value(key),
+                    describe_value(&value)
+                )),
+            }
         }
 
         let mut first_entry = true;
+        let mut inherit_working_dir = true;
         let mut pane = Self::Value::default();
-        while let Some((key, val)) = map.next_entry::<PaneKeyType, PaneOption>()? {
+        let mut entries: Vec<(PaneKeyType, Value)> = vec![];
+        while let Some(key) = map.next_key::<PaneKeyType>()? {
+            entries.push((key, map.next_value()?));
+        }
+
+        // `- shell: command` is a pane named shell, unless the map is a definition
+        let is_definition = entries.iter().any(|(key, _)| {
+            matches!(
+                key.as_deref(),
+                Some("name") | Some("title") | Some("commands") | Some("command")
+            )
+        });
+
+        for (key, value) in entries {
+            let shell_is_field = !first_entry || is_definition;
+            let val = PaneOption::deserialize(value.clone())
+                .map_err(|_| invalid_value(key.as_deref(), value, first_entry))?;
+
             match key {
                 None => {
                     if !first_entry {
@@ -217,32 +409,52 @@ impl<'de> Visitor<'de> for PaneVisitor {
                                 val
                             )));
                         }
-                        PaneOption::String(string) => pane.commands = vec![process_command(string)],
+                        PaneOption::String(string) => {
+                            pane.commands = vec![PaneCommand::from(process_command(string))]
+                        }
                         PaneOption::CommandList(commands) => {
-                            pane.commands = process_command_list(commands)
+                            pane.commands = process_pane_command_list(commands)
                         }
+                        PaneOption::PaneCommandList(commands) => pane.commands = commands,
+                        PaneOption::PaneCommand(command) => pane.commands = vec![command],
                         PaneOption::Definition(def) => {
                             pane.working_dir = def.working_dir;
+                            inherit_working_dir = def.inherit_working_dir;
                             pane.split = def.split;
                             pane.split_from = def.split_from;
                             pane.split_size = def.split_size;
                             pane.clear = def.clear;
+                            pane.zoom = def.zoom;
+                            pane.mark = def.mark;
+                            pane.style = def.style;
+                            pane.shell = def.shell;
                             pane.on_create = def.on_create;
                             pane.post_create = def.post_create;
                             pane.commands = def.commands;
+                            pane.join_commands = def.join_commands;
                             pane.send_keys = def.send_keys;
+                            pane.raw = def.raw;
+                            pane.exec = def.exec;
                         }
                         PaneOption::DefinitionWithName(def) => {
                             pane.name = def.name;
                             pane.working_dir = def.working_dir;
+                            inherit_working_dir = def.inherit_working_dir;
                             pane.split = def.split;
                             pane.split_from = def.split_from;
                             pane.split_size = def.split_size;
                             pane.clear = def.clear;
+                            pane.zoom = def.zoom;
+                            pane.mark = def.mark;
+                            pane.style = def.style;
+                            pane.shell = def.shell;
                             pane.on_create = def.on_create;
                             pane.post_create = def.post_create;
                             pane.commands = def.commands;
+                            pane.join_commands = def.join_commands;
                             pane.send_keys = def.send_keys;
+                            pane.raw = def.raw;
+                            pane.exec = def.exec;
                         }
                     }
                 }
@@ -250,14 +462,22 @@ impl<'de> Visitor<'de> for PaneVisitor {
                     PaneOption::None => match key.as_str() {
                         "name" | "title" => pane.name = None,
                         "working_dir" | "root" => pane.working_dir = Some(home_working_dir()),
+                        "inherit_working_dir" => inherit_working_dir = true,
                         "split" => pane.split = None,
                         "split_from" => pane.split_from = None,
                         "split_size" => pane.split_size = None,
                         "clear" => pane.clear = false,
+                        "zoom" => pane.zoom = false,
+                        "mark" => pane.mark = false,
+                        "style" => pane.style = None,
+                        "shell" if shell_is_field => pane.shell = None,
                         "on_create" => pane.on_create = vec![],
                         "post_create" => pane.post_create = vec![],
                         "commands" | "command" => pane.commands = vec![],
+                        "join_commands" => pane.join_commands = None,
                         "send_keys" => pane.send_keys = vec![],
+                        "raw" => pane.raw = false,
+                        "exec" => pane.exec = false,
                         _ => {
                             if !first_entry {
                                 return Err(de::Error::custom(format!(
@@ -271,6 +491,11 @@ impl<'de> Visitor<'de> for PaneVisitor {
                     },
                     PaneOption::Bool(val) => match key.as_str() {
                         "clear" => pane.clear = val,
+                        "zoom" => pane.zoom = val,
+                        "mark" => pane.mark = val,
+                        "raw" => pane.raw = val,
+                        "exec" => pane.exec = val,
+                        "inherit_working_dir" => inherit_working_dir = val,
                         _ => {
                             return Err(de::Error::custom(format!(
                                 "pane field {:?} cannot be a boolean",
@@ -281,11 +506,19 @@ impl<'de> Visitor<'de> for PaneVisitor {
                     PaneOption::Number(val) => match key.as_str() {
                         "name" | "title" => pane.name = Some(val.to_string()),
                         "working_dir" | "root" => {
-                            pane.working_dir = Some(process_working_dir(val.to_string().as_str()))
+                            pane.working_dir = Some(
+                                process_working_dir(val.to_string().as_str())
+                                    .map_err(de::Error::custom)?,
+                            )
                         }
-                        "split_from" => pane.split_from = Some(val),
+                        "split_from" => pane.split_from = Some(PaneReference::Index(val)),
                         "split_size" => pane.split_size = Some(val.to_string()),
                         "clear" => pane.clear = val != 0,
+                        "zoom" => pane.zoom = val != 0,
+                        "mark" => pane.mark = val != 0,
+                        "raw" => pane.raw = val != 0,
+                        "exec" => pane.exec = val != 0,
+                        "inherit_working_dir" => inherit_working_dir = val != 0,
                         _ => {
                             return Err(de::Error::custom(format!(
                                 "pane field {:?} cannot be a number",
@@ -295,8 +528,11 @@ impl<'de> Visitor<'de> for PaneVisitor {
                     },
                     PaneOption::String(val) => match key.as_str() {
                         "name" | "title" => pane.name = Some(val),
+                        "style" => pane.style = Some(val),
+                        "shell" if shell_is_field => pane.shell = Some(val),
                         "working_dir" | "root" => {
-                            pane.working_dir = Some(process_working_dir(val.as_str()))
+                            pane.working_dir =
+                                Some(process_working_dir(val.as_str()).map_err(de::Error::custom)?)
                         }
                         "split" => {
                             pane.split = Some(match val {
@@ -315,10 +551,14 @@ impl<'de> Visitor<'de> for PaneVisitor {
                                 }
                             })
                         }
+                        "split_from" => pane.split_from = Some(PaneReference::Name(val)),
                         "split_size" => pane.split_size = Some(val),
                         "on_create" => pane.on_create = vec![process_command(val)],
                         "post_create" => pane.post_create = vec![process_command(val)],
-                        "commands" | "command" => pane.commands = vec![process_command(val)],
+                        "commands" | "command" => {
+                            pane.commands = vec![PaneCommand::from(process_command(val))]
+                        }
+                        "join_commands" => pane.join_commands = Some(val),
                         "send_keys" => pane.send_keys = vec![process_command(val)],
                         _ => {
                             if !first_entry {
@@ -329,13 +569,15 @@ impl<'de> Visitor<'de> for PaneVisitor {
                             }
 
                             pane.name = Some(key);
-                            pane.commands = vec![process_command(val)];
+                            pane.commands = vec![PaneCommand::from(process_command(val))];
                         }
                     },
                     PaneOption::CommandList(commands) => match key.as_str() {
                         "on_create" => pane.on_create = process_command_list(commands),
                         "post_create" => pane.post_create = process_command_list(commands),
-                        "commands" | "command" => pane.commands = process_command_list(commands),
+                        "commands" | "command" => {
+                            pane.commands = process_pane_command_list(commands)
+                        }
                         "send_keys" => pane.send_keys = process_command_list(commands),
                         _ => {
                             if !first_entry {
@@ -346,7 +588,35 @@ impl<'de> Visitor<'de> for PaneVisitor {
                             }
 
                             pane.name = Some(key);
-                            pane.commands = process_command_list(commands);
+                            pane.commands = process_pane_command_list(commands);
+                        }
+                    },
+                    PaneOption::PaneCommandList(commands) => match key.as_str() {
+                        "commands" | "command" => pane.commands = commands,
+                        _ => {
+                            if !first_entry {
+                                return Err(de::Error::custom(format!(
+                                    "pane field {:?} cannot be a command list",
+                                    key
+                                )));
+                            }
+
+                            pane.name = Some(key);
+                            pane.commands = commands;
+                        }
+                    },
+                    PaneOption::PaneCommand(command) => match key.as_str() {
+                        "commands" | "command" => pane.commands = vec![command],
+                        _ => {
+                            if !first_entry {
+                                return Err(de::Error::custom(format!(
+                                    "pane field {:?} cannot be a command definition",
+                                    key
+                                )));
+                            }
+
+                            pane.name = Some(key);
+                            pane.commands = vec![command];
                         }
                     },
                     PaneOption::Definition(def) => {
@@ -359,14 +629,22 @@ impl<'de> Visitor<'de> for PaneVisitor {
 
                         pane.name = Some(key);
                         pane.working_dir = def.working_dir;
+                        inherit_working_dir = def.inherit_working_dir;
                         pane.split = def.split;
                         pane.split_from = def.split_from;
                         pane.split_size = def.split_size;
                         pane.clear = def.clear;
+                        pane.zoom = def.zoom;
+                        pane.mark = def.mark;
+                        pane.style = def.style;
+                        pane.shell = def.shell;
                         pane.on_create = def.on_create;
                         pane.post_create = def.post_create;
                         pane.commands = def.commands;
+                        pane.join_commands = def.join_commands;
                         pane.send_keys = def.send_keys;
+                        pane.raw = def.raw;
+                        pane.exec = def.exec;
                     }
                     PaneOption::DefinitionWithName(def) => {
                         if !first_entry {
@@ -378,14 +656,22 @@ impl<'de> Visitor<'de> for PaneVisitor {
 
                         pane.name = def.name;
                         pane.working_dir = def.working_dir;
+                        inherit_working_dir = def.inherit_working_dir;
                         pane.split = def.split;
                         pane.split_from = def.split_from;
                         pane.split_size = def.split_size;
                         pane.clear = def.clear;
+                        pane.zoom = def.zoom;
+                        pane.mark = def.mark;
+                        pane.style = def.style;
+                        pane.shell = def.shell;
                         pane.on_create = def.on_create;
                         pane.post_create = def.post_create;
                         pane.commands = def.commands;
+                        pane.join_commands = def.join_commands;
                         pane.send_keys = def.send_keys;
+                        pane.raw = def.raw;
+                        pane.exec = def.exec;
                     }
                 },
             }
@@ -393,6 +679,21 @@ impl<'de> Visitor<'de> for PaneVisitor {
             first_entry = false;
         }
 
+        // Panes that don't inherit their working_dir start in the home directory
+        if !inherit_working_dir && pane.working_dir.is_none() {
+            pane.working_dir = Some(home_working_dir());
+        }
+
+        // Commands are escaped as they're parsed, revert it once we know the pane is raw
+        if pane.raw {
+            pane.commands = pane
+                .commands
+                .into_iter()
+                .map(PaneCommand::into_raw)
+                .collect();
+            pane.send_keys = pane.send_keys.into_iter().map(unprocess_command).collect();
+        }
+
         Ok(pane)
     }
 }
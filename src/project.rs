@@ -1,23 +1,71 @@
-use crate::command::de_command_list;
+use crate::command::{de_command_list, de_pane_command_list, PaneCommand};
 use crate::config::Config;
+use crate::defaults::Defaults;
+use crate::dependency::{de_dependency_list, Dependency};
 use crate::pane::Pane;
+use crate::pane_reference::PaneReference;
 use crate::pane_split::PaneSplit;
 use crate::startup_window::StartupWindow;
-use crate::utils::{is_default, parse_command, valid_tmux_identifier};
+use crate::utils::{
+    describe_value, has_key, is_default, parse_command, replace_placeholders,
+    sanitize_tmux_identifier, valid_tmux_identifier,
+};
 use crate::window::Window;
-use crate::working_dir::{de_working_dir, ser_working_dir};
+use crate::working_dir::{de_working_dir, process_working_dir, ser_working_dir};
 
 use serde::ser::{SerializeSeq, Serializer};
 use serde::{de, Deserialize, Serialize};
+use serde_yaml::Value;
 use shell_words::{join, split};
 
+use std::collections::BTreeMap;
+use std::env;
 use std::error::Error;
 use std::iter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Latest version of the project file format, bumped on breaking changes
+pub const SCHEMA_VERSION: usize = 1;
+
+// Terminal title used by set_titles when it's not given a format of its own
+pub const DEFAULT_TITLES_STRING: &str = "#S - #W";
+
+// Aliases kept for compatibility with other tools, along with the field to use instead
+pub const DEPRECATED_FIELDS: &[(&str, &str)] = &[
+    ("socket_name", "tmux_socket"),
+    ("on_project_start", "on_start"),
+    ("on_project_first_start", "on_first_start"),
+    ("on_create", "on_first_start"),
+    ("on_project_restart", "on_restart"),
+    ("on_project_exit", "on_exit"),
+    ("on_project_stop", "on_stop"),
+    ("pre_window", "pane_commands"),
+    ("tmux_attached", "attach"),
+    ("tmux_detached", "detached"),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum FileFormat {
+    Yaml,
+    Json,
+}
+
+impl FileFormat {
+    // Accepts both format names and file extensions, anything unknown is written as YAML
+    pub fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "json" => Self::Json,
+            _ => Self::Yaml,
+        }
+    }
+}
 
 #[derive(Serialize, Debug, PartialEq, Clone)]
 pub struct Project {
     pub session_name: Option<String>,
+    pub sanitize_names: bool,
+    pub per_worktree: bool,
     pub tmux_command: Option<String>,
     pub tmux_options: Option<String>,
     pub tmux_socket: Option<String>,
@@ -26,24 +74,124 @@ pub struct Project {
     pub pane_base_index: usize,
     pub startup_window: StartupWindow,
     pub startup_pane: Option<usize>,
+    pub startup_zoom: bool,
+    pub reselect_startup: bool,
+    pub mouse: Option<bool>,
+    pub history_limit: Option<usize>,
+    pub set_titles: Option<String>,
+    pub window_status_style: Option<String>,
+    pub window_status_current_style: Option<String>,
+    pub plugins: Vec<String>,
+    pub plugins_dir: Option<PathBuf>,
     pub on_start: Vec<String>,
     pub on_first_start: Vec<String>,
+    pub on_every_start: Vec<String>,
     pub on_restart: Vec<String>,
     pub on_exit: Vec<String>,
     pub on_stop: Vec<String>,
     pub post_create: Vec<String>,
+    pub on_window_create: Vec<String>,
+    pub post_window_create: Vec<String>,
     pub on_pane_create: Vec<String>,
     pub post_pane_create: Vec<String>,
-    pub pane_commands: Vec<String>,
+    pub hook_timeout: Option<u64>,
+    pub hook_login_shell: bool,
+    pub hook_log: Option<PathBuf>,
+    pub strict_hooks: bool,
+    pub pane_commands: Vec<PaneCommand>,
+    pub join_commands: Option<String>,
     pub clear_panes: bool,
+    pub reorder_windows: bool,
+    pub preserve_first_window: bool,
+    pub worktrees: bool,
+    pub expand_env: bool,
+    pub env_capture: Vec<String>,
+    pub environment: BTreeMap<String, String>,
+    pub placeholders: BTreeMap<String, String>,
+    pub notify: bool,
+    pub keep_alive: bool,
+    pub autostart: bool,
+    pub freeze_on_stop: bool,
+    pub autofreeze: bool,
+    pub depends_on: Vec<Dependency>,
     pub attach: bool,
+    pub attach_command: Option<String>,
+    pub defaults: Defaults,
     pub windows: Vec<Window>,
 }
 
 impl Project {
+    fn replace_placeholders(self) -> Self {
+        let placeholders: Vec<(&str, String)> = self
+            .placeholders
+            .iter()
+            .map(|(placeholder, value)| {
+                (placeholder.as_str(), shellexpand::tilde(value).to_string())
+            })
+            .collect();
+        let placeholders = &placeholders[..];
+        let replace_list = |commands: &[String]| -> Vec<String> {
+            commands
+                .iter()
+                .map(|command| replace_placeholders(command, placeholders))
+                .collect()
+        };
+
+        Self {
+            on_start: replace_list(&self.on_start),
+            on_first_start: replace_list(&self.on_first_start),
+            on_every_start: replace_list(&self.on_every_start),
+            on_restart: replace_list(&self.on_restart),
+            on_exit: replace_list(&self.on_exit),
+            on_stop: replace_list(&self.on_stop),
+            post_create: replace_list(&self.post_create),
+            on_window_create: replace_list(&self.on_window_create),
+            post_window_create: replace_list(&self.post_window_create),
+            on_pane_create: replace_list(&self.on_pane_create),
+            post_pane_create: replace_list(&self.post_pane_create),
+            pane_commands: self
+                .pane_commands
+                .iter()
+                .map(|command| command.replace_placeholders(placeholders))
+                .collect(),
+            windows: self
+                .windows
+                .iter()
+                .map(|window| window.replace_placeholders(placeholders))
+                .collect(),
+            ..self
+        }
+    }
+
+    pub fn deprecated_fields(project_yaml: &Value) -> Vec<(&'static str, &'static str)> {
+        DEPRECATED_FIELDS
+            .iter()
+            .filter(|(alias, _)| has_key(project_yaml, alias))
+            .copied()
+            .collect()
+    }
+
     pub fn prepare(self, config: &Config, project_name: &str, force_attach: Option<bool>) -> Self {
+        // Each git worktree gets its own session, started from the worktree's root
+        let worktree = if self.per_worktree {
+            Self::get_worktree()
+        } else {
+            None
+        };
+        let working_dir = match &worktree {
+            Some((worktree_dir, _)) => Some(worktree_dir.to_owned()),
+            None => self.working_dir,
+        };
+
+        let session_name = self.session_name.as_deref().unwrap_or(project_name);
+        let mut session_name = Self::expand_placeholders(session_name, working_dir.as_deref());
+        if let Some((_, worktree_name)) = &worktree {
+            session_name = format!("{}-{}", session_name, worktree_name);
+        }
+
         let mut project = Self {
-            session_name: self.session_name.or_else(|| Some(project_name.to_string())),
+            session_name: Some(config.get_session_name(&session_name)),
+            working_dir,
             ..self
         };
 
@@ -51,50 +199,165 @@ impl Project {
             project.attach = attach;
         }
 
+        // Directory used to look for worktrees and to resolve relative foreach_dir patterns
+        let base_dir = project
+            .working_dir
+            .to_owned()
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_default();
+
+        // Add a window for each git worktree, replacing the default window if there are no others
+        if project.worktrees {
+            let worktree_windows: Vec<Window> = Self::get_worktrees(&base_dir)
+                .into_iter()
+                .map(|(worktree_dir, worktree_name)| Window {
+                    name: Some(worktree_name),
+                    working_dir: Some(worktree_dir),
+                    ..Window::default()
+                })
+                .collect();
+
+            if !worktree_windows.is_empty() && project.windows == Self::default_windows() {
+                project.windows = worktree_windows;
+            } else {
+                project.windows.extend(worktree_windows);
+            }
+        }
+
+        // Generate panes and windows for windows that use foreach_dir
+        project.windows = project
+            .windows
+            .into_iter()
+            .flat_map(|window| window.expand_foreach(&base_dir))
+            .collect();
+
+        // Substitute the project's own placeholders into its hooks and commands
+        if !project.placeholders.is_empty() {
+            project = project.replace_placeholders();
+        }
+
+        for window in &mut project.windows {
+            if let Some(name) = &window.name {
+                let working_dir = window.working_dir.as_ref().or(project.working_dir.as_ref());
+                let expanded_name =
+                    Self::expand_placeholders(name, working_dir.map(PathBuf::as_path));
+
+                // Keep startup_window pointing at the same window
+                if let StartupWindow::Name(startup_window) = &project.startup_window {
+                    if StartupWindow::split_name(startup_window).0 == name {
+                        project.startup_window = project
+                            .startup_window
+                            .map_name(|_| expanded_name.to_owned());
+                    }
+                }
+
+                window.name = Some(expanded_name);
+            }
+        }
+
+        if project.sanitize_names {
+            project.session_name = project
+                .session_name
+                .map(|name| sanitize_tmux_identifier(&name));
+
+            project.startup_window = project.startup_window.map_name(sanitize_tmux_identifier);
+
+            for window in &mut project.windows {
+                window.name = window.name.as_deref().map(sanitize_tmux_identifier);
+            }
+        }
+
+        // Hook transcripts default to a per-session file under the config dir
+        if project.hook_log.as_deref() == Some(Path::new("")) {
+            project.hook_log = config.get_config_dir("logs").ok().map(|logs_dir| {
+                logs_dir.join(format!("{}.log", project.session_name.as_ref().unwrap()))
+            });
+        }
+
         if let Some(tmux_command) = &config.tmux_command {
             project.tmux_command = Some(tmux_command.to_owned());
         } else if project.tmux_command.is_none() {
             project.tmux_command = Some(String::from("tmux"));
         }
+        if let Some(tmux_socket) = &config.tmux_socket {
+            project.tmux_socket = Some(tmux_socket.to_owned());
+        }
+        if let Some(tmux_options) = &config.tmux_options {
+            project.tmux_options = Some(tmux_options.to_owned());
+        }
 
         project
     }
 
-    pub fn check(&self) -> Result<(), Box<dyn Error>> {
-        // Make sure session name is valid
-        if let Some(session_name) = &self.session_name {
-            valid_tmux_identifier(session_name)?;
-        }
-
-        // Make sure start up window exists
+    // Position of the startup window in the project's windows
+    pub fn startup_window_position(&self) -> Result<usize, Box<dyn Error>> {
         match &self.startup_window {
-            StartupWindow::Index(index) => {
-                if *index >= self.window_base_index + self.windows.len()
-                    || *index < self.window_base_index
-                {
-                    return Err(
-                        format!("startup_window: there is no window with index {}", index).into(),
-                    );
-                }
+            StartupWindow::Index(index)
+                if (*index >= self.window_base_index + self.windows.len()
+                    || *index < self.window_base_index) =>
+            {
+                Err(format!("startup_window: there is no window with index {}", index).into())
             }
+            StartupWindow::Index(index) => Ok(index - self.window_base_index),
             StartupWindow::Name(name) => {
-                if self
+                let (name, index) = StartupWindow::split_name(name);
+                let positions: Vec<usize> = self
                     .windows
                     .iter()
-                    .find(|window| match &window.name {
-                        Some(window_name) => window_name == name,
-                        _ => false,
-                    })
-                    .is_none()
-                {
-                    return Err(
-                        format!("startup_window: there is no window with name {:?}", name).into(),
-                    );
+                    .enumerate()
+                    .filter(|(_, window)| window.name.as_deref() == Some(name))
+                    .map(|(window_pos, _)| window_pos)
+                    .collect();
+
+                match (index, &positions[..]) {
+                    (_, []) => Err(format!(
+                        "startup_window: there is no window with name {:?}",
+                        name
+                    )
+                    .into()),
+                    (None, [window_pos]) => Ok(*window_pos),
+                    (None, _) => Err(format!(
+                        "startup_window: there are several windows named {:?}, \
+                        use \"{}:<index>\" to pick one (indexes: {})",
+                        name,
+                        name,
+                        positions
+                            .iter()
+                            .map(|window_pos| (self.window_base_index + window_pos).to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                    .into()),
+                    (Some(index), _) => positions
+                        .into_iter()
+                        .find(|window_pos| self.window_base_index + window_pos == index)
+                        .ok_or_else(|| {
+                            format!(
+                                "startup_window: there is no window named {:?} with index {}",
+                                name, index
+                            )
+                            .into()
+                        }),
                 }
             }
-            _ => {}
+            StartupWindow::Default => Ok(0),
+        }
+    }
+
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        // Generated windows may not leave anything to create
+        if self.windows.is_empty() {
+            return Err("windows: there are no windows to create".into());
+        }
+
+        // Make sure session name is valid
+        if let Some(session_name) = &self.session_name {
+            valid_tmux_identifier(session_name)?;
         }
 
+        // Make sure start up window exists
+        self.startup_window_position()?;
+
         // Make sure working_dir exists and is a directory
         if let Some(path) = &self.working_dir {
             if !path.is_dir() {
@@ -106,11 +369,42 @@ impl Project {
             }
         }
 
+        // Make sure plugins_dir exists and is a directory
+        if let Some(path) = &self.plugins_dir {
+            if !path.is_dir() {
+                return Err(format!(
+                    "project plugins_dir {:?} is not a directory or does not exist",
+                    path
+                )
+                .into());
+            }
+        }
+
+        // Plugins are looked up by directory name
+        if self
+            .plugins
+            .iter()
+            .any(|plugin| Self::plugin_name(plugin).is_empty())
+        {
+            return Err("plugins: plugin names cannot be empty".into());
+        }
+
         // Run checks for each window
         for window in &self.windows {
             window.check(self.pane_base_index)?;
         }
 
+        // tmux only keeps track of a single marked pane
+        let marked_panes = self
+            .windows
+            .iter()
+            .flat_map(|window| window.panes.iter())
+            .filter(|pane| pane.mark)
+            .count();
+        if marked_panes > 1 {
+            return Err("mark: only one pane can be marked".into());
+        }
+
         Ok(())
     }
 
@@ -140,7 +434,7 @@ impl Project {
         full_args.extend_from_slice(args);
 
         // Use utiliy to split command and append args to the split arguments
-        parse_command(&command, &full_args)
+        parse_command(command, &full_args)
     }
 
     // Sanitizes tmux_command for use in the template file
@@ -152,7 +446,7 @@ impl Project {
         let args: Vec<&str> = args.into_iter().map(AsRef::as_ref).collect();
         let (command, args) = self.tmux_command(&args)?;
 
-        Ok(join(iter::once(command).chain(args.into_iter())))
+        Ok(join(iter::once(command).chain(args)))
     }
 
     fn default_window_base_index() -> usize {
@@ -183,6 +477,140 @@ impl Project {
         *attach == Self::default_attach()
     }
 
+    // Resolves {dirname}, {git_branch}, {user} and {hostname} in session and window names
+    pub fn expand_placeholders(name: &str, working_dir: Option<&Path>) -> String {
+        if !name.contains('{') {
+            return name.to_string();
+        }
+
+        let working_dir = working_dir
+            .map(Path::to_path_buf)
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_default();
+
+        let mut name = name.to_string();
+        if name.contains("{dirname}") {
+            let dirname = working_dir
+                .file_name()
+                .map_or_else(String::new, |dirname| dirname.to_string_lossy().to_string());
+            name = name.replace("{dirname}", &dirname);
+        }
+        if name.contains("{git_branch}") {
+            let mut command = Command::new("git");
+            command
+                .arg("-C")
+                .arg(&working_dir)
+                .args(["symbolic-ref", "--short", "HEAD"]);
+            let git_branch = Self::command_output(&mut command);
+            name = name.replace("{git_branch}", &git_branch);
+        }
+        if name.contains("{user}") {
+            let user = env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .unwrap_or_default();
+            name = name.replace("{user}", &user);
+        }
+        if name.contains("{hostname}") {
+            let hostname = env::var("HOSTNAME")
+                .unwrap_or_else(|_| Self::command_output(&mut Command::new("hostname")));
+            name = name.replace("{hostname}", &hostname);
+        }
+
+        name
+    }
+
+    // Returns the root of the current git worktree along with its branch (or its directory name)
+    pub fn get_worktree() -> Option<(PathBuf, String)> {
+        let mut command = Command::new("git");
+        command.args(["rev-parse", "--show-toplevel"]);
+        let worktree_dir = Self::command_output(&mut command);
+        if worktree_dir.is_empty() {
+            return None;
+        }
+        let worktree_dir = PathBuf::from(worktree_dir);
+
+        let mut command = Command::new("git");
+        command
+            .arg("-C")
+            .arg(&worktree_dir)
+            .args(["symbolic-ref", "--short", "HEAD"]);
+        let mut worktree_name = Self::command_output(&mut command);
+        if worktree_name.is_empty() {
+            worktree_name = worktree_dir
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+        }
+
+        Some((worktree_dir, worktree_name))
+    }
+
+    // Lists the worktrees of the git repository in the given directory
+    pub fn get_worktrees(dir: &Path) -> Vec<(PathBuf, String)> {
+        let mut command = Command::new("git");
+        command
+            .arg("-C")
+            .arg(dir)
+            .args(["worktree", "list", "--porcelain"]);
+
+        Self::parse_worktree_list(&Self::command_output(&mut command))
+    }
+
+    // Worktrees are separated by empty lines, each starting with a "worktree <path>" line
+    pub fn parse_worktree_list(output: &str) -> Vec<(PathBuf, String)> {
+        output
+            .split("\n\n")
+            .filter_map(|worktree| {
+                let mut worktree_dir = None;
+                let mut branch = None;
+                for line in worktree.lines() {
+                    if let Some(path) = line.strip_prefix("worktree ") {
+                        worktree_dir = Some(PathBuf::from(path));
+                    } else if let Some(ref_name) = line.strip_prefix("branch ") {
+                        branch = Some(ref_name.trim_start_matches("refs/heads/").to_string());
+                    } else if line == "bare" {
+                        return None;
+                    }
+                }
+
+                let worktree_dir = worktree_dir?;
+                let worktree_name = branch.unwrap_or_else(|| {
+                    worktree_dir
+                        .file_name()
+                        .map_or_else(String::new, |name| name.to_string_lossy().to_string())
+                });
+
+                Some((worktree_dir, worktree_name))
+            })
+            .collect()
+    }
+
+    fn command_output(command: &mut Command) -> String {
+        command
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_default()
+    }
+
+    // Plugins can be given as their git repository (ie: tmux-plugins/tmux-logging)
+    pub fn plugin_name(plugin: &str) -> &str {
+        plugin
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+    }
+
+    fn default_expand_env() -> bool {
+        true
+    }
+
+    fn is_default_expand_env(expand_env: &bool) -> bool {
+        *expand_env == Self::default_expand_env()
+    }
+
     fn de_window_base_index<'de, D>(deserializer: D) -> Result<usize, D::Error>
     where
         D: de::Deserializer<'de>,
@@ -199,28 +627,136 @@ impl Project {
         Ok(opt.unwrap_or_else(Self::default_pane_base_index))
     }
 
-    fn de_windows<'de, D>(deserializer: D) -> Result<Vec<Window>, D::Error>
+    fn de_env_capture<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
     where
         D: de::Deserializer<'de>,
     {
-        #[derive(Deserialize, Debug)]
-        #[serde(untagged)]
-        enum WindowList {
-            Empty,
-            List(Vec<Window>),
-            Single(Window),
+        let value = Value::deserialize(deserializer)?;
+        Ok(match value {
+            Value::Null => vec![],
+            Value::String(name) => vec![name],
+            Value::Sequence(_) => Vec::<String>::deserialize(value).map_err(de::Error::custom)?,
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "expected a variable name or a list of variable names for env_capture, got {}",
+                    describe_value(&value)
+                )))
+            }
+        })
+    }
+
+    // Variable values can be written as numbers or booleans too
+    fn de_environment<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        let mapping = match value {
+            Value::Null => return Ok(BTreeMap::new()),
+            Value::Mapping(mapping) => mapping,
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "expected a map of variables for environment, got {}",
+                    describe_value(&value)
+                )))
+            }
+        };
+
+        mapping
+            .into_iter()
+            .map(|(name, value)| {
+                let name = String::deserialize(name).map_err(de::Error::custom)?;
+                let value = match value {
+                    Value::String(value) => value,
+                    Value::Number(value) => value.to_string(),
+                    Value::Bool(value) => value.to_string(),
+                    Value::Null => String::new(),
+                    _ => {
+                        return Err(de::Error::custom(format!(
+                            "environment: expected a string for {:?}, got {}",
+                            name,
+                            describe_value(&value)
+                        )))
+                    }
+                };
+
+                Ok((name, value))
+            })
+            .collect()
+    }
+
+    fn de_set_titles<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Ok(match value {
+            Value::Null | Value::Bool(false) => None,
+            Value::Bool(true) => Some(String::from(DEFAULT_TITLES_STRING)),
+            Value::String(titles_string) => Some(titles_string),
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "expected a boolean or a string for set_titles, got {}",
+                    describe_value(&value)
+                )))
+            }
+        })
+    }
+
+    // `true` is kept as an empty path until prepare() resolves it to the session's log file
+    fn de_hook_log<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Ok(match value {
+            Value::Null | Value::Bool(false) => None,
+            Value::Bool(true) => Some(PathBuf::new()),
+            Value::String(path) => Some(process_working_dir(&path).map_err(de::Error::custom)?),
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "expected a boolean or a path for hook_log, got {}",
+                    describe_value(&value)
+                )))
+            }
+        })
+    }
+
+    fn ser_hook_log<S>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match path {
+            Some(path) if path.as_os_str().is_empty() => serializer.serialize_bool(true),
+            Some(path) => serializer.serialize_str(&path.to_string_lossy()),
+            None => serializer.serialize_none(),
         }
+    }
 
-        let window_list: WindowList = de::Deserialize::deserialize(deserializer)?;
+    fn de_plugins_dir<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let opt: Option<PathBuf> = de::Deserialize::deserialize(deserializer)?;
+        opt.map(|path| process_working_dir(&path.to_string_lossy()))
+            .transpose()
+            .map_err(de::Error::custom)
+    }
 
-        Ok(match window_list {
-            WindowList::List(windows) => windows,
-            WindowList::Single(window) => vec![window],
-            WindowList::Empty => Self::default_windows(),
+    fn de_windows<'de, D>(deserializer: D) -> Result<Vec<Window>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+
+        Ok(match value {
+            Value::Null => Self::default_windows(),
+            Value::Sequence(_) => Vec::<Window>::deserialize(value).map_err(de::Error::custom)?,
+            _ => vec![Window::deserialize(value).map_err(de::Error::custom)?],
         })
     }
 
-    pub fn serialize_compact(&self, json: bool) -> Result<String, Box<dyn Error>> {
+    pub fn serialize_compact(&self, format: &FileFormat) -> Result<String, Box<dyn Error>> {
         fn is_default_windows(windows: &[CompactWindow]) -> bool {
             Project::default_windows()
                 .into_iter()
@@ -240,6 +776,10 @@ impl Project {
             #[serde(skip_serializing_if = "is_default")]
             session_name: Option<String>,
             #[serde(skip_serializing_if = "is_default")]
+            sanitize_names: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            per_worktree: bool,
+            #[serde(skip_serializing_if = "is_default")]
             tmux_command: Option<String>,
             #[serde(skip_serializing_if = "is_default")]
             tmux_options: Option<String>,
@@ -256,10 +796,30 @@ impl Project {
             #[serde(skip_serializing_if = "is_default")]
             startup_pane: Option<usize>,
             #[serde(skip_serializing_if = "is_default")]
+            startup_zoom: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            reselect_startup: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            mouse: Option<bool>,
+            #[serde(skip_serializing_if = "is_default")]
+            history_limit: Option<usize>,
+            #[serde(skip_serializing_if = "is_default")]
+            set_titles: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            window_status_style: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            window_status_current_style: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            plugins: Vec<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            plugins_dir: Option<PathBuf>,
+            #[serde(skip_serializing_if = "is_default")]
             on_start: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
             on_first_start: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
+            on_every_start: Vec<String>,
+            #[serde(skip_serializing_if = "is_default")]
             on_restart: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
             on_exit: Vec<String>,
@@ -268,15 +828,62 @@ impl Project {
             #[serde(skip_serializing_if = "is_default")]
             post_create: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
+            on_window_create: Vec<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            post_window_create: Vec<String>,
+            #[serde(skip_serializing_if = "is_default")]
             on_pane_create: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
             post_pane_create: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
-            pane_commands: Vec<String>,
+            hook_timeout: Option<u64>,
+            #[serde(skip_serializing_if = "is_default")]
+            hook_login_shell: bool,
+            #[serde(
+                skip_serializing_if = "is_default",
+                serialize_with = "Project::ser_hook_log"
+            )]
+            hook_log: Option<PathBuf>,
+            #[serde(skip_serializing_if = "is_default")]
+            strict_hooks: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            pane_commands: Vec<PaneCommand>,
+            #[serde(skip_serializing_if = "is_default")]
+            join_commands: Option<String>,
             #[serde(skip_serializing_if = "is_default")]
             clear_panes: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            reorder_windows: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            preserve_first_window: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            worktrees: bool,
+            #[serde(skip_serializing_if = "Project::is_default_expand_env")]
+            expand_env: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            env_capture: Vec<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            environment: BTreeMap<String, String>,
+            #[serde(skip_serializing_if = "is_default")]
+            placeholders: BTreeMap<String, String>,
+            #[serde(skip_serializing_if = "is_default")]
+            notify: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            keep_alive: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            autostart: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            freeze_on_stop: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            autofreeze: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            depends_on: Vec<Dependency>,
             #[serde(skip_serializing_if = "Project::is_default_attach")]
             attach: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            attach_command: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            defaults: Defaults,
             #[serde(skip_serializing_if = "is_default_windows")]
             windows: Vec<CompactWindow>,
         }
@@ -285,6 +892,8 @@ impl Project {
             fn from(copy: Project) -> Self {
                 Self {
                     session_name: copy.session_name,
+                    sanitize_names: copy.sanitize_names,
+                    per_worktree: copy.per_worktree,
                     tmux_command: copy.tmux_command,
                     tmux_options: copy.tmux_options,
                     tmux_socket: copy.tmux_socket,
@@ -293,17 +902,49 @@ impl Project {
                     pane_base_index: copy.pane_base_index,
                     startup_window: copy.startup_window,
                     startup_pane: copy.startup_pane,
+                    startup_zoom: copy.startup_zoom,
+                    reselect_startup: copy.reselect_startup,
+                    mouse: copy.mouse,
+                    history_limit: copy.history_limit,
+                    set_titles: copy.set_titles,
+                    window_status_style: copy.window_status_style,
+                    window_status_current_style: copy.window_status_current_style,
+                    plugins: copy.plugins,
+                    plugins_dir: copy.plugins_dir,
                     on_start: copy.on_start,
                     on_first_start: copy.on_first_start,
+                    on_every_start: copy.on_every_start,
                     on_restart: copy.on_restart,
                     on_exit: copy.on_exit,
                     on_stop: copy.on_stop,
                     post_create: copy.post_create,
+                    on_window_create: copy.on_window_create,
+                    post_window_create: copy.post_window_create,
                     on_pane_create: copy.on_pane_create,
                     post_pane_create: copy.post_pane_create,
+                    hook_timeout: copy.hook_timeout,
+                    hook_login_shell: copy.hook_login_shell,
+                    hook_log: copy.hook_log,
+                    strict_hooks: copy.strict_hooks,
                     pane_commands: copy.pane_commands,
+                    join_commands: copy.join_commands,
                     clear_panes: copy.clear_panes,
+                    reorder_windows: copy.reorder_windows,
+                    preserve_first_window: copy.preserve_first_window,
+                    worktrees: copy.worktrees,
+                    expand_env: copy.expand_env,
+                    env_capture: copy.env_capture,
+                    environment: copy.environment,
+                    placeholders: copy.placeholders,
+                    notify: copy.notify,
+                    keep_alive: copy.keep_alive,
+                    autostart: copy.autostart,
+                    freeze_on_stop: copy.freeze_on_stop,
+                    autofreeze: copy.autofreeze,
+                    depends_on: copy.depends_on,
                     attach: copy.attach,
+                    attach_command: copy.attach_command,
+                    defaults: copy.defaults,
                     windows: copy.windows.into_iter().map(CompactWindow::from).collect(),
                 }
             }
@@ -317,6 +958,16 @@ impl Project {
             #[serde(skip_serializing_if = "is_default")]
             layout: Option<String>,
             #[serde(skip_serializing_if = "is_default")]
+            main_pane_width: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            main_pane_height: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            pane_border_status: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            pane_border_format: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            shell: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
             on_create: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
             post_create: Vec<String>,
@@ -325,9 +976,21 @@ impl Project {
             #[serde(skip_serializing_if = "is_default")]
             post_pane_create: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
-            pane_commands: Vec<String>,
+            pane_commands: Vec<PaneCommand>,
             #[serde(skip_serializing_if = "is_default")]
             clear_panes: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            even_out: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            startup_pane: Option<usize>,
+            #[serde(skip_serializing_if = "is_default")]
+            foreach_dir: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            foreach: Vec<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            foreach_file: Option<PathBuf>,
+            #[serde(skip_serializing_if = "is_default")]
+            foreach_windows: bool,
             #[serde(skip_serializing_if = "is_default_panes", serialize_with = "ser_panes")]
             panes: Vec<CompactPane>,
         }
@@ -338,12 +1001,23 @@ impl Project {
                     name: copy.name,
                     working_dir: copy.working_dir,
                     layout: copy.layout,
+                    main_pane_width: copy.main_pane_width,
+                    main_pane_height: copy.main_pane_height,
+                    pane_border_status: copy.pane_border_status,
+                    pane_border_format: copy.pane_border_format,
+                    shell: copy.shell,
                     on_create: copy.on_create,
                     post_create: copy.post_create,
                     on_pane_create: copy.on_pane_create,
                     post_pane_create: copy.post_pane_create,
                     pane_commands: copy.pane_commands,
                     clear_panes: copy.clear_panes,
+                    even_out: copy.even_out,
+                    startup_pane: copy.startup_pane,
+                    foreach_dir: copy.foreach_dir,
+                    foreach: copy.foreach,
+                    foreach_file: copy.foreach_file,
+                    foreach_windows: copy.foreach_windows,
                     panes: copy.panes.into_iter().map(CompactPane::from).collect(),
                 }
             }
@@ -358,19 +1032,33 @@ impl Project {
             #[serde(skip_serializing_if = "is_default")]
             split: Option<PaneSplit>,
             #[serde(skip_serializing_if = "is_default")]
-            split_from: Option<usize>,
+            split_from: Option<PaneReference>,
             #[serde(skip_serializing_if = "is_default")]
             split_size: Option<String>,
             #[serde(skip_serializing_if = "is_default")]
             clear: bool,
             #[serde(skip_serializing_if = "is_default")]
+            zoom: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            mark: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            style: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            shell: Option<String>,
+            #[serde(skip_serializing_if = "is_default")]
             on_create: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
             post_create: Vec<String>,
             #[serde(skip_serializing_if = "is_default")]
-            commands: Vec<String>,
+            commands: Vec<PaneCommand>,
+            #[serde(skip_serializing_if = "is_default")]
+            join_commands: Option<String>,
             #[serde(skip_serializing_if = "is_default")]
             send_keys: Vec<String>,
+            #[serde(skip_serializing_if = "is_default")]
+            raw: bool,
+            #[serde(skip_serializing_if = "is_default")]
+            exec: bool,
         }
 
         impl From<Pane> for CompactPane {
@@ -382,10 +1070,17 @@ impl Project {
                     split_from: copy.split_from,
                     split_size: copy.split_size,
                     clear: copy.clear,
+                    zoom: copy.zoom,
+                    mark: copy.mark,
+                    style: copy.style,
+                    shell: copy.shell,
                     on_create: copy.on_create,
                     post_create: copy.post_create,
                     commands: copy.commands,
+                    join_commands: copy.join_commands,
                     send_keys: copy.send_keys,
+                    raw: copy.raw,
+                    exec: copy.exec,
                 }
             }
         }
@@ -397,15 +1092,23 @@ impl Project {
             let mut seq = serializer.serialize_seq(Some(panes.len()))?;
             for pane in panes {
                 if pane.commands.len() <= 1
+                    && pane.commands.iter().all(PaneCommand::is_simple)
                     && is_default(&pane.name)
                     && is_default(&pane.working_dir)
                     && is_default(&pane.split)
                     && is_default(&pane.split_from)
                     && is_default(&pane.split_size)
                     && is_default(&pane.clear)
+                    && is_default(&pane.zoom)
+                    && is_default(&pane.mark)
+                    && is_default(&pane.style)
+                    && is_default(&pane.shell)
                     && is_default(&pane.on_create)
                     && is_default(&pane.post_create)
+                    && is_default(&pane.join_commands)
                     && is_default(&pane.send_keys)
+                    && is_default(&pane.raw)
+                    && is_default(&pane.exec)
                 {
                     if pane.commands.is_empty() {
                         seq.serialize_element(&None as &Option<&str>)?;
@@ -421,18 +1124,37 @@ impl Project {
 
         let project = CompactProject::from(self.to_owned());
 
-        Ok(if json {
-            serde_json::to_string_pretty(&project)?
-        } else {
-            serde_yaml::to_string(&project)?
+        Ok(match format {
+            FileFormat::Yaml => serde_yaml::to_string(&project)?,
+            FileFormat::Json => serde_json::to_string_pretty(&project)?,
         })
     }
+
+    // Parses the output of serialize_compact back, returns the fields that didn't survive the trip
+    pub fn compact_round_trip_diff(&self, content: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let parsed = serde_yaml::from_str::<Project>(content)?;
+        let expected = serde_yaml::to_value(self)?;
+        let actual = serde_yaml::to_value(&parsed)?;
+
+        let (expected, actual) = match (expected.as_mapping(), actual.as_mapping()) {
+            (Some(expected), Some(actual)) => (expected, actual),
+            _ => return Ok(vec![]),
+        };
+
+        Ok(expected
+            .iter()
+            .filter(|(field, value)| actual.get(field) != Some(value))
+            .filter_map(|(field, _)| field.as_str().map(String::from))
+            .collect())
+    }
 }
 
 impl Default for Project {
     fn default() -> Self {
         Self {
             session_name: None,
+            sanitize_names: false,
+            per_worktree: false,
             tmux_command: None,
             tmux_options: None,
             tmux_socket: None,
@@ -441,17 +1163,49 @@ impl Default for Project {
             pane_base_index: Self::default_pane_base_index(),
             startup_window: StartupWindow::default(),
             startup_pane: None,
+            startup_zoom: false,
+            reselect_startup: false,
+            mouse: None,
+            history_limit: None,
+            set_titles: None,
+            window_status_style: None,
+            window_status_current_style: None,
+            plugins: vec![],
+            plugins_dir: None,
             on_start: vec![],
             on_first_start: vec![],
+            on_every_start: vec![],
             on_restart: vec![],
             on_exit: vec![],
             on_stop: vec![],
             post_create: vec![],
+            on_window_create: vec![],
+            post_window_create: vec![],
             on_pane_create: vec![],
             post_pane_create: vec![],
+            hook_timeout: None,
+            hook_login_shell: false,
+            hook_log: None,
+            strict_hooks: false,
             pane_commands: vec![],
+            join_commands: None,
             clear_panes: false,
+            reorder_windows: false,
+            preserve_first_window: false,
+            worktrees: false,
+            expand_env: Self::default_expand_env(),
+            env_capture: vec![],
+            environment: BTreeMap::new(),
+            placeholders: BTreeMap::new(),
+            notify: false,
+            keep_alive: false,
+            autostart: false,
+            freeze_on_stop: false,
+            autofreeze: false,
+            depends_on: vec![],
             attach: true,
+            attach_command: None,
+            defaults: Defaults::default(),
             windows: Self::default_windows(),
         }
     }
@@ -471,9 +1225,15 @@ impl<'de> Deserialize<'de> for Project {
         #[derive(Deserialize, Debug)]
         #[serde(deny_unknown_fields)]
         struct ProjectProxy {
+            #[serde(default)]
+            version: Option<usize>,
             #[serde(default, alias = "name")]
             session_name: Option<String>,
             #[serde(default)]
+            sanitize_names: bool,
+            #[serde(default)]
+            per_worktree: bool,
+            #[serde(default)]
             tmux_command: Option<String>,
             #[serde(default)]
             tmux_options: Option<String>,
@@ -495,6 +1255,24 @@ impl<'de> Deserialize<'de> for Project {
             startup_window: StartupWindow,
             #[serde(default)]
             startup_pane: Option<usize>,
+            #[serde(default)]
+            startup_zoom: bool,
+            #[serde(default)]
+            reselect_startup: bool,
+            #[serde(default)]
+            mouse: Option<bool>,
+            #[serde(default)]
+            history_limit: Option<usize>,
+            #[serde(default, deserialize_with = "Project::de_set_titles")]
+            set_titles: Option<String>,
+            #[serde(default)]
+            window_status_style: Option<String>,
+            #[serde(default)]
+            window_status_current_style: Option<String>,
+            #[serde(default, alias = "plugin", deserialize_with = "de_command_list")]
+            plugins: Vec<String>,
+            #[serde(default, deserialize_with = "Project::de_plugins_dir")]
+            plugins_dir: Option<PathBuf>,
             #[serde(
                 default,
                 alias = "on_project_start",
@@ -508,6 +1286,8 @@ impl<'de> Deserialize<'de> for Project {
                 deserialize_with = "de_command_list"
             )]
             on_first_start: Vec<String>,
+            #[serde(default, deserialize_with = "de_command_list")]
+            on_every_start: Vec<String>,
             #[serde(
                 default,
                 alias = "on_project_restart",
@@ -529,22 +1309,66 @@ impl<'de> Deserialize<'de> for Project {
             #[serde(default, deserialize_with = "de_command_list")]
             post_create: Vec<String>,
             #[serde(default, deserialize_with = "de_command_list")]
+            on_window_create: Vec<String>,
+            #[serde(default, deserialize_with = "de_command_list")]
+            post_window_create: Vec<String>,
+            #[serde(default, deserialize_with = "de_command_list")]
             on_pane_create: Vec<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             post_pane_create: Vec<String>,
+            #[serde(default)]
+            hook_timeout: Option<u64>,
+            #[serde(default)]
+            hook_login_shell: bool,
+            #[serde(default, deserialize_with = "Project::de_hook_log")]
+            hook_log: Option<PathBuf>,
+            #[serde(default)]
+            strict_hooks: bool,
             #[serde(
                 default,
                 alias = "pre_window",
                 alias = "pane_command",
-                deserialize_with = "de_command_list"
+                deserialize_with = "de_pane_command_list"
             )]
-            pane_commands: Vec<String>,
+            pane_commands: Vec<PaneCommand>,
+            #[serde(default)]
+            join_commands: Option<String>,
             #[serde(default)]
             clear_panes: bool,
+            #[serde(default)]
+            reorder_windows: bool,
+            #[serde(default)]
+            preserve_first_window: bool,
+            #[serde(default)]
+            worktrees: bool,
+            #[serde(default = "Project::default_expand_env")]
+            expand_env: bool,
+            #[serde(default, deserialize_with = "Project::de_env_capture")]
+            env_capture: Vec<String>,
+            #[serde(default, alias = "env", deserialize_with = "Project::de_environment")]
+            environment: BTreeMap<String, String>,
+            #[serde(default)]
+            placeholders: BTreeMap<String, String>,
+            #[serde(default)]
+            notify: bool,
+            #[serde(default)]
+            keep_alive: bool,
+            #[serde(default)]
+            autostart: bool,
+            #[serde(default)]
+            freeze_on_stop: bool,
+            #[serde(default)]
+            autofreeze: bool,
+            #[serde(default, deserialize_with = "de_dependency_list")]
+            depends_on: Vec<Dependency>,
             #[serde(default, alias = "tmux_attached")]
             attach: Option<bool>,
             #[serde(default, alias = "tmux_detached")]
             detached: Option<bool>,
+            #[serde(default)]
+            attach_command: Option<String>,
+            #[serde(default)]
+            defaults: Defaults,
             #[serde(
                 default = "Project::default_windows",
                 alias = "window",
@@ -558,6 +1382,15 @@ impl<'de> Deserialize<'de> for Project {
         Ok(match opt {
             None => Self::default(),
             Some(project) => {
+                if let Some(version) = project.version {
+                    if version > SCHEMA_VERSION {
+                        return Err(de::Error::custom(format!(
+                            "project file version {} is not supported (latest: {})",
+                            version, SCHEMA_VERSION
+                        )));
+                    }
+                }
+
                 let attach = match project.attach {
                     Some(attach) => match project.detached {
                         None => attach,
@@ -575,6 +1408,8 @@ impl<'de> Deserialize<'de> for Project {
 
                 Self {
                     session_name: project.session_name,
+                    sanitize_names: project.sanitize_names,
+                    per_worktree: project.per_worktree,
                     tmux_command: project.tmux_command,
                     tmux_options: project.tmux_options,
                     tmux_socket: project.tmux_socket,
@@ -583,17 +1418,49 @@ impl<'de> Deserialize<'de> for Project {
                     pane_base_index: project.pane_base_index,
                     startup_window: project.startup_window,
                     startup_pane: project.startup_pane,
+                    startup_zoom: project.startup_zoom,
+                    reselect_startup: project.reselect_startup,
+                    mouse: project.mouse,
+                    history_limit: project.history_limit,
+                    set_titles: project.set_titles,
+                    window_status_style: project.window_status_style,
+                    window_status_current_style: project.window_status_current_style,
+                    plugins: project.plugins,
+                    plugins_dir: project.plugins_dir,
                     on_start: project.on_start,
                     on_first_start: project.on_first_start,
+                    on_every_start: project.on_every_start,
                     on_restart: project.on_restart,
                     on_exit: project.on_exit,
                     on_stop: project.on_stop,
                     post_create: project.post_create,
+                    on_window_create: project.on_window_create,
+                    post_window_create: project.post_window_create,
                     on_pane_create: project.on_pane_create,
                     post_pane_create: project.post_pane_create,
+                    hook_timeout: project.hook_timeout,
+                    hook_login_shell: project.hook_login_shell,
+                    hook_log: project.hook_log,
+                    strict_hooks: project.strict_hooks,
                     pane_commands: project.pane_commands,
+                    join_commands: project.join_commands,
                     clear_panes: project.clear_panes,
+                    reorder_windows: project.reorder_windows,
+                    preserve_first_window: project.preserve_first_window,
+                    worktrees: project.worktrees,
+                    expand_env: project.expand_env,
+                    env_capture: project.env_capture,
+                    environment: project.environment,
+                    placeholders: project.placeholders,
+                    notify: project.notify,
+                    keep_alive: project.keep_alive,
+                    autostart: project.autostart,
+                    freeze_on_stop: project.freeze_on_stop,
+                    autofreeze: project.autofreeze,
+                    depends_on: project.depends_on,
                     attach,
+                    attach_command: project.attach_command,
+                    defaults: project.defaults,
                     windows: project.windows,
                 }
             }
@@ -603,4 +1470,5 @@ impl<'de> Deserialize<'de> for Project {
 
 #[cfg(test)]
 #[path = "test/project.rs"]
+#[allow(clippy::bool_assert_comparison)]
 mod tests;
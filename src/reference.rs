@@ -0,0 +1,435 @@
+// Docs of the fields of project files, shown by the language server and `freeze --with-comments`
+
+pub const PROJECT_FIELDS: &[(&str, &str)] = &[
+    (
+        "version",
+        "Version of the project file format. Files without it are considered older than version 1\n\
+        `airmux migrate` upgrades older files, renaming deprecated fields and setting this field",
+    ),
+    (
+        "session_name",
+        "Name of the session in tmux. Cannot contain a dot (.) or colon (:) (alias: name)\n\
+        Can use the {dirname}, {git_branch}, {user} and {hostname} placeholders (ie: \"{dirname}-{git_branch}\")\n\
+        {dirname} and {git_branch} are read from the project's working_dir",
+    ),
+    (
+        "sanitize_names",
+        "Replace dots (.) and colons (:) in the session and window names with underscores instead of failing\n\
+        Useful for projects named after versioned directories (ie: app-2.0 becomes app-2_0)",
+    ),
+    (
+        "per_worktree",
+        "Start a separate session for each git worktree of the project, from the worktree's root directory\n\
+        The session name is suffixed with the worktree's branch (ie: my_project-feature)",
+    ),
+    (
+        "tmux_command",
+        "Tmux command to use. Can also be overritten by using `--command` when running airmux",
+    ),
+    ("tmux_options", "Flags and options to pass to tmux every time it's executed"),
+    (
+        "tmux_socket",
+        "Socket name to pass to tmux (alias: socket_name)\n\
+        Equivalent to adding `-L <socket_name>` to `tmux_options`",
+    ),
+    (
+        "working_dir",
+        "Working directory for all the windows in this session (alias: root)\n\
+        If declared and left empty or set to ~ (null in Json), defaults to $HOME instead.",
+    ),
+    ("window_base_index", "The starting index for windows (should be a non-negative integer)"),
+    ("pane_base_index", "The starting index for panes (should be a non-negative integer)"),
+    (
+        "startup_window",
+        "Name of index window that's selected on startup\n\
+        When several windows share a name, pick one with `name:index` (e.g. `editor:3`)",
+    ),
+    ("startup_pane", "Index of pane that's selected on startup"),
+    ("startup_zoom", "Whether or not to zoom the startup pane when the session is created"),
+    (
+        "reselect_startup",
+        "Whether or not to select the startup window and pane each time the project is started,\n\
+        rather than only when the session is created (the pane is only selected if startup_pane is set)",
+    ),
+    (
+        "mouse",
+        "Whether or not to enable mouse support for this session (leaves the tmux option untouched if omitted)",
+    ),
+    (
+        "history_limit",
+        "Number of lines of scrollback kept in each pane of this session (leaves the tmux option untouched if omitted)",
+    ),
+    (
+        "set_titles",
+        "Whether or not to set the terminal title while attached to this session\n\
+        Can be a tmux format string to use instead of the default one (\"#S - #W\")",
+    ),
+    (
+        "window_status_style",
+        "Styles of the project's windows in the status bar, for the other windows and the current one",
+    ),
+    (
+        "window_status_current_style",
+        "Styles of the project's windows in the status bar, for the other windows and the current one",
+    ),
+    (
+        "plugins",
+        "tmux plugins to load when the session is created (alias: plugin)\n\
+        Plugins are looked up by name in plugins_dir, so both tmux-logging and tmux-plugins/tmux-logging work",
+    ),
+    (
+        "plugins_dir",
+        "Directory where the plugins are installed\n\
+        Defaults to tpm's $TMUX_PLUGIN_MANAGER_PATH, or ~/.tmux/plugins",
+    ),
+    (
+        "on_start",
+        "Shell commands to execute before the session is attached (alias: on_project_start)\n\
+        Available substitutions: __TMUX__, __SESSION__",
+    ),
+    (
+        "on_first_start",
+        "Shell commands to execute before the session is attached the first time (alias: on_project_first_start, on_create)\n\
+        Available substitutions: __TMUX__, __SESSION__",
+    ),
+    (
+        "on_every_start",
+        "Shell commands to execute each time the project is started, once the session exists (before its windows are created)\n\
+        Available substitutions: __TMUX__, __SESSION__",
+    ),
+    (
+        "on_restart",
+        "Shell commands to execute before the session is re-attached (alias: on_project_restart)\n\
+        Available substitutions: __TMUX__, __SESSION__",
+    ),
+    (
+        "on_exit",
+        "Shell commands to execute after the session is detached (alias: on_project_exit)\n\
+        Available substitutions: __TMUX__",
+    ),
+    (
+        "on_stop",
+        "Shell commands to execute after the session is destroyed (alias: on_project_stop)\n\
+        Available substitutions: __TMUX__",
+    ),
+    (
+        "post_create",
+        "Shell commands to execute after the session (with all its content) is created\n\
+        Available substitutions: __TMUX__, __SESSION__",
+    ),
+    (
+        "on_window_create",
+        "Shell commands to execute when a window is created (before its panes are created)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__",
+    ),
+    (
+        "post_window_create",
+        "Shell commands to execute after a window is created (with all its panes)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__",
+    ),
+    (
+        "on_pane_create",
+        "Shell commands to execute when a pane is created (before pane_commands are typed in)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__,\n\
+        __PANE__, __PANE_INDEX__",
+    ),
+    (
+        "post_pane_create",
+        "Shell commands to execute after a pane is created (after pane_commands are typed in)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__,\n\
+        __PANE__, __PANE_INDEX__",
+    ),
+    (
+        "hook_timeout",
+        "Seconds after which a startup hook (on_start, on_first_start, on_every_start, on_restart, post_create,\n\
+        and window and pane on_create/post_create hooks) is killed and reported",
+    ),
+    (
+        "hook_login_shell",
+        "Whether or not to run hooks through the user's login shell ($SHELL -lc) instead of /bin/sh,\n\
+        so they see the same PATH as interactive shells (rbenv, nvm, ...)",
+    ),
+    (
+        "hook_log",
+        "Whether or not to log every hook that's executed (timestamp, hook, exit status and expanded command)\n\
+        Logs to <config dir>/logs/<session name>.log when set to true, or to the given file when set to a path",
+    ),
+    (
+        "strict_hooks",
+        "Whether or not to abort startup when an on_start or on_first_start hook exits with a non-zero status\n\
+        The rest of the session is not created, a freshly created session is killed, and the failed hook is reported",
+    ),
+    (
+        "pane_commands",
+        "Commands that are executed in the shell of each pane (alias: pre_window, pane_command)",
+    ),
+    (
+        "join_commands",
+        "Separator used to type all of a pane's commands as a single line (ie: \"&&\")\n\
+        Sleep and expect steps split the line. Defaults to typing each command separately.",
+    ),
+    (
+        "clear_panes",
+        "Whether or not to clear (send ctrl+L) all of the underlying panes after creation",
+    ),
+    (
+        "expand_env",
+        "Whether or not to expand environment variables and parameters in the project file",
+    ),
+    (
+        "environment",
+        "Environment variables set in the session before its windows are created (alias: env)",
+    ),
+    (
+        "env_capture",
+        "Environment variables whose values `airmux freeze` records into `environment`\n\
+        Read from the current shell when freezing the current session, from the session's environment otherwise",
+    ),
+    (
+        "placeholders",
+        "Custom placeholders substituted in all hooks, pane commands and window names (ie: {__API_DIR__: ~/code/api})",
+    ),
+    (
+        "reorder_windows",
+        "Whether or not to move named windows back to their declared index when the session already exists",
+    ),
+    (
+        "preserve_first_window",
+        "Whether or not to reuse the window tmux creates with the session as the first window\n\
+        By default, that window is moved out of the way and killed once the project's windows are created",
+    ),
+    (
+        "worktrees",
+        "Whether or not to add a window for each git worktree of the project, named after its branch\n\
+        Replaces the default window when no other windows are defined",
+    ),
+    (
+        "notify",
+        "Whether or not to send desktop notifications when the session is ready, when an expect step times out\n\
+        and when on_exit commands run (uses notify-send, or osascript on macOS)",
+    ),
+    (
+        "keep_alive",
+        "Whether or not `airmux daemon` should keep this project's session running, restarting it when it dies",
+    ),
+    ("autostart", "Whether or not `airmux boot` should start this project"),
+    (
+        "freeze_on_stop",
+        "Whether or not `airmux kill` should save the layout of the session into the project file before killing it\n\
+        The captured windows, panes and layouts are merged into the project file, its commands and hooks are kept",
+    ),
+    (
+        "autofreeze",
+        "Whether or not `airmux autofreeze` should keep the project file in sync with the project's running session",
+    ),
+    (
+        "depends_on",
+        "Projects to start (detached) before this one, unless their session is already running\n\
+        Dependencies of dependencies are started first, and cycles are reported as errors\n\
+        Each dependency can also wait for a window to exist and/or a health command (ran from the dependency's\n\
+        working_dir) to pass, for up to `timeout` seconds (defaults to 30), before going on:\n\
+        depends_on:\n\
+        - project: infra\n\
+        window: db\n\
+        ready: pg_isready -h localhost\n\
+        timeout: 60",
+    ),
+    ("attach", "Whether or not the session automatically attaches on creation (can't use both)"),
+    (
+        "detached",
+        "Whether or not the session automatically attaches on creation (can't use both)",
+    ),
+    (
+        "attach_command",
+        "Shell command that attaches the session, instead of the built-in attach-session or switch-client\n\
+        __TMUX__ is replaced by the tmux command and __SESSION__ by the session name\n\
+        e.g. `__TMUX__ -CC attach -t __SESSION__` for iTerm2's tmux integration",
+    ),
+    (
+        "defaults",
+        "Flags `airmux start` always uses for this project, on top of the ones it's given\n\
+        Supports no_attach, verbose, switch, prune, refresh, no_input and check_splits,\n\
+        and args, used when no argument is passed on the command line\n\
+        --attach still attaches a project that sets `no_attach: true`\n\
+        e.g. `{no_attach: true, args: [staging]}`",
+    ),
+    ("windows", "Window declarations (alias: window)"),
+];
+
+pub const WINDOW_FIELDS: &[(&str, &str)] = &[
+    (
+        "working_dir",
+        "Window names should not contain dots (.) and colons (:)\n\
+        You can have multiple windows with the same name\n\
+        It can also have a null (~) name, in which case it'll use default tmux behavior\n\
+        Working directory for the window (alias: root)\n\
+        If declared and left empty or set to ~ (null in Json), defaults to $HOME instead.",
+    ),
+    (
+        "inherit_working_dir",
+        "Set to false to start the window in $HOME instead of the project's working directory\n\
+        Ignored when the window sets its own working_dir",
+    ),
+    (
+        "layout",
+        "Layout, can be: even-horizontal, even-vertical, main-horizontal, main-vertical, tiled\n\
+        Or a custom layout, see `Layouts` section for details. Can't use with custom pane splits.",
+    ),
+    (
+        "main_pane_width",
+        "Size of the main pane in the main-vertical and main-horizontal layouts\n\
+        Can be a number of cells or a percentage (ie: 60%)",
+    ),
+    (
+        "main_pane_height",
+        "Size of the main pane in the main-vertical and main-horizontal layouts\n\
+        Can be a number of cells or a percentage (ie: 60%)",
+    ),
+    (
+        "pane_border_status",
+        "Where to show pane border labels (off, top or bottom) and what they display\n\
+        Pane names are shown as labels by the default tmux format, ie: \"#{pane_index} #{pane_title}\"",
+    ),
+    (
+        "pane_border_format",
+        "Where to show pane border labels (off, top or bottom) and what they display\n\
+        Pane names are shown as labels by the default tmux format, ie: \"#{pane_index} #{pane_title}\"",
+    ),
+    (
+        "shell",
+        "Shell the window's panes start with before their commands are typed in (ie: fish)\n\
+        As the first key of a window without a name, `shell: command` names the window \"shell\" instead",
+    ),
+    (
+        "even_out",
+        "Whether or not to resize panes evenly once they're all created (can't use with layout)\n\
+        Uses even-horizontal or even-vertical when all panes are split in the same direction, tiled otherwise",
+    ),
+    (
+        "startup_pane",
+        "Index of the window's pane that's selected when it's created (starts at pane_base_index)\n\
+        The project-wide startup_pane still applies to the startup window when set",
+    ),
+    (
+        "foreach_dir",
+        "Repeat the window's panes for each directory matching the pattern (relative to the working directory)\n\
+        Supports * and ? wildcards. {dir} and {basename} are replaced by the directory's path and name\n\
+        in the names and commands of the window and its panes, which start in that directory",
+    ),
+    (
+        "foreach",
+        "Repeat the window's panes for each item of a list instead, replacing {item} in names and commands",
+    ),
+    (
+        "foreach_file",
+        "Same as foreach, but reads the items from a file (one per line, relative to the working directory)\n\
+        Empty lines and lines starting with # are skipped",
+    ),
+    (
+        "foreach_windows",
+        "Whether or not foreach_dir, foreach and foreach_file create a window per item instead of a pane\n\
+        Generated windows are named after their item unless the window has a name",
+    ),
+    (
+        "on_create",
+        "Shell commands to execute when a window is created (before child pane panes are configured)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__",
+    ),
+    (
+        "post_create",
+        "Shell commands to execute after a window is created (after all child panes are configured)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__",
+    ),
+    (
+        "on_pane_create",
+        "Shell commands to execute when a pane is created (before pane_commands are typed in)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__,\n\
+        __PANE__, __PANE_INDEX__",
+    ),
+    (
+        "post_pane_create",
+        "Shell commands to execute after a pane is created (after pane_commands are typed in)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__,\n\
+        __PANE__, __PANE_INDEX__",
+    ),
+    (
+        "pane_commands",
+        "Commands that are executed in the shell of each pane (alias: pre, pane_command)",
+    ),
+    (
+        "clear_panes",
+        "Whether or not to clear (send ctrl+L) all of the underlying panes after creation",
+    ),
+    ("panes", "List of panes"),
+];
+
+pub const PANE_FIELDS: &[(&str, &str)] = &[
+    (
+        "working_dir",
+        "Working directory for the pane (alias: root)\n\
+        If declared and left empty or set to ~ (null in Json), defaults to $HOME instead.",
+    ),
+    (
+        "inherit_working_dir",
+        "Set to false to start the pane in $HOME instead of the window's or project's working directory\n\
+        Ignored when the pane sets its own working_dir",
+    ),
+    (
+        "split_from",
+        "The pane to split from when creating this one. Does not apply to the first pane.\n\
+        These indexes follow the same order as in the project file and always start with pane_base_index\n\
+        Can also be the name of a pane declared before this one, which keeps working when panes are reordered",
+    ),
+    ("split", "How to split when creating this pane (v, vertical, h, horizontal)"),
+    ("split_size", "Size of this pane (number of columns/rows or a percentage)"),
+    (
+        "clear",
+        "Whether or not to send a clear combination (Ctrl+L) after typing the pane commands",
+    ),
+    (
+        "zoom",
+        "Whether or not to zoom the pane once its window is created (only one pane per window)\n\
+        Zooming a pane makes it the active pane of its window",
+    ),
+    (
+        "mark",
+        "Whether or not to mark the pane (select-pane -m), so tmux commands can target it with `{marked}`\n\
+        Only one pane can be marked",
+    ),
+    (
+        "style",
+        "Style of the pane (select-pane -P), for instance to tell production panes apart (ie: bg=colour52)",
+    ),
+    (
+        "shell",
+        "Shell the pane starts with, overrides the window's shell\n\
+        As the first key of a pane without a name or commands, `shell: command` names the pane \"shell\" instead",
+    ),
+    (
+        "on_create",
+        "Shell commands to execute when a pane is created (before pane_commands are typed in)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__,\n\
+        __PANE__, __PANE_INDEX__",
+    ),
+    (
+        "post_create",
+        "Shell commands to execute after a pane is created (before pane_commands are typed in)\n\
+        Available substitutions: __TMUX__, __SESSION__, __WINDOW__, __WINDOW_INDEX__, __WINDOW_NAME__,\n\
+        __PANE__, __PANE_INDEX__",
+    ),
+    ("commands", "Commands to type and run in this pane's shell (alias: command)"),
+    (
+        "join_commands",
+        "Separator used to type this pane's commands as a single line (ie: \"&&\")",
+    ),
+    (
+        "send_keys",
+        "Key strokes that are typed but not executed... as long as they don't contain a new line",
+    ),
+    ("raw", "Whether or not to type commands and send_keys without escaping `#` characters"),
+    (
+        "exec",
+        "Run the pane's single command as the pane process instead of a shell (ie: for `tail -f` or `htop` panes)\n\
+        Nothing is typed in the pane (pane_commands, send_keys and clear are skipped), and it closes when the command exits",
+    ),
+];
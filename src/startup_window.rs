@@ -1,15 +1,39 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
 #[serde(untagged)]
 pub enum StartupWindow {
+    #[default]
     Default,
     Name(String),
     Index(usize),
 }
 
-impl Default for StartupWindow {
-    fn default() -> Self {
-        StartupWindow::Default
+impl StartupWindow {
+    // Names can end with `:index` to pick one of several windows that share the same name
+    pub fn split_name(name: &str) -> (&str, Option<usize>) {
+        match name.rsplit_once(':') {
+            Some((window_name, index)) => match index.parse() {
+                Ok(index) => (window_name, Some(index)),
+                Err(_) => (name, None),
+            },
+            None => (name, None),
+        }
+    }
+
+    // Changes the window name, leaving the `:index` suffix alone
+    pub fn map_name<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&str) -> String,
+    {
+        match &self {
+            StartupWindow::Name(name) => match StartupWindow::split_name(name) {
+                (window_name, Some(index)) => {
+                    StartupWindow::Name(format!("{}:{}", f(window_name), index))
+                }
+                (window_name, None) => StartupWindow::Name(f(window_name)),
+            },
+            _ => self,
+        }
     }
 }
@@ -14,7 +14,15 @@ fn make_config(tmux_command: Option<&str>, config_dir: Option<PathBuf>) -> Confi
         app_name: "test_app_name",
         app_author: "test_app_author",
         tmux_command: Some(String::from(tmux_command.unwrap_or("tmux"))),
+        tmux_socket: None,
+        tmux_options: None,
         config_dir,
+        lenient: false,
+        picker: None,
+        profile: false,
+        session_prefix: None,
+        editor_args: vec![],
+        file_extensions: vec![],
     }
 }
 
@@ -33,7 +41,9 @@ fn edit_project_fails_when_editor_is_empty() {
             Some("yml"),
             "",
             false,
-            &[]
+            false,
+            &[],
+            None
         )
         .err()
         .unwrap()
@@ -61,7 +71,9 @@ fn edit_project_succeeds_when_project_file_does_not_exist() {
         Some("yml"),
         TEST_EDITOR_BIN,
         true,
+        false,
         &[],
+        None,
     );
 
     assert!(project_path.is_file());
@@ -90,7 +102,9 @@ fn edit_project_succeeds_when_project_file_exists() {
         Some("yml"),
         TEST_EDITOR_BIN,
         true,
+        false,
         &[],
+        None,
     );
 
     assert!(project_path.is_file());
@@ -116,7 +130,9 @@ fn edit_project_creates_sub_directories_as_needed() {
         Some("yml"),
         TEST_EDITOR_BIN,
         true,
+        false,
         &[],
+        None,
     )
     .unwrap();
 
@@ -124,6 +140,33 @@ fn edit_project_creates_sub_directories_as_needed() {
     assert!(project_path.is_file());
 }
 
+#[test]
+fn edit_project_creates_project_file_without_an_editor_when_not_opening_it() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+    let project_name = "project";
+    let project_path = test_config
+        .get_projects_dir(project_name)
+        .unwrap()
+        .with_extension("yml");
+
+    let result = edit_project(
+        &test_config,
+        Some(project_name),
+        None,
+        Some("yml"),
+        "",
+        false,
+        true,
+        &[],
+        None,
+    );
+
+    assert!(result.is_ok());
+    assert!(project_path.is_file());
+}
+
 #[test]
 fn edit_project_fails_when_project_path_is_directory() {
     let temp_dir = tempdir().unwrap();
@@ -145,7 +188,9 @@ fn edit_project_fails_when_project_path_is_directory() {
         Some("yml"),
         TEST_EDITOR_BIN,
         false,
+        false,
         &[],
+        None,
     );
     assert!(result.is_err());
     assert!(matches!(
@@ -168,7 +213,9 @@ fn edit_project_project_name_cannot_be_empty() {
         Some("yml"),
         TEST_EDITOR_BIN,
         false,
+        false,
         &[],
+        None,
     );
     assert!(result.is_err());
     assert!(matches!(
@@ -192,7 +239,9 @@ fn edit_project_fails_if_extension_is_not_supported() {
         Some(unsupported_extension),
         TEST_EDITOR_BIN,
         false,
+        false,
         &[],
+        None,
     );
     assert!(result.is_err());
     assert!(matches!(
@@ -222,12 +271,34 @@ fn edit_project_creates_file_locally() {
         Some(extension),
         TEST_EDITOR_BIN,
         true,
+        false,
         &[],
+        None,
     )
     .unwrap();
     assert!(project_file.exists());
 }
 
+#[test]
+fn check_project_reports_invalid_project_files() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+    let project_name = "project";
+
+    let projects_dir = test_config.get_projects_dir("").unwrap();
+    let project_path = projects_dir.join(project_name).with_extension("yml");
+    mkdirp(projects_dir).unwrap();
+
+    fs::write(&project_path, "windows: [editor]\n").unwrap();
+    assert!(check_project(&test_config, Some(project_name), None, false, &[]).is_ok());
+
+    fs::write(&project_path, "windows:\n  - editor\n   logs: [\n").unwrap();
+    let result = check_project(&test_config, Some(project_name), None, false, &[]);
+    let diagnostic = result.err().unwrap().downcast::<Diagnostic>().unwrap();
+    assert_eq!(diagnostic.line, Some(3));
+}
+
 #[test]
 fn remove_project_removes_existing_project() {
     let temp_dir = tempdir().unwrap();
@@ -345,7 +416,7 @@ fn remove_project_removes_local_project() {
 
     let project_file = temp_local_dir.join(".airmux");
 
-    for extension in FILE_EXTENSIONS {
+    for extension in test_config.get_file_extensions() {
         let project_file = project_file.with_extension(extension);
 
         let file = fs::File::create(&project_file).unwrap();
@@ -370,27 +441,53 @@ fn list_project_does_not_fail() {
         edit::create_project(&project_name, projects_dir.join(&project_name), "yml", None).unwrap();
     }
 
-    list_projects(&test_config).unwrap();
+    list_projects(&test_config, false, false).unwrap();
+}
+
+#[test]
+fn get_dirty_projects_lists_projects_with_errors() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+    let projects_dir = test_config.get_projects_dir("").unwrap();
+
+    edit::create_project("valid", projects_dir.join("valid.yml"), "yml", None).unwrap();
+    fs::write(
+        projects_dir.join("unknown_field.yml"),
+        "unknown_field: true",
+    )
+    .unwrap();
+    fs::write(projects_dir.join("invalid.yml"), "windows: [").unwrap();
+
+    let mut dirty_projects: Vec<String> = list::get_dirty_projects(&test_config)
+        .unwrap()
+        .into_iter()
+        .map(|(project_name, _)| project_name)
+        .collect();
+    dirty_projects.sort();
+
+    assert_eq!(dirty_projects, vec!["invalid", "unknown_field"]);
 }
 
 #[test]
 fn get_project_list_returns_projects_without_extensions() {
     let temp_dir = tempdir().unwrap();
     let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, None);
 
     let mut expected_project_list = Vec::with_capacity(5);
     for n in 0..5 {
         let project_name = format!("project{}", n);
 
         let project_path = temp_dir.join(&project_name);
-        let project_path = project::test_for_file_extensions(project_path).unwrap();
+        let project_path = project::test_for_file_extensions(&test_config, project_path).unwrap();
 
         edit::create_project(&project_name, &project_path, "yml", None).unwrap();
         expected_project_list.push(project_name);
     }
     expected_project_list.sort();
 
-    let mut project_list = list::get_projects(&temp_dir).unwrap();
+    let mut project_list = list::get_projects(&test_config, &temp_dir).unwrap();
     project_list.sort();
 
     assert_eq!(project_list, expected_project_list);
@@ -400,13 +497,14 @@ fn get_project_list_returns_projects_without_extensions() {
 fn list_shows_projects_in_subdirectories() {
     let temp_dir = tempdir().unwrap();
     let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, None);
 
     let mut expected_project_list = Vec::with_capacity(4);
     for n in 0..2 {
         let project_name = format!("project{}", n);
 
         let project_path = temp_dir.join(&project_name);
-        let project_path = project::test_for_file_extensions(project_path).unwrap();
+        let project_path = project::test_for_file_extensions(&test_config, project_path).unwrap();
 
         edit::create_project(&project_name, &project_path, "yml", None).unwrap();
         expected_project_list.push(project_name);
@@ -416,7 +514,7 @@ fn list_shows_projects_in_subdirectories() {
         mkdirp(temp_dir.join("subdir1")).unwrap();
 
         let project_path = temp_dir.join(&project_name);
-        let project_path = project::test_for_file_extensions(project_path).unwrap();
+        let project_path = project::test_for_file_extensions(&test_config, project_path).unwrap();
 
         edit::create_project(&project_name, &project_path, "yml", None).unwrap();
         expected_project_list.push(project_name);
@@ -426,14 +524,14 @@ fn list_shows_projects_in_subdirectories() {
         mkdirp(temp_dir.join("subdir2")).unwrap();
 
         let project_path = temp_dir.join(&project_name);
-        let project_path = project::test_for_file_extensions(project_path).unwrap();
+        let project_path = project::test_for_file_extensions(&test_config, project_path).unwrap();
 
         edit::create_project(&project_name, &project_path, "yml", None).unwrap();
         expected_project_list.push(project_name);
     }
     expected_project_list.sort();
 
-    let mut project_list = list::get_projects(&temp_dir).unwrap();
+    let mut project_list = list::get_projects(&test_config, &temp_dir).unwrap();
     project_list.sort();
 
     assert_eq!(project_list, expected_project_list);
@@ -443,13 +541,14 @@ fn list_shows_projects_in_subdirectories() {
 fn list_follows_symlinks() {
     let temp_dir = tempdir().unwrap();
     let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, None);
 
     let mut expected_project_list = Vec::with_capacity(4);
     for n in 0..2 {
         let project_name = format!("project{}", n);
 
         let project_path = temp_dir.join(&project_name);
-        let project_path = project::test_for_file_extensions(project_path).unwrap();
+        let project_path = project::test_for_file_extensions(&test_config, project_path).unwrap();
 
         edit::create_project(&project_name, &project_path, "yml", None).unwrap();
         expected_project_list.push(project_name);
@@ -459,7 +558,7 @@ fn list_follows_symlinks() {
         mkdirp(temp_dir.join("subdir1")).unwrap();
 
         let project_path = temp_dir.join(&project_name);
-        let project_path = project::test_for_file_extensions(project_path).unwrap();
+        let project_path = project::test_for_file_extensions(&test_config, project_path).unwrap();
 
         edit::create_project(&project_name, &project_path, "yml", None).unwrap();
         expected_project_list.push(project_name);
@@ -477,7 +576,7 @@ fn list_follows_symlinks() {
     os::unix::fs::symlink(temp_dir.join("subdir1"), temp_dir.join("subdir2")).unwrap();
     assert!(temp_dir.join("subdir2").is_dir());
 
-    let mut project_list = list::get_projects(&temp_dir).unwrap();
+    let mut project_list = list::get_projects(&test_config, &temp_dir).unwrap();
     project_list.sort();
 
     assert_eq!(project_list, expected_project_list);
@@ -487,13 +586,14 @@ fn list_follows_symlinks() {
 fn list_detects_symlink_loops() {
     let temp_dir = tempdir().unwrap();
     let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, None);
 
     let mut expected_project_list = Vec::with_capacity(4);
     for n in 0..2 {
         let project_name = format!("project{}", n);
 
         let project_path = temp_dir.join(&project_name);
-        let project_path = project::test_for_file_extensions(project_path).unwrap();
+        let project_path = project::test_for_file_extensions(&test_config, project_path).unwrap();
 
         edit::create_project(&project_name, &project_path, "yml", None).unwrap();
         expected_project_list.push(project_name);
@@ -503,7 +603,7 @@ fn list_detects_symlink_loops() {
         mkdirp(temp_dir.join("subdir1")).unwrap();
 
         let project_path = temp_dir.join(&project_name);
-        let project_path = project::test_for_file_extensions(project_path).unwrap();
+        let project_path = project::test_for_file_extensions(&test_config, project_path).unwrap();
 
         edit::create_project(&project_name, &project_path, "yml", None).unwrap();
         expected_project_list.push(project_name);
@@ -516,7 +616,7 @@ fn list_detects_symlink_loops() {
     os::unix::fs::symlink(&temp_dir, temp_dir.join("subdir2")).unwrap();
     assert!(temp_dir.join("subdir2").is_dir());
 
-    let mut project_list = list::get_projects(&temp_dir).unwrap();
+    let mut project_list = list::get_projects(&test_config, &temp_dir).unwrap();
     project_list.sort();
 
     assert_eq!(project_list, expected_project_list);
@@ -611,3 +711,2303 @@ fn get_filename_fails_if_path_does_not_contain_a_filename() {
         Error::CannotExtractProjectName { project_file } if project_file == &PathBuf::from(test_project_file)
     ));
 }
+
+#[test]
+fn history_is_empty_when_no_project_was_started() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+
+    let entries = history::get_entries(&test_config).unwrap();
+    assert!(entries.is_empty());
+
+    let result = history::get_last_project(&test_config);
+    assert!(result.is_err());
+    assert!(matches!(
+        result.err().unwrap().downcast_ref::<Error>().unwrap(),
+        Error::NoRecentProject
+    ));
+}
+
+#[test]
+fn history_moves_restarted_projects_to_the_top() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.to_owned()));
+
+    let project1_file = temp_dir.join("project1.yml");
+    let project2_file = temp_dir.join("project2.yml");
+    edit::create_project("project1", &project1_file, "yml", None).unwrap();
+    edit::create_project("project2", &project2_file, "yml", None).unwrap();
+
+    history::add_entry(&test_config, "project1", &project1_file).unwrap();
+    history::add_entry(&test_config, "project2", &project2_file).unwrap();
+    history::add_entry(&test_config, "project1", &project1_file).unwrap();
+
+    let entries: Vec<String> = history::get_entries(&test_config)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.project_name)
+        .collect();
+    assert_eq!(entries, vec!["project1", "project2"]);
+
+    let (project_name, project_file) = history::get_last_project(&test_config).unwrap();
+    assert_eq!(project_name, "project1");
+    assert_eq!(project_file, fs::canonicalize(project1_file).unwrap());
+}
+
+#[test]
+fn history_ignores_stdin_projects() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+
+    history::add_entry(&test_config, "project", PathBuf::new()).unwrap();
+
+    let entries = history::get_entries(&test_config).unwrap();
+    assert!(entries.is_empty());
+}
+
+#[test]
+fn registry_keeps_one_entry_per_session() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.to_owned()));
+
+    let project1_file = temp_dir.join("project1.yml");
+    let project2_file = temp_dir.join("project2.yml");
+    edit::create_project("project1", &project1_file, "yml", None).unwrap();
+    edit::create_project("project2", &project2_file, "yml", None).unwrap();
+
+    registry::add_entry(&test_config, "session1", "project1", &project1_file).unwrap();
+    registry::add_entry(&test_config, "session2", "project1", &project1_file).unwrap();
+    registry::add_entry(&test_config, "session1", "project2", &project2_file).unwrap();
+    registry::remove_entry(&test_config, "session2").unwrap();
+
+    assert_eq!(
+        registry::get_entries(&test_config).unwrap(),
+        vec![registry::Entry {
+            session_name: String::from("session1"),
+            project_name: String::from("project2"),
+            project_file: fs::canonicalize(project2_file).unwrap(),
+        }]
+    );
+}
+
+#[test]
+fn session_lock_is_exclusive_until_dropped() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+
+    let lock = lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(0));
+    assert!(lock.is_ok());
+
+    let result = lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(0));
+    assert!(result.is_err());
+    assert!(
+        lock::SessionLock::acquire(&test_config, "other_session", Duration::from_secs(0)).is_ok()
+    );
+
+    drop(lock);
+    assert!(lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(0)).is_ok());
+}
+
+#[test]
+fn session_lock_is_acquired_once_by_concurrent_starts() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+    let barrier = std::sync::Barrier::new(2);
+
+    // Both locks are kept until the threads are joined, so neither can be released early
+    let locks = thread::scope(|scope| {
+        let acquire = || {
+            barrier.wait();
+            lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(0))
+                .map_err(|err| err.to_string())
+        };
+
+        let first = scope.spawn(acquire);
+        let second = scope.spawn(acquire);
+        [first.join().unwrap(), second.join().unwrap()]
+    });
+
+    assert_eq!(locks.iter().filter(|lock| lock.is_ok()).count(), 1);
+}
+
+#[test]
+fn session_lock_waits_for_concurrent_starts() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+
+    let lock = lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(0));
+    assert!(lock.is_ok());
+
+    thread::scope(|scope| {
+        let waiting = scope.spawn(|| {
+            lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(5)).is_ok()
+        });
+
+        thread::sleep(Duration::from_millis(200));
+        drop(lock);
+        assert!(waiting.join().unwrap());
+    });
+}
+
+#[test]
+fn session_lock_is_stale_once_its_owner_is_gone() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+    let lock_file = test_config
+        .get_data_dir(LOCKS_DIR)
+        .unwrap()
+        .join("session.lock");
+
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    fs::write(&lock_file, child.id().to_string()).unwrap();
+    assert!(lock::is_stale(&lock_file));
+    assert!(lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(0)).is_ok());
+
+    // Old locks of running starts are kept
+    fs::write(&lock_file, std::process::id().to_string()).unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&lock_file)
+        .unwrap()
+        .set_modified(SystemTime::now() - LOCK_STALE_AFTER * 2)
+        .unwrap();
+    assert!(!lock::is_stale(&lock_file));
+    assert!(lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(0)).is_err());
+}
+
+#[test]
+fn clean_finds_stale_locks_and_running_starts() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+
+    assert_eq!(clean::get_locks(&test_config).unwrap(), (vec![], false));
+
+    let locks_dir = test_config.get_data_dir(LOCKS_DIR).unwrap();
+    let stale_lock = locks_dir.join("stale.lock");
+    fs::write(&stale_lock, "").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&stale_lock)
+        .unwrap()
+        .set_modified(SystemTime::now() - LOCK_STALE_AFTER * 2)
+        .unwrap();
+    fs::write(locks_dir.join("notes.txt"), "").unwrap();
+
+    assert_eq!(
+        clean::get_locks(&test_config).unwrap(),
+        (vec![stale_lock.to_owned()], false)
+    );
+
+    let _lock = lock::SessionLock::acquire(&test_config, "session", Duration::from_secs(0));
+    assert_eq!(
+        clean::get_locks(&test_config).unwrap(),
+        (vec![stale_lock], true)
+    );
+}
+
+#[test]
+fn clean_lists_airmux_global_variables() {
+    let output =
+        "TERM=screen\n__AIRMUX_SESSION_ATTACHED=1\n-__AIRMUX_HOOK_FAILED\nAIRMUX_COMMAND=tmux\n";
+
+    assert_eq!(
+        clean::get_global_variables(output),
+        vec!["__AIRMUX_SESSION_ATTACHED", "__AIRMUX_HOOK_FAILED"]
+    );
+}
+
+#[test]
+fn clean_splits_registry_entries_of_missing_sessions_and_projects() {
+    let temp_dir = tempdir().unwrap();
+    let project_file = temp_dir.path().join("project.yml");
+    fs::write(&project_file, "").unwrap();
+
+    let entry = |session_name: &str, project_file: &Path| registry::Entry {
+        session_name: String::from(session_name),
+        project_name: String::from("project"),
+        project_file: project_file.to_path_buf(),
+    };
+    let running_sessions = vec![String::from("running"), String::from("orphan")];
+
+    let (kept, removed) = clean::split_stale_entries(
+        vec![
+            entry("running", &project_file),
+            entry("stopped", &project_file),
+            entry("orphan", &temp_dir.path().join("missing.yml")),
+        ],
+        &running_sessions,
+    );
+
+    assert_eq!(kept, vec![entry("running", &project_file)]);
+    assert_eq!(
+        removed,
+        vec![
+            entry("stopped", &project_file),
+            entry("orphan", &temp_dir.path().join("missing.yml")),
+        ]
+    );
+}
+
+#[test]
+fn start_project_fails_with_dash_when_history_is_empty() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir));
+
+    let result = start_project(
+        &test_config,
+        Some("-"),
+        None,
+        None,
+        true,
+        false,
+        false,
+        &[],
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        None,
+        None,
+        None,
+    );
+    assert!(result.is_err());
+    assert!(matches!(
+        result.err().unwrap().downcast_ref::<Error>().unwrap(),
+        Error::NoRecentProject
+    ));
+}
+
+#[test]
+fn prune_keeps_windows_with_a_declared_marker() {
+    let project = Project {
+        windows: vec![
+            Window {
+                name: Some(String::from("editor")),
+                ..Window::default()
+            },
+            Window::default(),
+        ],
+        ..Project::default()
+    };
+
+    let window = |id: &str, index: usize, name: &str, marker: Option<usize>| prune::TmuxWindow {
+        id: String::from(id),
+        index,
+        name: String::from(name),
+        marker,
+    };
+    let windows = vec![
+        // Moved and renamed by hand
+        window("@1", 5, "renamed", Some(1)),
+        window("@2", 2, "zsh", Some(2)),
+        // Opened by hand at an index and with a name the project declares
+        window("@3", 1, "editor", None),
+        // Left over from an older version of the project that had more windows
+        window("@4", 3, "logs", Some(3)),
+    ];
+
+    let undeclared = prune::get_undeclared_windows(&project, windows);
+    assert_eq!(
+        undeclared.iter().map(|w| w.id.as_str()).collect::<Vec<_>>(),
+        vec!["@3", "@4"]
+    );
+}
+
+#[test]
+fn load_project_skips_env_expansion_when_expand_env_is_false() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project.yml");
+    fs::write(
+        &project_file,
+        "expand_env: false\nwindows:\n  - echo ${1:-default} $$HOME\n",
+    )
+    .unwrap();
+
+    let project = project::load(&test_config, "project", &project_file, None, &["arg"]).unwrap();
+    assert!(!project.expand_env);
+    assert_eq!(
+        project.windows[0].panes[0].commands,
+        vec![PaneCommand::from("echo ${1:-default} $$HOME")]
+    );
+}
+
+#[test]
+fn load_project_expands_env_by_default() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project.yml");
+    fs::write(&project_file, "windows:\n  - echo ${1:-default} $${VAR}\n").unwrap();
+
+    let project = project::load(&test_config, "project", &project_file, None, &["arg"]).unwrap();
+    assert_eq!(
+        project.windows[0].panes[0].commands,
+        vec![PaneCommand::from("echo arg ${VAR}")]
+    );
+}
+
+#[test]
+fn get_defaults_reads_the_project_file_before_expanding_it() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project.yml");
+    fs::write(
+        &project_file,
+        "defaults:\n  no_attach: true\n  args: [staging]\nwindows:\n  - echo ${1}\n",
+    )
+    .unwrap();
+
+    let defaults = project::get_defaults(&project_file);
+    assert_eq!(defaults.force_attach(None), Some(false));
+    assert_eq!(defaults.force_attach(Some(true)), Some(true));
+    assert_eq!(defaults.args(&[]), vec!["staging"]);
+    assert_eq!(defaults.args(&["production"]), vec!["production"]);
+
+    let args = defaults.args(&[]);
+    let project = project::load(&test_config, "project", &project_file, None, &args).unwrap();
+    assert_eq!(
+        project.windows[0].panes[0].commands,
+        vec![PaneCommand::from("echo staging")]
+    );
+
+    fs::write(&project_file, "windows:\n  - echo ${1}\n").unwrap();
+    assert_eq!(project::get_defaults(&project_file), Defaults::default());
+}
+
+#[test]
+fn load_session_picks_one_of_the_sessions_of_the_project_file() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project.yml");
+    fs::write(
+        &project_file,
+        "sessions:\n  - name: app\n    windows: [vim]\n  - session_name: ops\n    windows:\n      - echo ${1}\n",
+    )
+    .unwrap();
+
+    let project_yaml = project::read(&project_file, &["prod"]).unwrap();
+    let projects = project::load_sessions(
+        &test_config,
+        "project",
+        &project_file,
+        &project_yaml,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        projects
+            .iter()
+            .map(|project| project.session_name.as_deref().unwrap())
+            .collect::<Vec<&str>>(),
+        vec!["app", "ops"]
+    );
+
+    let project = project::load_session(
+        &test_config,
+        "project",
+        &project_file,
+        None,
+        &["prod"],
+        Some("ops"),
+    )
+    .unwrap();
+    assert_eq!(project.session_name, Some(String::from("ops")));
+    assert_eq!(
+        project.windows[0].panes[0].commands,
+        vec![PaneCommand::from("echo prod")]
+    );
+
+    let result = project::load_session(&test_config, "project", &project_file, None, &[], None);
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "the project file defines several sessions (app, ops), pick one with --session"
+    );
+
+    let result = project::load_session(
+        &test_config,
+        "project",
+        &project_file,
+        None,
+        &[],
+        Some("db"),
+    );
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "session \"db\" is not defined in the project file"
+    );
+}
+
+#[test]
+fn check_project_file_checks_every_session_unless_one_is_picked() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project.yml");
+    fs::write(
+        &project_file,
+        "sessions:\n  - name: app\n    windows: [vim]\n  - name: ops\n    bogus: 1\n",
+    )
+    .unwrap();
+
+    let result = edit::check_project_file(&test_config, "project", &project_file, &[], None);
+    assert!(result.is_err());
+
+    let result = edit::check_project_file(&test_config, "project", &project_file, &[], Some("app"));
+    assert!(result.is_ok());
+}
+
+#[test]
+fn get_sessions_requires_named_sessions_only() {
+    assert!(project::get_sessions("windows: [vim]\n").unwrap().is_none());
+
+    let result = project::get_sessions("sessions:\n  - windows: [vim]\n");
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "sessions: session #1 has no name"
+    );
+
+    let result = project::get_sessions("sessions: []\nwindows: [vim]\n");
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "sessions: cannot be combined with other project fields (windows)"
+    );
+
+    let result = project::get_sessions("sessions: []\n");
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "sessions: expected at least one session"
+    );
+}
+
+#[test]
+fn parse_lenient_drops_unknown_top_level_fields() {
+    let (project, unknown_fields) =
+        project::parse_lenient("foo: 1\nbar: [x]\nwindows:\n  - vim\n").unwrap();
+
+    assert_eq!(unknown_fields, vec!["foo", "bar"]);
+    assert_eq!(
+        project.windows[0].panes[0].commands,
+        vec![PaneCommand::from("vim")]
+    );
+}
+
+#[test]
+fn parse_lenient_drops_unknown_window_and_pane_fields() {
+    let yaml = r#"
+windows:
+  - name: editor
+    foo: 1
+    panes:
+      - commands: vim
+        bar: 2
+      - logs:
+          commands: tail -f log
+          baz: 3
+  - server:
+      qux: 4
+      panes: [{commands: make, quux: 5}]
+"#;
+    let (project, unknown_fields) = project::parse_lenient(yaml).unwrap();
+
+    assert_eq!(
+        unknown_fields,
+        vec![
+            "windows[0].foo",
+            "windows[0].panes[0].bar",
+            "windows[0].panes[1].logs.baz",
+            "windows[1].server.qux",
+            "windows[1].server.panes[0].quux",
+        ]
+    );
+    assert_eq!(project.windows[0].name, Some(String::from("editor")));
+    assert_eq!(
+        project.windows[0].panes[1].commands,
+        vec![PaneCommand::from("tail -f log")]
+    );
+    assert_eq!(project.windows[1].name, Some(String::from("server")));
+    assert_eq!(
+        project.windows[1].panes[0].commands,
+        vec![PaneCommand::from("make")]
+    );
+}
+
+#[test]
+fn load_project_fails_on_unknown_fields_unless_lenient() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let mut test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project.yml");
+    fs::write(&project_file, "foo: 1\nwindows:\n  - vim\n").unwrap();
+
+    let result = project::load(&test_config, "project", &project_file, None, &[]);
+    assert!(result.is_err());
+
+    test_config.lenient = true;
+    let project = project::load(&test_config, "project", &project_file, None, &[]).unwrap();
+    assert_eq!(
+        project.windows[0].panes[0].commands,
+        vec![PaneCommand::from("vim")]
+    );
+}
+
+#[test]
+fn migrate_yaml_renames_deprecated_fields_and_sets_version() {
+    let content = "# comment\npre_window: echo hi # activate\non_create: make\nwindows:\n  - vim:\n      on_create: ls\n";
+
+    assert_eq!(
+        migrate::migrate_yaml(content),
+        "version: 1\n# comment\npane_commands: echo hi # activate\non_first_start: make\nwindows:\n  - vim:\n      on_create: ls\n"
+    );
+}
+
+#[test]
+fn migrate_yaml_replaces_existing_version() {
+    let content = "---\nversion: 0\nsocket_name: test\n";
+
+    assert_eq!(
+        migrate::migrate_yaml(content),
+        "---\nversion: 1\ntmux_socket: test\n"
+    );
+}
+
+#[test]
+fn migrate_json_renames_deprecated_fields_and_sets_version() {
+    let content = r#"{"tmux_detached": true}"#;
+
+    assert_eq!(
+        migrate::migrate_json(content).unwrap(),
+        "{\n  \"detached\": true,\n  \"version\": 1\n}\n"
+    );
+}
+
+#[test]
+fn migrate_project_fails_on_newer_version() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project.yml");
+    fs::write(&project_file, "version: 99\n").unwrap();
+
+    let result = migrate_project(
+        &test_config,
+        None,
+        Some(project_file.to_str().unwrap()),
+        true,
+    );
+    assert!(matches!(
+        result.err().unwrap().downcast_ref::<Error>(),
+        Some(Error::UnsupportedProjectVersion { version: 99 })
+    ));
+}
+
+#[test]
+fn detect_format_sniffs_content() {
+    assert_eq!(
+        project::detect_format("# comment\n\n{\"windows\": []}"),
+        FileFormat::Json
+    );
+    assert_eq!(
+        project::detect_format("session_name: project\nwindows: [vim]\n"),
+        FileFormat::Yaml
+    );
+    assert_eq!(project::detect_format("- echo a=b\n"), FileFormat::Yaml);
+    assert_eq!(project::detect_format(""), FileFormat::Yaml);
+}
+
+#[test]
+fn toml_content_is_recognized() {
+    assert!(project::is_toml("session_name = \"project\"\n"));
+    assert!(project::is_toml("# comment\n[[windows]]\n"));
+    assert!(!project::is_toml("session_name: project\n"));
+    assert!(!project::is_toml("- echo a=b\n"));
+    assert!(!project::is_toml("{\"windows\": []}"));
+    assert!(!project::is_toml(""));
+}
+
+#[test]
+fn load_project_reports_json_errors_with_location() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project.conf");
+    fs::write(&project_file, "{\n  \"windows\": [\"vim\"}\n").unwrap();
+
+    let error = project::load(&test_config, "project", &project_file, None, &[])
+        .err()
+        .unwrap();
+    let diagnostic = error.downcast_ref::<Diagnostic>().unwrap();
+    assert_eq!(diagnostic.line, Some(2));
+    assert_eq!(diagnostic.column, Some(20));
+}
+
+#[test]
+fn load_project_rejects_toml() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let project_file = temp_dir.join("project");
+    fs::write(&project_file, "session_name = \"project\"\n").unwrap();
+
+    let result = project::load(&test_config, "project", &project_file, None, &[]);
+    assert!(matches!(
+        result.err().unwrap().downcast_ref::<Error>(),
+        Some(Error::TomlNotSupported)
+    ));
+}
+
+#[test]
+fn test_for_file_extensions_accepts_existing_files_with_unknown_extensions() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, None);
+
+    let project_file = temp_dir.join("project.conf");
+    assert!(project::test_for_file_extensions(&test_config, &project_file).is_err());
+
+    fs::write(&project_file, "windows: [vim]\n").unwrap();
+    assert_eq!(
+        project::test_for_file_extensions(&test_config, &project_file).unwrap(),
+        project_file
+    );
+}
+
+#[test]
+fn get_filename_uses_project_file_with_explicit_project_name() {
+    let test_config = make_config(None, None);
+
+    let (project_name, project_path) =
+        project::get_filename(&test_config, Some("project"), Some("/some/path/myfile.yml"))
+            .unwrap();
+
+    assert_eq!(project_name, "project");
+    assert_eq!(project_path, PathBuf::from("/some/path/myfile.yml"));
+}
+
+#[test]
+fn fetch_extracts_project_name_from_url() {
+    assert_eq!(
+        fetch::get_project_name("https://example.com/projects/web.yml?token=1"),
+        Some(String::from("web"))
+    );
+    assert_eq!(
+        fetch::get_project_name("https://example.com/projects/web/"),
+        Some(String::from("web"))
+    );
+    assert_eq!(fetch::get_project_name("https://example.com"), None);
+}
+
+#[test]
+fn fetch_lists_project_commands() {
+    let project = serde_yaml::from_str::<Project>(
+        r##"
+        on_start: echo "#start"
+        windows:
+          - editor:
+              pane_commands: source .env
+              panes:
+                - commands:
+                    - vim
+                    - expect: "Password:"
+                      send: hunter2
+        "##,
+    )
+    .unwrap();
+
+    assert_eq!(
+        fetch::get_commands(&project),
+        vec!["echo \"#start\"", "source .env", "vim", "hunter2"]
+    );
+}
+
+#[test]
+fn up_parses_named_windows() {
+    let window = up::parse_window("server: cargo run");
+
+    assert_eq!(window.name, Some(String::from("server")));
+    assert_eq!(window.panes, vec![Pane::from("cargo run")]);
+}
+
+#[test]
+fn up_parses_unnamed_windows() {
+    let window = up::parse_window("echo a:b");
+
+    assert_eq!(window.name, None);
+    assert_eq!(window.panes, vec![Pane::from("echo a:b")]);
+}
+
+#[test]
+fn up_parses_windows_without_commands() {
+    let window = up::parse_window("editor:");
+
+    assert_eq!(window.name, Some(String::from("editor")));
+    assert_eq!(window.panes, vec![Pane::default()]);
+}
+
+#[test]
+fn init_detects_windows_from_project_files() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+
+    fs::write(temp_dir.join("Cargo.toml"), "").unwrap();
+    fs::write(
+        temp_dir.join("package.json"),
+        r#"{"scripts": {"dev": "vite"}}"#,
+    )
+    .unwrap();
+
+    let names: Vec<Option<String>> = init::detect_windows(&temp_dir)
+        .into_iter()
+        .map(|window| window.name)
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            Some(String::from("editor")),
+            Some(String::from("cargo")),
+            Some(String::from("npm"))
+        ]
+    );
+}
+
+#[test]
+fn init_picks_package_script() {
+    assert_eq!(
+        init::get_package_script(r#"{"scripts": {"build": "tsc", "start": "node ."}}"#),
+        Some(String::from("start"))
+    );
+    assert_eq!(
+        init::get_package_script(r#"{"scripts": {"build": "tsc"}}"#),
+        None
+    );
+}
+
+#[test]
+fn init_picks_default_make_target() {
+    assert_eq!(
+        init::get_make_target("CC := gcc\n.PHONY: all\nall: build\nbuild:\n\tcc main.c\n"),
+        Some(String::from("all"))
+    );
+    assert_eq!(init::get_make_target("CC = gcc\n"), None);
+}
+
+#[test]
+fn ui_reads_session_name_from_project_file() {
+    let config = make_config(None, None);
+
+    assert_eq!(
+        ui::get_session_name(&config, "project", "name: session\nwindows: [vim]\n"),
+        Some(String::from("session"))
+    );
+    assert_eq!(
+        ui::get_session_name(&config, "project", "windows: [vim]\n"),
+        Some(String::from("project"))
+    );
+    assert_eq!(
+        ui::get_session_name(&config, "app-2.0", "sanitize_names: true\n"),
+        Some(String::from("app-2_0"))
+    );
+    assert_eq!(
+        ui::get_session_name(&config, "project", "name: [vim]\n"),
+        None
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn stop_all_projects_kills_running_sessions_only() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let log_file = temp_dir.join("tmux.log");
+
+    // Pretends that only project1's session is running, and logs the other tmux calls
+    let tmux_command = format!(
+        "sh -c '[ \"$1\" = ls ] && echo project1 || echo \"$*\" >> {}' tmux",
+        log_file.to_string_lossy()
+    );
+    let test_config = make_config(Some(&tmux_command), Some(temp_dir.clone()));
+
+    let projects_dir = test_config.get_projects_dir("").unwrap();
+    fs::write(projects_dir.join("project1.yml"), "windows: [vim]\n").unwrap();
+    fs::write(projects_dir.join("project2.yml"), "windows: [vim]\n").unwrap();
+
+    stop_all_projects(&test_config, true).unwrap();
+    assert!(!log_file.exists());
+
+    stop_all_projects(&test_config, false).unwrap();
+    assert_eq!(
+        fs::read_to_string(&log_file).unwrap(),
+        "kill-session -t project1\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn stop_all_projects_kills_sessions_by_their_registered_name() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let log_file = temp_dir.join("tmux.log");
+
+    let tmux_command = format!(
+        "sh -c '[ \"$1\" = ls ] && printf \"app-main\\nops\\n\" || echo \"$*\" >> {}' tmux",
+        log_file.to_string_lossy()
+    );
+    let test_config = make_config(Some(&tmux_command), Some(temp_dir.clone()));
+
+    // The sessions were started under names the project file doesn't resolve to anymore
+    let projects_dir = test_config.get_projects_dir("").unwrap();
+    let project_file = projects_dir.join("project.yml");
+    fs::write(
+        &project_file,
+        "sessions:\n  - name: app-{git_branch}\n  - name: ops\n",
+    )
+    .unwrap();
+    registry::add_entry(&test_config, "app-main", "project", &project_file).unwrap();
+    registry::add_entry(&test_config, "ops", "project", &project_file).unwrap();
+
+    stop_all_projects(&test_config, false).unwrap();
+    assert_eq!(
+        fs::read_to_string(&log_file).unwrap(),
+        "kill-session -t app-main\nkill-session -t ops\n"
+    );
+    assert!(registry::get_entries(&test_config).unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn stop_all_projects_kills_sessions_on_their_project_tmux_server() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let log_file = temp_dir.join("tmux.log");
+
+    // The ops session only runs on the project's own socket
+    let tmux_command = format!(
+        "sh -c 'case \"$*\" in \"ls \"*) echo default ;; \"-L work ls \"*) echo ops ;; \
+        *) echo \"$*\" >> {} ;; esac' tmux",
+        log_file.to_string_lossy()
+    );
+    let test_config = make_config(Some(&tmux_command), Some(temp_dir.clone()));
+
+    let projects_dir = test_config.get_projects_dir("").unwrap();
+    let project_file = projects_dir.join("ops.yml");
+    fs::write(&project_file, "tmux_socket: work\nwindows: [vim]\n").unwrap();
+    registry::add_entry(&test_config, "ops", "ops", &project_file).unwrap();
+
+    stop_all_projects(&test_config, false).unwrap();
+    assert_eq!(
+        fs::read_to_string(&log_file).unwrap(),
+        "-L work kill-session -t ops\n"
+    );
+}
+
+#[test]
+fn ui_lists_projects_with_their_session_names() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(Some("false"), Some(temp_dir.clone()));
+
+    let projects_dir = test_config.get_projects_dir("").unwrap();
+    fs::write(projects_dir.join("project1.yml"), "windows: [vim]\n").unwrap();
+    fs::write(projects_dir.join("project2.yml"), "session_name: other\n").unwrap();
+
+    assert_eq!(
+        ui::get_entries(&test_config).unwrap(),
+        vec![
+            ui::Entry {
+                project_name: String::from("project1"),
+                session_name: String::from("project1"),
+                running: false,
+            },
+            ui::Entry {
+                project_name: String::from("project2"),
+                session_name: String::from("other"),
+                running: false,
+            },
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn pick_project_reads_selection_from_picker() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.clone()));
+
+    let projects_dir = test_config.get_projects_dir("").unwrap();
+    fs::write(projects_dir.join("project1.yml"), "").unwrap();
+    fs::write(projects_dir.join("project2.yml"), "").unwrap();
+
+    assert_eq!(
+        list::pick_project(&test_config, "tail -n 1").unwrap(),
+        Some(String::from("project2"))
+    );
+    assert_eq!(list::pick_project(&test_config, "true").unwrap(), None);
+}
+
+#[test]
+fn menu_binds_projects_to_keys() {
+    let test_config = make_config(Some("tmux -L test"), Some(PathBuf::from("/tmp/config")));
+    let projects = vec![String::from("project1"), String::from("my project")];
+
+    let menu_args = menu::get_menu_args(&test_config, "/bin/airmux", "airmux", &projects);
+    assert_eq!(
+        menu_args,
+        vec![
+            "display-menu",
+            "-T",
+            "airmux",
+            "project1",
+            "1",
+            "run-shell '/bin/airmux --config-dir /tmp/config start --switch --command '\"'\"'tmux -L test'\"'\"' project1'",
+            "my project",
+            "2",
+            "run-shell '/bin/airmux --config-dir /tmp/config start --switch --command '\"'\"'tmux -L test'\"'\"' '\"'\"'my project'\"'\"''",
+        ]
+    );
+}
+
+#[test]
+fn menu_leaves_extra_projects_without_a_key() {
+    assert_eq!(menu::get_key(0), "1");
+    assert_eq!(menu::get_key(10), "a");
+    assert_eq!(menu::get_key(100), "");
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn notify_command_never_fails() {
+    assert_eq!(
+        source::notify_command("session project is ready"),
+        "notify-send -a airmux airmux 'session project is ready' >/dev/null 2>&1 || true"
+    );
+}
+
+#[test]
+fn hook_command_wraps_hooks_in_timeout() {
+    let project = Project::default();
+    assert_eq!(
+        source::hook_command(&project, "on_start hook", String::from("sleep 10")).unwrap(),
+        "sleep 10"
+    );
+
+    let project = Project {
+        tmux_command: Some(String::from("tmux")),
+        hook_timeout: Some(5),
+        ..Project::default()
+    };
+    let command =
+        source::hook_command(&project, "on_start hook", String::from("sleep 10")).unwrap();
+    assert!(command.starts_with("timeout 5 sh -c 'sleep 10'; [ $? -ne 124 ] || "));
+    assert!(command.contains("tmux setb -b __airmux_hook_timeout_"));
+    assert!(command.ends_with(" 'on_start hook was killed after 5 seconds'"));
+}
+
+#[test]
+fn hook_command_logs_hooks_to_hook_log() {
+    let project = Project {
+        hook_log: Some(PathBuf::from("/tmp/project.log")),
+        ..Project::default()
+    };
+    assert_eq!(
+        source::hook_command(&project, "on_start hook", String::from("make")).unwrap(),
+        "sh -c make; status=$?; printf '%s %s exited with %s: %s\\n' \
+        \"$(date '+%Y-%m-%d %H:%M:%S')\" 'on_start hook' \"$status\" make >> /tmp/project.log; \
+        (exit $status)"
+    );
+
+    let project = Project {
+        tmux_command: Some(String::from("tmux")),
+        hook_timeout: Some(5),
+        ..project
+    };
+    let command = source::hook_command(&project, "on_start hook", String::from("make")).unwrap();
+    assert!(command.starts_with("timeout 5 sh -c make; status=$?; printf "));
+    assert!(command.contains("; (exit $status); [ $? -ne 124 ] || "));
+}
+
+#[test]
+fn strict_hook_command_flags_failed_hooks() {
+    let project = Project {
+        tmux_command: Some(String::from("tmux")),
+        ..Project::default()
+    };
+    assert_eq!(
+        source::strict_hook_command(&project, "on_start hook", String::from("make")).unwrap(),
+        "make"
+    );
+
+    let project = Project {
+        strict_hooks: true,
+        ..project
+    };
+    assert_eq!(
+        source::strict_hook_command(&project, "on_start hook", String::from("make")).unwrap(),
+        "sh -c make; status=$?; [ $status -eq 0 ] || { tmux setb -b __airmux_hook_failed \
+        'on_start hook failed with exit status '\"$status\"; tmux setenv -g __AIRMUX_HOOK_FAILED 1; }"
+    );
+}
+
+#[test]
+fn preserve_first_window_reuses_the_session_window() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        working_dir: Some(PathBuf::from("/code")),
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+    assert!(source.contains("movew -s project:^ -t project:999999"));
+    assert!(source.contains("killw -t project:999999"));
+
+    let project = Project {
+        preserve_first_window: true,
+        ..project
+    };
+    let source = source::generate(&project, false).unwrap();
+    assert!(!source.contains("999999"));
+    assert!(source
+        .contains("new -s project -d -n __airmux_window_1; set -t project @airmux_windows 1; "));
+    assert!(source.contains("respawnw -k -t "));
+}
+
+#[test]
+fn on_every_start_runs_outside_of_the_first_start_block() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        on_first_start: vec![String::from("echo first")],
+        on_every_start: vec![String::from("echo every")],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+
+    // The first start hook is nested in the session creation, the other runs on its own
+    assert!(source.contains("run '\"'\"'echo first'\"'\"'; set -t project @airmux_created 1'"));
+    assert!(source.contains("set -t project @airmux_attached 1'; run 'echo every'; "));
+}
+
+#[test]
+fn existing_windows_get_their_missing_panes() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        windows: vec![
+            Window::from("echo single"),
+            Window {
+                panes: vec![Pane::default(), Pane::default()],
+                ..Window::default()
+            },
+        ],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+
+    // Only the window with more than one pane can miss any
+    assert_eq!(source.matches("##{window_panes}").count(), 2);
+    assert!(source.contains("-lt 2 ]"));
+    assert!(source.contains("-le 1 ]"));
+}
+
+#[test]
+fn reselect_startup_selects_the_startup_window_of_existing_sessions() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        startup_window: StartupWindow::Index(2),
+        startup_pane: Some(3),
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+    assert!(!source.contains("selectp -t project:2.3"));
+
+    let project = Project {
+        reselect_startup: true,
+        ..project
+    };
+    let source = source::generate(&project, false).unwrap();
+    assert!(source.contains("'selectw -t project:2; selectp -t project:2.3'"));
+}
+
+#[test]
+fn startup_window_is_selected_by_index_when_names_are_shared() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        startup_window: StartupWindow::Name(String::from("editor:1")),
+        windows: vec![
+            Window {
+                name: Some(String::from("editor")),
+                ..Window::default()
+            },
+            Window {
+                name: Some(String::from("editor")),
+                ..Window::default()
+            },
+        ],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+    assert!(source.contains("selectw -t project:1;"));
+    assert!(!source.contains("selectw -t project:editor"));
+}
+
+#[test]
+fn expect_steps_give_up_after_their_timeout() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        windows: vec![Window {
+            panes: vec![Pane {
+                commands: vec![
+                    PaneCommand::Expect {
+                        expect: String::from("Password:"),
+                        send: String::from("secret"),
+                        timeout: None,
+                    },
+                    PaneCommand::Expect {
+                        expect: String::from("$"),
+                        send: String::from("ls"),
+                        timeout: Some(2),
+                    },
+                ],
+                ..Pane::default()
+            }],
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+
+    // Expect steps wait 60 seconds by default, polling 5 times a second
+    assert!(source.contains("[ $polls -lt 300 ] || exit 1"));
+    assert!(source.contains("[ $polls -lt 10 ] || exit 1"));
+    assert!(!source.contains("timeout 60"));
+    assert_eq!(
+        source.matches("setb -b __airmux_expect_timeout_").count(),
+        2
+    );
+}
+
+#[test]
+fn timeout_command_is_only_needed_with_timeouts() {
+    let project = Project {
+        windows: vec![Window::from("make")],
+        ..Project::default()
+    };
+    assert!(!source::uses_timeout_command(&project));
+
+    let project = Project {
+        hook_timeout: Some(10),
+        ..project
+    };
+    assert!(source::uses_timeout_command(&project));
+
+    let project = Project {
+        hook_timeout: None,
+        pane_commands: vec![PaneCommand::Run {
+            command: String::from("make"),
+            retries: 0,
+            timeout: Some(5),
+            raw: false,
+        }],
+        ..project
+    };
+    assert!(source::uses_timeout_command(&project));
+}
+
+#[test]
+fn pane_setup_is_batched_into_a_single_run_per_pane() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        windows: vec![Window {
+            name: Some(String::from("editor")),
+            panes: vec![
+                Pane {
+                    commands: vec![PaneCommand::from("vim")],
+                    ..Pane::default()
+                },
+                Pane {
+                    on_create: vec![String::from("echo created")],
+                    commands: vec![PaneCommand::from("make"), PaneCommand::from("make test")],
+                    post_create: vec![String::from("echo done")],
+                    ..Pane::default()
+                },
+            ],
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+
+    let target_window = "project:=__airmux_window_1";
+    let pane_run = |steps: &str| tmux_join(&["run", "-t", target_window, steps]);
+    let new_window = [
+        tmux_join(&[
+            "if",
+            "tmux lsw -t project -F '##I' | grep -Fx 1",
+            "neww -d -n __airmux_window_1 -t project:",
+            "neww -d -n __airmux_window_1 -t project:1",
+        ]),
+        tmux_join(&["set", "-w", "-t", target_window, "@airmux_window", "1"]),
+        tmux_join(&["set", "-s", "-t", target_window, "pane-base-index", "1"]),
+        pane_run("tmux set -w -t '#D' @airmux_pane_1 '#D'; tmux send -t '#D' vim C-m"),
+        tmux_join(&["splitw", "-h", "-t", target_window]),
+        pane_run(
+            "tmux set -w -t '#D' @airmux_pane_2 '#D'; echo created; \
+            tmux send -t '#D' make C-m; tmux send -t '#D' 'make test' C-m; echo done",
+        ),
+        tmux_join(&["selectp", "-t", &format!("{}.1", target_window)]),
+        tmux_join(&["set", "-wu", "-t", target_window, "@airmux_pane_1"]),
+        tmux_join(&["set", "-wu", "-t", target_window, "@airmux_pane_2"]),
+        tmux_join(&["renamew", "-t", target_window, "editor"]),
+        String::from("set -t project @airmux_updated 1"),
+    ]
+    .join("; ");
+    assert!(source.contains(&tmux_quote(&new_window)));
+}
+
+#[test]
+fn windows_moved_by_hand_are_found_by_their_marker() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        windows: vec![
+            Window::from("vim"),
+            Window {
+                panes: vec![Pane::default(), Pane::default()],
+                ..Window::default()
+            },
+        ],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+
+    // Commands nested in another command's argument have their quotes escaped once more
+    let nested = |command: &str| command.replace('\'', "'\"'\"'");
+
+    // A window is only created when none carries its marker, wherever it was moved
+    assert!(source.contains(&format!(
+        "; {} ",
+        tmux_join(&[
+            "if",
+            "! tmux lsw -t project -F '##{@airmux_window}' | grep -Fx 2"
+        ])
+    )));
+
+    // The panes an existing window is missing are added to the window with the marker
+    let existing_window = "\"$(tmux lsw -t project -F '##{@airmux_window} ##{window_id}' \
+        | awk '$1 == 2 { print $2; exit }')\"";
+    let has_missing_panes = format!(
+        "[ \"$(tmux display -p -t {} '##{{window_panes}}')\" -lt 2 ]",
+        existing_window
+    );
+    assert!(source.contains(&nested(&tmux_join(&["if", &has_missing_panes]))));
+    assert!(source.contains(&nested(&nested(&tmux_join(&[
+        "run",
+        &format!("tmux renamew -t {} __airmux_window_2", existing_window),
+    ])))));
+
+    // Past its creation, the window is only targeted by its temporary name, not by its index
+    assert_eq!(source.matches("project:2").count(), 2);
+    assert!(source.contains("tmux set -w -t project:2 @airmux_window 2 2>/dev/null"));
+    assert!(source.contains(&nested(&tmux_join(&[
+        "neww",
+        "-d",
+        "-n",
+        "__airmux_window_2",
+        "-t",
+        "project:2"
+    ]))));
+}
+
+#[test]
+fn windows_closed_by_hand_are_created_at_the_next_free_index() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        windows: vec![Window::from("vim"), Window::from("make")],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+
+    // The window's index may have been taken by another window since it was closed
+    let create_window = tmux_join(&[
+        "if",
+        "tmux lsw -t project -F '##I' | grep -Fx 2",
+        "neww -d -n __airmux_window_2 -t project:",
+        "neww -d -n __airmux_window_2 -t project:2",
+    ]);
+    let target_window = "project:=__airmux_window_2";
+    let window_setup = [
+        create_window,
+        tmux_join(&["set", "-w", "-t", target_window, "@airmux_window", "2"]),
+        tmux_join(&["set", "-s", "-t", target_window, "pane-base-index", "1"]),
+    ]
+    .join("; ");
+    let nested = |command: &str| command.replace('\'', "'\"'\"'");
+    assert!(source.contains(&nested(&window_setup)));
+}
+
+#[test]
+fn pane_ids_are_captured_in_window_options_to_split_from() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        windows: vec![Window {
+            panes: vec![
+                Pane::default(),
+                Pane::default(),
+                Pane {
+                    split: Some(PaneSplit::Vertical),
+                    split_from: Some(PaneReference::Index(1)),
+                    ..Pane::default()
+                },
+            ],
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+
+    // Each pane saves its own id as soon as it exists, and splits target the saved ids
+    let target_window = "project:=__airmux_window_1";
+    let pane_run = |steps: &str| tmux_join(&["run", "-t", target_window, steps]);
+    let capture = |pane_index: usize| {
+        pane_run(&format!(
+            "tmux set -w -t '#D' @airmux_pane_{} '#D'",
+            pane_index
+        ))
+    };
+    let panes_setup = [
+        capture(1),
+        tmux_join(&["splitw", "-h", "-t", target_window]),
+        capture(2),
+        pane_run(&tmux_join(&[
+            "tmux",
+            "splitw",
+            "-v",
+            "-t",
+            "#{@airmux_pane_1}",
+        ])),
+        capture(3),
+        tmux_join(&["selectp", "-t", &format!("{}.1", target_window)]),
+        tmux_join(&["set", "-wu", "-t", target_window, "@airmux_pane_1"]),
+        tmux_join(&["set", "-wu", "-t", target_window, "@airmux_pane_2"]),
+        tmux_join(&["set", "-wu", "-t", target_window, "@airmux_pane_3"]),
+    ]
+    .join("; ");
+    let nested = |command: &str| command.replace('\'', "'\"'\"'");
+    assert!(source.contains(&nested(&panes_setup)));
+
+    // Existing windows get the ids of the panes they already have saved the same way
+    let existing_panes = format!(
+        "{} | while read index id; do {}; done",
+        tmux_join(&[
+            "tmux",
+            "lsp",
+            "-t",
+            target_window,
+            "-F",
+            "##{pane_index} ##{pane_id}"
+        ]),
+        tmux_join(&[
+            "tmux",
+            "set",
+            "-w",
+            "-t",
+            target_window,
+            "\"@airmux_pane_$index\"",
+            "\"$id\"",
+        ])
+        .replace("'\"@airmux_pane_$index\"'", "\"@airmux_pane_$index\"")
+        .replace("'\"$id\"'", "\"$id\""),
+    );
+    assert!(source.contains(&nested(&nested(&tmux_join(&["run", &existing_panes])))));
+}
+
+#[test]
+fn window_hooks_target_the_window_by_its_id() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        windows: vec![Window {
+            name: Some(String::from("editor")),
+            on_create: vec![String::from("__TMUX__ bind x send -t __WINDOW__ y")],
+            post_create: vec![String::from(
+                "__TMUX__ set-hook -t __WINDOW__ pane-exited kill",
+            )],
+            panes: vec![Pane {
+                on_create: vec![String::from("echo __WINDOW__ __PANE__")],
+                ..Pane::default()
+            }],
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+
+    // The temporary name the window is targeted with is gone once the window is renamed
+    let target_window = "project:=__airmux_window_1";
+    let window_hook = |hook: &str| tmux_join(&["run", "-t", target_window, hook]);
+    let nested = |command: &str| command.replace('\'', "'\"'\"'");
+    assert!(source.contains(&nested(&window_hook("tmux bind x send -t #{window_id} y"))));
+    assert!(source.contains(&nested(&window_hook(
+        "tmux set-hook -t #{window_id} pane-exited kill"
+    ))));
+    assert!(source.contains("echo #{window_id} "));
+    assert!(!source.contains("-t project:=__airmux_window_1 y"));
+}
+
+#[test]
+fn session_state_is_not_kept_in_the_global_environment() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        windows: vec![Window {
+            panes: vec![
+                Pane::default(),
+                Pane {
+                    split_from: Some(PaneReference::Index(1)),
+                    ..Pane::default()
+                },
+            ],
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+    assert!(!source.contains("__RMUX_PANE"));
+    assert!(!source.contains("AIRMUX_PANE"));
+
+    // Whether the session was created, updated or already there is kept on the session,
+    // so concurrent starts of other sessions can't clear it
+    assert!(!source.contains("setenv -g"));
+    assert!(source.contains("set -t project @airmux_created 1"));
+    assert!(source.contains("if -F -t project '#{@airmux_created}' "));
+    assert!(source.ends_with(
+        "set -u -t project @airmux_created; set -u -t project @airmux_updated; \
+        set -u -t project @airmux_attached"
+    ));
+}
+
+#[test]
+fn strict_hooks_skip_the_rest_of_the_source() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        tmux_command: Some(String::from("tmux")),
+        strict_hooks: true,
+        on_start: vec![String::from("make")],
+        ..Project::default()
+    };
+    let source = source::generate(&project, false).unwrap();
+    assert!(source.contains("setenv -gu __AIRMUX_HOOK_FAILED; run "));
+    assert!(source.contains("; if -F '#{?__AIRMUX_HOOK_FAILED,0,1}' 'if "));
+    assert!(!source.contains("kill-session"));
+
+    // The session is only killed when it was created by the source
+    let project = Project {
+        on_first_start: vec![String::from("make")],
+        ..project
+    };
+    let source = source::generate(&project, false).unwrap();
+    let teardown = tmux_join(&[
+        "if",
+        "-F",
+        "-t",
+        "project",
+        "#{@airmux_created}",
+        "kill-session -t project",
+    ]);
+    let guard = tmux_join(&["if", "-F", "#{__AIRMUX_HOOK_FAILED}", &teardown]);
+    assert!(source.contains(&guard.replace('\'', "'\"'\"'")));
+}
+
+#[test]
+fn attach_command_is_printed_with_the_project_tmux_command() {
+    let project = Project {
+        session_name: Some(String::from("my project")),
+        tmux_command: Some(String::from("tmux")),
+        tmux_socket: Some(String::from("work")),
+        ..Project::default()
+    };
+    assert_eq!(
+        source::attach_command(&project, true, false).unwrap(),
+        "tmux -L work switch-client -t 'my project'"
+    );
+}
+
+#[test]
+fn attach_args_use_control_mode_only_for_new_clients() {
+    assert_eq!(
+        source::attach_args("project", false, true),
+        ["-CC", "attach-session", "-t", "project"]
+    );
+    assert_eq!(
+        source::attach_args("project", true, true),
+        ["switch-client", "-t", "project"]
+    );
+    assert_eq!(
+        source::attach_args("project", false, false),
+        ["attach-session", "-t", "project"]
+    );
+}
+
+#[test]
+fn attach_command_can_be_overridden() {
+    let project = Project {
+        session_name: Some(String::from("my project")),
+        tmux_command: Some(String::from("tmux")),
+        attach_command: Some(String::from("__TMUX__ -CC attach -t __SESSION__")),
+        ..Project::default()
+    };
+    assert_eq!(
+        source::attach_command(&project, false, false).unwrap(),
+        "tmux -CC attach -t 'my project'"
+    );
+}
+
+#[test]
+fn shell_hook_command_uses_login_shell() {
+    let project = Project::default();
+    assert_eq!(
+        source::shell_hook_command(&project, String::from("nvm use")),
+        "nvm use"
+    );
+
+    let project = Project {
+        hook_login_shell: true,
+        ..Project::default()
+    };
+    assert_eq!(
+        source::shell_hook_command(&project, String::from("nvm use")),
+        r#""${SHELL:-/bin/sh}" -lc 'nvm use'"#
+    );
+}
+
+#[test]
+fn profile_reports_steps_and_total() {
+    let mut enabled = profile::Profile::new(true);
+    enabled.step("load");
+    enabled.step("check");
+
+    let report = enabled.report();
+    let steps: Vec<&str> = report
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect();
+    assert_eq!(steps, vec!["load", "check", "total"]);
+
+    let mut disabled = profile::Profile::new(false);
+    disabled.step("load");
+    assert_eq!(disabled.report().lines().count(), 1);
+}
+
+#[test]
+fn source_hash_changes_with_source() {
+    assert_eq!(source::get_hash("new -s a"), source::get_hash("new -s a"));
+    assert_ne!(source::get_hash("new -s a"), source::get_hash("new -s b"));
+
+    let project = Project {
+        session_name: Some(String::from("my session")),
+        ..Project::default()
+    };
+    assert_eq!(
+        source::with_hash(&project, "new -s a", "0123"),
+        "new -s a; setenv -t 'my session' __AIRMUX_SOURCE_HASH 0123"
+    );
+}
+
+#[test]
+fn unchanged_sessions_are_sourced_again_to_reorder_windows() {
+    assert!(!source::runs_on_every_start(&Project::default()));
+
+    let project = Project {
+        reorder_windows: true,
+        ..Project::default()
+    };
+    assert!(source::runs_on_every_start(&project));
+
+    let project = Project {
+        on_every_start: vec![String::from("echo started")],
+        ..Project::default()
+    };
+    assert!(source::runs_on_every_start(&project));
+}
+
+#[test]
+fn import_parses_resurrect_save_files() {
+    let content = [
+        "pane\twork\t1\t1\t:*\t1\thost\t:/src\t0\tvim\t:vim README.md",
+        "pane\twork\t1\t1\t:*\t2\thost\t:/src\t1\tbash\t:",
+        "pane\twork\t2\t0\t:-\t1\thost\t:/tmp\t1\tbash\t:",
+        "window\twork\t1\t:editor\t1\t:*\tc5d1,200x50,0,0{100x50,0,0,1,99x50,101,0,2}\t:",
+        "window\twork\t2\t:logs\t0\t:-\tb25d,200x50,0,0,3\toff",
+        "state\twork\t",
+    ]
+    .join("\n");
+
+    let projects = import::parse_resurrect(&content);
+    assert_eq!(projects.len(), 1);
+
+    let project = &projects[0];
+    assert_eq!(project.session_name, Some(String::from("work")));
+    assert_eq!(project.working_dir, Some(PathBuf::from("/src")));
+    assert_eq!(project.startup_window, StartupWindow::Default);
+    assert_eq!(project.startup_pane, Some(2));
+    assert_eq!(project.windows.len(), 2);
+
+    let window = &project.windows[0];
+    assert_eq!(window.name, Some(String::from("editor")));
+    assert_eq!(
+        window.layout,
+        Some(String::from("c5d1,200x50,0,0{100x50,0,0,1,99x50,101,0,2}"))
+    );
+    assert_eq!(window.working_dir, None);
+    assert_eq!(window.panes.len(), 2);
+    assert_eq!(
+        window.panes[0].commands,
+        vec![PaneCommand::from("vim README.md")]
+    );
+    assert!(window.panes[1].commands.is_empty());
+
+    let window = &project.windows[1];
+    assert_eq!(window.name, Some(String::from("logs")));
+    assert_eq!(window.working_dir, Some(PathBuf::from("/tmp")));
+}
+
+#[test]
+fn imported_projects_survive_a_round_trip() {
+    let content = [
+        "pane\twork\t1\t1\t:*\t1\thost\t:/src\t0\tvim\t:vim README.md",
+        "pane\twork\t1\t1\t:*\t2\thost\t:/src\t1\tbash\t:",
+        "pane\twork\t2\t0\t:-\t1\thost\t:/tmp\t1\tbash\t:",
+        "window\twork\t1\t:editor\t1\t:*\tc5d1,200x50,0,0{100x50,0,0,1,99x50,101,0,2}\t:",
+        "window\twork\t2\t:logs\t0\t:-\tb25d,200x50,0,0,3\toff",
+    ]
+    .join("\n");
+
+    for project in import::parse_resurrect(&content) {
+        for format in &[FileFormat::Yaml, FileFormat::Json] {
+            let content = project.serialize_compact(format).unwrap();
+            verify_round_trip(&project, &content).unwrap();
+        }
+    }
+}
+
+#[test]
+fn freeze_adds_comments_to_frozen_projects() {
+    let content = "---\nsession_name: work\non_start: make\nwindows:\n  - editor: vim";
+    let output = freeze::add_comments(content);
+
+    let project: Project = serde_yaml::from_str(&output).unwrap();
+    assert_eq!(project, serde_yaml::from_str(content).unwrap());
+
+    let lines: Vec<&str> = output.lines().collect();
+    let windows = lines.iter().position(|line| *line == "windows:").unwrap();
+    let on_stop = lines.iter().position(|line| *line == "# on_stop:").unwrap();
+    assert!(on_stop < windows);
+    assert!(!lines.contains(&"# on_start:"));
+    assert!(lines.contains(&"on_start: make"));
+
+    let session_name = lines
+        .iter()
+        .position(|line| *line == "session_name: work")
+        .unwrap();
+    assert!(lines[session_name - 1].starts_with("# "));
+}
+
+#[test]
+fn verify_round_trip_reports_dropped_fields() {
+    let project = Project {
+        session_name: Some(String::from("work")),
+        startup_pane: Some(2),
+        ..Project::default()
+    };
+
+    let result = verify_round_trip(&project, "session_name: work");
+    assert!(matches!(
+        result.err().unwrap().downcast_ref::<Error>().unwrap(),
+        Error::RoundTripFailed { fields } if fields == &[String::from("startup_pane")]
+    ));
+}
+
+#[test]
+fn import_parses_legacy_resurrect_save_files() {
+    let content = [
+        "pane\tlogs\t0\t:main\t1\t:*\t0\t:/var/log\t1\ttail\t:tail -f syslog",
+        "pane\tlogs\t0\t:main\t1\t:*\t1\t:/var/log\t0\tbash\t:",
+        "window\tlogs\t0\t:main\t1\t:*\tb25d,200x50,0,0,3",
+    ]
+    .join("\n");
+
+    let projects = import::parse_resurrect(&content);
+    assert_eq!(projects.len(), 1);
+
+    let project = &projects[0];
+    assert_eq!(project.session_name, Some(String::from("logs")));
+    assert_eq!(project.window_base_index, 0);
+    assert_eq!(project.pane_base_index, 0);
+    assert_eq!(project.startup_pane, None);
+    assert_eq!(project.working_dir, Some(PathBuf::from("/var/log")));
+    assert_eq!(project.windows[0].panes.len(), 2);
+    assert_eq!(
+        project.windows[0].panes[0].commands,
+        vec![PaneCommand::from("tail -f syslog")]
+    );
+}
+
+#[test]
+fn import_projects_fails_when_session_is_not_in_save_file() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.to_owned()));
+
+    let save_file = temp_dir.join("last");
+    fs::write(
+        &save_file,
+        "pane\twork\t1\t1\t:*\t1\thost\t:/src\t1\tbash\t:",
+    )
+    .unwrap();
+
+    let result = import_projects(
+        &test_config,
+        Some(&save_file.to_string_lossy()),
+        Some("other"),
+        None,
+        false,
+        true,
+        false,
+    );
+
+    assert!(matches!(
+        result.err().unwrap().downcast_ref::<Error>().unwrap(),
+        Error::ImportSessionNotFound { session_name, .. } if session_name == "other"
+    ));
+}
+
+#[test]
+fn project_has_flag_reads_flags_from_project_files() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.to_owned()));
+
+    fs::write(
+        temp_dir.join("marked.yml"),
+        "keep_alive: true\nautostart: true",
+    )
+    .unwrap();
+    fs::write(temp_dir.join("unmarked.yml"), "windows: [editor]").unwrap();
+    fs::write(temp_dir.join("broken.yml"), "keep_alive: [").unwrap();
+
+    for flag in &["keep_alive", "autostart"] {
+        assert!(project::has_flag(&test_config, "marked", flag));
+        assert!(!project::has_flag(&test_config, "unmarked", flag));
+        assert!(!project::has_flag(&test_config, "broken", flag));
+        assert!(!project::has_flag(&test_config, "missing", flag));
+    }
+}
+
+#[test]
+fn service_starts_project_without_attaching() {
+    let test_config = make_config(Some("tmux -L test"), Some(PathBuf::from("/config")));
+
+    let command = service::get_command(&test_config, "/bin/airmux", "start", "work/api");
+    assert_eq!(
+        command,
+        vec![
+            "/bin/airmux",
+            "--config-dir",
+            "/config",
+            "start",
+            "--command",
+            "tmux -L test",
+            "--no-attach",
+            "--no-input",
+            "work/api",
+        ]
+    );
+    assert_eq!(service::get_label("work/api"), "airmux-work-api");
+
+    let kill_command = service::get_command(&test_config, "/bin/airmux", "kill", "work/api");
+    let unit = service::get_systemd_unit("work/api", &command, &kill_command);
+    assert!(unit.contains(
+        "ExecStart=/bin/airmux --config-dir /config start --command 'tmux -L test' --no-attach --no-input work/api\n"
+    ));
+    assert!(unit.contains(
+        "ExecStop=-/bin/airmux --config-dir /config kill --command 'tmux -L test' work/api\n"
+    ));
+    assert!(unit.contains("WantedBy=default.target\n"));
+}
+
+#[test]
+fn service_escapes_launchd_plist_values() {
+    let command = vec![String::from("/bin/airmux"), String::from("a<b&c")];
+    let plist = service::get_launchd_plist("project", &command);
+
+    assert!(plist.contains("    <string>airmux-project</string>\n"));
+    assert!(plist.contains(
+        "    <array>\n        <string>/bin/airmux</string>\n        <string>a&lt;b&amp;c</string>\n    </array>\n"
+    ));
+}
+
+#[test]
+fn dependencies_are_resolved_in_start_order() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.to_owned()));
+
+    fs::write(temp_dir.join("cache.yml"), "depends_on: db").unwrap();
+    fs::write(temp_dir.join("db.yml"), "windows: [psql]").unwrap();
+
+    let project = Project {
+        depends_on: vec![Dependency::from("cache"), Dependency::from("db")],
+        ..Project::default()
+    };
+
+    let dependencies = dependencies::resolve(&test_config, "app", &project).unwrap();
+    let names: Vec<&str> = dependencies
+        .iter()
+        .map(|(dependency, _)| dependency.project.as_str())
+        .collect();
+    assert_eq!(names, vec!["db", "cache"]);
+}
+
+#[test]
+fn dependencies_fail_on_cycles() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+    let test_config = make_config(None, Some(temp_dir.to_owned()));
+
+    fs::write(temp_dir.join("api.yml"), "depends_on: db").unwrap();
+    fs::write(temp_dir.join("db.yml"), "depends_on: api").unwrap();
+
+    let project = Project {
+        depends_on: vec![Dependency::from("api")],
+        ..Project::default()
+    };
+
+    let result = dependencies::resolve(&test_config, "app", &project);
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "depends_on: dependency cycle between projects \"api\" -> \"db\" -> \"api\""
+    );
+}
+
+#[test]
+fn merge_projects_prefixes_windows_with_their_project() {
+    let window = |name: Option<&str>| Window {
+        name: name.map(String::from),
+        ..Window::default()
+    };
+
+    let first = Project {
+        working_dir: Some(PathBuf::from("/first")),
+        startup_window: StartupWindow::Name(String::from("editor")),
+        on_start: vec![String::from("echo first")],
+        windows: vec![window(Some("editor"))],
+        ..Project::default()
+    };
+    let second = Project {
+        working_dir: Some(PathBuf::from("/second")),
+        pane_base_index: 0,
+        pane_commands: vec![PaneCommand::from("source .env")],
+        on_start: vec![String::from("echo second")],
+        windows: vec![
+            window(None),
+            Window {
+                panes: vec![
+                    Pane::default(),
+                    Pane {
+                        split_from: Some(PaneReference::Index(0)),
+                        ..Pane::default()
+                    },
+                ],
+                ..window(Some("logs"))
+            },
+        ],
+        ..Project::default()
+    };
+
+    let merged = merge::merge_projects(vec![
+        (String::from("first"), first),
+        (String::from("second"), second),
+    ])
+    .unwrap();
+
+    let names: Vec<Option<&str>> = merged
+        .windows
+        .iter()
+        .map(|window| window.name.as_deref())
+        .collect();
+    assert_eq!(
+        names,
+        vec![Some("first/editor"), Some("second"), Some("second/logs")]
+    );
+    assert_eq!(
+        merged.startup_window,
+        StartupWindow::Name(String::from("first/editor"))
+    );
+    assert_eq!(merged.on_start, vec!["echo first", "echo second"]);
+    assert!(merged.pane_commands.is_empty());
+
+    assert_eq!(merged.windows[0].working_dir, Some(PathBuf::from("/first")));
+    assert!(merged.windows[0].pane_commands.is_empty());
+    assert_eq!(
+        merged.windows[1].working_dir,
+        Some(PathBuf::from("/second"))
+    );
+    assert_eq!(
+        merged.windows[1].pane_commands,
+        vec![PaneCommand::from("source .env")]
+    );
+    assert_eq!(
+        merged.windows[2].panes[1].split_from,
+        Some(PaneReference::Index(1))
+    );
+}
+
+#[test]
+fn backend_renders_projects_for_other_terminals() {
+    let project = Project {
+        session_name: Some(String::from("demo")),
+        working_dir: Some(PathBuf::from("/code")),
+        windows: vec![Window {
+            name: Some(String::from("editor")),
+            panes: vec![
+                Pane {
+                    commands: vec![PaneCommand::from("vim")],
+                    ..Pane::default()
+                },
+                Pane {
+                    split: Some(PaneSplit::Vertical),
+                    split_size: Some(String::from("30%")),
+                    ..Pane::default()
+                },
+            ],
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+
+    let script = backend::render(&project, "wezterm").unwrap();
+    assert!(script.contains("pane_1_1=$(wezterm cli spawn --new-window --cwd /code)\n"));
+    assert!(script.contains("wezterm cli set-tab-title --pane-id \"$pane_1_1\" editor\n"));
+    assert!(script.contains("send \"$pane_1_1\" vim\n"));
+    assert!(script.contains(
+        "pane_1_2=$(wezterm cli split-pane --pane-id \"$pane_1_1\" --bottom --percent 30 --cwd /code)\n"
+    ));
+
+    let session = backend::render(&project, "kitty").unwrap();
+    assert!(session.ends_with(
+        "new_tab editor\nlayout splits\n\
+        launch --cwd /code sh -c 'vim; exec \"${SHELL:-/bin/sh}\"'\n\
+        launch --location hsplit --bias 30 --cwd /code\n"
+    ));
+
+    assert!(matches!(
+        backend::render(&project, "alacritty")
+            .err()
+            .unwrap()
+            .downcast_ref::<Error>()
+            .unwrap(),
+        Error::UnsupportedBackend { .. }
+    ));
+}
+
+#[test]
+fn lsp_reads_and_writes_messages() {
+    let message = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "shutdown" });
+
+    let mut buffer = vec![];
+    lsp::write_message(&mut buffer, &message).unwrap();
+    assert!(buffer.starts_with(b"Content-Length: "));
+
+    let mut reader = io::BufReader::new(&buffer[..]);
+    let content = lsp::read_content(&mut reader).unwrap().unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&content).unwrap(),
+        message
+    );
+    assert_eq!(lsp::read_content(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn lsp_answers_malformed_messages_with_a_parse_error() {
+    let config = make_config(None, None);
+
+    let mut input = b"Content-Length: 9\r\n\r\n{\"bogus\":".to_vec();
+    let message = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "shutdown" });
+    lsp::write_message(&mut input, &message).unwrap();
+
+    let mut output = vec![];
+    lsp::serve(&config, &mut io::BufReader::new(&input[..]), &mut output).unwrap();
+
+    let mut reader = io::BufReader::new(&output[..]);
+    let mut read_reply = || {
+        let content = lsp::read_content(&mut reader).unwrap().unwrap();
+        serde_json::from_slice::<serde_json::Value>(&content).unwrap()
+    };
+
+    let reply = read_reply();
+    assert_eq!(reply["id"], serde_json::Value::Null);
+    assert_eq!(reply["error"]["code"], -32700);
+
+    let reply = read_reply();
+    assert_eq!(reply["id"], 1);
+    assert_eq!(reply["result"], serde_json::Value::Null);
+}
+
+#[test]
+fn lsp_publishes_diagnostics_with_locations() {
+    let config = make_config(None, None);
+    let mut server = lsp::Server::new(&config);
+
+    let replies = server.handle(&serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {
+            "textDocument": {
+                "uri": "file:///projects/project.yml",
+                "text": "windows:\n  - editor\nbogus: 1\n",
+            },
+        },
+    }));
+
+    let diagnostics = &replies[0]["params"]["diagnostics"];
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+    assert!(diagnostics[0]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("unknown field `bogus`"));
+
+    let replies = server.handle(&serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {
+            "textDocument": { "uri": "file:///projects/project.yml" },
+            "contentChanges": [{ "text": "windows:\n  - editor\n" }],
+        },
+    }));
+    assert_eq!(replies[0]["params"]["diagnostics"], serde_json::json!([]));
+}
+
+#[test]
+fn lsp_finds_field_context() {
+    let text = [
+        "windows:",
+        "  - editor:",
+        "      layout: tiled",
+        "      panes:",
+        "        - working_dir: /tmp",
+        "          ",
+        "name: project",
+    ]
+    .join("\n");
+
+    assert_eq!(lsp::context(&text, 0), lsp::Context::Project);
+    assert_eq!(lsp::context(&text, 2), lsp::Context::Window);
+    assert_eq!(lsp::context(&text, 3), lsp::Context::Window);
+    assert_eq!(lsp::context(&text, 5), lsp::Context::Pane);
+    assert_eq!(lsp::context(&text, 6), lsp::Context::Project);
+}
+
+#[test]
+fn lsp_reads_field_docs_from_the_reference_table() {
+    let fields = lsp::reference(lsp::Context::Pane);
+    let (_, doc) = fields.iter().find(|(field, _)| field == "zoom").unwrap();
+    assert!(!doc.is_empty());
+
+    let fields = lsp::reference(lsp::Context::Window);
+    assert!(fields.iter().any(|(field, _)| field == "panes"));
+    assert!(!fields.iter().any(|(field, _)| field == "split_size"));
+
+    let fields = lsp::reference(lsp::Context::Project);
+    assert!(fields.iter().any(|(field, _)| field == "windows"));
+    assert!(fields.iter().any(|(field, _)| field == "hook_timeout"));
+}
+
+#[test]
+fn lsp_hovers_field_names() {
+    let text = "windows:\n  - editor:\n      layout: tiled\n";
+
+    let hover = lsp::hover(text, 2, 8);
+    assert!(hover["contents"]["value"]
+        .as_str()
+        .unwrap()
+        .starts_with("**layout**\n\n"));
+
+    assert_eq!(lsp::hover(text, 2, 16), serde_json::Value::Null);
+}
+
+#[test]
+fn lsp_decodes_file_uris() {
+    assert_eq!(
+        lsp::uri_to_path("file:///home/me/my%20project/.airmux.yml"),
+        PathBuf::from("/home/me/my project/.airmux.yml")
+    );
+}
+
+#[test]
+fn frozen_sessions_keep_the_commands_and_hooks_of_the_project_file() {
+    let yaml = r#"
+        name: project
+        on_first_start: docker-compose up -d
+        windows:
+          - editor:
+              layout: main-vertical
+              panes:
+                - vim
+                - ${LOGS_COMMAND:-tail -f log/development.log}
+          - server: make run
+    "#;
+    let project_file = Path::new("project.yml");
+    let project = autofreeze::parse_unexpanded(project_file, yaml).unwrap();
+
+    // The editor window got a third pane, and the server window was renamed by its command
+    let layout = "1b2c,160x40,0,0{80x40,0,0,1,79x40,81,0[79x20,81,0,2,79x19,81,21,3]}";
+    let frozen = Project {
+        session_name: Some(String::from("project")),
+        working_dir: Some(PathBuf::from("/code")),
+        windows: vec![
+            Window {
+                name: Some(String::from("editor")),
+                layout: Some(String::from(layout)),
+                panes: vec![Pane::default(), Pane::default(), Pane::default()],
+                ..Window::default()
+            },
+            Window {
+                layout: Some(String::from("a0b1,160x40,0,0,4")),
+                panes: vec![Pane::default()],
+                ..Window::default()
+            },
+        ],
+        ..Project::default()
+    };
+
+    let merged = autofreeze::merge_frozen(project.to_owned(), frozen.to_owned());
+    let content = merged.serialize_compact(&FileFormat::Yaml).unwrap();
+    assert!(content.contains("${LOGS_COMMAND:-tail -f log/development.log}"));
+
+    let frozen_project = autofreeze::parse_unexpanded(project_file, &content).unwrap();
+    assert_eq!(frozen_project, merged);
+    assert_eq!(frozen_project.on_first_start, project.on_first_start);
+    assert_eq!(frozen_project.working_dir, Some(PathBuf::from("/code")));
+
+    let editor = &frozen_project.windows[0];
+    assert_eq!(editor.layout, Some(String::from(layout)));
+    assert_eq!(editor.panes.len(), 3);
+    assert_eq!(
+        editor.panes[0].commands,
+        project.windows[0].panes[0].commands
+    );
+    assert_eq!(
+        editor.panes[1].commands,
+        project.windows[0].panes[1].commands
+    );
+    assert!(editor.panes[2].commands.is_empty());
+
+    let server = &frozen_project.windows[1];
+    assert_eq!(server.name, Some(String::from("server")));
+    assert_eq!(
+        server.panes[0].commands,
+        project.windows[1].panes[0].commands
+    );
+
+    // Nothing changes when the session still looks like the project file
+    assert_eq!(
+        autofreeze::merge_frozen(frozen_project.to_owned(), frozen),
+        frozen_project
+    );
+}
+
+#[test]
+fn frozen_sessions_are_not_merged_into_files_with_several_sessions() {
+    let yaml = r#"
+        sessions:
+          - name: frontend
+          - name: backend
+    "#;
+
+    let result = autofreeze::parse_unexpanded(Path::new("project.yml"), yaml);
+    assert!(result.is_err());
+    assert!(matches!(
+        result.err().unwrap().downcast_ref::<Error>().unwrap(),
+        Error::CannotFreezeInto { .. }
+    ));
+}
+
+#[test]
+fn frozen_sessions_replace_their_entry_in_files_with_several_sessions() {
+    let yaml = "sessions:\n  - name: frontend\n    windows: [vim]\n  - name: backend\n    windows: [make run]\n";
+    let project_file = Path::new("project.yml");
+
+    let frozen = Project {
+        session_name: Some(String::from("backend")),
+        windows: vec![Window {
+            name: Some(String::from("logs")),
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+    let content =
+        autofreeze::replace_session(project_file, yaml, &frozen, &FileFormat::Yaml).unwrap();
+
+    let sessions = project::get_sessions(&content).unwrap().unwrap();
+    assert_eq!(
+        sessions[0].1,
+        project::get_sessions(yaml).unwrap().unwrap()[0].1
+    );
+    let backend = serde_yaml::from_value::<Project>(sessions[1].1.to_owned()).unwrap();
+    assert_eq!(backend.windows[0].name, Some(String::from("logs")));
+
+    let frozen = Project {
+        session_name: Some(String::from("db")),
+        ..Project::default()
+    };
+    let result = autofreeze::replace_session(project_file, yaml, &frozen, &FileFormat::Yaml);
+    assert!(matches!(
+        result.err().unwrap().downcast_ref::<Error>().unwrap(),
+        Error::CannotFreezeInto { .. }
+    ));
+}
+
+#[test]
+fn autofreeze_only_applies_to_projects_that_opt_in() {
+    assert!(project::autofreezes(
+        "autofreeze: true\nwindows:\n  - vim\n"
+    ));
+    assert!(!project::autofreezes("autofreeze: false\n"));
+    assert!(!project::autofreezes("windows:\n  - vim\n"));
+    assert!(!project::autofreezes("{{ not yaml"));
+}
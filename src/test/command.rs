@@ -13,3 +13,174 @@ fn process_command_removes_line_carriages() {
 
     assert_eq!(result, "hello  world  ")
 }
+
+#[test]
+fn pane_command_deserializes_from_string() {
+    let yaml = r#"
+        "echo #hello"
+    "#;
+
+    let command: PaneCommand = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(command, PaneCommand::from("echo ##hello"));
+}
+
+#[test]
+fn pane_command_deserializes_retries_and_timeout() {
+    let yaml = r#"
+        command: apt-get update
+        retries: 3
+        timeout: 60
+    "#;
+
+    let command: PaneCommand = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        command,
+        PaneCommand::Run {
+            command: String::from("apt-get update"),
+            retries: 3,
+            timeout: Some(60),
+            raw: false,
+        }
+    );
+}
+
+#[test]
+fn pane_command_shell_command_is_unchanged_when_simple() {
+    let command = PaneCommand::from("echo hello");
+
+    assert_eq!(command.shell_command().unwrap(), "echo hello");
+}
+
+#[test]
+fn pane_command_shell_command_wraps_timeout_and_retries() {
+    let command = PaneCommand::Run {
+        command: String::from("curl http://localhost"),
+        retries: 2,
+        timeout: Some(5),
+        raw: false,
+    };
+
+    assert_eq!(
+        command.shell_command().unwrap(),
+        "for __airmux_attempt in 1 2 3; do \
+            timeout 5 sh -c 'curl http://localhost' && break; sleep 1; \
+        done"
+    );
+}
+
+#[test]
+fn pane_command_deserializes_sleep() {
+    let yaml = r#"
+        sleep: 1.5
+    "#;
+
+    let command: PaneCommand = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(command, PaneCommand::Sleep(1.5));
+    assert_eq!(command.shell_command(), None);
+}
+
+#[test]
+fn pane_command_list_deserializes_sleep_between_commands() {
+    #[derive(Deserialize)]
+    struct Test {
+        #[serde(deserialize_with = "de_pane_command_list")]
+        commands: Vec<PaneCommand>,
+    }
+
+    let yaml = r#"
+        commands:
+          - docker-compose up -d
+          - sleep: 2
+          - docker-compose logs -f
+    "#;
+
+    let test: Test = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        test.commands,
+        vec![
+            PaneCommand::from("docker-compose up -d"),
+            PaneCommand::Sleep(2.0),
+            PaneCommand::from("docker-compose logs -f"),
+        ]
+    );
+}
+
+#[test]
+fn pane_command_raises_error_on_negative_sleep() {
+    let yaml = r#"
+        sleep: -1
+    "#;
+
+    let result = serde_yaml::from_str::<PaneCommand>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("sleep duration cannot be negative"));
+}
+
+#[test]
+fn pane_command_sleep_serializes_as_a_map() {
+    let yaml = serde_yaml::to_string(&PaneCommand::Sleep(2.0)).unwrap();
+
+    assert_eq!(yaml.trim_start_matches("---\n").trim(), "sleep: 2.0");
+}
+
+#[test]
+fn pane_command_deserializes_expect() {
+    let yaml = r##"
+        expect: "Password:"
+        send: "#secret"
+        timeout: 10
+    "##;
+
+    let command: PaneCommand = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        command,
+        PaneCommand::Expect {
+            expect: String::from("Password:"),
+            send: String::from("##secret"),
+            timeout: Some(10),
+        }
+    );
+    assert!(!command.is_simple());
+}
+
+#[test]
+fn pane_command_raises_error_on_expect_without_send() {
+    let yaml = r#"
+        expect: "Password:"
+    "#;
+
+    let result = serde_yaml::from_str::<PaneCommand>(yaml);
+    assert!(result.is_err());
+}
+
+#[test]
+fn pane_command_raw_skips_escaping_pounds() {
+    let yaml = r##"
+        command: "echo #{pane_id} # comment"
+        raw: true
+    "##;
+
+    let command: PaneCommand = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        command,
+        PaneCommand::Run {
+            command: String::from("echo #{pane_id} # comment"),
+            retries: 0,
+            timeout: None,
+            raw: true,
+        }
+    );
+    assert!(!command.is_simple());
+}
+
+#[test]
+fn pane_command_into_raw_reverts_escaping_once() {
+    let command = PaneCommand::from(process_command(String::from("echo ## #")));
+
+    let command = command.into_raw().into_raw();
+    assert_eq!(command.shell_command().unwrap(), "echo ## #");
+}
@@ -17,7 +17,15 @@ fn make_config(
         app_name: app_name.unwrap_or(APP_NAME),
         app_author: app_author.unwrap_or(APP_AUTHOR),
         tmux_command: Some(String::from(tmux_command.unwrap_or("tmux"))),
+        tmux_socket: None,
+        tmux_options: None,
         config_dir,
+        lenient: false,
+        picker: None,
+        profile: false,
+        session_prefix: None,
+        editor_args: vec![],
+        file_extensions: vec![],
     }
 }
 
@@ -178,3 +186,28 @@ fn get_tmux_command_splits_commands_correctly() {
         ],
     );
 }
+
+#[test]
+fn get_data_dir_uses_config_dir_when_set() {
+    let temp_dir = tempdir().unwrap();
+    let temp_dir = temp_dir.path().to_path_buf();
+
+    let subdir = "my_subdir";
+    let expected_path = temp_dir.join(subdir);
+    let test_config = make_config(None, None, None, Some(temp_dir));
+
+    let result = test_config.get_data_dir(subdir).unwrap();
+    assert_eq!(expected_path, result);
+    assert!(result.is_dir());
+}
+
+#[test]
+fn get_file_extensions_falls_back_to_the_defaults() {
+    let mut test_config = make_config(None, None, None, None);
+    assert_eq!(test_config.get_file_extensions(), FILE_EXTENSIONS);
+    assert!(test_config.is_supported_extension("JSON"));
+
+    test_config.file_extensions = vec![String::from("yml")];
+    assert_eq!(test_config.get_file_extensions(), vec!["yml"]);
+    assert!(!test_config.is_supported_extension("json"));
+}
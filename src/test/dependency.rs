@@ -0,0 +1,66 @@
+use super::*;
+
+#[test]
+fn dependency_deserializes_from_string() {
+    let yaml = r#"
+        infra
+    "#;
+
+    let dependency: Dependency = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(dependency, Dependency::from("infra"));
+    assert!(!dependency.has_readiness_gate());
+}
+
+#[test]
+fn dependency_deserializes_readiness_gates() {
+    let yaml = r#"
+        project: infra
+        window: db
+        ready: pg_isready
+        timeout: 60
+    "#;
+
+    let dependency: Dependency = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        dependency,
+        Dependency {
+            project: String::from("infra"),
+            window: Some(String::from("db")),
+            ready: Some(String::from("pg_isready")),
+            timeout: Some(60),
+        }
+    );
+    assert!(dependency.has_readiness_gate());
+}
+
+#[test]
+fn dependency_fails_without_a_project() {
+    let yaml = r#"
+        window: db
+    "#;
+
+    let result = serde_yaml::from_str::<Dependency>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("invalid dependency definition: expected a `project` field"));
+}
+
+#[test]
+fn dependency_serializes_to_string_when_simple() {
+    let dependency = Dependency::from("infra");
+    assert_eq!(serde_yaml::to_string(&dependency).unwrap(), "---\ninfra");
+
+    let dependency = Dependency {
+        project: String::from("infra"),
+        window: Some(String::from("db")),
+        ready: None,
+        timeout: None,
+    };
+    assert_eq!(
+        serde_yaml::to_string(&dependency).unwrap(),
+        "---\nproject: infra\nwindow: db"
+    );
+}
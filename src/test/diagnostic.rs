@@ -0,0 +1,33 @@
+use super::*;
+use crate::project::Project;
+
+#[test]
+fn diagnostic_from_yaml_error_has_location_and_snippet() {
+    let source = "windows:\n  - panes:\n      - split_size: [42]\n";
+    let error = serde_yaml::from_str::<Project>(source).err().unwrap();
+
+    let diagnostic = Diagnostic::from_yaml_error("project.yml", source, error);
+    assert!(diagnostic.line.is_some());
+    assert!(diagnostic.column.is_some());
+    assert!(diagnostic.snippet.is_some());
+    assert!(!diagnostic.message.contains(" at line "));
+    assert!(diagnostic.hint.is_some());
+}
+
+#[test]
+fn diagnostic_displays_snippet_with_caret() {
+    let diagnostic = Diagnostic::new("project.yml", String::from("unknown field `nmae`"))
+        .with_location("windows:\n  - nmae: editor\n", 2, 5);
+
+    assert_eq!(
+        diagnostic.to_string(),
+        "project.yml:2:5: unknown field `nmae`\n  |\n2 |   - nmae: editor\n  |     ^\n  = hint: check the field name for typos"
+    );
+}
+
+#[test]
+fn diagnostic_displays_stdin_for_empty_path() {
+    let diagnostic = Diagnostic::new(PathBuf::new(), String::from("something went wrong"));
+
+    assert_eq!(diagnostic.to_string(), "<stdin>: something went wrong");
+}
@@ -93,9 +93,14 @@ fn pane_1st_form_deserializes_correctly() {
         split_from: 1
         split_size: 42%
         clear: true
+        zoom: true
+        mark: true
+        style: bg=colour236
+        shell: fish
         on_create: echo on_create
         post_create: echo post_create
         command: echo command
+        join_commands: "&&"
         send_keys: echo send_keys
     "#;
 
@@ -106,13 +111,20 @@ fn pane_1st_form_deserializes_correctly() {
             name: Some(String::from("pane name")),
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Vertical),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: true,
+            mark: true,
+            style: Some(String::from("bg=colour236")),
+            shell: Some(String::from("fish")),
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
-            send_keys: vec![String::from("echo send_keys")]
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: Some(String::from("&&")),
+            send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -156,11 +168,9 @@ fn pane_1st_form_raises_error_on_invalid_split_size_value() {
 
     let result = serde_yaml::from_str::<Pane>(yaml);
     assert!(result.is_err());
-    assert!(result
-        .err()
-        .unwrap()
-        .to_string()
-        .contains("data did not match any variant of untagged enum PaneOption"));
+    assert!(result.err().unwrap().to_string().contains(
+        "pane field \"split_size\": expected a number of cells or a percentage, got a list"
+    ));
 }
 
 #[test]
@@ -185,13 +195,20 @@ fn pane_1st_form_deserializes_correctly_with_key_name() {
             name: Some(String::from("pane name")),
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Vertical),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: false,
+            mark: false,
+            style: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: None,
             send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -218,13 +235,20 @@ fn pane_1st_form_deserializes_correctly_with_null_key_name() {
             name: None,
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Horizontal),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: false,
+            mark: false,
+            style: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: None,
             send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -288,6 +312,31 @@ fn pane_1st_form_deserializes_working_dir_from_number() {
     assert_eq!(pane.working_dir, Some(PathBuf::from("0")));
 }
 
+#[test]
+fn pane_deserializes_split_from_names() {
+    let yaml = r#"
+        pane:
+        split_from: editor
+    "#;
+
+    let pane: Pane = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        pane.split_from,
+        Some(PaneReference::Name(String::from("editor")))
+    );
+
+    let yaml = r#"
+        pane:
+          split_from: editor
+    "#;
+
+    let pane: Pane = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        pane.split_from,
+        Some(PaneReference::Name(String::from("editor")))
+    );
+}
+
 #[test]
 fn pane_1st_form_fails_when_a_field_does_not_accept_a_string() {
     let yaml = r#"
@@ -434,13 +483,20 @@ fn pane_2nd_form_deserializes_correctly_with_name() {
             name: Some(String::from("pane name")),
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Vertical),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: false,
+            mark: false,
+            style: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: None,
             send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -467,13 +523,20 @@ fn pane_2nd_form_deserializes_correctly_with_null_name() {
             name: None,
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Vertical),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: false,
+            mark: false,
+            style: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: None,
             send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -489,7 +552,7 @@ fn pane_2nd_form_deserializes_correctly_from_string() {
         pane,
         Pane {
             name: Some(String::from("pane name")),
-            commands: vec![String::from("command")],
+            commands: vec![PaneCommand::from("command")],
             ..Pane::default()
         }
     )
@@ -568,7 +631,7 @@ fn pane_2nd_form_deserializes_correctly_from_command_list_with_name() {
         pane,
         Pane {
             name: Some(String::from("pane name")),
-            commands: vec![String::from("command1"), String::from("command2")],
+            commands: vec![PaneCommand::from("command1"), PaneCommand::from("command2")],
             ..Pane::default()
         }
     )
@@ -587,7 +650,7 @@ fn pane_2nd_form_deserializes_correctly_from_command_list_with_null_name() {
         pane,
         Pane {
             name: None,
-            commands: vec![String::from("command1"), String::from("command2")],
+            commands: vec![PaneCommand::from("command1"), PaneCommand::from("command2")],
             ..Pane::default()
         }
     )
@@ -604,7 +667,7 @@ fn pane_2nd_form_deserializes_correctly_from_single_command_with_null_name() {
         pane,
         Pane {
             name: None,
-            commands: vec![String::from("command")],
+            commands: vec![PaneCommand::from("command")],
             ..Pane::default()
         }
     )
@@ -653,11 +716,9 @@ fn pane_2nd_form_raises_error_on_invalid_split_size_value() {
 
     let result = serde_yaml::from_str::<Pane>(yaml);
     assert!(result.is_err());
-    assert!(result
-        .err()
-        .unwrap()
-        .to_string()
-        .contains("data did not match any variant of untagged enum PaneOption"));
+    assert!(result.err().unwrap().to_string().contains(
+        "pane \"pane\": split_size: expected a number of cells or a percentage, got a list"
+    ));
 }
 
 #[test]
@@ -717,7 +778,7 @@ fn pane_2nd_form_raises_error_on_invalid_split_value() {
         .err()
         .unwrap()
         .to_string()
-        .contains("data did not match any variant of untagged enum PaneOption"));
+        .contains("pane \"pane\": expected split value \"o\" to match v|h|vertical|horizontal"));
 }
 
 #[test]
@@ -743,13 +804,20 @@ fn pane_3rd_form_deserializes_correctly_with_name() {
             name: Some(String::from("pane name")),
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Vertical),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: false,
+            mark: false,
+            style: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: None,
             send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -777,13 +845,20 @@ fn pane_3rd_form_deserializes_correctly_with_null_name() {
             name: None,
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Horizontal),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: false,
+            mark: false,
+            style: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: None,
             send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -811,13 +886,20 @@ fn pane_3rd_form_deserializes_correctly_with_id() {
             name: Some(String::from("pane name")),
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Vertical),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: false,
+            mark: false,
+            style: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: None,
             send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -845,13 +927,20 @@ fn pane_3rd_form_deserializes_correctly_with_null_id() {
             name: Some(String::from("pane name")),
             working_dir: Some(PathBuf::from("/home")),
             split: Some(PaneSplit::Horizontal),
-            split_from: Some(1),
+            split_from: Some(PaneReference::Index(1)),
             split_size: Some(String::from("42%")),
             clear: true,
+            zoom: false,
+            mark: false,
+            style: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
-            commands: vec![String::from("echo command")],
+            commands: vec![PaneCommand::from("echo command")],
+            join_commands: None,
             send_keys: vec![String::from("echo send_keys")],
+            raw: false,
+            exec: false,
         }
     )
 }
@@ -869,7 +958,30 @@ fn pane_raises_error_on_invalid_split_from_value() {
         .err()
         .unwrap()
         .to_string()
-        .contains("data did not match any variant of untagged enum PaneOption"));
+        .contains("pane field \"split_from\": expected a pane index or name, got a list"));
+}
+
+#[test]
+fn pane_opts_out_of_working_dir_inheritance() {
+    let yaml = r#"
+        inherit_working_dir: false
+        command: htop
+    "#;
+
+    let pane: Pane = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(pane.working_dir, Some(home_working_dir()));
+
+    let yaml = r#"
+        monitor:
+          inherit_working_dir: false
+          working_dir: /tmp
+    "#;
+
+    let pane: Pane = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(pane.working_dir, Some(PathBuf::from("/tmp")));
+
+    let pane: Pane = serde_yaml::from_str("inherit_working_dir: true").unwrap();
+    assert_eq!(pane.working_dir, None);
 }
 
 #[test]
@@ -892,7 +1004,13 @@ fn pane_deserializes_sequence_as_command() {
     "#;
 
     let pane: Pane = serde_yaml::from_str(yaml).unwrap();
-    assert_eq!(pane.commands, vec!["echo cmd1", "echo cmd2"]);
+    assert_eq!(
+        pane.commands,
+        vec![
+            PaneCommand::from("echo cmd1"),
+            PaneCommand::from("echo cmd2")
+        ]
+    );
 }
 
 #[test]
@@ -912,7 +1030,7 @@ fn pane_deserializes_string_command() {
     "#;
 
     let pane: Pane = serde_yaml::from_str(yaml).unwrap();
-    assert_eq!(pane.commands, vec!["echo cmd1"]);
+    assert_eq!(pane.commands, vec![PaneCommand::from("echo cmd1")]);
 }
 
 #[test]
@@ -924,7 +1042,13 @@ fn pane_deserializes_sequence_commands() {
     "#;
 
     let pane: Pane = serde_yaml::from_str(yaml).unwrap();
-    assert_eq!(pane.commands, vec!["echo cmd1", "echo cmd2"]);
+    assert_eq!(
+        pane.commands,
+        vec![
+            PaneCommand::from("echo cmd1"),
+            PaneCommand::from("echo cmd2")
+        ]
+    );
 }
 
 #[test]
@@ -940,7 +1064,7 @@ fn pane_raises_error_on_invalid_commands_value() {
         .err()
         .unwrap()
         .to_string()
-        .contains("data did not match any variant of untagged enum PaneOption"));
+        .contains("pane field \"command\": invalid command definition: expected a `command`, `sleep` or `expect` field"));
 }
 
 #[test]
@@ -950,5 +1074,95 @@ fn pane_from_string_translates_to_single_command_pane() {
     let pane = Pane::from(command);
 
     assert_eq!(pane.commands.len(), 1);
-    assert_eq!(pane.commands[0], command);
+    assert_eq!(pane.commands[0], PaneCommand::from(command));
+}
+
+#[test]
+fn pane_deserializes_structured_commands() {
+    let yaml = r#"
+        commands:
+          - echo cmd1
+          - command: echo cmd2
+            retries: 2
+            timeout: 10
+    "#;
+
+    let pane: Pane = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        pane.commands,
+        vec![
+            PaneCommand::from("echo cmd1"),
+            PaneCommand::Run {
+                command: String::from("echo cmd2"),
+                retries: 2,
+                timeout: Some(10),
+                raw: false,
+            }
+        ]
+    );
+}
+
+#[test]
+fn pane_raw_skips_escaping_pounds() {
+    let yaml = r##"
+        commands:
+          - "echo #{pane_id}"
+          - command: "echo ## raw"
+            raw: true
+        send_keys: "# comment"
+        raw: true
+    "##;
+
+    let pane: Pane = serde_yaml::from_str(yaml).unwrap();
+    assert!(pane.raw);
+    assert_eq!(
+        pane.commands
+            .iter()
+            .map(|command| command.shell_command().unwrap())
+            .collect::<Vec<_>>(),
+        vec!["echo #{pane_id}", "echo ## raw"]
+    );
+    assert_eq!(pane.send_keys, vec![String::from("# comment")]);
+}
+
+#[test]
+fn pane_raises_error_on_unknown_field() {
+    let yaml = r#"
+        pane1: vim
+        splt:
+            direction: h
+    "#;
+
+    let result = serde_yaml::from_str::<Pane>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("unknown pane field \"splt\""));
+}
+
+#[test]
+fn pane_exec_runs_its_single_command_as_is() {
+    let pane: Pane = serde_yaml::from_str("command: 'tail -f #log'\nexec: true\n").unwrap();
+    assert!(pane.check().is_ok());
+    assert_eq!(pane.exec_command(), Some(String::from("tail -f #log")));
+
+    let pane: Pane = serde_yaml::from_str("commands: [htop, top]\nexec: true\n").unwrap();
+    assert!(pane.check().is_err());
+
+    let pane = Pane::from("htop");
+    assert_eq!(pane.exec_command(), None);
+}
+
+#[test]
+fn pane_named_shell_is_not_mistaken_for_the_shell_field() {
+    let pane: Pane = serde_yaml::from_str("shell: echo hi\n").unwrap();
+    assert_eq!(pane.name, Some(String::from("shell")));
+    assert_eq!(pane.shell, None);
+    assert_eq!(pane.commands, vec![PaneCommand::from("echo hi")]);
+
+    let pane: Pane = serde_yaml::from_str("shell: fish\ncommands: [vim]\n").unwrap();
+    assert_eq!(pane.name, None);
+    assert_eq!(pane.shell, Some(String::from("fish")));
 }
@@ -10,7 +10,15 @@ fn make_config(tmux_command: Option<&str>, config_dir: Option<PathBuf>) -> Confi
         app_name: "test_app_name",
         app_author: "test_app_author",
         tmux_command: tmux_command.map(String::from),
+        tmux_socket: None,
+        tmux_options: None,
         config_dir,
+        lenient: false,
+        picker: None,
+        profile: false,
+        session_prefix: None,
+        editor_args: vec![],
+        file_extensions: vec![],
     }
 }
 
@@ -48,6 +56,209 @@ fn project_prepare_replaces_session_name_when_none() {
     assert_eq!(project.session_name, Some(String::from("project")));
 }
 
+#[test]
+fn project_prepare_adds_session_prefix() {
+    let config = Config {
+        session_prefix: Some(String::from("am/")),
+        editor_args: vec![],
+        file_extensions: vec![],
+        ..make_config(None, None)
+    };
+
+    let project = Project::default().prepare(&config, "project", None);
+    assert_eq!(project.session_name, Some(String::from("am/project")));
+
+    let project = Project {
+        session_name: Some(String::from("named")),
+        ..Project::default()
+    };
+    let project = project.prepare(&config, "project", None);
+    assert_eq!(project.session_name, Some(String::from("am/named")));
+}
+
+#[test]
+fn project_prepare_uses_socket_and_tmux_options_from_config() {
+    let config = Config {
+        tmux_socket: Some(String::from("scripts")),
+        tmux_options: Some(String::from("-f /dev/null")),
+        ..make_config(None, None)
+    };
+
+    let project = Project {
+        tmux_socket: Some(String::from("project")),
+        ..Project::default()
+    };
+    let project = project.prepare(&config, "project", None);
+
+    let (_, args) = project.tmux_command(&["ls"]).unwrap();
+    assert_eq!(args, vec!["-L", "scripts", "-f", "/dev/null", "ls"]);
+
+    let (_, args) = config.get_tmux_command(&["ls"]).unwrap();
+    assert_eq!(args, vec!["-L", "scripts", "-f", "/dev/null", "ls"]);
+}
+
+#[test]
+fn project_prepare_expands_session_name_placeholders() {
+    let config = make_config(None, None);
+    let temp_dir = tempdir().unwrap();
+    let working_dir = temp_dir.path().join("my-app");
+    fs::create_dir(&working_dir).unwrap();
+
+    let project = Project {
+        session_name: Some(String::from("{dirname}-{git_branch}-{unknown}")),
+        working_dir: Some(working_dir),
+        ..Project::default()
+    };
+    let project = project.prepare(&config, "project", None);
+    assert_eq!(
+        project.session_name,
+        Some(String::from("my-app--{unknown}"))
+    );
+}
+
+#[test]
+fn project_prepare_expands_window_name_placeholders() {
+    let config = make_config(None, None);
+    let temp_dir = tempdir().unwrap();
+    let project_dir = temp_dir.path().join("project-dir");
+    let window_dir = temp_dir.path().join("window-dir");
+    fs::create_dir(&project_dir).unwrap();
+    fs::create_dir(&window_dir).unwrap();
+
+    let project = Project {
+        working_dir: Some(project_dir),
+        startup_window: StartupWindow::Name(String::from("api-{dirname}")),
+        windows: vec![
+            Window {
+                name: Some(String::from("api-{dirname}")),
+                ..Window::default()
+            },
+            Window {
+                name: Some(String::from("web-{dirname}")),
+                working_dir: Some(window_dir),
+                ..Window::default()
+            },
+        ],
+        ..Project::default()
+    };
+    let project = project.prepare(&config, "project", None);
+    assert_eq!(
+        project.windows[0].name,
+        Some(String::from("api-project-dir"))
+    );
+    assert_eq!(
+        project.windows[1].name,
+        Some(String::from("web-window-dir"))
+    );
+    assert_eq!(
+        project.startup_window,
+        StartupWindow::Name(String::from("api-project-dir"))
+    );
+}
+
+#[test]
+fn project_prepare_replaces_custom_placeholders() {
+    let config = make_config(None, None);
+    let project = Project {
+        placeholders: [(String::from("__API_DIR__"), String::from("/code/api"))]
+            .iter()
+            .cloned()
+            .collect(),
+        on_start: vec![String::from("cd __API_DIR__ && make")],
+        windows: vec![Window {
+            name: Some(String::from("api")),
+            on_create: vec![String::from("ls __API_DIR__")],
+            panes: vec![Pane {
+                commands: vec![PaneCommand::from("tail -f __API_DIR__/log")],
+                ..Pane::default()
+            }],
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+    let project = project.prepare(&config, "project", None);
+    assert_eq!(project.on_start, vec![String::from("cd /code/api && make")]);
+    assert_eq!(
+        project.windows[0].on_create,
+        vec![String::from("ls /code/api")]
+    );
+    assert_eq!(
+        project.windows[0].panes[0].commands,
+        vec![PaneCommand::from("tail -f /code/api/log")]
+    );
+}
+
+#[test]
+fn project_prepare_resolves_hook_log() {
+    let temp_dir = tempdir().unwrap();
+    let config = make_config(None, Some(temp_dir.path().to_path_buf()));
+
+    let project = Project {
+        hook_log: Some(PathBuf::new()),
+        ..Project::default()
+    };
+    let project = project.prepare(&config, "project", None);
+    assert_eq!(
+        project.hook_log,
+        Some(temp_dir.path().join("logs").join("project.log"))
+    );
+
+    let project = Project {
+        hook_log: Some(PathBuf::from("/tmp/hooks.log")),
+        ..Project::default()
+    };
+    let project = project.prepare(&config, "project", None);
+    assert_eq!(project.hook_log, Some(PathBuf::from("/tmp/hooks.log")));
+}
+
+#[test]
+fn project_parses_worktree_list() {
+    let output = "worktree /code/repo\nHEAD 1234\nbranch refs/heads/main\n\n\
+        worktree /code/repo-review\nHEAD 5678\nbranch refs/heads/fix/login\n\n\
+        worktree /code/repo-old\nHEAD 9abc\ndetached\n\n\
+        worktree /code/repo.git\nbare\n";
+
+    assert_eq!(
+        Project::parse_worktree_list(output),
+        vec![
+            (PathBuf::from("/code/repo"), String::from("main")),
+            (
+                PathBuf::from("/code/repo-review"),
+                String::from("fix/login")
+            ),
+            (PathBuf::from("/code/repo-old"), String::from("repo-old")),
+        ]
+    );
+    assert_eq!(Project::parse_worktree_list(""), vec![]);
+}
+
+#[test]
+fn project_prepare_sanitizes_names_when_enabled() {
+    let config = make_config(None, None);
+
+    let project = Project {
+        sanitize_names: true,
+        startup_window: StartupWindow::Name(String::from("v1.2")),
+        windows: vec![Window {
+            name: Some(String::from("v1.2")),
+            ..Window::default()
+        }],
+        ..Project::default()
+    };
+    let project = project.prepare(&config, "app-2.0:beta", None);
+    assert_eq!(project.session_name, Some(String::from("app-2_0_beta")));
+    assert_eq!(
+        project.startup_window,
+        StartupWindow::Name(String::from("v1_2"))
+    );
+    assert_eq!(project.windows[0].name, Some(String::from("v1_2")));
+    assert!(project.check().is_ok());
+
+    // Names are left untouched otherwise
+    let project = Project::default().prepare(&config, "app-2.0", None);
+    assert_eq!(project.session_name, Some(String::from("app-2.0")));
+}
+
 #[test]
 fn project_prepare_replaces_attach_when_force_attach_is_set() {
     let config = make_config(None, None);
@@ -303,6 +514,8 @@ fn project_get_tmux_command_for_template_returns_single_command() {
 fn project_deserializes_correctly() {
     let yaml = r#"
         name: project
+        sanitize_names: true
+        per_worktree: true
         tmux_command: teemux
         tmux_options: -d option-d
         tmux_socket: soquette
@@ -311,17 +524,54 @@ fn project_deserializes_correctly() {
         pane_base_index: 102
         startup_window: 103
         startup_pane: 104
+        startup_zoom: true
+        reselect_startup: true
+        mouse: true
+        history_limit: 50000
+        set_titles: "airmux #S"
+        window_status_style: fg=colour244
+        window_status_current_style: fg=colour208,bold
+        plugins: tmux-plugins/tmux-logging
+        plugins_dir: /plugins
         on_start: echo on_start
         on_first_start: echo on_first_start
+        on_every_start: echo on_every_start
         on_restart: echo on_restart
         on_exit: echo on_exit
         on_stop: echo on_stop
         post_create: echo post_create
+        on_window_create: echo on_window_create
+        post_window_create: echo post_window_create
         on_pane_create: echo on_pane_create
         post_pane_create: echo post_pane_create
+        hook_timeout: 30
+        hook_login_shell: true
+        hook_log: true
+        strict_hooks: true
         pane_command: echo pane_command
+        join_commands: "&&"
         clear_panes: true
+        reorder_windows: true
+        preserve_first_window: true
+        worktrees: true
+        expand_env: false
+        env_capture: AWS_PROFILE
+        env:
+          KUBECONFIG: ~/.kube/dev
+          PORT: 3000
+        placeholders:
+          __API_DIR__: ~/code/api
+        notify: true
+        keep_alive: true
+        autostart: true
+        freeze_on_stop: true
+        autofreeze: true
+        depends_on: infra
         attach: false
+        attach_command: tmux -CC attach -t __SESSION__
+        defaults:
+          no_attach: true
+          args: [staging]
         window: echo not_a_portal
     "#;
 
@@ -330,6 +580,8 @@ fn project_deserializes_correctly() {
         project,
         Project {
             session_name: Some(String::from("project")),
+            sanitize_names: true,
+            per_worktree: true,
             tmux_command: Some(String::from("teemux")),
             tmux_options: Some(String::from("-d option-d")),
             tmux_socket: Some(String::from("soquette")),
@@ -338,17 +590,60 @@ fn project_deserializes_correctly() {
             pane_base_index: 102,
             startup_window: StartupWindow::Index(103),
             startup_pane: Some(104),
+            startup_zoom: true,
+            reselect_startup: true,
+            mouse: Some(true),
+            history_limit: Some(50000),
+            set_titles: Some(String::from("airmux #S")),
+            window_status_style: Some(String::from("fg=colour244")),
+            window_status_current_style: Some(String::from("fg=colour208,bold")),
+            plugins: vec![String::from("tmux-plugins/tmux-logging")],
+            plugins_dir: Some(PathBuf::from("/plugins")),
             on_start: vec![String::from("echo on_start")],
             on_first_start: vec![String::from("echo on_first_start")],
+            on_every_start: vec![String::from("echo on_every_start")],
             on_restart: vec![String::from("echo on_restart")],
             on_exit: vec![String::from("echo on_exit")],
             on_stop: vec![String::from("echo on_stop")],
             post_create: vec![String::from("echo post_create")],
+            on_window_create: vec![String::from("echo on_window_create")],
+            post_window_create: vec![String::from("echo post_window_create")],
             on_pane_create: vec![String::from("echo on_pane_create")],
             post_pane_create: vec![String::from("echo post_pane_create")],
-            pane_commands: vec![String::from("echo pane_command")],
+            hook_timeout: Some(30),
+            hook_login_shell: true,
+            hook_log: Some(PathBuf::new()),
+            strict_hooks: true,
+            pane_commands: vec![PaneCommand::from("echo pane_command")],
+            join_commands: Some(String::from("&&")),
             clear_panes: true,
+            reorder_windows: true,
+            preserve_first_window: true,
+            worktrees: true,
+            expand_env: false,
+            env_capture: vec![String::from("AWS_PROFILE")],
+            environment: vec![
+                (String::from("KUBECONFIG"), String::from("~/.kube/dev")),
+                (String::from("PORT"), String::from("3000")),
+            ]
+            .into_iter()
+            .collect(),
+            placeholders: vec![(String::from("__API_DIR__"), String::from("~/code/api"))]
+                .into_iter()
+                .collect(),
+            notify: true,
+            keep_alive: true,
+            autostart: true,
+            freeze_on_stop: true,
+            autofreeze: true,
+            depends_on: vec![Dependency::from("infra")],
             attach: false,
+            attach_command: Some(String::from("tmux -CC attach -t __SESSION__")),
+            defaults: Defaults {
+                no_attach: true,
+                args: vec![String::from("staging")],
+                ..Defaults::default()
+            },
             windows: vec![Window::from("echo not_a_portal")],
         }
     );
@@ -379,10 +674,13 @@ fn project_deserializer_accepts_empty_values() {
         startup_pane:
         on_start:
         on_first_start:
+        on_every_start:
         on_restart:
         on_exit:
         on_stop:
         post_create:
+        on_window_create:
+        post_window_create:
         on_pane_create:
         post_pane_create:
         pane_command:
@@ -493,6 +791,47 @@ fn project_deserializer_attach_value_is_set_correctly_when_detached_is_set() {
     assert_eq!(project.attach, true);
 }
 
+#[test]
+fn project_deserializes_set_titles() {
+    let yaml = r#"
+        set_titles: true
+    "#;
+
+    let project: Project = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        project.set_titles,
+        Some(String::from(DEFAULT_TITLES_STRING))
+    );
+
+    let yaml = r#"
+        set_titles: false
+    "#;
+
+    let project: Project = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(project.set_titles, None);
+
+    let yaml = r#"
+        set_titles: "airmux: #S"
+    "#;
+
+    let project: Project = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(project.set_titles, Some(String::from("airmux: #S")));
+}
+
+#[test]
+fn project_plugin_name_strips_repository_owner() {
+    assert_eq!(Project::plugin_name("tmux-logging"), "tmux-logging");
+    assert_eq!(
+        Project::plugin_name("tmux-plugins/tmux-logging"),
+        "tmux-logging"
+    );
+    assert_eq!(
+        Project::plugin_name("tmux-plugins/tmux-logging/"),
+        "tmux-logging"
+    );
+    assert_eq!(Project::plugin_name("tmux-plugins/"), "tmux-plugins");
+}
+
 #[test]
 fn project_deserializes_working_dir() {
     let yaml = r#"
@@ -533,6 +872,57 @@ fn project_deserializes_working_dir_null_as_home() {
     );
 }
 
+#[test]
+fn project_check_picks_startup_window_by_name_and_index() {
+    let windows = vec![
+        Window {
+            name: Some(String::from("editor")),
+            ..Window::default()
+        },
+        Window {
+            name: Some(String::from("shell")),
+            ..Window::default()
+        },
+        Window {
+            name: Some(String::from("editor")),
+            ..Window::default()
+        },
+    ];
+
+    let project = Project {
+        startup_window: StartupWindow::Name(String::from("editor:3")),
+        window_base_index: 1,
+        windows,
+        ..Project::default()
+    };
+    assert_eq!(project.startup_window_position().unwrap(), 2);
+
+    let project = Project {
+        startup_window: StartupWindow::Name(String::from("editor")),
+        ..project
+    };
+    assert_eq!(
+        project.check().err().unwrap().to_string(),
+        "startup_window: there are several windows named \"editor\", \
+        use \"editor:<index>\" to pick one (indexes: 1, 3)",
+    );
+
+    let project = Project {
+        startup_window: StartupWindow::Name(String::from("editor:2")),
+        ..project
+    };
+    assert_eq!(
+        project.check().err().unwrap().to_string(),
+        "startup_window: there is no window named \"editor\" with index 2",
+    );
+
+    let project = Project {
+        startup_window: StartupWindow::Name(String::from("shell:2")),
+        ..project
+    };
+    assert_eq!(project.startup_window_position().unwrap(), 1);
+}
+
 #[test]
 fn project_startup_window_by_index() {
     let yaml = r#"
@@ -580,6 +970,7 @@ fn project_on_create_deserializes_as_on_first_start() {
 
     let project: Project = serde_yaml::from_str(yaml).unwrap();
     assert_eq!(project.on_first_start, vec![String::from("echo on_create")]);
+    assert!(project.on_every_start.is_empty());
 }
 
 #[test]
@@ -590,7 +981,7 @@ fn project_pane_no_command_serializes_to_an_empty_string() {
         ..Pane::default()
     };
 
-    let output = project.serialize_compact(false).unwrap();
+    let output = project.serialize_compact(&FileFormat::Yaml).unwrap();
     let expected_output = r#"---
 {}"#;
 
@@ -601,11 +992,11 @@ fn project_pane_no_command_serializes_to_an_empty_string() {
 fn project_pane_single_command_serializes_to_a_single_string() {
     let mut project = Project::default();
     project.windows[0].panes[0] = Pane {
-        commands: vec![String::from("echo cmd1")],
+        commands: vec![PaneCommand::from("echo cmd1")],
         ..Pane::default()
     };
 
-    let output = project.serialize_compact(false).unwrap();
+    let output = project.serialize_compact(&FileFormat::Yaml).unwrap();
     let expected_output = r#"---
 windows:
   - name: ~
@@ -619,11 +1010,14 @@ windows:
 fn project_pane_two_or_more_commands_serializes_to_a_full_object() {
     let mut project = Project::default();
     project.windows[0].panes[0] = Pane {
-        commands: vec![String::from("echo cmd1"), String::from("echo cmd2")],
+        commands: vec![
+            PaneCommand::from("echo cmd1"),
+            PaneCommand::from("echo cmd2"),
+        ],
         ..Pane::default()
     };
 
-    let output = project.serialize_compact(false).unwrap();
+    let output = project.serialize_compact(&FileFormat::Yaml).unwrap();
     let expected_output = r#"---
 windows:
   - name: ~
@@ -634,3 +1028,149 @@ windows:
 
     assert_eq!(output, expected_output);
 }
+
+#[test]
+fn project_serializes_to_each_format() {
+    let project = Project {
+        session_name: Some(String::from("project")),
+        ..Project::default()
+    };
+
+    assert_eq!(
+        project.serialize_compact(&FileFormat::Yaml).unwrap(),
+        "---\nsession_name: project"
+    );
+    assert_eq!(
+        project.serialize_compact(&FileFormat::Json).unwrap(),
+        "{\n  \"session_name\": \"project\"\n}"
+    );
+}
+
+#[test]
+fn project_survives_a_compact_round_trip() {
+    let yaml = r#"
+        name: project
+        sanitize_names: true
+        root: /database
+        window_base_index: 0
+        startup_window: 2
+        startup_pane: 1
+        mouse: false
+        set_titles: true
+        on_first_start: echo on_first_start
+        on_window_create: echo on_window_create
+        hook_timeout: 30
+        hook_log: true
+        pane_command: echo pane_command
+        env:
+          PORT: 3000
+        placeholders:
+          __API_DIR__: ~/code/api
+        depends_on: infra
+        windows:
+          - editor:
+              layout: main-vertical
+              panes:
+                - vim
+                - split: vertical
+                  commands:
+                    - cargo build
+                    - cargo test
+          - logs: tail -f log
+    "#;
+
+    let project: Project = serde_yaml::from_str(yaml).unwrap();
+    for format in &[FileFormat::Yaml, FileFormat::Json] {
+        let content = project.serialize_compact(format).unwrap();
+        assert_eq!(
+            project.compact_round_trip_diff(&content).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+}
+
+#[test]
+fn file_format_is_read_from_names_and_extensions() {
+    assert_eq!(FileFormat::from_name("yaml"), FileFormat::Yaml);
+    assert_eq!(FileFormat::from_name("yml"), FileFormat::Yaml);
+    assert_eq!(FileFormat::from_name("JSON"), FileFormat::Json);
+    assert_eq!(FileFormat::from_name("toml"), FileFormat::Yaml);
+    assert_eq!(FileFormat::from_name(""), FileFormat::Yaml);
+}
+
+#[test]
+fn project_lists_deprecated_fields() {
+    let yaml = serde_yaml::from_str::<serde_yaml::Value>(
+        "pre_window: echo hi\ntmux_detached: true\non_create: make\nwindows: vim\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        Project::deprecated_fields(&yaml),
+        vec![
+            ("on_create", "on_first_start"),
+            ("pre_window", "pane_commands"),
+            ("tmux_detached", "detached")
+        ]
+    );
+}
+
+#[test]
+fn project_raises_error_on_unsupported_version() {
+    let yaml = r#"
+        version: 99
+    "#;
+
+    let result = serde_yaml::from_str::<Project>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("project file version 99 is not supported"));
+}
+
+#[test]
+fn project_check_fails_when_several_panes_are_marked() {
+    let marked = || Window {
+        panes: vec![Pane {
+            mark: true,
+            ..Pane::default()
+        }],
+        ..Window::default()
+    };
+
+    let project = Project {
+        windows: vec![marked(), marked()],
+        ..Project::default()
+    };
+
+    let result = project.check();
+    assert!(result.is_err());
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "mark: only one pane can be marked"
+    );
+}
+
+#[test]
+fn project_check_fails_when_plugins_dir_does_not_exist() {
+    let temp_dir = tempdir().unwrap();
+    let plugins_dir = temp_dir.path().join("plugins");
+
+    let project = Project {
+        plugins: vec![String::from("tmux-logging")],
+        plugins_dir: Some(plugins_dir.to_owned()),
+        ..Project::default()
+    };
+
+    let result = project.check();
+    assert!(result.is_err());
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        format!(
+            "project plugins_dir {:?} is not a directory or does not exist",
+            plugins_dir
+        )
+    );
+}
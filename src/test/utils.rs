@@ -108,6 +108,52 @@ fn correct_command_parses_returns_correct_arguments() {
     assert_eq!(result, expected_result);
 }
 
+#[test]
+fn editor_command_passes_editor_args_verbatim() {
+    let editor_args = vec![String::from("--eval"), String::from("(find-file \"x\")")];
+    let result = editor_command("emacsclient -t", &editor_args, "my project.yml", None).unwrap();
+    assert_eq!(
+        result,
+        (
+            String::from("emacsclient"),
+            vec![
+                String::from("-t"),
+                String::from("--eval"),
+                String::from("(find-file \"x\")"),
+                String::from("my project.yml")
+            ]
+        )
+    );
+
+    let editor_args = vec![String::from("+edit {file}")];
+    let result = editor_command("vim", &editor_args, "project.yml", Some(3)).unwrap();
+    assert_eq!(
+        result,
+        (String::from("vim"), vec![String::from("+edit project.yml")])
+    );
+
+    let result = editor_command("vim -p", &[], "project.yml", None).unwrap();
+    assert_eq!(
+        result,
+        (
+            String::from("vim"),
+            vec![String::from("-p"), String::from("project.yml")]
+        )
+    );
+}
+
+#[test]
+fn editor_command_opens_file_at_line() {
+    let result = editor_command("vim", &[], "project.yml", Some(12)).unwrap();
+    assert_eq!(
+        result,
+        (
+            String::from("vim"),
+            vec![String::from("+12"), String::from("project.yml")]
+        )
+    );
+}
+
 #[test]
 fn correct_command_fails_on_empty_command() {
     let command = "";
@@ -120,3 +166,73 @@ fn correct_command_fails_on_empty_command() {
         Error::EmptyCommand {}
     ));
 }
+
+#[test]
+fn format_elapsed_uses_the_largest_unit() {
+    assert_eq!(format_elapsed(12), "just now");
+    assert_eq!(format_elapsed(60), "1 minute ago");
+    assert_eq!(format_elapsed(150), "2 minutes ago");
+    assert_eq!(format_elapsed(7200), "2 hours ago");
+    assert_eq!(format_elapsed(86400 * 3 + 5), "3 days ago");
+}
+
+#[test]
+fn parse_duration_supports_units() {
+    assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+    assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+    assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+}
+
+#[test]
+fn parse_duration_fails_on_invalid_durations() {
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("0m").is_err());
+    assert!(parse_duration("-5s").is_err());
+    assert!(parse_duration("15 minutes").is_err());
+}
+
+#[test]
+fn sanitize_tmux_identifier_replaces_illegal_characters() {
+    assert_eq!(sanitize_tmux_identifier("app-2.0:beta"), "app-2_0_beta");
+    assert_eq!(sanitize_tmux_identifier("project"), "project");
+}
+
+#[test]
+fn wildcard_match_supports_stars_and_question_marks() {
+    assert!(wildcard_match("*", "anything"));
+    assert!(wildcard_match("app-*", "app-web"));
+    assert!(wildcard_match("v?.?", "v1.2"));
+    assert!(!wildcard_match("v?.?", "v10.2"));
+    assert!(!wildcard_match("app-*", "lib-web"));
+}
+
+#[test]
+fn glob_dirs_lists_matching_directories() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let base_dir = temp_dir.path();
+    for dir in &["packages/b", "packages/a", "packages/.hidden", "libs/c"] {
+        fs::create_dir_all(base_dir.join(dir)).unwrap();
+    }
+    fs::write(base_dir.join("packages/file"), "").unwrap();
+
+    assert_eq!(
+        glob_dirs(base_dir, "packages/*"),
+        vec![base_dir.join("packages/a"), base_dir.join("packages/b")]
+    );
+    assert_eq!(
+        glob_dirs(base_dir, "*/?"),
+        vec![
+            base_dir.join("libs/c"),
+            base_dir.join("packages/a"),
+            base_dir.join("packages/b"),
+        ]
+    );
+    assert_eq!(glob_dirs(base_dir, "missing/*"), Vec::<PathBuf>::new());
+}
+
+#[test]
+fn is_in_path_finds_installed_programs() {
+    assert!(is_in_path("sh"));
+    assert!(!is_in_path("__airmux_missing_program"));
+}
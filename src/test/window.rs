@@ -1,8 +1,10 @@
 use super::*;
 
+use crate::pane_reference::PaneReference;
 use crate::pane_split::PaneSplit;
 use tempfile::tempdir;
 
+use std::env;
 use std::fs;
 
 #[test]
@@ -47,7 +49,7 @@ fn window_check_fails_on_invalid_name() {
 fn window_check_fails_when_pane_split_from_is_out_of_bounds() {
     let window = Window {
         panes: vec![Pane {
-            split_from: Some(2),
+            split_from: Some(PaneReference::Index(2)),
             ..Pane::default()
         }],
         ..Window::default()
@@ -62,6 +64,74 @@ fn window_check_fails_when_pane_split_from_is_out_of_bounds() {
     )
 }
 
+#[test]
+fn window_resolves_split_from_pane_names() {
+    let window = Window {
+        panes: vec![
+            Pane {
+                name: Some(String::from("editor")),
+                ..Pane::default()
+            },
+            Pane::default(),
+            Pane {
+                split_from: Some(PaneReference::Name(String::from("editor"))),
+                ..Pane::default()
+            },
+        ],
+        ..Window::default()
+    };
+    assert!(window.check(1).is_ok());
+    assert_eq!(window.split_from_index(2, 1), Some(1));
+    assert_eq!(window.split_from_index(1, 1), None);
+
+    // Only panes declared before can be referenced by name
+    let window = Window {
+        panes: vec![
+            Pane::default(),
+            Pane {
+                split_from: Some(PaneReference::Name(String::from("logs"))),
+                ..Pane::default()
+            },
+            Pane {
+                name: Some(String::from("logs")),
+                ..Pane::default()
+            },
+        ],
+        ..Window::default()
+    };
+    assert_eq!(
+        window.check(1).err().unwrap().to_string(),
+        "split_from: there is no pane named \"logs\" before pane 2"
+    );
+}
+
+#[test]
+fn window_finds_panes_that_do_not_fit() {
+    let half = Pane {
+        split: Some(PaneSplit::Vertical),
+        split_size: Some(String::from("50%")),
+        ..Pane::default()
+    };
+    let window = Window {
+        panes: vec![Pane::default(), half.clone(), half.clone(), half],
+        ..Window::default()
+    };
+    assert_eq!(window.unfitting_panes(1, 80, 23), Vec::<usize>::new());
+    assert_eq!(window.unfitting_panes(1, 80, 6), vec![4]);
+
+    let window = Window {
+        panes: vec![
+            Pane::default(),
+            Pane {
+                split_size: Some(String::from("100")),
+                ..Pane::default()
+            },
+        ],
+        ..Window::default()
+    };
+    assert_eq!(window.unfitting_panes(0, 80, 23), vec![1]);
+}
+
 #[test]
 fn window_check_succeeds_when_working_dir_is_a_existing_dir() {
     let temp_dir = tempdir().unwrap();
@@ -211,6 +281,11 @@ fn window_1st_form_deserializes_correctly_with_key_name() {
         my name:
         working_dir: /home
         layout: main-vertical
+        main_pane_width: 60
+        main_pane_height: 30%
+        pane_border_status: top
+        pane_border_format: '#{pane_index}: #{pane_title}'
+        shell: fish
         on_create: echo on_create
         post_create: echo post_create
         on_pane_create: echo on_pane_create
@@ -227,12 +302,23 @@ fn window_1st_form_deserializes_correctly_with_key_name() {
             name: Some(String::from("my name")),
             working_dir: Some(PathBuf::from("/home")),
             layout: Some(String::from("main-vertical")),
+            main_pane_width: Some(String::from("60")),
+            main_pane_height: Some(String::from("30%")),
+            pane_border_status: Some(String::from("top")),
+            pane_border_format: Some(String::from("#{pane_index}: #{pane_title}")),
+            shell: Some(String::from("fish")),
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
             on_pane_create: vec![String::from("echo on_pane_create")],
             post_pane_create: vec![String::from("echo post_pane_create")],
-            pane_commands: vec![String::from("echo pane_command")],
+            pane_commands: vec![PaneCommand::from("echo pane_command")],
             clear_panes: true,
+            even_out: false,
+            startup_pane: None,
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
             panes: vec![Pane::from("echo pane")],
         }
     );
@@ -260,12 +346,23 @@ fn window_1st_form_deserializes_correctly_with_null_key_name() {
             name: None,
             working_dir: Some(PathBuf::from("/home")),
             layout: Some(String::from("main-vertical")),
+            main_pane_width: None,
+            main_pane_height: None,
+            pane_border_status: None,
+            pane_border_format: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
             on_pane_create: vec![String::from("echo on_pane_create")],
             post_pane_create: vec![String::from("echo post_pane_create")],
-            pane_commands: vec![String::from("echo pane_command")],
+            pane_commands: vec![PaneCommand::from("echo pane_command")],
             clear_panes: true,
+            even_out: false,
+            startup_pane: None,
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
             panes: vec![Pane::from("echo pane")],
         }
     );
@@ -341,12 +438,23 @@ fn window_1st_form_deserializes_correctly_with_explicit_name() {
             name: Some(String::from("my name")),
             working_dir: Some(PathBuf::from("/home")),
             layout: Some(String::from("main-vertical")),
+            main_pane_width: None,
+            main_pane_height: None,
+            pane_border_status: None,
+            pane_border_format: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
             on_pane_create: vec![String::from("echo on_pane_create")],
             post_pane_create: vec![String::from("echo post_pane_create")],
-            pane_commands: vec![String::from("echo pane_command")],
+            pane_commands: vec![PaneCommand::from("echo pane_command")],
             clear_panes: true,
+            even_out: false,
+            startup_pane: None,
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
             panes: vec![Pane::from("echo pane")],
         }
     );
@@ -396,8 +504,8 @@ fn window_1st_form_deserializes_correctly_deserializes_command_lists() {
                 String::from("echo post_pane_create2")
             ],
             pane_commands: vec![
-                String::from("echo pane_command1"),
-                String::from("echo pane_command2")
+                PaneCommand::from("echo pane_command1"),
+                PaneCommand::from("echo pane_command2")
             ],
             panes: vec![Pane::from("echo pane1"), Pane::from("echo pane2")],
             ..Window::default()
@@ -617,12 +725,23 @@ fn window_2nd_form_deserializes_correctly_with_name() {
             name: Some(String::from("my name")),
             working_dir: Some(PathBuf::from("/home")),
             layout: Some(String::from("main-vertical")),
+            main_pane_width: None,
+            main_pane_height: None,
+            pane_border_status: None,
+            pane_border_format: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
             on_pane_create: vec![String::from("echo on_pane_create")],
             post_pane_create: vec![String::from("echo post_pane_create")],
-            pane_commands: vec![String::from("echo pane_command")],
+            pane_commands: vec![PaneCommand::from("echo pane_command")],
             clear_panes: true,
+            even_out: false,
+            startup_pane: None,
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
             panes: vec![Pane::from("echo pane")],
         }
     );
@@ -650,12 +769,23 @@ fn window_2nd_form_deserializes_correctly_with_null_name() {
             name: None,
             working_dir: Some(PathBuf::from("/home")),
             layout: Some(String::from("main-vertical")),
+            main_pane_width: None,
+            main_pane_height: None,
+            pane_border_status: None,
+            pane_border_format: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
             on_pane_create: vec![String::from("echo on_pane_create")],
             post_pane_create: vec![String::from("echo post_pane_create")],
-            pane_commands: vec![String::from("echo pane_command")],
+            pane_commands: vec![PaneCommand::from("echo pane_command")],
             clear_panes: true,
+            even_out: false,
+            startup_pane: None,
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
             panes: vec![Pane::from("echo pane")],
         }
     );
@@ -751,7 +881,7 @@ fn window_2nd_form_raises_error_on_invalid_layout_value() {
         .err()
         .unwrap()
         .to_string()
-        .contains("data did not match any variant of untagged enum WindowOption"));
+        .contains("window \"win1\": invalid type: integer `42`, expected a string"));
 }
 
 #[test]
@@ -792,12 +922,23 @@ fn window_3rd_form_deserializes_correctly_with_string_key() {
             name: Some(String::from("my name")),
             working_dir: Some(PathBuf::from("/home")),
             layout: Some(String::from("main-vertical")),
+            main_pane_width: None,
+            main_pane_height: None,
+            pane_border_status: None,
+            pane_border_format: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
             on_pane_create: vec![String::from("echo on_pane_create")],
             post_pane_create: vec![String::from("echo post_pane_create")],
-            pane_commands: vec![String::from("echo pane_command")],
+            pane_commands: vec![PaneCommand::from("echo pane_command")],
             clear_panes: true,
+            even_out: false,
+            startup_pane: None,
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
             panes: vec![Pane::from("echo pane")],
         }
     );
@@ -826,12 +967,23 @@ fn window_3rd_form_deserializes_correctly_with_null_key() {
             name: Some(String::from("my name")),
             working_dir: Some(PathBuf::from("/home")),
             layout: Some(String::from("main-vertical")),
+            main_pane_width: None,
+            main_pane_height: None,
+            pane_border_status: None,
+            pane_border_format: None,
+            shell: None,
             on_create: vec![String::from("echo on_create")],
             post_create: vec![String::from("echo post_create")],
             on_pane_create: vec![String::from("echo on_pane_create")],
             post_pane_create: vec![String::from("echo post_pane_create")],
-            pane_commands: vec![String::from("echo pane_command")],
+            pane_commands: vec![PaneCommand::from("echo pane_command")],
             clear_panes: true,
+            even_out: false,
+            startup_pane: None,
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
             panes: vec![Pane::from("echo pane")],
         }
     );
@@ -882,3 +1034,442 @@ fn window_deserializes_pane_keyword_as_panes() {
         }
     );
 }
+
+#[test]
+fn window_deserializes_structured_pane_commands() {
+    let yaml = r#"
+        pane_commands:
+          command: echo cmd1
+          retries: 1
+    "#;
+
+    let window: Window = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        window.pane_commands,
+        vec![PaneCommand::Run {
+            command: String::from("echo cmd1"),
+            retries: 1,
+            timeout: None,
+            raw: false,
+        }]
+    );
+}
+
+#[test]
+fn window_expands_env_vars_in_working_dir() {
+    env::set_var("__AIRMUX_TEST_PROJECTS", "/projects");
+
+    let yaml = r#"
+        working_dir: $__AIRMUX_TEST_PROJECTS/api
+    "#;
+
+    let window: Window = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(window.working_dir, Some(PathBuf::from("/projects/api")));
+}
+
+#[test]
+fn window_raises_error_on_unknown_env_var_in_working_dir() {
+    let yaml = r#"
+        working_dir: $__AIRMUX_TEST_UNDEFINED/api
+    "#;
+
+    let result = serde_yaml::from_str::<Window>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("__AIRMUX_TEST_UNDEFINED"));
+}
+
+#[test]
+fn window_raises_error_on_boolean_value() {
+    let yaml = r#"
+        true
+    "#;
+
+    let result = serde_yaml::from_str::<Window>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("expected a command, a list of panes or a window definition"));
+}
+
+#[test]
+fn window_raises_error_on_invalid_field_type() {
+    let yaml = r#"
+        win1: vim
+        clear_panes: 3
+    "#;
+
+    let result = serde_yaml::from_str::<Window>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("window field \"clear_panes\": expected a boolean, got a number"));
+}
+
+#[test]
+fn window_raises_error_on_unknown_field_in_definition() {
+    let yaml = r#"
+        win1:
+            layuot: main-vertical
+    "#;
+
+    let result = serde_yaml::from_str::<Window>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("window \"win1\": unknown field `layuot`"));
+}
+
+#[test]
+fn window_definition_deserializes_main_pane_size() {
+    let yaml = r#"
+        editor:
+          layout: main-vertical
+          main_pane_width: 50%
+          main_pane_height: 20
+    "#;
+
+    let window: Window = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(window.main_pane_width, Some(String::from("50%")));
+    assert_eq!(window.main_pane_height, Some(String::from("20")));
+
+    let yaml = r#"
+        editor:
+          main_pane_width: [50]
+    "#;
+
+    let result = serde_yaml::from_str::<Window>(yaml);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("expected a number of cells or a percentage, got a list"));
+}
+
+#[test]
+fn window_even_layout_follows_pane_splits() {
+    let window = Window {
+        even_out: true,
+        panes: vec![Pane::default(), Pane::default()],
+        ..Window::default()
+    };
+    assert_eq!(window.even_layout(), "even-horizontal");
+
+    let vertical = || Pane {
+        split: Some(PaneSplit::Vertical),
+        ..Pane::default()
+    };
+
+    let window = Window {
+        even_out: true,
+        panes: vec![Pane::default(), vertical(), vertical()],
+        ..Window::default()
+    };
+    assert_eq!(window.even_layout(), "even-vertical");
+
+    let window = Window {
+        even_out: true,
+        panes: vec![Pane::default(), vertical(), Pane::default()],
+        ..Window::default()
+    };
+    assert_eq!(window.even_layout(), "tiled");
+}
+
+#[test]
+fn window_check_fails_on_invalid_pane_border_status() {
+    let window = Window {
+        pane_border_status: Some(String::from("left")),
+        ..Window::default()
+    };
+
+    let result = window.check(1);
+    assert!(result.is_err());
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "pane_border_status: expected one of off, top or bottom, got \"left\""
+    );
+}
+
+#[test]
+fn window_deserializes_startup_pane() {
+    let yaml = r#"
+        editor:
+          startup_pane: 2
+          panes:
+            - vim
+            - cargo watch
+    "#;
+
+    let window: Window = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(window.startup_pane, Some(2));
+    assert!(window.check(1).is_ok());
+}
+
+#[test]
+fn window_check_fails_when_startup_pane_does_not_exist() {
+    let window = Window {
+        startup_pane: Some(2),
+        ..Window::default()
+    };
+
+    let result = window.check(1);
+    assert!(result.is_err());
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "startup_pane: there is no pane with index 2 (pane indexes always start at pane_base_index)"
+    );
+}
+
+#[test]
+fn window_check_fails_when_even_out_is_used_with_layout() {
+    let window = Window {
+        layout: Some(String::from("tiled")),
+        even_out: true,
+        ..Window::default()
+    };
+
+    let result = window.check(1);
+    assert!(result.is_err());
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "even_out: cannot use even_out along with layout"
+    );
+}
+
+#[test]
+fn window_check_fails_when_several_panes_are_zoomed() {
+    let zoomed = || Pane {
+        zoom: true,
+        ..Pane::default()
+    };
+
+    let window = Window {
+        panes: vec![zoomed(), zoomed()],
+        ..Window::default()
+    };
+
+    let result = window.check(1);
+    assert!(result.is_err());
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "zoom: only one pane per window can be zoomed"
+    );
+}
+
+#[test]
+fn window_deserializes_foreach_fields() {
+    let yaml = r#"
+        packages:
+          foreach_dir: packages/*
+          foreach_windows: true
+          panes: npm run watch
+    "#;
+
+    let window: Window = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(window.foreach_dir, Some(String::from("packages/*")));
+    assert!(window.foreach_windows);
+
+    let yaml = r#"
+        hosts:
+          foreach: [web1, web2]
+          foreach_file: hosts.txt
+    "#;
+
+    let window: Window = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        window.foreach,
+        vec![String::from("web1"), String::from("web2")]
+    );
+    assert_eq!(window.foreach_file, Some(PathBuf::from("hosts.txt")));
+}
+
+#[test]
+fn window_expand_foreach_creates_a_pane_per_directory() {
+    let temp_dir = tempdir().unwrap();
+    let packages_dir = temp_dir.path().join("packages");
+    fs::create_dir_all(packages_dir.join("api")).unwrap();
+    fs::create_dir_all(packages_dir.join("web")).unwrap();
+    fs::write(packages_dir.join("README.md"), "").unwrap();
+
+    let window = Window {
+        name: Some(String::from("watchers")),
+        foreach_dir: Some(String::from("packages/*")),
+        panes: vec![Pane::from("echo {basename} in {dir}")],
+        ..Window::default()
+    };
+
+    let windows = window.expand_foreach(temp_dir.path());
+    assert_eq!(
+        windows,
+        vec![Window {
+            name: Some(String::from("watchers")),
+            panes: vec!["api", "web"]
+                .into_iter()
+                .map(|name| {
+                    let dir = packages_dir.join(name);
+                    Pane {
+                        working_dir: Some(dir.to_owned()),
+                        ..Pane::from(format!("echo {} in {}", name, dir.to_string_lossy()))
+                    }
+                })
+                .collect(),
+            ..Window::default()
+        }]
+    );
+}
+
+#[test]
+fn window_expand_foreach_creates_a_window_per_directory() {
+    let temp_dir = tempdir().unwrap();
+    fs::create_dir_all(temp_dir.path().join("api")).unwrap();
+    fs::create_dir_all(temp_dir.path().join("web")).unwrap();
+
+    let window = Window {
+        foreach_dir: Some(String::from("*")),
+        foreach_windows: true,
+        panes: vec![Pane::from("make {basename}")],
+        ..Window::default()
+    };
+
+    let windows = window.expand_foreach(temp_dir.path());
+    assert_eq!(
+        windows,
+        vec![
+            Window {
+                name: Some(String::from("api")),
+                working_dir: Some(temp_dir.path().join("api")),
+                panes: vec![Pane::from("make api")],
+                ..Window::default()
+            },
+            Window {
+                name: Some(String::from("web")),
+                working_dir: Some(temp_dir.path().join("web")),
+                panes: vec![Pane::from("make web")],
+                ..Window::default()
+            },
+        ]
+    );
+}
+
+#[test]
+fn window_expand_foreach_creates_a_pane_per_item() {
+    let window = Window {
+        foreach: vec![String::from("web1"), String::from("web2")],
+        panes: vec![Pane::from("ssh {item}")],
+        ..Window::default()
+    };
+
+    let windows = window.expand_foreach(Path::new("/"));
+    assert_eq!(
+        windows,
+        vec![Window {
+            panes: vec![Pane::from("ssh web1"), Pane::from("ssh web2")],
+            ..Window::default()
+        }]
+    );
+}
+
+#[test]
+fn window_expand_foreach_reads_items_from_file() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("hosts.txt"),
+        "# web servers\nweb1\n\n  web2  \n",
+    )
+    .unwrap();
+
+    let window = Window {
+        foreach_file: Some(PathBuf::from("hosts.txt")),
+        foreach_windows: true,
+        panes: vec![Pane::from("ssh {item}")],
+        ..Window::default()
+    };
+
+    let windows = window.expand_foreach(temp_dir.path());
+    assert_eq!(
+        windows,
+        vec![
+            Window {
+                name: Some(String::from("web1")),
+                panes: vec![Pane::from("ssh web1")],
+                ..Window::default()
+            },
+            Window {
+                name: Some(String::from("web2")),
+                panes: vec![Pane::from("ssh web2")],
+                ..Window::default()
+            },
+        ]
+    );
+}
+
+#[test]
+fn window_check_fails_when_foreach_file_cannot_be_read() {
+    let temp_dir = tempdir().unwrap();
+    let window = Window {
+        foreach_file: Some(PathBuf::from("missing.txt")),
+        ..Window::default()
+    };
+
+    let windows = window.expand_foreach(temp_dir.path());
+    let result = windows[0].check(1);
+    assert!(result.is_err());
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "foreach_file: cannot read \"missing.txt\", make sure the file exists"
+    );
+
+    let window = Window {
+        foreach_dir: Some(String::from("*")),
+        foreach: vec![String::from("item")],
+        ..Window::default()
+    };
+    let result = window.check(1);
+    assert!(result.is_err());
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "foreach: only one of foreach_dir, foreach and foreach_file can be used"
+    );
+}
+
+#[test]
+fn window_opts_out_of_working_dir_inheritance() {
+    let yaml = r#"
+        logs:
+          inherit_working_dir: false
+          panes:
+            - tail -f app.log
+            - working_dir: /tmp
+    "#;
+
+    let window: Window = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(window.working_dir, Some(home_working_dir()));
+    assert_eq!(window.panes[0].working_dir, None);
+    assert_eq!(window.panes[1].working_dir, Some(PathBuf::from("/tmp")));
+}
+
+#[test]
+fn window_named_shell_is_not_mistaken_for_the_shell_field() {
+    let window: Window = serde_yaml::from_str("shell: echo hi\n").unwrap();
+    assert_eq!(window.name, Some(String::from("shell")));
+    assert_eq!(window.shell, None);
+    assert_eq!(window.panes, vec![Pane::from("echo hi")]);
+
+    let window: Window = serde_yaml::from_str("shell:\n").unwrap();
+    assert_eq!(window.name, Some(String::from("shell")));
+    assert_eq!(window.shell, None);
+
+    let window: Window = serde_yaml::from_str("shell: fish\nname: editor\n").unwrap();
+    assert_eq!(window.name, Some(String::from("editor")));
+    assert_eq!(window.shell, Some(String::from("fish")));
+}
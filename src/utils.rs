@@ -1,9 +1,16 @@
 use console::Term;
+use serde_yaml::Value;
 use shell_words::{quote, split};
+use shellexpand::tilde;
 use snafu::{ensure, Snafu};
+use std::env;
 use std::error;
+use std::fs;
+use std::io::Write;
 use std::path;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -17,6 +24,13 @@ pub enum Error {
     TmuxIdentifierIllegalCharacters { identifier: String },
     #[snafu(display("name cannot be empty"))]
     TmuxIdentifierEmpty {},
+    #[snafu(display(
+        "invalid duration {:?}, expected a number followed by s, m or h (ie: 15m)",
+        duration
+    ))]
+    InvalidDuration { duration: String },
+    #[snafu(display("cannot copy to the clipboard, tried: {}", tools))]
+    ClipboardUnavailable { tools: String },
 }
 
 pub fn valid_tmux_identifier(identifier: &str) -> Result<(), Box<dyn error::Error>> {
@@ -29,6 +43,11 @@ pub fn valid_tmux_identifier(identifier: &str) -> Result<(), Box<dyn error::Erro
     Ok(())
 }
 
+// Replaces the characters tmux doesn't allow in names, the same way tmux itself does
+pub fn sanitize_tmux_identifier(identifier: &str) -> String {
+    identifier.replace(&['.', ':'][..], "_")
+}
+
 pub fn get_project_namespace(project_name: &str) -> Result<PathBuf, Box<dyn error::Error>> {
     let has_trailing_slash = project_name.ends_with(path::MAIN_SEPARATOR);
     ensure!(
@@ -48,7 +67,7 @@ pub fn parse_command(
 ) -> Result<(String, Vec<String>), Box<dyn error::Error>> {
     ensure!(!command.is_empty(), EmptyCommand {});
 
-    let args_iter = args.to_owned().into_iter().map(String::from);
+    let args_iter = args.iter().copied().map(String::from);
     let mut command_parts = split(command)?.into_iter().chain(args_iter);
 
     let new_command = command_parts.next().unwrap();
@@ -56,6 +75,77 @@ pub fn parse_command(
     Ok((new_command, new_args))
 }
 
+// Editor arguments are passed as they are, after the ones parsed from the editor command
+// The file replaces {file} in them, or goes last (after +line, if any) if none of them has it
+pub fn editor_command(
+    editor: &str,
+    editor_args: &[String],
+    file: &str,
+    line: Option<usize>,
+) -> Result<(String, Vec<String>), Box<dyn error::Error>> {
+    let (command, mut args) = parse_command(editor, &[])?;
+
+    args.extend(editor_args.iter().map(|arg| arg.replace("{file}", file)));
+    if !editor_args.iter().any(|arg| arg.contains("{file}")) {
+        if let Some(line) = line {
+            args.push(format!("+{}", line));
+        }
+        args.push(String::from(file));
+    }
+
+    Ok((command, args))
+}
+
+// Clipboard tools to try, in order
+pub fn clipboard_commands() -> Vec<Vec<&'static str>> {
+    if cfg!(target_os = "macos") {
+        vec![vec!["pbcopy"]]
+    } else if cfg!(windows) {
+        vec![vec!["clip"]]
+    } else {
+        vec![
+            vec!["wl-copy"],
+            vec!["xclip", "-selection", "clipboard"],
+            vec!["xsel", "--clipboard", "--input"],
+        ]
+    }
+}
+
+pub fn copy_to_clipboard(text: &str) -> Result<(), Box<dyn error::Error>> {
+    let commands = clipboard_commands();
+
+    for command in &commands {
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        // Move on to the next tool if this one isn't installed
+        let mut child = match child {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+
+        // Closing stdin lets the tool know the whole text was sent
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+
+    let tools: Vec<&str> = commands.iter().map(|command| command[0]).collect();
+    Err(ClipboardUnavailable {
+        tools: tools.join(", "),
+    }
+    .build()
+    .into())
+}
+
 pub fn is_default<T>(t: &T) -> bool
 where
     T: Default + PartialEq,
@@ -75,7 +165,7 @@ pub fn prompt_confirmation(message: &str, default: bool) -> Result<bool, Box<dyn
     let reply = if reply == '\n' {
         default
     } else {
-        reply.to_ascii_lowercase() == 'y'
+        reply.eq_ignore_ascii_case(&'y')
     };
 
     // Type out the reply before returning
@@ -84,15 +174,125 @@ pub fn prompt_confirmation(message: &str, default: bool) -> Result<bool, Box<dyn
     Ok(reply)
 }
 
+pub fn format_elapsed(seconds: u64) -> String {
+    let (value, unit) = match seconds {
+        0..=59 => return String::from("just now"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+
+    format!("{} {}{} ago", value, unit, if value > 1 { "s" } else { "" })
+}
+
+// Accepts a number of seconds, optionally followed by a unit (s, m or h)
+pub fn parse_duration(duration: &str) -> Result<Duration, Box<dyn error::Error>> {
+    let (value, multiplier) = match duration.trim().strip_suffix(&['s', 'm', 'h'][..]) {
+        Some(value) if duration.trim().ends_with('m') => (value, 60),
+        Some(value) if duration.trim().ends_with('h') => (value, 3600),
+        Some(value) => (value, 1),
+        None => (duration.trim(), 1),
+    };
+
+    let value = value.parse::<u64>().ok().filter(|value| *value > 0);
+    ensure!(value.is_some(), InvalidDuration { duration });
+
+    Ok(Duration::from_secs(value.unwrap() * multiplier))
+}
+
+// Lists the directories matching a pattern that can use * and ? in any of its components
+pub fn glob_dirs(base_dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let pattern = base_dir.join(tilde(pattern).to_string());
+
+    let mut paths = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let component = component.as_os_str().to_string_lossy();
+        if component.find(&['*', '?'][..]).is_none() {
+            paths = paths
+                .into_iter()
+                .map(|path| path.join(&*component))
+                .collect();
+            continue;
+        }
+
+        let mut matches: Vec<PathBuf> = paths
+            .iter()
+            .filter_map(|path| fs::read_dir(path).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                // Hidden directories are only matched explicitly
+                (!name.starts_with('.') || component.starts_with('.'))
+                    && wildcard_match(&component, &name)
+            })
+            .collect();
+        matches.sort();
+        paths = matches;
+    }
+
+    paths.into_iter().filter(|path| path.is_dir()).collect()
+}
+
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+            Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && matches(rest, &name[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+pub fn replace_placeholders(string: &str, placeholders: &[(&str, String)]) -> String {
+    placeholders
+        .iter()
+        .fold(string.to_string(), |string, (placeholder, value)| {
+            string.replace(placeholder, value)
+        })
+}
+
+// Used to tell users what they've written when a value doesn't have the expected type
+pub fn describe_value(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Sequence(_) => "a list",
+        Value::Mapping(_) => "a map",
+    }
+}
+
+pub fn has_key(value: &Value, key: &str) -> bool {
+    match value {
+        Value::Mapping(mapping) => mapping.contains_key(&Value::from(key)),
+        _ => false,
+    }
+}
+
+// Looks a program up in PATH, like `command -v` does
+pub fn is_in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
 pub fn tmux_quote(part: &str) -> String {
     quote(part).replace("'\\''", "'\"'\"'")
 }
 
 pub fn tmux_join(parts: &[&str]) -> String {
-    let parts: Vec<String> = parts.to_owned().into_iter().map(tmux_quote).collect();
+    let parts: Vec<String> = parts.iter().copied().map(tmux_quote).collect();
     parts.join(" ")
 }
 
 #[cfg(test)]
 #[path = "test/utils.rs"]
+#[allow(clippy::needless_borrow, clippy::needless_borrows_for_generic_args)]
 mod tests;
@@ -1,26 +1,49 @@
-use crate::command::{de_command_list, process_command, process_command_list};
+use crate::command::{
+    de_command_list, de_pane_command_list, process_command, process_command_list,
+    process_pane_command_list, PaneCommand,
+};
 use crate::pane::Pane;
-use crate::utils::valid_tmux_identifier;
+use crate::pane_reference::PaneReference;
+use crate::pane_split::PaneSplit;
+use crate::utils::{
+    describe_value, glob_dirs, has_key, replace_placeholders, valid_tmux_identifier,
+};
 use crate::working_dir::{de_working_dir, home_working_dir, process_working_dir};
 
 use de::Visitor;
 use serde::{de, Deserialize, Serialize};
+use serde_yaml::Value;
 
 use std::error::Error;
 use std::fmt;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Working directory and placeholder values used for each generated pane or window
+type ForeachItem = (Option<PathBuf>, Vec<(&'static str, String)>);
 
 #[derive(Serialize, Debug, PartialEq, Clone)]
 pub struct Window {
     pub name: Option<String>,
     pub working_dir: Option<PathBuf>,
     pub layout: Option<String>,
+    pub main_pane_width: Option<String>,
+    pub main_pane_height: Option<String>,
+    pub pane_border_status: Option<String>,
+    pub pane_border_format: Option<String>,
+    pub shell: Option<String>,
     pub on_create: Vec<String>,
     pub post_create: Vec<String>,
     pub on_pane_create: Vec<String>,
     pub post_pane_create: Vec<String>,
-    pub pane_commands: Vec<String>,
+    pub pane_commands: Vec<PaneCommand>,
     pub clear_panes: bool,
+    pub even_out: bool,
+    pub startup_pane: Option<usize>,
+    pub foreach_dir: Option<String>,
+    pub foreach: Vec<String>,
+    pub foreach_file: Option<PathBuf>,
+    pub foreach_windows: bool,
     pub panes: Vec<Pane>,
 }
 
@@ -31,7 +54,51 @@ impl Window {
             valid_tmux_identifier(name)?;
         }
 
-        // Check that split_from for each pane points to an existing pane
+        if let Some(status) = &self.pane_border_status {
+            if !["off", "top", "bottom"].contains(&status.as_str()) {
+                return Err(format!(
+                    "pane_border_status: expected one of off, top or bottom, got {:?}",
+                    status
+                )
+                .into());
+            }
+        }
+
+        if self.even_out && self.layout.is_some() {
+            return Err("even_out: cannot use even_out along with layout".into());
+        }
+
+        // Windows using foreach sources are expanded when the project is prepared
+        if self.foreach_dir.is_some() && (!self.foreach.is_empty() || self.foreach_file.is_some())
+            || !self.foreach.is_empty() && self.foreach_file.is_some()
+        {
+            return Err(
+                "foreach: only one of foreach_dir, foreach and foreach_file can be used".into(),
+            );
+        }
+        if let Some(foreach_file) = &self.foreach_file {
+            return Err(format!(
+                "foreach_file: cannot read {:?}, make sure the file exists",
+                foreach_file
+            )
+            .into());
+        }
+
+        if let Some(startup_pane) = self.startup_pane {
+            if startup_pane < base_pane_index || startup_pane >= base_pane_index + self.panes.len()
+            {
+                return Err(format!(
+                    "startup_pane: there is no pane with index {} (pane indexes always start at pane_base_index)",
+                    startup_pane
+                ).into());
+            }
+        }
+
+        // A window can only have one zoomed pane at a time
+        if self.panes.iter().filter(|pane| pane.zoom).count() > 1 {
+            return Err("zoom: only one pane per window can be zoomed".into());
+        }
+
         for pane in &self.panes {
             pane.check()?;
 
@@ -40,15 +107,31 @@ impl Window {
                     "layout: cannot use layout when sub-panes use split or split_size".into(),
                 );
             }
+        }
 
-            if let Some(split_from) = pane.split_from {
-                if split_from < base_pane_index || split_from >= base_pane_index + self.panes.len()
+        // Check that split_from for each pane points to an existing pane
+        for (pane_index, pane) in self.panes.iter().enumerate() {
+            match &pane.split_from {
+                Some(PaneReference::Index(split_from))
+                    if *split_from < base_pane_index
+                        || *split_from >= base_pane_index + self.panes.len() =>
                 {
                     return Err(format!(
                         "split_from: there is no pane with index {} (pane indexes always start at pane_base_index)",
                         split_from
                     ).into());
                 }
+                Some(PaneReference::Name(name))
+                    if self.split_from_index(pane_index, base_pane_index).is_none() =>
+                {
+                    return Err(format!(
+                        "split_from: there is no pane named {:?} before pane {}",
+                        name,
+                        pane_index + base_pane_index
+                    )
+                    .into());
+                }
+                _ => {}
             }
         }
 
@@ -71,28 +154,304 @@ impl Window {
         Ok(())
     }
 
+    // Index of the pane the given pane is split from, either referenced by its index
+    // or by the name of a pane declared before it
+    pub fn split_from_index(&self, pane_index: usize, base_pane_index: usize) -> Option<usize> {
+        match self.panes.get(pane_index)?.split_from.as_ref()? {
+            PaneReference::Index(split_from) => Some(*split_from),
+            PaneReference::Name(name) => self.panes[..pane_index]
+                .iter()
+                .position(|pane| pane.name.as_ref() == Some(name))
+                .map(|index| index + base_pane_index),
+        }
+    }
+
+    // Turns a window using foreach_dir, foreach or foreach_file into a pane, or a window, per item
+    pub fn expand_foreach(self, base_dir: &Path) -> Vec<Window> {
+        let base_dir = self
+            .working_dir
+            .to_owned()
+            .unwrap_or_else(|| base_dir.into());
+
+        // Windows with conflicting or unreadable sources are left as they are for check to report
+        let sources = self.foreach_dir.is_some() as usize
+            + !self.foreach.is_empty() as usize
+            + self.foreach_file.is_some() as usize;
+        if sources != 1 {
+            return vec![self];
+        }
+
+        let (items, default_name) = if let Some(pattern) = &self.foreach_dir {
+            let items = glob_dirs(&base_dir, pattern)
+                .into_iter()
+                .map(|dir| {
+                    let basename = dir
+                        .file_name()
+                        .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+                    let placeholders = vec![
+                        ("{dir}", dir.to_string_lossy().to_string()),
+                        ("{basename}", basename),
+                    ];
+
+                    (Some(dir), placeholders)
+                })
+                .collect();
+
+            (items, "{basename}")
+        } else {
+            let list = match &self.foreach_file {
+                Some(foreach_file) => match fs::read_to_string(base_dir.join(foreach_file)) {
+                    Ok(content) => Self::parse_foreach_file(&content),
+                    Err(_) => return vec![self],
+                },
+                None => self.foreach.to_owned(),
+            };
+            let items = list
+                .into_iter()
+                .map(|item| (None, vec![("{item}", item)]))
+                .collect();
+
+            (items, "{item}")
+        };
+
+        // Generated windows are named after their item by default
+        let name = match (&self.name, self.foreach_windows) {
+            (None, true) => Some(String::from(default_name)),
+            (name, _) => name.to_owned(),
+        };
+
+        Self { name, ..self }.expand_items(items)
+    }
+
+    // One item per line, skipping empty lines and comments
+    pub fn parse_foreach_file(content: &str) -> Vec<String> {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| process_command(line.to_string()))
+            .collect()
+    }
+
+    fn expand_items(self, items: Vec<ForeachItem>) -> Vec<Window> {
+        let template = Self {
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
+            ..self
+        };
+
+        if self.foreach_windows {
+            return items
+                .into_iter()
+                .map(|(working_dir, placeholders)| {
+                    let mut window = template.replace_placeholders(&placeholders);
+                    if working_dir.is_some() {
+                        window.working_dir = working_dir;
+                    }
+
+                    window
+                })
+                .collect();
+        }
+
+        let mut panes: Vec<Pane> = items
+            .iter()
+            .flat_map(|(working_dir, placeholders)| {
+                template.panes.iter().map(move |pane| {
+                    let mut pane = pane.replace_placeholders(placeholders);
+                    if pane.working_dir.is_none() {
+                        pane.working_dir = working_dir.to_owned();
+                    }
+
+                    pane
+                })
+            })
+            .collect();
+        if panes.is_empty() {
+            panes = Self::default_panes();
+        }
+
+        vec![Self { panes, ..template }]
+    }
+
+    pub fn replace_placeholders(&self, placeholders: &[(&str, String)]) -> Self {
+        let replace_list = |commands: &[String]| -> Vec<String> {
+            commands
+                .iter()
+                .map(|command| replace_placeholders(command, placeholders))
+                .collect()
+        };
+
+        Self {
+            name: self
+                .name
+                .as_deref()
+                .map(|name| replace_placeholders(name, placeholders)),
+            on_create: replace_list(&self.on_create),
+            post_create: replace_list(&self.post_create),
+            on_pane_create: replace_list(&self.on_pane_create),
+            post_pane_create: replace_list(&self.post_pane_create),
+            pane_commands: self
+                .pane_commands
+                .iter()
+                .map(|command| command.replace_placeholders(placeholders))
+                .collect(),
+            panes: self
+                .panes
+                .iter()
+                .map(|pane| pane.replace_placeholders(placeholders))
+                .collect(),
+            ..self.to_owned()
+        }
+    }
+
+    // Panes that cannot be split from their target pane in a window of the given size,
+    // each side of a split needs at least one cell, plus one for the border between them
+    pub fn unfitting_panes(
+        &self,
+        base_pane_index: usize,
+        width: usize,
+        height: usize,
+    ) -> Vec<usize> {
+        let mut sizes = vec![(width, height)];
+        let mut unfitting = vec![];
+
+        for (pane_index, pane) in self.panes.iter().enumerate().skip(1) {
+            let target = self
+                .split_from_index(pane_index, base_pane_index)
+                .and_then(|index| index.checked_sub(base_pane_index))
+                .filter(|index| *index < pane_index)
+                .unwrap_or(pane_index - 1);
+            let (target_width, target_height) = sizes[target];
+
+            let vertical = pane.split == Some(PaneSplit::Vertical);
+            let available = if vertical {
+                target_height
+            } else {
+                target_width
+            };
+            let size = match pane.split_size.as_deref() {
+                Some(size) => match size.strip_suffix('%') {
+                    Some(percent) => percent.parse::<usize>().ok().map(|p| available * p / 100),
+                    None => size.parse().ok(),
+                },
+                None => None,
+            }
+            .unwrap_or(available.saturating_sub(1) / 2);
+
+            if size == 0 || size + 1 >= available {
+                unfitting.push(pane_index + base_pane_index);
+                sizes.push((0, 0));
+                continue;
+            }
+
+            let remaining = available - size - 1;
+            if vertical {
+                sizes[target] = (target_width, remaining);
+                sizes.push((target_width, size));
+            } else {
+                sizes[target] = (remaining, target_height);
+                sizes.push((size, target_height));
+            }
+        }
+
+        unfitting
+    }
+
+    // Picks the layout that spreads panes evenly in the direction they were split in
+    pub fn even_layout(&self) -> &'static str {
+        let splits: Vec<bool> = self
+            .panes
+            .iter()
+            .skip(1)
+            .map(|pane| pane.split == Some(PaneSplit::Vertical))
+            .collect();
+
+        if splits.iter().all(|vertical| !vertical) {
+            "even-horizontal"
+        } else if splits.iter().all(|vertical| *vertical) {
+            "even-vertical"
+        } else {
+            "tiled"
+        }
+    }
+
     pub fn default_panes() -> Vec<Pane> {
         vec![Pane::default()]
     }
 
-    fn de_panes<'de, D>(deserializer: D) -> Result<Vec<Pane>, D::Error>
+    fn default_inherit_working_dir() -> bool {
+        true
+    }
+
+    // Whether the key is a field of a window definition rather than the name of the window
+    pub fn is_field(key: &str) -> bool {
+        Self::expected_value(Some(key)) != Self::expected_value(None)
+    }
+
+    // Describes the values a window field accepts, for error messages
+    fn expected_value(key: Option<&str>) -> &'static str {
+        match key {
+            Some("name") | Some("title") => "a string",
+            Some("working_dir") | Some("root") => "a path",
+            Some("layout") => "a layout name or a custom layout string",
+            Some("main_pane_width") | Some("main_pane_height") => {
+                "a number of cells or a percentage"
+            }
+            Some("on_create")
+            | Some("post_create")
+            | Some("on_pane_create")
+            | Some("post_pane_create") => "a command or a list of commands",
+            Some("pane_commands") | Some("pane_command") | Some("pre") => {
+                "a command, a command definition or a list of those"
+            }
+            Some("clear_panes")
+            | Some("even_out")
+            | Some("foreach_windows")
+            | Some("inherit_working_dir") => "a boolean",
+            Some("foreach_dir") => "a glob pattern",
+            Some("startup_pane") => "a pane index",
+            Some("pane_border_status") => "one of off, top or bottom",
+            Some("pane_border_format") => "a tmux format string",
+            Some("shell") => "a string",
+            Some("foreach") => "an item or a list of items",
+            Some("foreach_file") => "a path",
+            Some("panes") | Some("pane") => "a pane or a list of panes",
+            _ => "a command, a list of panes or a window definition",
+        }
+    }
+
+    fn de_main_pane_size<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
     where
         D: de::Deserializer<'de>,
     {
-        #[derive(Deserialize, Debug)]
-        #[serde(untagged)]
-        enum PaneList {
-            Empty,
-            List(Vec<Pane>),
-            Single(Pane),
-        }
+        let value = Value::deserialize(deserializer)?;
+        Ok(match value {
+            Value::Null => None,
+            Value::String(percent) => Some(percent),
+            Value::Number(cells) if cells.is_u64() => Some(cells.to_string()),
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "expected {}, got {}",
+                    Self::expected_value(Some("main_pane_width")),
+                    describe_value(&value)
+                )))
+            }
+        })
+    }
 
-        let pane_list: PaneList = de::Deserialize::deserialize(deserializer)?;
+    fn de_panes<'de, D>(deserializer: D) -> Result<Vec<Pane>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
 
-        Ok(match pane_list {
-            PaneList::List(panes) => panes,
-            PaneList::Single(pane) => vec![pane],
-            PaneList::Empty => Self::default_panes(),
+        Ok(match value {
+            Value::Null => Self::default_panes(),
+            Value::Sequence(_) => Vec::<Pane>::deserialize(value).map_err(de::Error::custom)?,
+            _ => vec![Pane::deserialize(value).map_err(de::Error::custom)?],
         })
     }
 }
@@ -127,12 +486,23 @@ impl Default for Window {
             name: None,
             working_dir: None,
             layout: None,
+            main_pane_width: None,
+            main_pane_height: None,
+            pane_border_status: None,
+            pane_border_format: None,
+            shell: None,
             on_create: vec![],
             post_create: vec![],
             on_pane_create: vec![],
             post_pane_create: vec![],
             pane_commands: vec![],
             clear_panes: false,
+            even_out: false,
+            startup_pane: None,
+            foreach_dir: None,
+            foreach: vec![],
+            foreach_file: None,
+            foreach_windows: false,
             panes: Self::default_panes(),
         }
     }
@@ -143,7 +513,7 @@ impl<'de> Visitor<'de> for WindowVisitor {
     type Value = Window;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a window definition")
+        formatter.write_str(Window::expected_value(None))
     }
 
     fn visit_none<E>(self) -> Result<Self::Value, E>
@@ -191,8 +561,20 @@ impl<'de> Visitor<'de> for WindowVisitor {
         struct WindowDef {
             #[serde(default, alias = "root", deserialize_with = "de_working_dir")]
             working_dir: Option<PathBuf>,
+            #[serde(default = "Window::default_inherit_working_dir")]
+            inherit_working_dir: bool,
             #[serde(default)]
             layout: Option<String>,
+            #[serde(default, deserialize_with = "Window::de_main_pane_size")]
+            main_pane_width: Option<String>,
+            #[serde(default, deserialize_with = "Window::de_main_pane_size")]
+            main_pane_height: Option<String>,
+            #[serde(default)]
+            pane_border_status: Option<String>,
+            #[serde(default)]
+            pane_border_format: Option<String>,
+            #[serde(default)]
+            shell: Option<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             on_create: Vec<String>,
             #[serde(default, deserialize_with = "de_command_list")]
@@ -205,11 +587,23 @@ impl<'de> Visitor<'de> for WindowVisitor {
                 default,
                 alias = "pre",
                 alias = "pane_command",
-                deserialize_with = "de_command_list"
+                deserialize_with = "de_pane_command_list"
             )]
-            pane_commands: Vec<String>,
+            pane_commands: Vec<PaneCommand>,
             #[serde(default)]
             clear_panes: bool,
+            #[serde(default)]
+            even_out: bool,
+            #[serde(default)]
+            startup_pane: Option<usize>,
+            #[serde(default)]
+            foreach_dir: Option<String>,
+            #[serde(default, deserialize_with = "de_command_list")]
+            foreach: Vec<String>,
+            #[serde(default)]
+            foreach_file: Option<PathBuf>,
+            #[serde(default)]
+            foreach_windows: bool,
             #[serde(
                 default = "Window::default_panes",
                 alias = "pane",
@@ -225,8 +619,20 @@ impl<'de> Visitor<'de> for WindowVisitor {
             name: Option<String>,
             #[serde(default, alias = "root", deserialize_with = "de_working_dir")]
             working_dir: Option<PathBuf>,
+            #[serde(default = "Window::default_inherit_working_dir")]
+            inherit_working_dir: bool,
             #[serde(default)]
             layout: Option<String>,
+            #[serde(default, deserialize_with = "Window::de_main_pane_size")]
+            main_pane_width: Option<String>,
+            #[serde(default, deserialize_with = "Window::de_main_pane_size")]
+            main_pane_height: Option<String>,
+            #[serde(default)]
+            pane_border_status: Option<String>,
+            #[serde(default)]
+            pane_border_format: Option<String>,
+            #[serde(default)]
+            shell: Option<String>,
             #[serde(default, deserialize_with = "de_command_list")]
             on_create: Vec<String>,
             #[serde(default, deserialize_with = "de_command_list")]
@@ -239,11 +645,23 @@ impl<'de> Visitor<'de> for WindowVisitor {
                 default,
                 alias = "pre",
                 alias = "pane_command",
-                deserialize_with = "de_command_list"
+                deserialize_with = "de_pane_command_list"
             )]
-            pane_commands: Vec<String>,
+            pane_commands: Vec<PaneCommand>,
             #[serde(default)]
             clear_panes: bool,
+            #[serde(default)]
+            even_out: bool,
+            #[serde(default)]
+            startup_pane: Option<usize>,
+            #[serde(default)]
+            foreach_dir: Option<String>,
+            #[serde(default, deserialize_with = "de_command_list")]
+            foreach: Vec<String>,
+            #[serde(default)]
+            foreach_file: Option<PathBuf>,
+            #[serde(default)]
+            foreach_windows: bool,
             #[serde(
                 default = "Window::default_panes",
                 alias = "pane",
@@ -257,16 +675,83 @@ impl<'de> Visitor<'de> for WindowVisitor {
         enum WindowOption {
             None,
             Boolean(bool),
+            Number(usize),
             String(String),
             CommandList(Vec<String>),
+            PaneCommandList(Vec<PaneCommand>),
             PaneList(Vec<Pane>),
             Definition(WindowDef),
             DefinitionWithName(WindowDefWithName),
+            PaneCommand(PaneCommand),
+        }
+
+        // Tries the shapes the field accepts again to surface the most relevant error
+        fn invalid_value<E>(key: Option<&str>, value: Value, first_entry: bool) -> E
+        where
+            E: de::Error,
+        {
+            let is_field = Window::expected_value(key) != Window::expected_value(None);
+            let field_error = match key {
+                Some("panes") | Some("pane") => match &value {
+                    Value::Sequence(_) => Vec::<Pane>::deserialize(value.clone()).err(),
+                    _ => Pane::deserialize(value.clone()).err(),
+                },
+                Some("pane_commands") | Some("pane_command") | Some("pre") => {
+                    de_pane_command_list(value.clone()).err()
+                }
+                Some("on_create")
+                | Some("post_create")
+                | Some("on_pane_create")
+                | Some("post_pane_create") => de_command_list(value.clone()).err(),
+                _ if is_field => None,
+                _ if !first_entry => {
+                    return E::custom(format!("unknown window field {:?}", key.unwrap_or("~")))
+                }
+                _ if has_key(&value, "name") || has_key(&value, "title") => {
+                    WindowDefWithName::deserialize(value.clone()).err()
+                }
+                _ => match &value {
+                    Value::Mapping(_) => WindowDef::deserialize(value.clone()).err(),
+                    Value::Sequence(_) => Vec::<Pane>::deserialize(value.clone()).err(),
+                    _ => None,
+                },
+            };
+
+            let target = match key {
+                Some(key) if is_field => format!("window field {:?}", key),
+                Some(name) => format!("window {:?}", name),
+                None => String::from("window"),
+            };
+
+            match field_error {
+                Some(err) => E::custom(format!("{}: {}", target, err)),
+                None => E::custom(format!(
+                    "{}: expected {}, got {}",
+                    target,
+                    Window::expected_value(key),
+                    describe_value(&value)
+                )),
+            }
         }
 
         let mut first_entry = true;
+        let mut inherit_working_dir = true;
         let mut window = Self::Value::default();
-        while let Some((key, value)) = map.next_entry::<WindowKeyType, WindowOption>()? {
+        let mut entries: Vec<(WindowKeyType, Value)> = vec![];
+        while let Some(key) = map.next_key::<WindowKeyType>()? {
+            entries.push((key, map.next_value()?));
+        }
+
+        // `- shell: command` is a window named shell, unless the map is a definition
+        let is_definition = entries
+            .iter()
+            .any(|(key, _)| matches!(key.as_deref(), Some("name") | Some("title")));
+
+        for (key, value) in entries {
+            let shell_is_field = !first_entry || is_definition;
+            let value = WindowOption::deserialize(value.clone())
+                .map_err(|_| invalid_value(key.as_deref(), value, first_entry))?;
+
             match key {
                 None => {
                     if !first_entry {
@@ -281,27 +766,54 @@ impl<'de> Visitor<'de> for WindowVisitor {
                         WindowOption::CommandList(commands) => {
                             window.panes = commands.into_iter().map(Pane::from).collect()
                         }
+                        WindowOption::PaneCommandList(commands) => {
+                            window.panes = commands.into_iter().map(Pane::from).collect()
+                        }
                         WindowOption::DefinitionWithName(def) => {
                             window.name = def.name;
                             window.working_dir = def.working_dir;
+                            inherit_working_dir = def.inherit_working_dir;
                             window.layout = def.layout;
+                            window.main_pane_width = def.main_pane_width;
+                            window.main_pane_height = def.main_pane_height;
+                            window.pane_border_status = def.pane_border_status;
+                            window.pane_border_format = def.pane_border_format;
+                            window.shell = def.shell;
                             window.on_create = def.on_create;
                             window.post_create = def.post_create;
                             window.on_pane_create = def.on_pane_create;
                             window.post_pane_create = def.post_pane_create;
                             window.pane_commands = def.pane_commands;
                             window.clear_panes = def.clear_panes;
+                            window.even_out = def.even_out;
+                            window.startup_pane = def.startup_pane;
+                            window.foreach_dir = def.foreach_dir;
+                            window.foreach = def.foreach;
+                            window.foreach_file = def.foreach_file;
+                            window.foreach_windows = def.foreach_windows;
                             window.panes = def.panes;
                         }
                         WindowOption::Definition(def) => {
                             window.working_dir = def.working_dir;
+                            inherit_working_dir = def.inherit_working_dir;
                             window.layout = def.layout;
+                            window.main_pane_width = def.main_pane_width;
+                            window.main_pane_height = def.main_pane_height;
+                            window.pane_border_status = def.pane_border_status;
+                            window.pane_border_format = def.pane_border_format;
+                            window.shell = def.shell;
                             window.on_create = def.on_create;
                             window.post_create = def.post_create;
                             window.on_pane_create = def.on_pane_create;
                             window.post_pane_create = def.post_pane_create;
                             window.pane_commands = def.pane_commands;
                             window.clear_panes = def.clear_panes;
+                            window.even_out = def.even_out;
+                            window.startup_pane = def.startup_pane;
+                            window.foreach_dir = def.foreach_dir;
+                            window.foreach = def.foreach;
+                            window.foreach_file = def.foreach_file;
+                            window.foreach_windows = def.foreach_windows;
                             window.panes = def.panes;
                         }
                         WindowOption::PaneList(panes) => window.panes = panes,
@@ -312,13 +824,25 @@ impl<'de> Visitor<'de> for WindowVisitor {
                     WindowOption::None => match key.as_str() {
                         "name" | "title" => window.name = None,
                         "working_dir" | "root" => window.working_dir = Some(home_working_dir()),
+                        "inherit_working_dir" => inherit_working_dir = true,
                         "layout" => window.layout = None,
+                        "main_pane_width" => window.main_pane_width = None,
+                        "main_pane_height" => window.main_pane_height = None,
+                        "pane_border_status" => window.pane_border_status = None,
+                        "pane_border_format" => window.pane_border_format = None,
+                        "shell" if shell_is_field => window.shell = None,
                         "on_create" => window.on_create = vec![],
                         "post_create" => window.post_create = vec![],
                         "on_pane_create" => window.on_pane_create = vec![],
                         "post_pane_create" => window.post_pane_create = vec![],
                         "pane_commands" | "pane_command" | "pre" => window.pane_commands = vec![],
                         "clear_panes" => window.clear_panes = false,
+                        "even_out" => window.even_out = false,
+                        "startup_pane" => window.startup_pane = None,
+                        "foreach_dir" => window.foreach_dir = None,
+                        "foreach" => window.foreach = vec![],
+                        "foreach_file" => window.foreach_file = None,
+                        "foreach_windows" => window.foreach_windows = false,
                         "panes" | "pane" => window.panes = vec![Pane::default()],
                         _ => {
                             if !first_entry {
@@ -333,6 +857,9 @@ impl<'de> Visitor<'de> for WindowVisitor {
                     },
                     WindowOption::Boolean(val) => match key.as_str() {
                         "clear_panes" => window.clear_panes = val,
+                        "even_out" => window.even_out = val,
+                        "foreach_windows" => window.foreach_windows = val,
+                        "inherit_working_dir" => inherit_working_dir = val,
                         _ => {
                             return Err(de::Error::custom(format!(
                                 "window field {:?} cannot be a boolean",
@@ -340,18 +867,33 @@ impl<'de> Visitor<'de> for WindowVisitor {
                             )));
                         }
                     },
+                    WindowOption::Number(val) => match key.as_str() {
+                        "main_pane_width" => window.main_pane_width = Some(val.to_string()),
+                        "main_pane_height" => window.main_pane_height = Some(val.to_string()),
+                        "startup_pane" => window.startup_pane = Some(val),
+                        _ => return Err(invalid_value(Some(&key), Value::from(val), first_entry)),
+                    },
                     WindowOption::String(val) => match key.as_str() {
                         "name" | "title" => window.name = Some(val),
                         "working_dir" | "root" => {
-                            window.working_dir = Some(process_working_dir(val.as_str()))
+                            window.working_dir =
+                                Some(process_working_dir(val.as_str()).map_err(de::Error::custom)?)
                         }
                         "layout" => window.layout = Some(val),
+                        "foreach_dir" => window.foreach_dir = Some(val),
+                        "foreach" => window.foreach = vec![process_command(val)],
+                        "foreach_file" => window.foreach_file = Some(PathBuf::from(val)),
+                        "main_pane_width" => window.main_pane_width = Some(val),
+                        "main_pane_height" => window.main_pane_height = Some(val),
+                        "pane_border_status" => window.pane_border_status = Some(val),
+                        "pane_border_format" => window.pane_border_format = Some(val),
+                        "shell" if shell_is_field => window.shell = Some(val),
                         "on_create" => window.on_create = vec![process_command(val)],
                         "post_create" => window.post_create = vec![process_command(val)],
                         "on_pane_create" => window.on_pane_create = vec![process_command(val)],
                         "post_pane_create" => window.post_pane_create = vec![process_command(val)],
                         "pane_commands" | "pane_command" | "pre" => {
-                            window.pane_commands = vec![process_command(val)]
+                            window.pane_commands = vec![PaneCommand::from(process_command(val))]
                         }
                         "panes" | "pane" => window.panes = vec![Pane::from(val)],
                         _ => {
@@ -368,14 +910,32 @@ impl<'de> Visitor<'de> for WindowVisitor {
                     },
                     WindowOption::CommandList(commands) => match key.as_str() {
                         "on_create" => window.on_create = process_command_list(commands),
+                        "foreach" => window.foreach = process_command_list(commands),
                         "post_create" => window.post_create = process_command_list(commands),
                         "on_pane_create" => window.on_pane_create = process_command_list(commands),
                         "post_pane_create" => {
                             window.post_pane_create = process_command_list(commands)
                         }
                         "pane_commands" | "pane_command" | "pre" => {
-                            window.pane_commands = process_command_list(commands)
+                            window.pane_commands = process_pane_command_list(commands)
+                        }
+                        "panes" | "pane" => {
+                            window.panes = commands.into_iter().map(Pane::from).collect()
+                        }
+                        _ => {
+                            if !first_entry {
+                                return Err(de::Error::custom(format!(
+                                    "window field {:?} cannot be a command list",
+                                    key
+                                )));
+                            }
+
+                            window.name = Some(key);
+                            window.panes = commands.into_iter().map(Pane::from).collect()
                         }
+                    },
+                    WindowOption::PaneCommandList(commands) => match key.as_str() {
+                        "pane_commands" | "pane_command" | "pre" => window.pane_commands = commands,
                         "panes" | "pane" => {
                             window.panes = commands.into_iter().map(Pane::from).collect()
                         }
@@ -391,6 +951,17 @@ impl<'de> Visitor<'de> for WindowVisitor {
                             window.panes = commands.into_iter().map(Pane::from).collect()
                         }
                     },
+                    WindowOption::PaneCommand(command) => match key.as_str() {
+                        "pane_commands" | "pane_command" | "pre" => {
+                            window.pane_commands = vec![command]
+                        }
+                        _ => {
+                            return Err(de::Error::custom(format!(
+                                "window field {:?} cannot be a command definition",
+                                key
+                            )));
+                        }
+                    },
                     WindowOption::Definition(def) => {
                         if !first_entry {
                             return Err(de::Error::custom(format!(
@@ -401,13 +972,25 @@ impl<'de> Visitor<'de> for WindowVisitor {
 
                         window.name = Some(key);
                         window.working_dir = def.working_dir;
+                        inherit_working_dir = def.inherit_working_dir;
                         window.layout = def.layout;
+                        window.main_pane_width = def.main_pane_width;
+                        window.main_pane_height = def.main_pane_height;
+                        window.pane_border_status = def.pane_border_status;
+                        window.pane_border_format = def.pane_border_format;
+                        window.shell = def.shell;
                         window.on_create = def.on_create;
                         window.post_create = def.post_create;
                         window.on_pane_create = def.on_pane_create;
                         window.post_pane_create = def.post_pane_create;
                         window.pane_commands = def.pane_commands;
                         window.clear_panes = def.clear_panes;
+                        window.even_out = def.even_out;
+                        window.startup_pane = def.startup_pane;
+                        window.foreach_dir = def.foreach_dir;
+                        window.foreach = def.foreach;
+                        window.foreach_file = def.foreach_file;
+                        window.foreach_windows = def.foreach_windows;
                         window.panes = def.panes;
                     }
                     WindowOption::DefinitionWithName(def) => {
@@ -420,13 +1003,25 @@ impl<'de> Visitor<'de> for WindowVisitor {
 
                         window.name = def.name;
                         window.working_dir = def.working_dir;
+                        inherit_working_dir = def.inherit_working_dir;
                         window.layout = def.layout;
+                        window.main_pane_width = def.main_pane_width;
+                        window.main_pane_height = def.main_pane_height;
+                        window.pane_border_status = def.pane_border_status;
+                        window.pane_border_format = def.pane_border_format;
+                        window.shell = def.shell;
                         window.on_create = def.on_create;
                         window.post_create = def.post_create;
                         window.on_pane_create = def.on_pane_create;
                         window.post_pane_create = def.post_pane_create;
                         window.pane_commands = def.pane_commands;
                         window.clear_panes = def.clear_panes;
+                        window.even_out = def.even_out;
+                        window.startup_pane = def.startup_pane;
+                        window.foreach_dir = def.foreach_dir;
+                        window.foreach = def.foreach;
+                        window.foreach_file = def.foreach_file;
+                        window.foreach_windows = def.foreach_windows;
                         window.panes = def.panes;
                     }
                     WindowOption::PaneList(panes) => match key.as_str() {
@@ -449,6 +1044,11 @@ impl<'de> Visitor<'de> for WindowVisitor {
             first_entry = false;
         }
 
+        // Windows that don't inherit the project's working_dir start in the home directory
+        if !inherit_working_dir && window.working_dir.is_none() {
+            window.working_dir = Some(home_working_dir());
+        }
+
         Ok(window)
     }
 }
@@ -1,6 +1,7 @@
 use serde::{de, ser};
-use shellexpand::tilde;
+use shellexpand::{full, tilde, LookupError};
 
+use std::env::VarError;
 use std::path::PathBuf;
 
 pub fn de_working_dir<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
@@ -8,9 +9,10 @@ where
     D: de::Deserializer<'de>,
 {
     let opt: Option<PathBuf> = de::Deserialize::deserialize(deserializer)?;
-    Ok(Some(opt.map_or_else(home_working_dir, |path| {
-        process_working_dir(&path.to_string_lossy())
-    })))
+    Ok(Some(match opt {
+        None => home_working_dir(),
+        Some(path) => process_working_dir(&path.to_string_lossy()).map_err(de::Error::custom)?,
+    }))
 }
 
 pub fn ser_working_dir<S>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error>
@@ -34,8 +36,9 @@ where
     }
 }
 
-pub fn process_working_dir(str_path: &str) -> PathBuf {
-    PathBuf::from(tilde(str_path).to_string())
+// Expands both `~` and environment variables (ie: `$PROJECTS/api`)
+pub fn process_working_dir(str_path: &str) -> Result<PathBuf, LookupError<VarError>> {
+    Ok(PathBuf::from(full(str_path)?.to_string()))
 }
 
 pub fn home_working_dir() -> PathBuf {